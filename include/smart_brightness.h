@@ -0,0 +1,46 @@
+/* include/smart_brightness.h
+ *
+ * C API of the smart_brightness library (`ffi` feature); see src/ffi.rs.
+ * Build it with:
+ *
+ *   cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * (or `--crate-type staticlib`) and link against target/release. */
+#ifndef SMART_BRIGHTNESS_H
+#define SMART_BRIGHTNESS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct sb_curve sb_curve;
+typedef struct sb_ema sb_ema;
+typedef struct sb_transition sb_transition;
+
+/* `count` anchors as luma, fraction pairs; NULL if not a valid curve. */
+sb_curve *sb_curve_new(const float *points, size_t count);
+float sb_curve_eval(const sb_curve *curve, float luma);
+void sb_curve_free(sb_curve *curve);
+
+sb_ema *sb_ema_new(float alpha);
+float sb_ema_update(sb_ema *ema, float x, float weight);
+void sb_ema_free(sb_ema *ema);
+
+sb_transition *sb_transition_new(uint32_t initial, uint64_t interval_ms, uint32_t divisor,
+                                 uint32_t max_step);
+void sb_transition_set_target(sb_transition *transition, uint32_t target,
+                              uint32_t max_brightness);
+/* Time in milliseconds from any start; true with `*level` set if a step was due. */
+bool sb_transition_update(sb_transition *transition, uint64_t ms, uint32_t *level);
+uint32_t sb_transition_current(const sb_transition *transition);
+void sb_transition_free(sb_transition *transition);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -232,17 +232,17 @@ if [[ "$calibrate_choice" =~ ^[Yy]$ ]]; then
     fi
     
     # Run calibration
-    if $BINARY_PATH --calibrate; then
+    if $BINARY_PATH calibrate; then
         echo
         echo "✓ Calibration completed successfully!"
     else
         echo
         echo "⚠ Calibration failed or was cancelled."
-        echo "  You can run it later with: smart-brightness --calibrate"
+        echo "  You can run it later with: smart-brightness calibrate"
     fi
 else
     echo "Skipping calibration."
-    echo "You can run it later with: smart-brightness --calibrate"
+    echo "You can run it later with: smart-brightness calibrate"
 fi
 
 echo
@@ -298,7 +298,7 @@ echo "Daemon mode: $DAEMON_MODE"
 echo
 echo "Useful commands:"
 echo "  smart-brightness --help       - Show help"
-echo "  smart-brightness --calibrate  - Run calibration"
+echo "  smart-brightness calibrate    - Run calibration"
 echo "  sudo systemctl status smart-brightnessd - Check service status"
 echo "  journalctl -u smart-brightnessd -f      - View logs"
 echo
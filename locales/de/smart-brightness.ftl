@@ -0,0 +1,192 @@
+# Smart Brightness – Deutsch
+
+## --help
+
+help-title = Smart Brightness - Automatische Anpassung der Bildschirmhelligkeit
+help-usage = AUFRUF:
+help-options = OPTIONEN:
+help-opt-configure = TUI-Konfigurationsoberfläche starten
+help-opt-calibrate = Kalibrierungsassistent für Kameraempfindlichkeit
+help-opt-calibrate-cont = und Helligkeitsbereich des Monitors starten
+help-opt-config = Diese Konfigurationsdatei laden und speichern, statt eine zu suchen
+help-opt-load-state = Mit einem per 'ctl dump-state' gesicherten Zustand starten (dessen Konfiguration ersetzt Ihre)
+help-opt-ab-test = Zwischen zwei Einstellungsdateien wechseln und den Verlauf mit der jeweils aktiven markieren
+help-opt-help = Diese Hilfe anzeigen
+help-opt-profile = Überschreibungen aus [profiles.<NAME>] der Konfiguration anwenden
+help-opt-preset = Mit einem eingebauten Parametersatz beginnen, vor --profile angewendet
+help-commands = BEFEHLE:
+help-cmd-run = Den Daemon starten (Standard ohne Befehl)
+help-cmd-devices = Gefundene Hintergrundbeleuchtungen, Lichtsensoren und Kameras auflisten
+help-cmd-completions = Skript für die Shell-Vervollständigung ausgeben
+help-cmd-profiles = In der Konfiguration definierte Profile auflisten
+help-cmd-camera-test = Prüfen, ob die Kamera-Luma der Belichtung folgt und die Belichtung sich sperren lässt
+help-cmd-circadian = Tag-/Nachtzeiten aus dem aufgezeichneten Umgebungslicht vorschlagen; --apply speichert sie
+help-cmd-ctl = Einstellung des laufenden Daemons lesen oder ändern; --persist speichert sie auch
+help-cmd-ctl-ramp = Helligkeit bis zu einer Uhrzeit erreichen, danach wieder dem Umgebungslicht folgen
+help-cmd-ctl-lock = Eine Helligkeit unabhängig vom Umgebungslicht halten, bis sie entsperrt wird oder die Minuten abgelaufen sind
+help-cmd-ctl-status = Aktuellen Umgebungswert und Helligkeit des laufenden Daemons ausgeben
+help-cmd-ctl-pause = Helligkeitsanpassung anhalten oder fortsetzen
+help-cmd-ctl-dump-state = Den internen Zustand des Daemons als JSON ausgeben, zum Anhängen an Fehlerberichte
+help-cmd-ctl-report = Heute zusammenfassen: Zeit je Helligkeitsstufe, manuelle Korrekturen, Umgebungslicht pro Stunde
+help-cmd-ctl-toggle = Eine Stufe bis zum Beenden des Dienstes ab- oder wieder einschalten, um zu prüfen, ob sie ein seltsames Verhalten verursacht
+help-example-completions = # Bash-Vervollständigung installieren
+help-example-ctl = # Sanftere Reaktion ohne Neustart ausprobieren
+help-configuration = KONFIGURATION:
+help-config-order = Konfigurationsdateien werden geladen aus (in dieser Reihenfolge):
+help-config-cwd = (aktuelles Verzeichnis)
+help-daemon-modes = DAEMON-MODI:
+help-mode-realtime = Helligkeit fortlaufend anpassen (Standard)
+help-mode-boot = Für die angegebene Dauer laufen, dann beenden
+help-mode-interval = Für die Dauer laufen, pausieren, wiederholen
+help-examples = BEISPIELE:
+help-example-calibrate = # Kalibrierung ausführen
+help-example-run = # Im Echtzeitmodus laufen (liest die Konfiguration)
+help-more-info = Weitere Informationen:
+
+## Calibration wizard
+
+calib-title = Smart Brightness - Kalibrierungsassistent
+calib-intro = Kalibriert werden:
+calib-intro-camera = 1. Kameraempfindlichkeit (Umgebungslichterkennung)
+calib-intro-monitor = 2. Helligkeitsbereich des Monitors (Min/Max)
+calib-intro-curve = 3. Ihre bevorzugte Helligkeit bei verschiedenem Licht (optional)
+calib-step1-title = Schritt 1: Kamerakalibrierung
+calib-step1-body = DUNKELSTE übliche Situation herstellen (Objektiv abdecken / Raum abdunkeln)
+calib-step2-title = Schritt 2: Messung bei hellem Licht
+calib-step2-body = HELLSTE übliche Situation herstellen (helles Licht / Tageslicht)
+calib-als-title = Schritt 1: Kamerakalibrierung am Lichtsensor
+calib-als-body = Ändern Sie in den nächsten { $secs } s das Licht: Lampen an und aus, Jalousien auf und zu
+calib-als-sample = Kamera { $camera } → Lichtsensor { $sensor } ({ $count } Messwerte)
+calib-als-fitted = ✓ Korrekturkurve mit { $points } Punkten ermittelt; Lichtbereich { $min } → { $max }
+calib-als-failed = ⚠ Das Licht hat sich kaum verändert; stattdessen werden Dunkel und Hell von Hand gemessen.
+calib-roi = Gemessen werden { $w } × { $h } des Bildes, ab { $x } von links und { $y } von oben (camera_roi)
+calib-roi-full = Gemessen wird das ganze Bild (camera_roi)
+calib-warming-up = Kamera wird vorbereitet...
+calib-measuring-dark = Dunkles Umgebungslicht wird gemessen...
+calib-measured-dark = ✓ Gemessene dunkle Luma: { $luma }
+calib-measuring-bright = Helles Umgebungslicht wird gemessen...
+calib-measured-bright = ✓ Gemessene helle Luma: { $luma }
+calib-range-small = ⚠ WARNUNG: Luma-Bereich ist sehr klein ({ $range })
+calib-range-small-hint = Stärkeren Lichtkontrast verwenden und die Kalibrierung wiederholen.
+calib-range-good = ✓ Guter Luma-Bereich erkannt: { $range }
+calib-results-title = Kalibrierungsergebnis
+calib-result-luma = Kamera-Luma-Bereich: { $min } → { $max }
+calib-result-brightness = Monitorhelligkeit:   { $min } → { $max }
+calib-saved = ✓ Kalibrierung erfolgreich gespeichert!
+calib-managed = Die Kalibrierung legt Ihr Administrator in { $path } fest; nichts zu tun.
+calib-monitor-intro = 3) Monitorkalibrierung mit den Helligkeitstasten.
+calib-monitor-reading = (Werte werden aus { $path } gelesen – Intel-Panels stellen z. B. /sys/class/backlight/intel_backlight/actual_brightness bereit)
+calib-monitor-set-max = • Monitor mit den Tasten auf MAXIMALE Helligkeit stellen, dann Enter drücken.
+calib-monitor-recorded-max = → Erfasste maximale Helligkeit: { $level }
+calib-monitor-set-min = • Jetzt die NIEDRIGSTE Helligkeit einstellen, bei der der Bildschirm noch lesbar ist, dann Enter drücken.
+calib-monitor-recorded-min = → Erfasste minimale Helligkeit: { $level }
+calib-monitor-range-error = Die maximale Helligkeit muss größer als die minimale sein; bitte Kalibrierung wiederholen.
+calib-monitor-read-error = actual_brightness des Backlight-Geräts konnte nicht gelesen werden
+calib-press-enter = Enter drücken, um fortzufahren...
+calib-curve-title = Schritt 4: Ihre bevorzugte Helligkeit
+calib-curve-body = Optional: Zeigen Sie bei beliebig vielen Lichtverhältnissen, welche Helligkeit Sie möchten
+calib-curve-prompt = • Licht einrichten, den Bildschirm mit den Hardware-Tasten auf die gewünschte Helligkeit stellen und Enter drücken. Zum Beenden q und Enter eingeben.
+calib-curve-point = → Punkt { $n }: Licht { $luma } → { $percent } % Helligkeit
+calib-curve-saved = ✓ Helligkeitskurve mit { $points } Punkten gespeichert ([curve])
+calib-curve-skipped = Weniger als zwei Punkte aufgenommen; die Helligkeitskurve bleibt unverändert.
+
+## Configurator (TUI)
+
+tui-title = Smart Brightness Konfiguration
+tui-settings = Einstellungen
+tui-item-mode = Daemon-Modus
+tui-item-run-duration = Laufzeit (Boot/Intervall)
+tui-item-pause-interval = Pausenintervall (Intervall)
+tui-item-interval-boot = Intervallmodus beim Start
+tui-item-min-brightness = Minimale Helligkeit
+tui-item-max-brightness = Maximale Helligkeit
+tui-item-smoothing = Glättungsfaktor
+tui-item-half-precision = Halbe Genauigkeit (schnellere Aufnahme)
+tui-item-save = Speichern & Beenden
+tui-item-cancel = Abbrechen
+tui-help = 'Enter' zum Bearbeiten, 'h' für Verlauf, 'c' für Kurve, 'd' für Bildschirme, 'q' zum Beenden
+tui-editing-help = Bearbeiten... Enter zum Bestätigen, Esc zum Abbrechen
+tui-editing = BEARBEITEN: { $input } (Aktuell: { $current })
+tui-editing-cancelled = Bearbeitung abgebrochen
+tui-invalid-mode = Ungültiger Modus! Erlaubt: boot, interval, realtime
+tui-invalid-value = Ungültiger Wert '{ $input }'
+tui-invalid-setting = Nicht übernommen: { $error }
+tui-value-updated = Wert geändert. 'Speichern & Beenden' nicht vergessen
+tui-save-error = Fehler beim Speichern: { $error }
+tui-managed = verwaltet
+tui-on = an
+tui-off = aus
+tui-managed-locked = Diese Einstellung legt Ihr Administrator in { $path } fest
+tui-history-help = ←/→ verschieben, +/- zoomen, 'r' neu laden, Esc zurück
+tui-history-error = Verlauf kann nicht gelesen werden: { $error }
+tui-history-title = Verlauf – { $count } Messwerte, Fenster { $hours } h
+tui-history-hours = Stunden
+tui-history-now = jetzt
+tui-series-luma = Luma
+tui-series-brightness = Helligkeit
+tui-series-manual = manuell
+tui-curve-help = Tab wählen, Pfeile bewegen (Shift = grob), 'a' hinzufügen, 'd' löschen, 'r' zurücksetzen, Enter übernehmen, Esc verwerfen
+tui-curve-title = Kurve – Punkt { $index }/{ $count }: Luma { $luma } → { $brightness } % (Stufe { $level })
+tui-curve-title-nits = Kurve – Punkt { $index }/{ $count }: Luma { $luma } → { $nits } Nits (Stufe { $level })
+tui-curve-series = Kurve
+tui-curve-anchors = Stützpunkte
+tui-curve-luma-axis = Umgebungs-Luma
+tui-curve-discarded = Kurvenänderungen verworfen
+tui-displays-title = Bildschirme – [x] folgt dem Hauptbildschirm
+tui-displays-help = ↑/↓ wählen, Leertaste koppeln/entkoppeln, Enter Einstellungen, 'x' vergessen, Esc zurück
+tui-displays-none = Keine Hintergrundbeleuchtung gefunden
+tui-displays-missing = nicht erkannt
+tui-displays-not-linked = nicht gekoppelt
+tui-displays-page-title = Bildschirm { $name }
+tui-displays-page-help = ↑/↓ wählen, Enter bearbeiten (schaltet Aktiv um), Esc zurück
+tui-displays-editing = BEARBEITEN: { $input } – Enter zum Bestätigen, Esc zum Abbrechen
+tui-displays-invalid = Ungültiger Wert: { $error }
+tui-displays-enabled = Aktiv
+tui-displays-multiplier = Faktor
+tui-displays-offset = Versatz (%)
+tui-displays-min = Minimum (%)
+tui-displays-max = Maximum (%)
+
+## Status output
+
+status-transition-start = → Helligkeit { $from } → { $to } (normalisiert { $luma })
+status-transition-start-lux = → Helligkeit { $from } → { $to } (normalisiert { $luma }, ≈{ $lux } lx)
+status-transition-done = ✓ Helligkeit { $from } → { $to } in { $secs } s
+notify-sensor-failing = Umgebungslichtsensor funktioniert nicht
+notify-sensor-failing-body = Seit { $secs } s kein Messwert: { $error }
+notify-safe-mode = Helligkeitskalibrierung passt nicht mehr
+notify-safe-mode-body = Messwerte liegen seit { $minutes } min außerhalb des kalibrierten Bereichs; vorerst gilt der volle Kamerabereich. Mit smart-brightness --calibrate neu kalibrieren.
+summary-title = Zusammenfassung:
+summary-runtime = Laufzeit:              { $value }
+summary-changes = Helligkeitsänderungen: { $value }
+summary-luma = Luma (normalisiert):   min { $min }  max { $max }  Ø { $avg }
+summary-brightness = Helligkeit:            min { $min }  max { $max }  Ø { $avg }
+summary-lux = Beleuchtung (≈lx):     min { $min }  max { $max }  Ø { $avg }
+summary-capture-errors = Erfassungsfehler:      { $value }
+summary-motion-skipped = Verworfen (Bewegung):  { $value }
+summary-writes = Backlight-Schreibvorgänge: { $value }
+
+camtest-title = Kamera-Selbsttest
+camtest-auto = Automatische Belichtung: Luma { $luma } bei { $exposure }
+camtest-no-control = ✗ Die Kamera bietet keine manuelle Belichtungssteuerung; nur die Automatik ist nutzbar.
+camtest-range = Belichtungssteuerung: { $min } – { $max } (Standard { $default })
+camtest-sweep-header = Luma bei festen Belichtungszeiten:
+camtest-adjusted = (Treiber setzte { $value })
+camtest-not-monotonic = ✗ Nicht monoton: Luma fiel bei { $value } von { $from } auf { $to }
+camtest-flat = ✗ Luma ändert sich kaum mit der Belichtung; ist das Objektiv abgedeckt oder der Raum dunkel?
+camtest-monotonic = ✓ Luma steigt mit der Belichtung
+camtest-monotonic-saturated = ✓ Luma steigt mit der Belichtung (bei der längsten übersteuert)
+camtest-lock-held = ✓ Belichtungssperre hält bei { $value } (Luma-Drift { $drift })
+camtest-lock-moved = ✗ Belichtungssperre nicht gehalten: Der Treiber änderte die Belichtungszeit bei { $value }
+camtest-lock-drift = ✗ Luma driftet um { $drift } trotz gesperrter Belichtung; die Kamera regelt evtl. die Verstärkung nach
+camtest-lock-auto = ✗ Der Treiber ist zur automatischen Belichtung zurückgekehrt
+camtest-restore-failed = ⚠ Automatische Belichtung konnte nicht wieder eingeschaltet werden: { $error }
+camtest-verdict-ok = Die Kamera reagiert plausibel auf Licht.
+camtest-verdict-bad = Die Reaktion der Kamera ist unzuverlässig; Messwerte können springen oder nachhinken.
+
+circadian-header = Umgebungslicht nach Uhrzeit (letzte { $days } Tage):
+circadian-current = Aktuell:     Tag ab { $day }:00 ×{ $boost }, Nacht ab { $night }:00 ×{ $dim }
+circadian-suggested = Vorschlag:   Tag ab { $day }:00 ×{ $boost }, Nacht ab { $night }:00 ×{ $dim }
+circadian-no-suggestion = Noch kein Vorschlag: Erst { $hours } von 24 Stunden haben genug Verlauf, oder das Licht ändert sich über den Tag kaum.
+circadian-apply-hint = Mit --apply speichern oder circadian_auto_tune = true setzen.
+circadian-applied = ✓ In der Konfigurationsdatei gespeichert.
@@ -0,0 +1,195 @@
+# Smart Brightness – English (United States)
+#
+# Message values are trimmed by Fluent, so indentation and box drawing
+# stay in the code; only the text lives here.
+
+## --help
+
+help-title = Smart Brightness - Automatic screen brightness adjustment
+help-usage = USAGE:
+help-options = OPTIONS:
+help-opt-configure = Launch TUI configuration interface
+help-opt-calibrate = Run calibration wizard to detect camera sensitivity
+help-opt-calibrate-cont = and monitor brightness range
+help-opt-config = Load and save this config file instead of looking one up
+help-opt-load-state = Start from a state saved with 'ctl dump-state' (its config replaces yours)
+help-opt-ab-test = Alternate between two settings files and tag the history with the one in use
+help-opt-help = Display this help message
+help-opt-profile = Apply the overrides from [profiles.<NAME>] in the config
+help-opt-preset = Start from a built-in parameter set, applied before --profile
+help-commands = COMMANDS:
+help-cmd-run = Run the daemon (the default without a command)
+help-cmd-devices = List the backlights, light sensors and cameras found
+help-cmd-completions = Print a shell completion script
+help-cmd-profiles = List profiles defined in the config
+help-cmd-camera-test = Check that camera luma follows exposure and that exposure can be locked
+help-cmd-circadian = Suggest circadian hours from recorded ambient light; --apply saves them
+help-cmd-ctl = Read or change a setting of the running daemon; --persist also saves it
+help-cmd-ctl-ramp = Reach a brightness by a time of day, then follow ambient light again
+help-cmd-ctl-lock = Hold a brightness regardless of ambient light until unlocked, or for a number of minutes
+help-cmd-ctl-status = Print the current ambient reading and brightness of the running daemon
+help-cmd-ctl-pause = Stop adjusting brightness, or start again
+help-cmd-ctl-dump-state = Print the daemon's internal state as JSON, to attach to bug reports
+help-cmd-ctl-report = Summarize today: time at each brightness level, manual overrides, ambient light per hour
+help-cmd-ctl-toggle = Switch a stage off, or back on, until the daemon exits, to see whether it causes odd behavior
+help-example-completions = # Install bash completions
+help-example-ctl = # Try a smoother response without restarting
+help-configuration = CONFIGURATION:
+help-config-order = Config files are loaded from (in order):
+help-config-cwd = (current directory)
+help-daemon-modes = DAEMON MODES:
+help-mode-realtime = Continuously adjust brightness (default)
+help-mode-boot = Run for specified duration then exit
+help-mode-interval = Run for duration, pause, then repeat
+help-examples = EXAMPLES:
+help-example-calibrate = # Run calibration
+help-example-run = # Run in realtime mode (reads from config)
+help-more-info = For more information, visit:
+
+## Calibration wizard
+
+calib-title = Smart Brightness - Calibration Wizard
+calib-intro = This will calibrate:
+calib-intro-camera = 1. Camera sensitivity (ambient light detection)
+calib-intro-monitor = 2. Monitor brightness range (min/max values)
+calib-intro-curve = 3. Your preferred brightness in different light (optional)
+calib-step1-title = Step 1: Camera Calibration
+calib-step1-body = Prepare DARKEST typical condition (cover lens / dim room)
+calib-step2-title = Step 2: Bright Light Measurement
+calib-step2-body = Prepare BRIGHTEST typical condition (bright light/daylight)
+calib-als-title = Step 1: Camera Calibration against the Light Sensor
+calib-als-body = For the next { $secs } s, change the light: lamps on and off, blinds open and closed
+calib-als-sample = camera { $camera } → light sensor { $sensor } ({ $count } samples)
+calib-als-fitted = ✓ Fitted a { $points }-point correction curve; light range { $min } → { $max }
+calib-als-failed = ⚠ The light barely changed; measuring dark and bright by hand instead.
+calib-roi = Measuring { $w } × { $h } of the frame, from { $x } across and { $y } down (camera_roi)
+calib-roi-full = Measuring the whole frame (camera_roi)
+calib-warming-up = Warming up camera...
+calib-measuring-dark = Measuring dark ambient light...
+calib-measured-dark = ✓ Measured dark luma: { $luma }
+calib-measuring-bright = Measuring bright ambient light...
+calib-measured-bright = ✓ Measured bright luma: { $luma }
+calib-range-small = ⚠ WARNING: Luma range is very small ({ $range })
+calib-range-small-hint = Consider using stronger lighting contrast and re-running calibration.
+calib-range-good = ✓ Good luma range detected: { $range }
+calib-results-title = Calibration Results
+calib-result-luma = Camera Luma Range:  { $min } → { $max }
+calib-result-brightness = Monitor Brightness: { $min } → { $max }
+calib-saved = ✓ Calibration saved successfully!
+calib-managed = Calibration is set by your administrator in { $path }; nothing to do.
+calib-monitor-intro = 3) Monitor calibration using hardware brightness keys.
+calib-monitor-reading = (Reading levels from { $path } – e.g. intel panels expose /sys/class/backlight/intel_backlight/actual_brightness)
+calib-monitor-set-max = • Set the monitor to its MAXIMUM brightness using the hardware keys, then press Enter.
+calib-monitor-recorded-max = → Recorded maximum actual brightness: { $level }
+calib-monitor-set-min = • Now set the monitor to the LOWEST brightness that still keeps the screen visible, then press Enter.
+calib-monitor-recorded-min = → Recorded minimum actual brightness: { $level }
+calib-monitor-range-error = Recorded maximum brightness must be greater than minimum; please rerun calibration.
+calib-monitor-read-error = Unable to read actual_brightness from backlight device
+calib-press-enter = Press Enter to continue...
+calib-curve-title = Step 4: Your Preferred Brightness
+calib-curve-body = Optional: teach the brightness you like in as many lighting conditions as you want
+calib-curve-prompt = • Set up the light, set the screen to the brightness you want with the hardware keys and press Enter. Type q and Enter to finish.
+calib-curve-point = → Point { $n }: light { $luma } → { $percent } % brightness
+calib-curve-saved = ✓ Saved a { $points }-point brightness curve ([curve])
+calib-curve-skipped = Fewer than two points recorded; the brightness curve stays as it was.
+
+## Configurator (TUI)
+
+tui-title = Smart Brightness Configurator
+tui-settings = Settings
+tui-item-mode = Daemon Mode
+tui-item-run-duration = Run Duration (Boot/Interval)
+tui-item-pause-interval = Pause Interval (Interval)
+tui-item-interval-boot = Interval Mode at Startup
+tui-item-min-brightness = Min Brightness
+tui-item-max-brightness = Max Brightness
+tui-item-smoothing = Smoothing Factor
+tui-item-half-precision = Half Precision (faster capture)
+tui-item-save = Save & Exit
+tui-item-cancel = Cancel
+tui-help = Press 'Enter' to edit, 'h' for history, 'c' for curve, 'd' for displays, 'q' to quit
+tui-editing-help = Editing... Press Enter to confirm, Esc to cancel
+tui-editing = EDITING: { $input } (Current: { $current })
+tui-editing-cancelled = Editing cancelled
+tui-invalid-mode = Invalid mode! Use: boot, interval, realtime
+tui-invalid-value = Invalid value '{ $input }'
+tui-invalid-setting = Not applied: { $error }
+tui-value-updated = Value updated. Don't forget to 'Save & Exit'
+tui-save-error = Error saving: { $error }
+tui-managed = managed
+tui-on = on
+tui-off = off
+tui-managed-locked = This setting is set by your administrator in { $path }
+tui-history-help = ←/→ pan, +/- zoom, 'r' reload, Esc back
+tui-history-error = Cannot read history: { $error }
+tui-history-title = History – last { $count } samples, window { $hours }h
+tui-history-hours = hours
+tui-history-now = now
+tui-series-luma = luma
+tui-series-brightness = brightness
+tui-series-manual = manual
+tui-curve-help = Tab select, arrows move (Shift = coarse), 'a' add, 'd' delete, 'r' reset, Enter apply, Esc discard
+tui-curve-title = Curve – anchor { $index }/{ $count }: luma { $luma } → { $brightness }% (level { $level })
+tui-curve-title-nits = Curve – anchor { $index }/{ $count }: luma { $luma } → { $nits } nits (level { $level })
+tui-curve-series = curve
+tui-curve-anchors = anchors
+tui-curve-luma-axis = ambient luma
+tui-curve-discarded = Curve changes discarded
+tui-displays-title = Displays – [x] follows the primary display
+tui-displays-help = ↑/↓ select, Space link/unlink, Enter settings, 'x' forget, Esc back
+tui-displays-none = No backlight devices found
+tui-displays-missing = not detected
+tui-displays-not-linked = not linked
+tui-displays-page-title = Display { $name }
+tui-displays-page-help = ↑/↓ select, Enter edit (toggles Enabled), Esc back
+tui-displays-editing = EDITING: { $input } – Enter to confirm, Esc to cancel
+tui-displays-invalid = Invalid value: { $error }
+tui-displays-enabled = Enabled
+tui-displays-multiplier = Multiplier
+tui-displays-offset = Offset (%)
+tui-displays-min = Min (%)
+tui-displays-max = Max (%)
+
+## Status output
+
+status-transition-start = → Brightness { $from } → { $to } (normalized { $luma })
+status-transition-start-lux = → Brightness { $from } → { $to } (normalized { $luma }, ≈{ $lux } lx)
+status-transition-done = ✓ Brightness { $from } → { $to } in { $secs } s
+notify-sensor-failing = Ambient sensor not working
+notify-sensor-failing-body = No reading for { $secs } s: { $error }
+notify-safe-mode = Brightness calibration no longer fits
+notify-safe-mode-body = Readings have been outside the calibrated range for { $minutes } min; following the full camera range for now. Run smart-brightness --calibrate to fix this.
+summary-title = Run summary:
+summary-runtime = runtime:            { $value }
+summary-changes = brightness changes: { $value }
+summary-luma = luma (normalized):  min { $min }  max { $max }  avg { $avg }
+summary-brightness = brightness:         min { $min }  max { $max }  avg { $avg }
+summary-lux = illuminance (≈lx):  min { $min }  max { $max }  avg { $avg }
+summary-capture-errors = capture errors:     { $value }
+summary-motion-skipped = skipped (motion): { $value }
+summary-writes = backlight writes:   { $value }
+
+camtest-title = Camera self-check
+camtest-auto = Auto exposure: luma { $luma } at { $exposure }
+camtest-no-control = ✗ The camera offers no manual exposure control; only auto-exposure can be used.
+camtest-range = Exposure control: { $min } – { $max } (default { $default })
+camtest-sweep-header = Luma at fixed exposure times:
+camtest-adjusted = (driver set { $value })
+camtest-not-monotonic = ✗ Not monotonic: luma fell from { $from } to { $to } at { $value }
+camtest-flat = ✗ Luma barely changes with exposure; is the lens covered or the room dark?
+camtest-monotonic = ✓ Luma rises with exposure
+camtest-monotonic-saturated = ✓ Luma rises with exposure (clipped at the longest)
+camtest-lock-held = ✓ Exposure lock holds at { $value } (luma drift { $drift })
+camtest-lock-moved = ✗ Exposure lock not kept: the driver changed the exposure time at { $value }
+camtest-lock-drift = ✗ Luma drifts by { $drift } with the exposure locked; the camera may still adjust gain
+camtest-lock-auto = ✗ The driver went back to auto-exposure
+camtest-restore-failed = ⚠ Could not switch auto-exposure back on: { $error }
+camtest-verdict-ok = The camera responds sensibly to light.
+camtest-verdict-bad = The camera's response is unreliable; readings may jump or lag.
+
+circadian-header = Ambient light by hour (last { $days } days):
+circadian-current = Current:   day from { $day }:00 ×{ $boost }, night from { $night }:00 ×{ $dim }
+circadian-suggested = Suggested: day from { $day }:00 ×{ $boost }, night from { $night }:00 ×{ $dim }
+circadian-no-suggestion = No suggestion yet: { $hours } of 24 hours have enough history, or light barely changes over the day.
+circadian-apply-hint = Run with --apply to save it, or set circadian_auto_tune = true.
+circadian-applied = ✓ Saved to the config file.
@@ -0,0 +1,197 @@
+// src/ab_test.rs
+//! `--ab-test a.toml b.toml --interval 10m`: alternate between two sets of
+//! settings, each laid over the config like a profile, and tag the history
+//! samples with the file name of the set in use. Over a workday both see
+//! much the same light, so how often each got corrected by hand says which
+//! one fits better.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::history::{self, HistoryRecord};
+
+struct Variant {
+    name: String,
+    path: PathBuf,
+    cfg: Config,
+}
+
+pub struct AbTest {
+    variants: [Variant; 2],
+    interval: Duration,
+    current: usize,
+    since: Instant,
+    /// Start of the test, for the summary.
+    started: i64,
+}
+
+impl AbTest {
+    /// Both sets are applied to `cfg` and validated up front, so a typo
+    /// doesn't surface an hour in.
+    pub fn load(cfg: &Config, paths: &[PathBuf], interval: Duration) -> Result<Self, String> {
+        let [a, b] = paths else {
+            return Err("--ab-test takes two config files".into());
+        };
+        let (a, b) = (Self::variant(cfg, a)?, Self::variant(cfg, b)?);
+        if a.name == b.name {
+            return Err(format!("--ab-test files need different names, both are '{}'", a.name));
+        }
+        if !cfg.history_enabled {
+            warn!(
+                target: "daemon",
+                "history_enabled is off: the A/B test alternates, but records nothing to compare"
+            );
+        }
+        Ok(Self {
+            variants: [a, b],
+            interval,
+            current: 0,
+            since: Instant::now(),
+            started: Utc::now().timestamp(),
+        })
+    }
+
+    fn variant(cfg: &Config, path: &Path) -> Result<Variant, String> {
+        let variant = cfg.with_file(path)?;
+        variant
+            .validate()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let name = path
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Ok(Variant {
+            name,
+            path: path.to_path_buf(),
+            cfg: variant,
+        })
+    }
+
+    pub fn current(&self) -> (&str, &Config) {
+        let variant = &self.variants[self.current];
+        (&variant.name, &variant.cfg)
+    }
+
+    /// Lay both sets over `base` again, after the config file changed or a
+    /// profile was picked; the current one in its new form. On an error
+    /// the sets stay as they were.
+    pub fn rebase(&mut self, base: &Config) -> Result<(&str, &Config), String> {
+        let [a, b] = &self.variants;
+        let variants = [Self::variant(base, &a.path)?, Self::variant(base, &b.path)?];
+        self.variants = variants;
+        Ok(self.current())
+    }
+
+    /// The other set, once the current one has had its interval.
+    pub fn advance(&mut self) -> Option<(&str, &Config)> {
+        if self.since.elapsed() < self.interval {
+            return None;
+        }
+        self.current = 1 - self.current;
+        self.since = Instant::now();
+        Some(self.current())
+    }
+
+    /// Log what history recorded for each set since the test started.
+    pub fn log_summary(&self) {
+        let records = match history::load_since(&history::default_path(), self.started) {
+            Ok(records) => records,
+            Err(e) => {
+                warn!(target: "daemon", "A/B test: cannot read history: {}", e);
+                return;
+            }
+        };
+        let summary = summarize(&records);
+        for variant in &self.variants {
+            let Some(tally) = summary.get(variant.name.as_str()) else {
+                info!(target: "daemon", "A/B test {}: no samples recorded", variant.name);
+                continue;
+            };
+            info!(
+                target: "daemon",
+                "A/B test {}: {} samples, {} ({:.0}%) corrected by hand",
+                variant.name,
+                tally.samples,
+                tally.manual,
+                tally.manual as f32 / tally.samples as f32 * 100.0
+            );
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Tally {
+    samples: usize,
+    manual: usize,
+}
+
+fn summarize(records: &[HistoryRecord]) -> BTreeMap<&str, Tally> {
+    let mut summary: BTreeMap<&str, Tally> = BTreeMap::new();
+    for record in records {
+        let Some(variant) = record.variant.as_deref() else {
+            continue;
+        };
+        let tally = summary.entry(variant).or_default();
+        tally.samples += 1;
+        if record.is_manual() {
+            tally.manual += 1;
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(variant: Option<&str>, brightness: u32, actual: u32) -> HistoryRecord {
+        HistoryRecord {
+            t: 0,
+            luma: 0.5,
+            brightness,
+            actual: Some(actual),
+            variant: variant.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn tallies_manual_corrections_per_variant() {
+        let records = [
+            record(Some("a"), 100, 100),
+            record(Some("a"), 100, 140),
+            record(Some("b"), 100, 101),
+            record(None, 100, 20),
+        ];
+        let summary = summarize(&records);
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary["a"], Tally { samples: 2, manual: 1 });
+        assert_eq!(summary["b"], Tally { samples: 1, manual: 0 });
+    }
+
+    #[test]
+    fn sets_are_laid_over_a_changed_config_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("calm.toml"), dir.path().join("quick.toml"));
+        std::fs::write(&a, "smoothing_factor = 0.1\n").unwrap();
+        std::fs::write(&b, "smoothing_factor = 0.6\n").unwrap();
+        let mut test = AbTest::load(&Config::default(), &[a, b], Duration::from_secs(600)).unwrap();
+        let changed = Config {
+            real_max_brightness: 50,
+            ..Config::default()
+        };
+        let (name, cfg) = test.rebase(&changed).unwrap();
+        assert_eq!(name, "calm");
+        assert_eq!((cfg.smoothing_factor, cfg.real_max_brightness), (0.1, 50));
+        let broken = Config {
+            real_max_brightness: 0,
+            ..Config::default()
+        };
+        assert!(test.rebase(&broken).is_err());
+        assert_eq!(test.current().1.real_max_brightness, 50);
+    }
+}
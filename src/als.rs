@@ -0,0 +1,138 @@
+// src/als.rs
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::sensor::{AmbientSensor, Reading, Unit};
+
+/// Relative to the sysfs root.
+const IIO_DEVICES: &str = "bus/iio/devices";
+
+/// Illuminance channel prefixes; some drivers number the channel.
+const CHANNELS: [&str; 2] = ["in_illuminance", "in_illuminance0"];
+
+/// An IIO ambient light sensor exposed under `<sysfs_root>/bus/iio/devices`.
+///
+/// `_input` is in lux already. `_raw` is in device counts, which the IIO ABI
+/// turns into lux as `(raw + offset) * scale` where the driver has the
+/// `_scale` (and `_offset`) attributes; without them the counts are passed
+/// on as they are.
+pub struct AmbientLightSensor {
+    /// Channel file, `…_input` or `…_raw`.
+    path: PathBuf,
+    /// `…_scale` and `…_offset` next to a raw channel. Read with every
+    /// reading: auto-ranging drivers change the scale as the light does.
+    scale: Option<PathBuf>,
+    offset: Option<PathBuf>,
+}
+
+impl AmbientLightSensor {
+    /// Find the first IIO device with an illuminance channel, preferring
+    /// one that reports lux.
+    pub fn detect(sysfs_root: &Path) -> Option<Self> {
+        let dir = sysfs_root.join(IIO_DEVICES);
+        let mut devices: Vec<PathBuf> = fs::read_dir(&dir).ok()?.flatten().map(|e| e.path()).collect();
+        devices.sort();
+        let find = |suffix: &str| {
+            devices.iter().find_map(|d| {
+                CHANNELS
+                    .iter()
+                    .map(|c| (d.join(format!("{}_{}", c, suffix)), d, *c))
+                    .find(|(p, _, _)| p.exists())
+            })
+        };
+        if let Some((path, _, _)) = find("input") {
+            return Some(Self {
+                path,
+                scale: None,
+                offset: None,
+            });
+        }
+        let (path, device, channel) = find("raw")?;
+        let attribute = |name: &str| {
+            [format!("{}_{}", channel, name), format!("in_illuminance_{}", name)]
+                .into_iter()
+                .map(|file| device.join(file))
+                .find(|p| p.exists())
+        };
+        Some(Self {
+            scale: attribute("scale"),
+            offset: attribute("offset"),
+            path,
+        })
+    }
+}
+
+impl AmbientSensor for AmbientLightSensor {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn read(&mut self) -> Result<Reading, Box<dyn Error>> {
+        let value = read_value(&self.path)?;
+        let (value, unit) = if self.path.to_string_lossy().ends_with("_input") {
+            (value, Unit::Lux)
+        } else if let Some(scale) = &self.scale {
+            let offset = self.offset.as_deref().map(read_value).transpose()?.unwrap_or(0.0);
+            ((value + offset) * read_value(scale)?, Unit::Lux)
+        } else {
+            (value, Unit::Raw)
+        };
+        Ok(Reading {
+            value,
+            unit,
+            timestamp: Instant::now(),
+        })
+    }
+}
+
+fn read_value(path: &Path) -> Result<f32, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    text.trim()
+        .parse()
+        .map_err(|e| format!("{}: '{}': {}", path.display(), text.trim(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join(IIO_DEVICES).join("iio:device0");
+        fs::create_dir_all(&dir).unwrap();
+        for (name, value) in files {
+            fs::write(dir.join(name), value).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn raw_counts_are_scaled_to_lux() {
+        let root = device(&[
+            ("in_illuminance_raw", "200\n"),
+            ("in_illuminance_scale", "0.5\n"),
+            ("in_illuminance_offset", "-10\n"),
+        ]);
+        let reading = AmbientLightSensor::detect(root.path()).unwrap().read().unwrap();
+        assert_eq!(reading.unit, Unit::Lux);
+        assert_eq!(reading.value, 95.0);
+    }
+
+    #[test]
+    fn raw_counts_without_scale_stay_raw() {
+        let root = device(&[("in_illuminance0_raw", "200\n")]);
+        let reading = AmbientLightSensor::detect(root.path()).unwrap().read().unwrap();
+        assert_eq!(reading.unit, Unit::Raw);
+        assert_eq!(reading.value, 200.0);
+    }
+
+    #[test]
+    fn input_channel_is_preferred() {
+        let root = device(&[("in_illuminance_raw", "200\n"), ("in_illuminance_input", "42.5\n")]);
+        let reading = AmbientLightSensor::detect(root.path()).unwrap().read().unwrap();
+        assert_eq!(reading.unit, Unit::Lux);
+        assert_eq!(reading.value, 42.5);
+    }
+}
@@ -0,0 +1,126 @@
+// src/audit.rs
+//! A record of every backlight write and why it happened (`audit_log`).
+//!
+//! One line per value actually written, apart from the general log and
+//! whatever its level:
+//!
+//! ```text
+//! 2026-10-17T14:32:05.123+02:00 old=180 new=176 target=150 reason=ambient luma=0.412 lux=120
+//! ```
+//!
+//! `reason` is what set the target the write is heading for: `ambient`
+//! (the room changed), `circadian` (the time of day did), `rule`, `manual`
+//! (a boost or ramp from `ctl` or the dashboard), `ramp` (`[[ramps]]`) or
+//! `override` (the override file).
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, SecondsFormat};
+use tracing::warn;
+
+use crate::config::Config;
+
+const AUDIT_FILE: &str = "audit.log";
+/// Size past which the log is moved to `audit.log.1` when the daemon starts.
+const MAX_SIZE: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Ambient,
+    Circadian,
+    Rule,
+    Manual,
+    Ramp,
+    Override,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Ambient => "ambient",
+            Reason::Circadian => "circadian",
+            Reason::Rule => "rule",
+            Reason::Manual => "manual",
+            Reason::Ramp => "ramp",
+            Reason::Override => "override",
+        })
+    }
+}
+
+/// One write as recorded.
+pub struct Entry {
+    pub old: Option<u32>,
+    pub new: u32,
+    pub target: u32,
+    pub reason: Reason,
+    pub luma: Option<f32>,
+    pub lux: Option<f32>,
+}
+
+pub fn default_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("smart-brightness")
+        .join(AUDIT_FILE)
+}
+
+pub struct AuditLog {
+    writer: Option<BufWriter<File>>,
+}
+
+impl AuditLog {
+    pub fn from_config(cfg: &Config) -> Self {
+        if !cfg.audit_log {
+            return Self { writer: None };
+        }
+        let path = cfg.audit_log_path.clone().unwrap_or_else(default_path);
+        match open(&path) {
+            Ok(file) => Self {
+                writer: Some(BufWriter::new(file)),
+            },
+            Err(e) => {
+                warn!(target: "daemon", "Audit log disabled: cannot open {}: {}", path.display(), e);
+                Self { writer: None }
+            }
+        }
+    }
+
+    pub fn record(&mut self, entry: Entry) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let mut line = format!(
+            "{} old={} new={} target={} reason={}",
+            Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            entry.old.map_or("-".into(), |v| v.to_string()),
+            entry.new,
+            entry.target,
+            entry.reason
+        );
+        if let Some(luma) = entry.luma {
+            line.push_str(&format!(" luma={:.3}", luma));
+        }
+        if let Some(lux) = entry.lux {
+            line.push_str(&format!(" lux={:.0}", lux));
+        }
+        if let Err(e) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            warn!(target: "daemon", "Audit log disabled: write failed: {}", e);
+            self.writer = None;
+        }
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if fs::metadata(path).is_ok_and(|m| m.len() > MAX_SIZE) {
+        let mut old = path.as_os_str().to_owned();
+        old.push(".1");
+        fs::rename(path, old)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
@@ -1,73 +1,429 @@
 // src/backlight.rs
 use std::cell::Cell;
-use std::fs::File;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::error::Error;
+use std::io;
+use std::time::{Duration, Instant};
 
-use crate::config::autodetect_backlight_file;
+use tracing::{debug, info, trace, warn};
 
-fn read_u32_from<P: AsRef<Path>>(p: P) -> Option<u32> {
-    std::fs::read_to_string(p).ok()?.trim().parse::<u32>().ok()
+use crate::displays::{Backend, LinkedDisplay};
+use crate::integrations::{self, OutputWatch, SoftwareDimmer};
+
+mod composite;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod command;
+#[cfg(target_os = "linux")]
+mod ddc;
+#[cfg(target_os = "linux")]
+mod logind;
+#[cfg(target_os = "linux")]
+mod sysfs;
+#[cfg(target_os = "windows")]
+mod wmi;
+#[cfg(target_os = "linux")]
+mod writer;
+
+use composite::Composite;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+use command::Device;
+#[cfg(target_os = "linux")]
+use sysfs::Device;
+#[cfg(target_os = "windows")]
+use wmi::Device;
+
+/// Something brightness levels can be written to: a panel backlight, a
+/// monitor over DDC/CI, a keyboard LED or the picture itself. Levels run
+/// from 0 to `max_value`.
+pub trait BrightnessOutput {
+    fn max_value(&self) -> u32;
+
+    fn set(&self, value: u32) -> io::Result<()>;
+
+    /// The level set now, if it can be read back.
+    fn get(&self) -> Option<u32>;
+
+    /// The level actually shown, where the hardware reports it separately
+    /// (sysfs `actual_brightness`).
+    fn actual(&self) -> Option<u32> {
+        self.get()
+    }
+
+    fn name(&self) -> String;
+
+    /// Where `actual` reads from, for display.
+    fn actual_source(&self) -> String {
+        self.name()
+    }
+
+    /// Whether the display is powered on (not DPMS-off or blanked), if
+    /// the output can tell.
+    fn powered(&self) -> Option<bool> {
+        None
+    }
 }
 
-fn write_u32_to<P: AsRef<Path>>(p: P, v: u32) -> std::io::Result<()> {
-    let mut f = File::create(p)?;
-    write!(f, "{}", v)
+/// A software dimmer as an output. The picture can't be read back; it
+/// reports the last level written, or full brightness before the first
+/// write.
+struct Picture {
+    dimmer: Box<dyn SoftwareDimmer>,
+    last_value: Cell<Option<u32>>,
+}
+
+impl Picture {
+    fn boxed(dimmer: Box<dyn SoftwareDimmer>) -> Box<dyn BrightnessOutput> {
+        Box::new(Self {
+            dimmer,
+            last_value: Cell::new(None),
+        })
+    }
+}
+
+impl BrightnessOutput for Picture {
+    fn max_value(&self) -> u32 {
+        self.dimmer.max_value()
+    }
+
+    fn set(&self, value: u32) -> io::Result<()> {
+        self.dimmer.set(value)?;
+        self.last_value.set(Some(value));
+        Ok(())
+    }
+
+    fn get(&self) -> Option<u32> {
+        Some(self.last_value.get().unwrap_or(self.dimmer.max_value()))
+    }
+
+    fn name(&self) -> String {
+        self.dimmer.name()
+    }
+}
+
+/// Picture dimming below the backlight floor (`pixel_dimming`): the lowest
+/// `levels` of the range keep the backlight at `floor` and dim the picture
+/// from full down to `min_factor` instead.
+struct PixelStage {
+    dimmer: Box<dyn SoftwareDimmer>,
+    floor: u32,
+    levels: u32,
+    min_factor: f32,
+    last_level: Cell<Option<u32>>,
+}
+
+impl PixelStage {
+    /// Hardware level and picture brightness factor for logical level `v`.
+    fn split(&self, v: u32) -> (u32, f32) {
+        if v >= self.floor + self.levels {
+            return (v - self.levels, 1.0);
+        }
+        let t = v.saturating_sub(self.floor) as f32 / self.levels as f32;
+        (self.floor, self.min_factor + (1.0 - self.min_factor) * t)
+    }
+
+    fn set_factor(&self, factor: f32) -> std::io::Result<()> {
+        let span = 1.0 - integrations::MIN_DIM_FACTOR;
+        let level = ((factor - integrations::MIN_DIM_FACTOR) / span * self.dimmer.max_value() as f32)
+            .round()
+            .clamp(0.0, self.dimmer.max_value() as f32) as u32;
+        if self.last_level.get() == Some(level) {
+            return Ok(());
+        }
+        self.dimmer.set(level)?;
+        self.last_level.set(Some(level));
+        trace!(target: "backlight", "picture at {:.0}% via {}", factor * 100.0, self.dimmer.name());
+        Ok(())
+    }
 }
 
 pub struct Backlight {
-    pub path: PathBuf,
+    output: Composite,
+    pixel: Option<PixelStage>,
+    /// Top of the logical range: the hardware maximum plus the pixel
+    /// dimming levels, if any.
     pub max_value: u32,
-    actual_path: Option<PathBuf>,
+    /// Hardware value no write goes below, whatever the level asked for.
+    floor: u32,
     last_value: Cell<Option<u32>>,
+    writes: Cell<u64>,
+    /// Some EC-driven backlights misbehave when written too often; values
+    /// requested faster than this, or closer than `min_change` to the last
+    /// written one, are held back until `flush`.
+    min_interval: Duration,
+    min_change: u32,
+    last_write: Cell<Option<Instant>>,
+    pending: Cell<Option<u32>>,
+    wake: Option<WakeWatch>,
+    hotplug: Option<Hotplug>,
 }
 
-impl Backlight {
-    pub fn resolve(_cfg: &crate::config::Config) -> Result<Self, Box<dyn std::error::Error>> {
-        let max_path =
-            autodetect_backlight_file("max_brightness").ok_or("cannot find max_brightness")?;
+/// Linked displays following outputs as they come and go; the config is
+/// kept for opening them.
+struct Hotplug {
+    outputs: OutputWatch,
+    cfg: crate::config::Config,
+}
+
+/// How often display power is checked for `backlight_restore_on_wake`.
+const POWER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long after power-on a level the driver changed still counts as its
+/// reset rather than somebody else's.
+const WAKE_WINDOW: Duration = Duration::from_secs(3);
 
-        let path = autodetect_backlight_file("brightness").ok_or("cannot find brightness")?;
+/// Notices the display coming back on (`backlight_restore_on_wake`).
+struct WakeWatch {
+    last_poll: Cell<Option<Instant>>,
+    powered: Cell<Option<bool>>,
+    powered_on: Cell<Option<Instant>>,
+}
 
-        let max_value = read_u32_from(&max_path).ok_or("cannot read max_brightness")?;
-        let actual_path = path
-            .parent()
-            .map(|p| p.join("actual_brightness"))
-            .filter(|p| p.exists());
+impl Backlight {
+    pub fn resolve(cfg: &crate::config::Config) -> Result<Self, Box<dyn Error>> {
+        #[cfg(target_os = "linux")]
+        let device = {
+            let exclude: Vec<&str> = cfg
+                .displays
+                .iter()
+                .filter(|d| matches!(d.backend, Backend::Sysfs | Backend::Logind))
+                .map(|d| d.name.as_str())
+                .collect();
+            Device::open(&cfg.sysfs_root(), &exclude)
+        };
+        #[cfg(not(target_os = "linux"))]
+        let device = Device::open();
+        let (primary, pixel) = match device {
+            Ok(device) => (Box::new(device) as Box<dyn BrightnessOutput>, pixel_stage(cfg)),
+            Err(err) => match integrations::software_dimmer(cfg) {
+                Some(dimmer) => {
+                    info!(
+                        target: "backlight",
+                        "No backlight device ({}); dimming in software with {}",
+                        err,
+                        dimmer.name()
+                    );
+                    // The picture is all there is to dim already.
+                    (Picture::boxed(dimmer), None)
+                }
+                None => return Err(err),
+            },
+        };
+        let mut output = Composite::new(primary);
+        for display in cfg.displays.iter().filter(|display| display.enabled) {
+            match open_follower(cfg, display) {
+                Ok(follower) => {
+                    info!(
+                        target: "backlight",
+                        "Linked display {} ({}): primary × {} {:+}%, {}–{}%",
+                        display.name,
+                        follower.name(),
+                        display.multiplier,
+                        display.offset,
+                        display.min,
+                        display.max
+                    );
+                    output.push(display.clone(), Some(follower));
+                }
+                Err(e) => {
+                    warn!(
+                        target: "backlight",
+                        "Linked display {} not attached: {}",
+                        display.name,
+                        e
+                    );
+                    output.push(display.clone(), None);
+                }
+            }
+        }
+        let floor = (cfg.backlight_floor_percent / 100.0 * output.max_value() as f32).ceil() as u32;
         Ok(Self {
-            path,
-            max_value,
-            actual_path,
+            max_value: output.max_value() + pixel.as_ref().map_or(0, |p| p.levels),
+            floor,
+            pixel,
+            output,
             last_value: Cell::new(None),
+            writes: Cell::new(0),
+            min_interval: Duration::from_millis(cfg.backlight_min_write_interval_ms),
+            min_change: cfg.backlight_min_change.max(1),
+            last_write: Cell::new(None),
+            pending: Cell::new(None),
+            wake: cfg.backlight_restore_on_wake.then(|| WakeWatch {
+                last_poll: Cell::new(None),
+                powered: Cell::new(None),
+                powered_on: Cell::new(None),
+            }),
+            hotplug: integrations::watch_outputs(cfg).map(|outputs| Hotplug {
+                outputs,
+                cfg: cfg.clone(),
+            }),
         })
     }
 
+    /// Attach and detach linked displays after outputs changed.
+    pub fn hotplug(&self) {
+        let Some(hotplug) = &self.hotplug else {
+            return;
+        };
+        if let Some(outputs) = hotplug.outputs.changed() {
+            self.output
+                .refresh(&outputs, |display| open_follower(&hotplug.cfg, display));
+        }
+    }
+
+    /// The display was off when its power state was last looked at by
+    /// `woke`.
+    pub fn display_off(&self) -> bool {
+        self.wake.as_ref().is_some_and(|wake| wake.powered.get() == Some(false))
+    }
+
+    /// True once the display has come back on, and again while the driver
+    /// moves the level off the last one written shortly after: some reset
+    /// to full brightness on every DPMS wake.
+    pub fn woke(&self) -> bool {
+        let Some(wake) = &self.wake else {
+            return false;
+        };
+        let now = Instant::now();
+        if wake
+            .last_poll
+            .get()
+            .is_some_and(|t| now.duration_since(t) < POWER_POLL_INTERVAL)
+        {
+            return false;
+        }
+        wake.last_poll.set(Some(now));
+        let Some(powered) = self.output.powered() else {
+            return false;
+        };
+        if wake.powered.replace(Some(powered)) == Some(false) && powered {
+            wake.powered_on.set(Some(now));
+            return true;
+        }
+        if wake.powered_on.get().is_none_or(|t| now.duration_since(t) >= WAKE_WINDOW) {
+            return false;
+        }
+        let (Some(v), Some(hw)) = (self.last_value.get(), self.output.get()) else {
+            return false;
+        };
+        let expected = self.pixel.as_ref().map_or(v, |pixel| pixel.split(v).0);
+        hw != expected.max(self.floor)
+    }
+
+    /// Write `value` now, whatever was written last and however recently:
+    /// the device may no longer be at the level on record.
+    pub fn reassert(&self, value: u32) -> std::io::Result<()> {
+        self.last_value.set(None);
+        self.pending.set(None);
+        self.write(value.clamp(0, self.max_value))
+    }
+
+    /// Write `value`, or hold it back if it comes too soon after the last
+    /// write or is too small a change; a later `set` or `flush` supersedes it.
     pub fn set(&self, value: u32) -> std::io::Result<()> {
         let v = value.clamp(0, self.max_value);
         if self.last_value.get() == Some(v) {
+            self.pending.set(None);
+            return Ok(());
+        }
+        let small = self
+            .last_value
+            .get()
+            .is_some_and(|last| last.abs_diff(v) < self.min_change);
+        if small || self.too_soon() {
+            self.pending.set(Some(v));
             return Ok(());
         }
-        let r = write_u32_to(&self.path, v);
-        if r.is_ok() {
-            self.last_value.set(Some(v));
+        self.write(v)
+    }
+
+    /// Write a held-back value once the minimum interval allows it. Call
+    /// when no further `set` is coming, so the last value isn't lost.
+    pub fn flush(&self) -> std::io::Result<()> {
+        match self.pending.get() {
+            Some(v) if !self.too_soon() => self.write(v),
+            _ => Ok(()),
+        }
+    }
+
+    fn too_soon(&self) -> bool {
+        self.last_write
+            .get()
+            .is_some_and(|t| t.elapsed() < self.min_interval)
+    }
+
+    fn write(&self, v: u32) -> std::io::Result<()> {
+        let (hw, factor) = match &self.pixel {
+            Some(pixel) => pixel.split(v),
+            None => (v, 1.0),
+        };
+        // Whatever the mapping asked for: some panels flicker below it.
+        let r = self.output.set(hw.max(self.floor));
+        // The backlight write decides the result; dimming errors are only logged.
+        if let Some(pixel) = &self.pixel
+            && let Err(e) = pixel.set_factor(factor)
+        {
+            debug!(target: "backlight", "picture dimming failed: {}", e);
+        }
+        match &r {
+            Ok(()) => {
+                self.last_value.set(Some(v));
+                self.pending.set(None);
+                self.last_write.set(Some(Instant::now()));
+                self.writes.set(self.writes.get() + 1);
+                trace!(target: "backlight", "wrote {} to {}", v, self.name());
+            }
+            Err(e) => debug!(target: "backlight", "write {} failed: {}", v, e),
         }
         r
     }
 
+    /// The last value written to the device, held-back ones excluded.
+    pub fn written(&self) -> Option<u32> {
+        self.last_value.get()
+    }
+
+    /// Number of values actually written to the device (deduplicated writes excluded).
+    pub fn writes(&self) -> u64 {
+        self.writes.get()
+    }
+
+    pub fn name(&self) -> String {
+        self.output.name()
+    }
+
+    /// Lowest hardware value ever written (`backlight_floor_percent`).
+    pub fn floor(&self) -> u32 {
+        self.floor
+    }
+
+    /// Levels below the backlight floor that dim the picture instead; 0
+    /// without `pixel_dimming`.
+    pub fn pixel_levels(&self) -> u32 {
+        self.pixel.as_ref().map_or(0, |p| p.levels)
+    }
+
     pub fn current(&self) -> Option<u32> {
-        read_u32_from(&self.path)
+        self.output.get().map(|hw| self.logical(hw))
     }
 
     pub fn actual(&self) -> Option<u32> {
-        if let Some(p) = &self.actual_path {
-            read_u32_from(p)
-        } else {
-            self.current()
+        self.output.actual().map(|hw| self.logical(hw))
+    }
+
+    /// Logical level for a hardware reading. The picture stage can't be
+    /// read back: at the floor the last level written stands in, and
+    /// before that the picture counts as undimmed.
+    fn logical(&self, hw: u32) -> u32 {
+        let Some(pixel) = &self.pixel else {
+            return hw;
+        };
+        match self.last_value.get() {
+            Some(v) if hw <= pixel.floor && v < pixel.floor + pixel.levels => v,
+            _ => hw + pixel.levels,
         }
     }
 
-    pub fn actual_path(&self) -> Option<&Path> {
-        self.actual_path.as_deref()
+    /// Where `actual` reads from, for display.
+    pub fn actual_source(&self) -> String {
+        self.output.actual_source()
     }
 
     /// Get the hardware minimum brightness (typically 0)
@@ -80,3 +436,58 @@ impl Backlight {
         (self.min_value(), self.max_value)
     }
 }
+
+fn pixel_stage(cfg: &crate::config::Config) -> Option<PixelStage> {
+    if !cfg.pixel_dimming {
+        return None;
+    }
+    let Some(dimmer) = integrations::picture_dimmer(cfg) else {
+        warn!(target: "backlight", "pixel_dimming is on, but this session can't dim the picture");
+        return None;
+    };
+    let range = cfg.real_max_brightness.saturating_sub(cfg.real_min_brightness) as f32;
+    let share = cfg.pixel_dimming_share;
+    let levels = ((range * share / (1.0 - share)).round() as u32).max(1);
+    info!(
+        target: "backlight",
+        "Below backlight level {}, dimming the picture down to {:.0}% with {}",
+        cfg.real_min_brightness,
+        cfg.pixel_dimming_min * 100.0,
+        dimmer.name()
+    );
+    Some(PixelStage {
+        dimmer,
+        floor: cfg.real_min_brightness,
+        levels,
+        min_factor: cfg.pixel_dimming_min,
+        last_level: Cell::new(None),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn open_follower(
+    cfg: &crate::config::Config,
+    display: &LinkedDisplay,
+) -> Result<Box<dyn BrightnessOutput>, Box<dyn Error>> {
+    let root = cfg.sysfs_root();
+    Ok(match display.backend {
+        Backend::Sysfs => Box::new(Device::open_named(&root, &display.name)?),
+        Backend::Logind => Box::new(Device::open_named(&root, &display.name)?.via_logind()),
+        Backend::Keyboard => Box::new(Device::open_keyboard(&root, &display.name)?),
+        Backend::Ddc => Box::new(ddc::Monitor::open(&display.name)?),
+        Backend::Xrandr => Picture::boxed(integrations::xrandr(&display.name)),
+    })
+}
+
+/// The platform tools address a single built-in panel only; the picture
+/// of an X11 output can still follow it.
+#[cfg(not(target_os = "linux"))]
+fn open_follower(
+    _cfg: &crate::config::Config,
+    display: &LinkedDisplay,
+) -> Result<Box<dyn BrightnessOutput>, Box<dyn Error>> {
+    match display.backend {
+        Backend::Xrandr => Ok(Picture::boxed(integrations::xrandr(&display.name))),
+        _ => Err("only the xrandr backend is supported outside Linux".into()),
+    }
+}
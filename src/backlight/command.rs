@@ -0,0 +1,117 @@
+// src/backlight/command.rs
+use std::error::Error;
+use std::io;
+use std::process::Command;
+
+use super::BrightnessOutput;
+
+/// A platform brightness utility. Levels are mapped onto `levels` integer
+/// steps so the rest of the daemon stays unchanged.
+struct Tool {
+    program: &'static str,
+    name: &'static str,
+    read_args: &'static [&'static str],
+    /// Output of `read_args` → brightness as a fraction in 0.0–1.0.
+    parse: fn(&str) -> Option<f32>,
+    /// Fraction in 0.0–1.0 → arguments that set it.
+    write_args: fn(f32) -> Vec<String>,
+    levels: u32,
+    install_hint: &'static str,
+}
+
+/// `brightness` wraps the private DisplayServices API.
+#[cfg(target_os = "macos")]
+const TOOL: Tool = Tool {
+    program: "brightness",
+    name: "built-in display",
+    read_args: &["-l"],
+    // `display 0: brightness 0.687500`
+    parse: |out| {
+        out.lines()
+            .find_map(|line| line.split_once("brightness ")?.1.trim().parse().ok())
+    },
+    write_args: |level| vec!["-m".into(), format!("{:.4}", level)],
+    levels: 1000,
+    install_hint: "brew install brightness",
+};
+
+/// backlight(8), FreeBSD 13 and later.
+#[cfg(target_os = "freebsd")]
+const TOOL: Tool = Tool {
+    program: "backlight",
+    name: "backlight(8)",
+    read_args: &["-q"],
+    // `75`
+    parse: |out| out.trim().parse::<f32>().ok().map(|p| p / 100.0),
+    write_args: |level| vec![format!("{:.0}", level * 100.0)],
+    levels: 100,
+    install_hint: "part of the base system since FreeBSD 13; load the backlight driver for your GPU",
+};
+
+/// wscons display controls.
+#[cfg(target_os = "openbsd")]
+const TOOL: Tool = Tool {
+    program: "wsconsctl",
+    name: "wscons display",
+    read_args: &["-n", "display.brightness"],
+    // `75.00%`
+    parse: |out| {
+        out.trim()
+            .trim_end_matches('%')
+            .parse::<f32>()
+            .ok()
+            .map(|p| p / 100.0)
+    },
+    write_args: |level| vec![format!("display.brightness={:.2}", level * 100.0)],
+    levels: 1000,
+    install_hint: "part of the base system; run as root or grant access to /dev/ttyC0",
+};
+
+/// The display driven through the platform's brightness utility.
+pub struct Device;
+
+impl Device {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        let dev = Device;
+        dev.get().ok_or_else(|| {
+            format!(
+                "cannot read display brightness with `{}` ({})",
+                TOOL.program, TOOL.install_hint
+            )
+        })?;
+        Ok(dev)
+    }
+}
+
+/// The panel has no separate "actual" readout, so `actual` is `get`.
+impl BrightnessOutput for Device {
+    fn max_value(&self) -> u32 {
+        TOOL.levels
+    }
+
+    fn get(&self) -> Option<u32> {
+        let out = Command::new(TOOL.program).args(TOOL.read_args).output().ok()?;
+        let level = (TOOL.parse)(&String::from_utf8_lossy(&out.stdout))?;
+        Some((level.clamp(0.0, 1.0) * TOOL.levels as f32).round() as u32)
+    }
+
+    fn set(&self, v: u32) -> io::Result<()> {
+        let level = v.min(TOOL.levels) as f32 / TOOL.levels as f32;
+        let status = Command::new(TOOL.program)
+            .args((TOOL.write_args)(level))
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("{} exited with {}", TOOL.program, status)))
+        }
+    }
+
+    fn name(&self) -> String {
+        TOOL.name.into()
+    }
+
+    fn actual_source(&self) -> String {
+        format!("`{} {}`", TOOL.program, TOOL.read_args.join(" "))
+    }
+}
@@ -0,0 +1,148 @@
+// src/backlight/composite.rs
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::io;
+
+use tracing::{debug, info, trace};
+
+use super::BrightnessOutput;
+use crate::displays::LinkedDisplay;
+use crate::integrations::Output;
+
+/// A display following the primary one (`[[displays]]`); detached while it
+/// couldn't be opened or its output is gone.
+struct Follower {
+    display: LinkedDisplay,
+    output: RefCell<Option<Box<dyn BrightnessOutput>>>,
+    last_value: Cell<Option<u32>>,
+}
+
+impl Follower {
+    fn follow(&self, primary: f32) {
+        let output = self.output.borrow();
+        let Some(output) = output.as_ref() else {
+            return;
+        };
+        let v = (self.display.fraction(primary) * output.max_value() as f32).round() as u32;
+        if self.last_value.get() == Some(v) {
+            return;
+        }
+        match output.set(v) {
+            Ok(()) => {
+                self.last_value.set(Some(v));
+                trace!(target: "backlight", "wrote {} to {}", v, output.name());
+            }
+            Err(e) => debug!(target: "backlight", "write {} to {} failed: {}", v, self.display.name, e),
+        }
+    }
+}
+
+/// The primary output and the ones following it. Reads and the level range
+/// are the primary's; each follower gets the primary's fraction of its own
+/// range through its `[[displays]]` mapping. A follower that fails to
+/// write is logged and doesn't fail the whole write.
+pub struct Composite {
+    primary: Box<dyn BrightnessOutput>,
+    followers: Vec<Follower>,
+    /// The primary's last fraction, for followers attached later.
+    fraction: Cell<Option<f32>>,
+}
+
+impl Composite {
+    pub fn new(primary: Box<dyn BrightnessOutput>) -> Self {
+        Self {
+            primary,
+            followers: Vec::new(),
+            fraction: Cell::new(None),
+        }
+    }
+
+    /// Add a follower; None leaves it detached until `refresh` opens it.
+    pub fn push(&mut self, display: LinkedDisplay, output: Option<Box<dyn BrightnessOutput>>) {
+        self.followers.push(Follower {
+            display,
+            output: RefCell::new(output),
+            last_value: Cell::new(None),
+        });
+    }
+
+    /// Follow a change in the compositor's `outputs`. A follower with an
+    /// `output` is attached while that output is connected and powered,
+    /// and detached otherwise. One without is opened again if it is
+    /// detached: any change may be a dock bringing its monitor.
+    pub fn refresh(
+        &self,
+        outputs: &[Output],
+        open: impl Fn(&LinkedDisplay) -> Result<Box<dyn BrightnessOutput>, Box<dyn Error>>,
+    ) {
+        for follower in &self.followers {
+            let display = &follower.display;
+            let lit = display.output.as_deref().is_none_or(|name| Output::lit(outputs, name));
+            let attached = follower.output.borrow().is_some();
+            if attached && !lit {
+                follower.output.replace(None);
+                follower.last_value.set(None);
+                info!(
+                    target: "backlight",
+                    "Linked display {} detached: its output is gone or off",
+                    display.name
+                );
+            } else if !attached && lit {
+                match open(display) {
+                    Ok(output) => {
+                        info!(
+                            target: "backlight",
+                            "Linked display {} attached ({})",
+                            display.name,
+                            output.name()
+                        );
+                        follower.output.replace(Some(output));
+                        if let Some(fraction) = self.fraction.get() {
+                            follower.follow(fraction);
+                        }
+                    }
+                    Err(e) => {
+                        debug!(target: "backlight", "linked display {} not there: {}", display.name, e)
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl BrightnessOutput for Composite {
+    fn max_value(&self) -> u32 {
+        self.primary.max_value()
+    }
+
+    fn set(&self, value: u32) -> io::Result<()> {
+        self.primary.set(value)?;
+        let max = self.primary.max_value();
+        let fraction = if max == 0 { 0.0 } else { value as f32 / max as f32 };
+        self.fraction.set(Some(fraction));
+        for follower in &self.followers {
+            follower.follow(fraction);
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<u32> {
+        self.primary.get()
+    }
+
+    fn actual(&self) -> Option<u32> {
+        self.primary.actual()
+    }
+
+    fn name(&self) -> String {
+        self.primary.name()
+    }
+
+    fn actual_source(&self) -> String {
+        self.primary.actual_source()
+    }
+
+    fn powered(&self) -> Option<bool> {
+        self.primary.powered()
+    }
+}
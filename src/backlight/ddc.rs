@@ -0,0 +1,82 @@
+// src/backlight/ddc.rs
+use std::cell::Cell;
+use std::error::Error;
+use std::io;
+use std::process::Command;
+
+use super::BrightnessOutput;
+
+/// VCP feature code for luminance.
+const VCP_BRIGHTNESS: &str = "10";
+
+/// An external monitor reached over DDC/CI with `ddcutil`, for monitors
+/// the ddcci kernel driver doesn't bind to. Each call takes a good part of
+/// a second, so reads report the last level written once there is one.
+pub struct Monitor {
+    display: String,
+    max_value: u32,
+    last_value: Cell<Option<u32>>,
+}
+
+impl Monitor {
+    pub fn open(display: &str) -> Result<Self, Box<dyn Error>> {
+        let (current, max_value) = read_vcp(display)
+            .map_err(|e| format!("ddcutil display {}: {}", display, e))?;
+        if max_value == 0 {
+            return Err(format!("ddcutil display {} reports no brightness range", display).into());
+        }
+        Ok(Self {
+            display: display.to_string(),
+            max_value,
+            last_value: Cell::new(Some(current)),
+        })
+    }
+}
+
+/// `ddcutil getvcp 10 --brief` prints `VCP 10 C <current> <max>`.
+fn read_vcp(display: &str) -> io::Result<(u32, u32)> {
+    let out = Command::new("ddcutil")
+        .args(["--display", display, "getvcp", VCP_BRIGHTNESS, "--brief"])
+        .output()?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let fields: Vec<&str> = stdout.split_whitespace().collect();
+    match fields.as_slice() {
+        ["VCP", _, "C", current, max, ..] => match (current.parse(), max.parse()) {
+            (Ok(current), Ok(max)) => Ok((current, max)),
+            _ => Err(io::Error::other(format!("unexpected output '{}'", stdout.trim()))),
+        },
+        _ => Err(io::Error::other(format!("unexpected output '{}'", stdout.trim()))),
+    }
+}
+
+impl BrightnessOutput for Monitor {
+    fn max_value(&self) -> u32 {
+        self.max_value
+    }
+
+    fn get(&self) -> Option<u32> {
+        self.last_value
+            .get()
+            .or_else(|| read_vcp(&self.display).ok().map(|(current, _)| current))
+    }
+
+    fn set(&self, v: u32) -> io::Result<()> {
+        let v = v.min(self.max_value);
+        let status = Command::new("ddcutil")
+            .args(["--display", &self.display, "setvcp", VCP_BRIGHTNESS, &v.to_string()])
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("ddcutil setvcp exited with {}", status)));
+        }
+        self.last_value.set(Some(v));
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!("ddcutil display {}", self.display)
+    }
+}
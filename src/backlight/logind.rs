@@ -0,0 +1,28 @@
+// src/backlight/logind.rs
+use std::io;
+use std::process::Command;
+
+/// Ask logind to set the level of `device` in `subsystem` (`backlight` or
+/// `leds`); it allows this for the active session's user without write
+/// access to sysfs.
+pub fn set_brightness(subsystem: &str, device: &str, v: u32) -> io::Result<()> {
+    let out = Command::new("busctl")
+        .args([
+            "call",
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            "org.freedesktop.login1.Session",
+            "SetBrightness",
+            "ssu",
+            subsystem,
+            device,
+            &v.to_string(),
+        ])
+        .output()?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        Err(io::Error::other(format!("logind SetBrightness: {}", stderr.trim())))
+    }
+}
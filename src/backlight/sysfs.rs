@@ -0,0 +1,264 @@
+// src/backlight/sysfs.rs
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use super::writer::Writer;
+use super::{logind, BrightnessOutput};
+
+fn read_u32_from<P: AsRef<Path>>(p: P) -> Option<u32> {
+    fs::read_to_string(p).ok()?.trim().parse::<u32>().ok()
+}
+
+fn write_u32_to<P: AsRef<Path>>(p: P, v: u32) -> io::Result<()> {
+    let mut f = File::create(p)?;
+    write!(f, "{}", v)
+}
+
+/// The first device under `class/backlight` not named in `exclude`.
+fn autodetect_backlight_dir(sysfs_root: &Path, exclude: &[&str]) -> Option<PathBuf> {
+    let dir = sysfs_root.join("class/backlight");
+    if !dir.exists() {
+        return None;
+    }
+    fs::read_dir(&dir).ok()?.flatten().find_map(|e| {
+        let p = e.path();
+        let excluded = exclude.iter().any(|name| e.file_name() == *name);
+        (!excluded && p.join("max_brightness").exists() && p.join("brightness").exists())
+            .then_some(p)
+    })
+}
+
+/// How often the writer thread reads `max_brightness` again.
+const SCALE_RECHECK: Duration = Duration::from_secs(10);
+
+/// `max_brightness` can change under a running daemon (i915 reloaded, a
+/// firmware update). Levels keep the scale the device was opened with and
+/// are mapped proportionally onto the one the driver reports now, so the
+/// calibrated range still spans the same part of the panel's.
+struct Scale {
+    path: PathBuf,
+    opened: u32,
+    now: AtomicU32,
+}
+
+impl Scale {
+    fn hw(&self, v: u32) -> u32 {
+        Self::map(v, self.opened, self.now.load(Ordering::Relaxed))
+    }
+
+    fn level(&self, hw: u32) -> u32 {
+        Self::map(hw, self.now.load(Ordering::Relaxed), self.opened)
+    }
+
+    fn map(v: u32, from: u32, to: u32) -> u32 {
+        if from == to || from == 0 {
+            return v;
+        }
+        ((v as u64 * to as u64 + from as u64 / 2) / from as u64) as u32
+    }
+
+    /// Read `max_brightness` again; true if it changed.
+    fn reread(&self) -> bool {
+        let Some(max) = read_u32_from(&self.path).filter(|&max| max > 0) else {
+            return false;
+        };
+        let was = self.now.swap(max, Ordering::Relaxed);
+        if was != max {
+            info!(
+                target: "backlight",
+                "{} went from {} to {}; levels are rescaled to match",
+                self.path.display(),
+                was,
+                max
+            );
+        }
+        was != max
+    }
+}
+
+/// A panel under `<sysfs_root>/class/backlight`, or an LED under
+/// `class/leds`. Levels are written on a thread of the device's own.
+pub struct Device {
+    path: PathBuf,
+    max_value: u32,
+    scale: Arc<Scale>,
+    actual_path: Option<PathBuf>,
+    /// DRM connector `dpms` ("On"/"Off") for native panel backlights,
+    /// else the backlight's own `bl_power` (0 when on).
+    power_path: Option<PathBuf>,
+    /// Set once sysfs refuses a write (e.g. group ownership changed after a
+    /// kernel update); later writes go through logind.
+    use_logind: Arc<AtomicBool>,
+    writer: Writer,
+}
+
+impl Device {
+    /// The first panel that isn't one of `exclude` (linked displays).
+    pub fn open(sysfs_root: &Path, exclude: &[&str]) -> Result<Self, Box<dyn Error>> {
+        let dir = autodetect_backlight_dir(sysfs_root, exclude).ok_or("cannot find brightness")?;
+        Self::open_dir("backlight", dir)
+    }
+
+    pub fn open_named(sysfs_root: &Path, name: &str) -> Result<Self, Box<dyn Error>> {
+        let dir = sysfs_root.join("class/backlight").join(name);
+        if !dir.join("brightness").exists() {
+            return Err(format!("no backlight device '{}'", name).into());
+        }
+        Self::open_dir("backlight", dir)
+    }
+
+    /// A keyboard backlight LED; `auto` picks the first `*::kbd_backlight`.
+    pub fn open_keyboard(sysfs_root: &Path, name: &str) -> Result<Self, Box<dyn Error>> {
+        let leds = sysfs_root.join("class/leds");
+        let dir = if name == "auto" {
+            let mut found: Vec<PathBuf> = fs::read_dir(&leds)
+                .map_err(|e| format!("cannot list {}: {}", leds.display(), e))?
+                .flatten()
+                .filter(|e| e.file_name().to_string_lossy().ends_with("::kbd_backlight"))
+                .map(|e| e.path())
+                .collect();
+            found.sort();
+            found.into_iter().next().ok_or("no keyboard backlight found")?
+        } else {
+            leds.join(name)
+        };
+        if !dir.join("brightness").exists() {
+            return Err(format!("no LED '{}'", name).into());
+        }
+        Self::open_dir("leds", dir)
+    }
+
+    /// Write through logind from the start rather than after sysfs refuses.
+    pub fn via_logind(self) -> Self {
+        self.use_logind.store(true, Ordering::Relaxed);
+        self
+    }
+
+    /// `class` is the sysfs class, which is also the logind subsystem name.
+    fn open_dir(class: &'static str, dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+        let max_path = dir.join("max_brightness");
+        let max_value = read_u32_from(&max_path).ok_or("cannot read max_brightness")?;
+        let scale = Arc::new(Scale {
+            path: max_path,
+            opened: max_value,
+            now: AtomicU32::new(max_value),
+        });
+        let actual_path = Some(dir.join("actual_brightness")).filter(|p| p.exists());
+        let power_path = [dir.join("device/dpms"), dir.join("bl_power")]
+            .into_iter()
+            .find(|p| p.exists());
+        let path = dir.join("brightness");
+        let use_logind = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let (path, scale) = (path.clone(), Arc::clone(&scale));
+            let use_logind = Arc::clone(&use_logind);
+            let mut checked = Instant::now();
+            Writer::spawn("backlight", move |v| {
+                if checked.elapsed() >= SCALE_RECHECK {
+                    scale.reread();
+                    checked = Instant::now();
+                }
+                match write_level(class, &path, &use_logind, scale.hw(v)) {
+                    // The driver refuses a level above a max that shrank.
+                    Err(e) if e.kind() == io::ErrorKind::InvalidInput && scale.reread() => {
+                        write_level(class, &path, &use_logind, scale.hw(v))
+                    }
+                    r => r,
+                }
+            })?
+        };
+        Ok(Self {
+            path,
+            max_value,
+            scale,
+            actual_path,
+            power_path,
+            use_logind,
+            writer,
+        })
+    }
+
+    fn device_name(&self) -> String {
+        device_name(&self.path)
+    }
+}
+
+/// Directory name under `class/<class>`, as logind expects it.
+fn device_name(path: &Path) -> String {
+    path.parent()
+        .and_then(Path::file_name)
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Runs on the device's writer thread.
+fn write_level(class: &str, path: &Path, use_logind: &AtomicBool, v: u32) -> io::Result<()> {
+    if !use_logind.load(Ordering::Relaxed) {
+        match write_u32_to(path, v) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                warn!(
+                    target: "backlight",
+                    "No permission to write {} ({}); using logind from now on",
+                    path.display(),
+                    e
+                );
+                use_logind.store(true, Ordering::Relaxed);
+            }
+            r => return r,
+        }
+    }
+    logind::set_brightness(class, &device_name(path), v)
+}
+
+impl BrightnessOutput for Device {
+    fn max_value(&self) -> u32 {
+        self.max_value
+    }
+
+    /// A level still on its way to the device counts as set.
+    fn get(&self) -> Option<u32> {
+        self.writer
+            .in_flight()
+            .or_else(|| read_u32_from(&self.path).map(|hw| self.scale.level(hw)))
+    }
+
+    fn actual(&self) -> Option<u32> {
+        self.actual_path
+            .as_ref()
+            .and_then(read_u32_from)
+            .map(|hw| self.scale.level(hw))
+            .or_else(|| self.get())
+    }
+
+    fn set(&self, v: u32) -> io::Result<()> {
+        self.writer.post(v)
+    }
+
+    fn name(&self) -> String {
+        if self.use_logind.load(Ordering::Relaxed) {
+            format!("logind ({})", self.device_name())
+        } else {
+            self.path.display().to_string()
+        }
+    }
+
+    fn actual_source(&self) -> String {
+        self.actual_path.as_ref().unwrap_or(&self.path).display().to_string()
+    }
+
+    fn powered(&self) -> Option<bool> {
+        let path = self.power_path.as_ref()?;
+        match fs::read_to_string(path).ok()?.trim() {
+            "On" | "0" => Some(true),
+            "Off" | "Standby" | "Suspend" => Some(false),
+            other => other.parse::<u32>().ok().map(|_| false),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// src/backlight/wmi.rs
+//! Panel brightness through WMI (`WmiMonitorBrightness` to read,
+//! `WmiMonitorBrightnessMethods` to write). Covers internal panels;
+//! external monitors generally don't implement it.
+//!
+//! The calls run in one PowerShell process kept open for the daemon's
+//! lifetime. Starting PowerShell takes a few hundred milliseconds, far too
+//! long for every step of a transition; in a running session a call is a
+//! WMI round trip, and the methods object is looked up only once.
+use std::cell::RefCell;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use super::BrightnessOutput;
+
+const PROGRAM: &str = "powershell";
+
+/// WMI brightness is a percentage.
+const LEVELS: u32 = 100;
+
+/// Run when a session starts; every write reuses `$methods`.
+const SETUP: &str = "$methods = Get-CimInstance -Namespace root/WMI \
+                     -ClassName WmiMonitorBrightnessMethods -ErrorAction Stop";
+
+/// `75`; the first panel when there are several.
+const READ: &str = "(Get-CimInstance -Namespace root/WMI -ClassName WmiMonitorBrightness \
+                    -ErrorAction Stop | Select-Object -First 1).CurrentBrightness";
+
+/// A PowerShell reading commands from its stdin, one line each.
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Session {
+    fn start() -> io::Result<Self> {
+        let mut child = Command::new(PROGRAM)
+            .args(["-NoProfile", "-NonInteractive", "-Command", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("PowerShell has no stdin or stdout"));
+        };
+        let mut session = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        };
+        session.run(SETUP)?;
+        Ok(session)
+    }
+
+    /// Run one line of PowerShell and return what it printed. Every command
+    /// answers with exactly one line, `OK <output>` or `ERR <message>`, so
+    /// a failure can't leave the reply to the next one waiting in the pipe.
+    fn run(&mut self, command: &str) -> io::Result<String> {
+        writeln!(
+            self.stdin,
+            "try {{ $out = {}; \"OK $out\" }} catch {{ \"ERR $($_.Exception.Message)\" }}",
+            command
+        )?;
+        self.stdin.flush()?;
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "PowerShell exited"));
+        }
+        let line = line.trim();
+        match line.strip_prefix("OK") {
+            Some(out) => Ok(out.trim().to_string()),
+            None => Err(io::Error::other(line.strip_prefix("ERR ").unwrap_or(line).to_string())),
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The panel, driven through a PowerShell session started on first use.
+pub struct Device {
+    session: RefCell<Option<Session>>,
+}
+
+impl Device {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        let dev = Device {
+            session: RefCell::new(None),
+        };
+        dev.call(READ).map_err(|e| {
+            format!(
+                "cannot read display brightness through WMI: {} \
+                 (the display driver does not expose WMI brightness control)",
+                e
+            )
+        })?;
+        Ok(dev)
+    }
+
+    /// Run `command` in the session. A session whose PowerShell has gone
+    /// away is dropped, and the next call starts a new one.
+    fn call(&self, command: &str) -> io::Result<String> {
+        let mut slot = self.session.borrow_mut();
+        let mut session = match slot.take() {
+            Some(session) => session,
+            None => Session::start()?,
+        };
+        let result = session.run(command);
+        if result.as_ref().err().is_none_or(|e| e.kind() == io::ErrorKind::Other) {
+            *slot = Some(session);
+        }
+        result
+    }
+}
+
+/// The panel has no separate "actual" readout, so `actual` is `get`.
+impl BrightnessOutput for Device {
+    fn max_value(&self) -> u32 {
+        LEVELS
+    }
+
+    fn get(&self) -> Option<u32> {
+        let percent: f32 = self.call(READ).ok()?.parse().ok()?;
+        Some(percent.round().clamp(0.0, LEVELS as f32) as u32)
+    }
+
+    fn set(&self, v: u32) -> io::Result<()> {
+        self.call(&format!(
+            "$methods | Invoke-CimMethod -MethodName WmiSetBrightness \
+             -Arguments @{{Timeout=0; Brightness={}}} -ErrorAction Stop | Out-Null",
+            v.min(LEVELS)
+        ))
+        .map(drop)
+    }
+
+    fn name(&self) -> String {
+        "WmiMonitorBrightnessMethods".into()
+    }
+
+    fn actual_source(&self) -> String {
+        "WMI WmiMonitorBrightness.CurrentBrightness".into()
+    }
+}
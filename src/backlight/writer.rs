@@ -0,0 +1,130 @@
+// src/backlight/writer.rs
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+/// Writes levels on a thread of its own: on some EC-backed panels a sysfs
+/// write blocks for tens of milliseconds, which would otherwise stall
+/// capture and transition timing. It holds one level at a time; a level
+/// posted before the last one was picked up replaces it.
+pub struct Writer {
+    shared: Arc<(Mutex<Slot>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct Slot {
+    /// Posted and not picked up yet.
+    pending: Option<u32>,
+    /// Being written now.
+    writing: Option<u32>,
+    /// The last write failed; reported by the next `post`.
+    failed: Option<io::Error>,
+    closed: bool,
+}
+
+impl Writer {
+    pub fn spawn(
+        name: &str,
+        mut write: impl FnMut(u32) -> io::Result<()> + Send + 'static,
+    ) -> io::Result<Self> {
+        let shared = Arc::new((Mutex::new(Slot::default()), Condvar::new()));
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new().name(name.into()).spawn(move || {
+                let (slot, posted) = &*shared;
+                loop {
+                    let v = {
+                        let mut slot = lock(slot);
+                        while slot.pending.is_none() && !slot.closed {
+                            slot = posted.wait(slot).unwrap_or_else(|e| e.into_inner());
+                        }
+                        // Closing still writes what was posted last.
+                        let Some(v) = slot.pending.take() else {
+                            return;
+                        };
+                        slot.writing = Some(v);
+                        v
+                    };
+                    let r = write(v);
+                    let mut slot = lock(slot);
+                    slot.writing = None;
+                    slot.failed = r.err();
+                }
+            })?
+        };
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Hand `v` to the thread. A failed earlier write is returned instead,
+    /// and `v` not posted, so the caller tries again as it would after a
+    /// write failing in place.
+    pub fn post(&self, v: u32) -> io::Result<()> {
+        let (slot, posted) = &*self.shared;
+        let mut slot = lock(slot);
+        if let Some(e) = slot.failed.take() {
+            return Err(e);
+        }
+        slot.pending = Some(v);
+        posted.notify_one();
+        Ok(())
+    }
+
+    /// The level posted last while it is still on its way to the device.
+    pub fn in_flight(&self) -> Option<u32> {
+        let slot = lock(&self.shared.0);
+        slot.pending.or(slot.writing)
+    }
+}
+
+impl Drop for Writer {
+    /// Waits for the last level to be written: on exit it is usually the
+    /// one being restored.
+    fn drop(&mut self) {
+        let (slot, posted) = &*self.shared;
+        lock(slot).closed = true;
+        posted.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn lock(slot: &Mutex<Slot>) -> MutexGuard<'_, Slot> {
+    slot.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn levels_posted_during_a_slow_write_collapse_into_the_last() {
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel();
+        let (tx, rx) = mpsc::channel();
+        let writer = Writer::spawn("test-writer", move |v| {
+            started_tx.send(v).unwrap();
+            release_rx.recv().unwrap();
+            tx.send(v).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        writer.post(1).unwrap();
+        // The thread is inside the write of 1 until released.
+        assert_eq!(started.recv().unwrap(), 1);
+        for v in 2..=5 {
+            writer.post(v).unwrap();
+        }
+        assert_eq!(writer.in_flight(), Some(5));
+        for _ in 0..2 {
+            release.send(()).unwrap();
+        }
+        drop(writer);
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![1, 5]);
+    }
+}
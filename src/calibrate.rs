@@ -1,45 +1,202 @@
 use std::io::{self, Write};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use crate::als::AmbientLightSensor;
 use crate::backlight::Backlight;
-use crate::camera::Camera;
-use crate::config::{save_config, Config};
+use crate::config::{save_config, AmbientSensorKind, Config, FULL_FRAME, MANAGED_PATH};
+use crate::correction;
+use crate::curve::Curve;
+use crate::sensor::AmbientInput;
+use crate::tr;
+
+/// Settings the wizard writes.
+const CALIBRATION: [&str; 6] = [
+    "ambient_luma_min",
+    "ambient_luma_max",
+    "camera_correction",
+    "screen_brightness_min",
+    "screen_brightness_max",
+    "calibrated",
+];
+
+/// How long the light is varied for, and how often both sensors are read,
+/// when calibrating against a light sensor.
+const ALS_CALIBRATION_SECS: u64 = 60;
+const ALS_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Frames averaged for each point of the brightness curve.
+const CURVE_SAMPLES: usize = 60;
 
 pub fn run(mut cfg: Config) -> Result<Config, Box<dyn std::error::Error>> {
-    println!("╔════════════════════════════════════════════════════════════════╗");
-    println!("║          Smart Brightness - Calibration Wizard                ║");
-    println!("╚════════════════════════════════════════════════════════════════╝");
+    let _span = tracing::info_span!("calibrate").entered();
+    if CALIBRATION.iter().any(|name| cfg.is_managed(name)) {
+        println!("{}", tr!("calib-managed", path = MANAGED_PATH));
+        return Ok(cfg);
+    }
+    print_banner(&[tr!("calib-title")]);
     println!();
-    println!("This will calibrate:");
-    println!("  1. Camera sensitivity (ambient light detection)");
-    println!("  2. Monitor brightness range (min/max values)");
+    println!("{}", tr!("calib-intro"));
+    println!("  {}", tr!("calib-intro-camera"));
+    println!("  {}", tr!("calib-intro-monitor"));
+    println!("  {}", tr!("calib-intro-curve"));
     println!();
-    
+
     // Camera calibration
-    println!("┌─ Step 1: Camera Calibration ─────────────────────────────────┐");
-    println!("│ Prepare DARKEST typical condition (cover lens / dim room)    │");
-    println!("└───────────────────────────────────────────────────────────────┘");
+    let (min_l, max_l) = match calibrate_against_als(&mut cfg)? {
+        Some(range) => range,
+        None => {
+            cfg.camera_correction.clear();
+            measure_dark_and_bright(&cfg)?
+        }
+    };
+
+    // Monitor brightness calibration
+    let (detected_min_brightness, detected_max_brightness) = calibrate_monitor_range(&cfg)?;
+    
+    println!();
+    print_banner(&[
+        tr!("calib-results-title"),
+        tr!(
+            "calib-result-luma",
+            min = format!("{:.6}", min_l),
+            max = format!("{:.6}", max_l)
+        ),
+        tr!(
+            "calib-result-brightness",
+            min = detected_min_brightness.to_string(),
+            max = detected_max_brightness.to_string()
+        ),
+    ]);
+    println!();
+
+    cfg.camera_min_luma = Some(min_l);
+    cfg.camera_max_luma = Some(max_l);
+    cfg.real_min_brightness = detected_min_brightness;
+    cfg.real_max_brightness = detected_max_brightness;
+    cfg.calibrated = true;
+
+    if !cfg.is_managed("curve")
+        && let Some(curve) = record_curve(&cfg)?
+    {
+        cfg.curve = curve;
+    }
+
+    save_config(&cfg)?;
+    println!("{}", tr!("calib-saved"));
+    println!();
+    Ok(cfg)
+}
+
+/// With a light sensor next to the camera, record both while the user
+/// varies the light and fit `camera_correction`; the luma range is then the
+/// sensor's. None without a sensor, or if the light hardly changed.
+fn calibrate_against_als(
+    cfg: &mut Config,
+) -> Result<Option<(f32, f32)>, Box<dyn std::error::Error>> {
+    if cfg.ambient_sensor != AmbientSensorKind::Camera
+        || AmbientLightSensor::detect(&cfg.sysfs_root()).is_none()
+    {
+        return Ok(None);
+    }
+    let als_cfg = Config {
+        ambient_sensor: AmbientSensorKind::Als,
+        sensor_fallbacks: Vec::new(),
+        ..cfg.clone()
+    };
+    let camera_cfg = Config {
+        camera_correction: Vec::new(),
+        sensor_fallbacks: Vec::new(),
+        ..cfg.clone()
+    };
+    let mut als = AmbientInput::open(&als_cfg)?;
+    let mut cam = AmbientInput::open(&camera_cfg)?;
+
+    print_step(
+        &tr!("calib-als-title"),
+        &tr!("calib-als-body", secs = ALS_CALIBRATION_SECS.to_string()),
+    );
+    wait_enter()?;
+    print_roi(cfg);
+    println!("{}", tr!("calib-warming-up"));
+    cam.warmup(cfg.warmup_frames.max(30), cfg.warmup_tolerance);
+
+    let end = Instant::now() + Duration::from_secs(ALS_CALIBRATION_SECS);
+    let mut samples = Vec::new();
+    while Instant::now() < end {
+        let camera = cam.measure(false)?;
+        let sensor = als.measure(false)?;
+        samples.push((camera, sensor));
+        print!(
+            "\r   {}",
+            tr!(
+                "calib-als-sample",
+                camera = format!("{:.3}", camera),
+                sensor = format!("{:.3}", sensor),
+                count = samples.len().to_string()
+            )
+        );
+        io::stdout().flush()?;
+        std::thread::sleep(ALS_SAMPLE_INTERVAL);
+    }
+    println!();
+
+    let Some(points) = correction::fit(&samples) else {
+        println!("{}", tr!("calib-als-failed"));
+        println!();
+        return Ok(None);
+    };
+    let (min_l, max_l) = (points[0][1], points[points.len() - 1][1]);
+    println!(
+        "{}",
+        tr!(
+            "calib-als-fitted",
+            points = points.len().to_string(),
+            min = format!("{:.4}", min_l),
+            max = format!("{:.4}", max_l)
+        )
+    );
+    println!();
+    cfg.camera_correction = points;
+    Ok(Some((min_l, max_l)))
+}
+
+/// The part of the frame the camera readings come from (`camera_roi`).
+fn print_roi(cfg: &Config) {
+    if cfg.camera_roi == FULL_FRAME {
+        println!("{}", tr!("calib-roi-full"));
+        return;
+    }
+    let percent = |share: f32| format!("{:.0} %", share * 100.0);
+    let [x, y, w, h] = cfg.camera_roi;
+    println!(
+        "{}",
+        tr!("calib-roi", x = percent(x), y = percent(y), w = percent(w), h = percent(h))
+    );
+}
+
+fn measure_dark_and_bright(cfg: &Config) -> Result<(f32, f32), Box<dyn std::error::Error>> {
+    print_step(&tr!("calib-step1-title"), &tr!("calib-step1-body"));
     wait_enter()?;
 
-    let (w, h) = (cfg.resolution[0], cfg.resolution[1]);
-    let mut cam = Camera::open(cfg.camera_device, w, h)?;
-    println!("Warming up camera...");
-    cam.warmup(cfg.warmup_frames.max(30));
+    let mut cam = AmbientInput::open(cfg)?;
+    if cfg.ambient_sensor == AmbientSensorKind::Camera {
+        print_roi(cfg);
+    }
+    println!("{}", tr!("calib-warming-up"));
+    cam.warmup(cfg.warmup_frames.max(30), cfg.warmup_tolerance);
 
-    println!("Measuring dark ambient light...");
-    let dark = cam.average_luma_over(120)?;
-    println!("✓ Measured dark luma: {:.6}", dark);
+    println!("{}", tr!("calib-measuring-dark"));
+    let dark = cam.average_over(120)?;
+    println!("{}", tr!("calib-measured-dark", luma = format!("{:.6}", dark)));
     println!();
 
-    println!("┌─ Step 2: Bright Light Measurement ───────────────────────────┐");
-    println!("│ Prepare BRIGHTEST typical condition (bright light/daylight)  │");
-    println!("└───────────────────────────────────────────────────────────────┘");
+    print_step(&tr!("calib-step2-title"), &tr!("calib-step2-body"));
     wait_enter()?;
 
     std::thread::sleep(Duration::from_millis(200));
-    println!("Measuring bright ambient light...");
-    let bright = cam.average_luma_over(120)?;
-    println!("✓ Measured bright luma: {:.6}", bright);
+    println!("{}", tr!("calib-measuring-bright"));
+    let bright = cam.average_over(120)?;
+    println!("{}", tr!("calib-measured-bright", luma = format!("{:.6}", bright)));
     println!();
 
     let (min_l, max_l) = if dark <= bright {
@@ -47,79 +204,165 @@ pub fn run(mut cfg: Config) -> Result<Config, Box<dyn std::error::Error>> {
     } else {
         (bright, dark)
     };
-    
+
     let luma_range = max_l - min_l;
     if luma_range < 0.02 {
-        println!("⚠ WARNING: Luma range is very small ({:.4})", luma_range);
-        println!("  Consider using stronger lighting contrast and re-running calibration.");
+        println!("{}", tr!("calib-range-small", range = format!("{:.4}", luma_range)));
+        println!("  {}", tr!("calib-range-small-hint"));
         println!();
     } else {
-        println!("✓ Good luma range detected: {:.4}", luma_range);
+        println!("{}", tr!("calib-range-good", range = format!("{:.4}", luma_range)));
         println!();
     }
+    Ok((min_l, max_l))
+}
 
-    // Monitor brightness calibration
-    let (detected_min_brightness, detected_max_brightness) = calibrate_monitor_range(&cfg)?;
-    
-    println!();
-    println!("╔════════════════════════════════════════════════════════════════╗");
-    println!("║                  Calibration Results                          ║");
-    println!("╠════════════════════════════════════════════════════════════════╣");
-    println!("║ Camera Luma Range:  {:.6} → {:.6}                  ║", min_l, max_l);
-    println!("║ Monitor Brightness: {} → {}                              ║", 
-             detected_min_brightness, detected_max_brightness);
-    println!("╚════════════════════════════════════════════════════════════════╝");
+/// Optional last step: in as many lighting conditions as the user likes,
+/// read the light and the brightness they set by hand, and turn the points
+/// into `[curve]`. None if fewer than two points were taken, or without a
+/// brightness range to place them in.
+fn record_curve(cfg: &Config) -> Result<Option<Curve>, Box<dyn std::error::Error>> {
+    let (min_b, max_b) = (cfg.real_min_brightness as f32, cfg.real_max_brightness as f32);
+    if max_b <= min_b {
+        return Ok(None);
+    }
+    print_step(&tr!("calib-curve-title"), &tr!("calib-curve-body"));
+    let bl = Backlight::resolve(cfg)?;
+    let mut cam = AmbientInput::open(cfg)?;
+    let (min_l, max_l) = (
+        cfg.camera_min_luma.unwrap_or(0.0),
+        cfg.camera_max_luma.unwrap_or(1.0),
+    );
+    let mut points = Vec::new();
+    loop {
+        println!("   {}", tr!("calib-curve-prompt"));
+        // End of input (`< /dev/null`, a closed pipe) finishes too.
+        match read_answer()? {
+            Some(answer) if !answer.eq_ignore_ascii_case("q") => {}
+            _ => break,
+        }
+        println!("{}", tr!("calib-warming-up"));
+        cam.warmup(cfg.warmup_frames.max(30), cfg.warmup_tolerance);
+        let luma = cam.average_over(CURVE_SAMPLES)?;
+        let level = read_manual_level(&bl)?;
+        let x = if max_l > min_l {
+            (luma - min_l) / (max_l - min_l)
+        } else {
+            luma
+        };
+        let x = x.clamp(0.0, 1.0);
+        let y = ((level as f32 - min_b) / (max_b - min_b)).clamp(0.0, 1.0);
+        points.push([x, y]);
+        println!(
+            "   {}",
+            tr!(
+                "calib-curve-point",
+                n = points.len().to_string(),
+                luma = format!("{:.3}", x),
+                percent = format!("{:.0}", y * 100.0)
+            )
+        );
+    }
+    let curve = Curve::from_points(&points).filter(|curve| curve.validate().is_ok());
+    match &curve {
+        Some(curve) => println!(
+            "{}",
+            tr!("calib-curve-saved", points = curve.points.len().to_string())
+        ),
+        None => println!("{}", tr!("calib-curve-skipped")),
+    }
     println!();
+    Ok(curve)
+}
 
-    cfg.camera_min_luma = Some(min_l);
-    cfg.camera_max_luma = Some(max_l);
-    cfg.real_min_brightness = detected_min_brightness;
-    cfg.real_max_brightness = detected_max_brightness;
-    cfg.calibrated = true;
+/// Box width adapts to the (translated) text; never narrower than the
+/// original English layout.
+const BOX_MIN_WIDTH: usize = 62;
 
-    save_config(&cfg)?;
-    println!("✓ Calibration saved successfully!");
-    println!();
-    Ok(cfg)
+fn box_width<'a>(lines: impl IntoIterator<Item = &'a str>) -> usize {
+    lines
+        .into_iter()
+        .map(|l| l.chars().count() + 2)
+        .max()
+        .unwrap_or(0)
+        .max(BOX_MIN_WIDTH)
+}
+
+fn pad(text: &str, width: usize, fill: char) -> String {
+    let n = width.saturating_sub(text.chars().count());
+    format!("{}{}", text, fill.to_string().repeat(n))
+}
+
+/// Double-lined banner; the first line is the title, the rest are
+/// separated from it by a rule.
+pub(crate) fn print_banner(lines: &[String]) {
+    let width = box_width(lines.iter().map(String::as_str));
+    let rule = "═".repeat(width);
+    println!("╔{}╗", rule);
+    for (i, line) in lines.iter().enumerate() {
+        if i == 0 {
+            let total = width - line.chars().count();
+            let left = total / 2;
+            println!("║{}{}{}║", " ".repeat(left), line, " ".repeat(total - left));
+            if lines.len() > 1 {
+                println!("╠{}╣", rule);
+            }
+        } else {
+            println!("║ {}║", pad(line, width - 1, ' '));
+        }
+    }
+    println!("╚{}╝", rule);
+}
+
+fn print_step(title: &str, body: &str) {
+    let width = box_width([title, body]).max(title.chars().count() + 4);
+    let head = format!("─ {} ", title);
+    println!("┌{}┐", pad(&head, width, '─'));
+    println!("│ {}│", pad(body, width - 1, ' '));
+    println!("└{}┘", "─".repeat(width));
 }
 
 fn wait_enter() -> io::Result<()> {
-    print!("Press Enter to continue...");
+    print!("{}", tr!("calib-press-enter"));
+    read_answer().map(drop)
+}
+
+/// One line from stdin, trimmed; None at end of input.
+fn read_answer() -> io::Result<Option<String>> {
     io::stdout().flush()?;
     let mut s = String::new();
-    io::stdin().read_line(&mut s)?;
-    Ok(())
+    if io::stdin().read_line(&mut s)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(s.trim().to_string()))
 }
 
 fn calibrate_monitor_range(cfg: &Config) -> Result<(u32, u32), Box<dyn std::error::Error>> {
-    println!("3) Monitor calibration using hardware brightness keys.");
+    println!("{}", tr!("calib-monitor-intro"));
     let bl = Backlight::resolve(cfg)?;
-    let actual_path = bl
-        .actual_path()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| bl.path.clone());
     println!(
-        "   (Reading levels from {} – e.g. intel panels expose /sys/class/backlight/intel_backlight/actual_brightness)",
-        actual_path.display()
+        "   {}",
+        tr!("calib-monitor-reading", path = bl.actual_source())
     );
 
-    println!(
-        "   • Set the monitor to its MAXIMUM brightness using the hardware keys, then press Enter."
-    );
+    println!("   {}", tr!("calib-monitor-set-max"));
     wait_enter()?;
     let max_level = read_manual_level(&bl)?;
-    println!("   → Recorded maximum actual brightness: {}", max_level);
+    println!(
+        "   {}",
+        tr!("calib-monitor-recorded-max", level = max_level.to_string())
+    );
 
-    println!("   • Now set the monitor to the LOWEST brightness that still keeps the screen visible, then press Enter.");
+    println!("   {}", tr!("calib-monitor-set-min"));
     wait_enter()?;
     let min_level = read_manual_level(&bl)?;
-    println!("   → Recorded minimum actual brightness: {}", min_level);
+    println!(
+        "   {}",
+        tr!("calib-monitor-recorded-min", level = min_level.to_string())
+    );
 
     if max_level <= min_level {
-        return Err(
-            "Recorded maximum brightness must be greater than minimum; please rerun calibration."
-                .into(),
-        );
+        return Err(tr!("calib-monitor-range-error").into());
     }
 
     Ok((min_level, max_level))
@@ -129,5 +372,5 @@ fn read_manual_level(bl: &Backlight) -> Result<u32, Box<dyn std::error::Error>>
     std::thread::sleep(Duration::from_millis(150));
     bl.actual()
         .or_else(|| bl.current())
-        .ok_or_else(|| "Unable to read actual_brightness from backlight device".into())
+        .ok_or_else(|| tr!("calib-monitor-read-error").into())
 }
@@ -1,106 +1,661 @@
 // src/camera.rs
 use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use v4l::buffer::Type;
-use v4l::device::Device;
-use v4l::format::FourCC;
-use v4l::io::traits::CaptureStream;
-use v4l::prelude::MmapStream;
-use v4l::video::Capture;
+use tracing::{debug, info, trace, warn};
+
+use crate::config::{
+    runtime_dir, CameraCaptureMode as CaptureMode, LumaMetric, LumaWeighting, PixelFormat, FULL_FRAME,
+};
+use crate::lux::{Exposure, LuxEstimator};
+use crate::masking::{BrightMask, CellGrid};
+use crate::motion::MotionDetector;
+
+#[cfg(any(target_os = "macos", target_os = "openbsd", target_os = "windows"))]
+mod ffmpeg;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+mod v4l2;
+
+#[cfg(any(target_os = "macos", target_os = "openbsd", target_os = "windows"))]
+pub use ffmpeg::camera_kind;
+#[cfg(any(target_os = "macos", target_os = "openbsd", target_os = "windows"))]
+use ffmpeg::{device_name, Session};
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use v4l2::camera_kind;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+use v4l2::{device_name, Session};
+
+/// Some cameras deliver a few identical black frames before auto-exposure
+/// kicks in; never treat those as settled.
+const WARMUP_MIN_FRAMES: usize = 5;
+/// Consecutive frame-to-frame changes within tolerance that count as settled.
+const WARMUP_STABLE_FRAMES: usize = 3;
+
+/// Consecutive blank frames after which the camera counts as covered by a
+/// privacy switch rather than looking at a dark room.
+const PRIVACY_BLANK_FRAMES: u32 = 3;
+/// Brightest Y value, and largest spread, of a blanked frame. Even in a dark
+/// room sensor noise spreads the values; limited-range black is 16.
+const BLANK_MAX_Y: u8 = 20;
+const BLANK_MAX_SPREAD: u8 = 1;
+/// Darkest Y value of a saturated frame; limited-range white is 235.
+const SATURATED_MIN_Y: u8 = 235;
+
+/// Wait before the first attempt to reopen a camera that disappeared, and
+/// the most the wait doubles up to.
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// `errno` of a read from a device that was unplugged; the same on Linux,
+/// the BSDs and macOS.
+const ENODEV: i32 = 19;
+
+/// A frame that can't be a reading of the room. Returned as the error of a
+/// measurement so sensor failover treats it like any other failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFault {
+    /// Uniformly black: a covered or blanked sensor.
+    Blank,
+    /// Byte for byte the previous frame: the driver stopped delivering.
+    Frozen,
+    /// Uniformly white: the sensor is overexposed.
+    Saturated,
+}
+
+impl fmt::Display for FrameFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FrameFault::Blank => "camera delivered a blank frame",
+            FrameFault::Frozen => "camera delivered the same frame twice; driver stalled",
+            FrameFault::Saturated => "camera delivered a saturated frame",
+        })
+    }
+}
+
+impl Error for FrameFault {}
+
+/// The camera delivers only uniform black frames or refuses access, as
+/// with a hardware kill switch or firmware privacy toggle.
+#[derive(Debug)]
+pub struct PrivacyBlocked;
+
+impl fmt::Display for PrivacyBlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("camera blocked by a privacy switch")
+    }
+}
+
+impl Error for PrivacyBlocked {}
+
+impl PixelFormat {
+    pub fn fourcc(self) -> &'static [u8; 4] {
+        match self {
+            PixelFormat::Yuyv => b"YUYV",
+            PixelFormat::Uyvy => b"UYVY",
+            PixelFormat::Grey => b"GREY",
+            PixelFormat::Nv12 => b"NV12",
+        }
+    }
+
+    /// Offset of a row's first Y byte, and the distance between the Y bytes
+    /// of neighbouring pixels.
+    fn luma_layout(self) -> (usize, usize) {
+        match self {
+            PixelFormat::Yuyv => (0, 2),
+            PixelFormat::Uyvy => (1, 2),
+            PixelFormat::Grey | PixelFormat::Nv12 => (0, 1),
+        }
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(self.fourcc()))
+    }
+}
+
+/// Layout of the frames a session delivers, as negotiated with the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameFormat {
+    pub width: u32,
+    pub height: u32,
+    /// Bytes per row of the luma plane, padding included.
+    pub stride: u32,
+    pub pixel: PixelFormat,
+}
+
+impl FrameFormat {
+    /// An unpadded frame.
+    pub fn new(width: u32, height: u32, pixel: PixelFormat) -> Self {
+        Self {
+            width,
+            height,
+            stride: width * pixel.luma_layout().1 as u32,
+            pixel,
+        }
+    }
+
+    /// Byte holding the Y value of pixel (`x`, `y`).
+    fn luma_index(&self, x: usize, y: usize) -> usize {
+        let (offset, step) = self.pixel.luma_layout();
+        y * self.stride as usize + offset + x * step
+    }
+}
+
+/// What a video device sees, as far as its name and formats tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraKind {
+    /// An ordinary colour camera.
+    Visible,
+    /// An infrared (face login) or depth camera: it lights the scene
+    /// itself, so its luma doesn't follow the room.
+    Infrared,
+    /// Not a capture device (e.g. the metadata node beside a camera), or
+    /// one that can't be looked at.
+    Other,
+}
+
+impl CameraKind {
+    /// From the driver's card name and the fourcc codes it offers.
+    pub fn classify(card: &str, formats: &[[u8; 4]]) -> Self {
+        let named = card
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| ["ir", "infrared", "depth"].contains(&word.to_ascii_lowercase().as_str()));
+        // IR and depth sensors only deliver grey or depth formats.
+        const COLOR: [&[u8; 4]; 9] =
+            [b"YUYV", b"UYVY", b"NV12", b"NV21", b"YU12", b"YV12", b"MJPG", b"RGB3", b"BGR3"];
+        let colorless = !formats.is_empty() && !formats.iter().any(|f| COLOR.contains(&f));
+        if named || colorless {
+            CameraKind::Infrared
+        } else {
+            CameraKind::Visible
+        }
+    }
+}
+
+/// Range of the manual exposure time in driver units (100 µs for V4L2).
+#[derive(Debug, Clone, Copy)]
+pub struct ExposureControl {
+    pub min: i64,
+    pub max: i64,
+    pub default: i64,
+}
 
 pub struct Camera {
-    _dev: Device,
-    stream: MmapStream<'static>,
+    index: usize,
     width: u32,
     height: u32,
+    pixel: PixelFormat,
+    /// What the driver delivers; it may substitute another size.
+    format: FrameFormat,
+    format_reported: bool,
+    mode: CaptureMode,
+    burst_frames: usize,
+    session: Option<Session>,
+    activity: ActivityIndicator,
+    analysis: FrameAnalysis,
+    lux: Option<LuxEstimator>,
+    last_luma: f32,
+    last_exposure: Option<Exposure>,
+    warm_start: Duration,
+    /// Blank frames measured in a row.
+    blank_frames: u32,
+    /// Hash of the last measured frame, to spot a stalled driver.
+    last_frame: Option<u64>,
+    /// Set while the device is gone (dock unplugged, USB hub asleep).
+    lost: Option<Reconnect>,
+    /// Last warmup asked for, repeated once a lost camera returns.
+    warmup: (usize, f32),
+    /// Exposure time and gain set on every open, in driver units.
+    fixed_exposure: Option<i64>,
+    fixed_gain: Option<i64>,
+}
+
+/// Attempts to reopen a camera that disappeared, and when to try next.
+#[derive(Debug, Clone, Copy)]
+struct Reconnect {
+    attempts: u32,
+    retry_at: Instant,
 }
 
 impl Camera {
-    pub fn open(idx: usize, w: u32, h: u32) -> Result<Self, Box<dyn Error>> {
-        let mut dev = Device::new(idx)?;
-        let mut fmt = dev.format()?;
-        fmt.width = w;
-        fmt.height = h;
-        fmt.fourcc = FourCC::new(b"YUYV");
-        dev.set_format(&fmt)?;
-        let stream = MmapStream::with_buffers(&mut dev, Type::VideoCapture, 4)?;
-        Ok(Self {
-            _dev: dev,
-            stream,
+    /// Open the camera and keep it streaming until dropped.
+    pub fn open(idx: usize, w: u32, h: u32, pixel: PixelFormat) -> Result<Self, Box<dyn Error>> {
+        let mut cam = Self::new(idx, w, h, pixel, CaptureMode::Continuous, 1);
+        cam.ensure_open()?;
+        Ok(cam)
+    }
+
+    /// Open the camera in burst mode: the device is only powered for
+    /// `frames` frames per measurement and closed in between.
+    pub fn open_burst(
+        idx: usize,
+        w: u32,
+        h: u32,
+        pixel: PixelFormat,
+        frames: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut cam = Self::new(idx, w, h, pixel, CaptureMode::Burst, frames.max(1));
+        // Probe once so a missing or busy device is reported at startup.
+        cam.ensure_open()?;
+        cam.close();
+        Ok(cam)
+    }
+
+    fn new(
+        idx: usize,
+        w: u32,
+        h: u32,
+        pixel: PixelFormat,
+        mode: CaptureMode,
+        burst_frames: usize,
+    ) -> Self {
+        Self {
+            index: idx,
             width: w,
             height: h,
-        })
+            pixel,
+            format: FrameFormat::new(w, h, pixel),
+            format_reported: false,
+            mode,
+            burst_frames,
+            session: None,
+            activity: ActivityIndicator::new(),
+            analysis: FrameAnalysis::default(),
+            lux: None,
+            last_luma: 0.0,
+            last_exposure: None,
+            warm_start: Duration::ZERO,
+            blank_frames: 0,
+            last_frame: None,
+            lost: None,
+            warmup: (0, 0.0),
+            fixed_exposure: None,
+            fixed_gain: None,
+        }
     }
 
-    pub fn warmup(&mut self, frames: usize) {
-        eprintln!("Warming up camera…");
-        for _ in 0..frames {
-            let _ = self.stream.next();
+    /// Cap warmup at `WARMUP_MIN_FRAMES` if the camera was streaming within
+    /// `window`, e.g. before a restart or the previous interval-mode run.
+    pub fn set_warm_start(&mut self, window: Duration) {
+        self.warm_start = window;
+    }
+
+    /// Hold exposure time and gain at these values instead of leaving them
+    /// to auto-exposure, from the next time the device is opened.
+    pub fn set_fixed_exposure(&mut self, exposure: Option<i64>, gain: Option<i64>) {
+        self.fixed_exposure = exposure;
+        self.fixed_gain = gain;
+    }
+
+    pub fn set_lux_estimator(&mut self, lux: LuxEstimator) {
+        self.lux = Some(lux);
+    }
+
+    /// Approximate illuminance of the last measured frame, when the driver
+    /// reports its exposure settings.
+    pub fn lux(&self) -> Option<f32> {
+        let estimator = self.lux.as_ref()?;
+        estimator.estimate(self.last_luma, self.last_exposure?)
+    }
+
+    pub fn set_bright_mask(&mut self, mask: Option<BrightMask>) {
+        self.analysis.mask = mask;
+    }
+
+    /// Only read the part `[x, y, w, h]` of the frame, in shares of its
+    /// width and height.
+    pub fn set_roi(&mut self, roi: [f32; 4]) {
+        self.analysis.roi = roi;
+        self.analysis.geometry = None;
+    }
+
+    /// Never read the central `fraction` of the frame's width and height.
+    pub fn set_center_exclusion(&mut self, fraction: f32) {
+        self.analysis.exclude_center = fraction;
+        self.analysis.geometry = None;
+    }
+
+    pub fn set_luma_weighting(&mut self, weighting: LumaWeighting) {
+        self.analysis.weighting = weighting;
+        self.analysis.geometry = None;
+    }
+
+    pub fn set_luma_metric(&mut self, metric: LumaMetric) {
+        self.analysis.metric = metric;
+    }
+
+    pub fn set_motion_detection(&mut self, enabled: bool) {
+        self.analysis.motion = enabled.then(MotionDetector::new);
+    }
+
+    /// Number of grid cells currently down-weighted as bright sources.
+    pub fn masked_cells(&self) -> usize {
+        self.analysis.mask.as_ref().map_or(0, BrightMask::masked_cells)
+    }
+
+    /// Motion score of the last measured frame (0 when detection is off).
+    pub fn motion(&self) -> f32 {
+        self.analysis.last_motion
+    }
+
+    /// Exposure settings of the last measured frame.
+    pub fn last_exposure(&self) -> Option<Exposure> {
+        self.last_exposure
+    }
+
+    /// Manual exposure range, if the driver offers one.
+    pub fn exposure_control(&mut self) -> Option<ExposureControl> {
+        self.ensure_open().ok()?.exposure_control()
+    }
+
+    /// Manual exposure time the driver reports, None under auto-exposure.
+    pub fn manual_exposure(&mut self) -> Option<i64> {
+        self.ensure_open().ok()?.manual_exposure()
+    }
+
+    /// Turn auto-exposure off at a fixed exposure time, until
+    /// `unlock_exposure` or the device is closed.
+    pub fn lock_exposure(&mut self, value: i64) -> Result<(), Box<dyn Error>> {
+        self.ensure_open()?.lock_exposure(value)
+    }
+
+    pub fn unlock_exposure(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.session.as_mut() {
+            Some(session) => session.unlock_exposure(),
+            None => Ok(()),
         }
-        eprintln!("Camera ready.");
     }
 
-    pub fn measure_luma(&mut self, half_precision: bool) -> Result<f32, Box<dyn Error>> {
-        let (buf, _) = self.stream.next()?;
-        let mut sum: f32 = 0.0;
-        let mut weight_sum: f32 = 0.0;
-
-        let w = self.width as usize;
-        let h = self.height as usize;
-        let cx = w / 2;
-        let cy = h / 2;
-        let max_dist_sq = ((cx * cx + cy * cy) as f32).max(1.0);
-
-        // YUYV format: 4 bytes = 2 pixels.
-        // Byte 0: Y0, Byte 1: U, Byte 2: Y1, Byte 3: V
-        // We iterate 2 bytes at a time to get each Y.
-        // Stride: If half_precision, step by 4 (skip every other Y).
-        // Y values are at index 0, 2, 4, 6...
-        
-        let step = if half_precision { 4 } else { 2 };
-        
-        // We need to track pixel coordinates for center weighting.
-        // Each step advances 1 pixel (if step=2) or 2 pixels (if step=4) but wait...
-        // chunks_exact(2) gave us pairs.
-        // Let's iterate raw buffer bytes.
-        
-        for (i, chunk) in buf.chunks(step).enumerate() {
-            if chunk.is_empty() { break; }
-            let y = chunk[0] as f32; // Y component is always at optional offset 0 of the block if we align right.
-            // Wait, YUYV = Y0 U0 Y1 V0
-            // idx 0 -> Y0
-            // idx 2 -> Y1
-            // idx 4 -> Y2
-            // If we step by 2, we get Y0, Y1, Y2...
-            // If we step by 4, we get Y0, Y2, Y4... (Skipping Y1, Y3) -> This is half precision.
-            
-            // To calculate weight, we need (x, y) coords.
-            // Pixel index = i * (step / 2) -> because each Y is 2 bytes in memory (effectively)
-            // No, Y is 1 byte, but shared UV makes it "2 bytes per pixel" on average, but positionally:
-            // Byte 0 is Px0, Byte 2 is Px1.
-            
-            let pixel_idx = if half_precision { i * 2 } else { i };
-            if pixel_idx >= w * h { break; }
-            
-            let px = pixel_idx % w;
-            let py = pixel_idx / w;
-            
-            // Simple center weight: 1.0 at center, falling off to 0.2 at edges
-            let dx = (px as isize - cx as isize) as f32;
-            let dy = (py as isize - cy as isize) as f32;
-            let dist_sq = dx*dx + dy*dy;
-            let weight = 1.0 - 0.8 * (dist_sq / max_dist_sq).min(1.0);
-            
-            sum += y * weight;
-            weight_sum += weight;
-        }
-
-        let avg = if weight_sum > 0.0 {
-            (sum / weight_sum) / 255.0
+    fn ensure_open(&mut self) -> Result<&mut Session, Box<dyn Error>> {
+        if self.session.is_none() {
+            let session = Session::open(self.index, self.width, self.height, self.pixel)?;
+            self.format = session.format();
+            self.session = Some(session);
+            self.activity.set(true);
+            debug!(
+                target: "camera",
+                "opened {} at {}x{}",
+                device_name(self.index),
+                self.format.width,
+                self.format.height
+            );
+            if !self.format_reported {
+                self.format_reported = true;
+                self.report_format();
+            }
+            self.apply_fixed_exposure();
+        }
+        Ok(self.session.as_mut().expect("session opened above"))
+    }
+
+    /// A control the driver refuses is reported once per open and left to
+    /// the camera; measuring goes on either way.
+    fn apply_fixed_exposure(&mut self) {
+        let name = device_name(self.index);
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Some(value) = self.fixed_exposure
+            && let Err(e) = session.lock_exposure(value)
+        {
+            warn!(target: "camera", "Cannot fix the exposure of {}: {}", name, e);
+        }
+        if let Some(value) = self.fixed_gain
+            && let Err(e) = session.set_gain(value)
+        {
+            warn!(target: "camera", "Cannot fix the gain of {}: {}", name, e);
+        }
+    }
+
+    /// Log what the driver agreed to, which can differ from what was asked.
+    fn report_format(&self) {
+        let f = self.format;
+        // GREY is picked over the configured format when offered.
+        let pixel_differs = f.pixel != self.pixel && f.pixel != PixelFormat::Grey;
+        if (f.width, f.height) != (self.width, self.height) || pixel_differs {
+            warn!(
+                target: "camera",
+                "{} delivers {}x{} {} instead of the requested {}x{} {}",
+                device_name(self.index),
+                f.width,
+                f.height,
+                f.pixel,
+                self.width,
+                self.height,
+                self.pixel
+            );
         } else {
-            0.0
+            info!(
+                target: "camera",
+                "{}: {}x{} {}",
+                device_name(self.index),
+                f.width,
+                f.height,
+                f.pixel
+            );
+        }
+        debug!(target: "camera", "{} bytes per row", f.stride);
+    }
+
+    /// Release the device; the next warmup or measurement reopens it.
+    pub fn close(&mut self) {
+        if self.session.take().is_some() {
+            self.activity.set(false);
+            if self.mode == CaptureMode::Continuous {
+                WarmStamp::touch(self.warm_start);
+            }
+            debug!(target: "camera", "closed {}", device_name(self.index));
+        }
+    }
+
+    /// Discard frames while auto-exposure settles. Stops once
+    /// `WARMUP_STABLE_FRAMES` consecutive frames differ in luma by at most
+    /// `tolerance`, and after `max_frames` at the latest; a tolerance of 0
+    /// always uses the full count.
+    pub fn warmup(&mut self, max_frames: usize, tolerance: f32) {
+        self.warmup = (max_frames, tolerance);
+        if self.mode == CaptureMode::Burst || self.lost.is_some() {
+            // Nothing to warm: every burst starts from a freshly opened
+            // device, and a lost one is warmed once it returns.
+            return;
+        }
+        let max_frames = if !self.warm_start.is_zero() && WarmStamp::is_warm(self.warm_start) {
+            debug!(target: "camera", "camera was used recently; shortening warmup");
+            max_frames.min(WARMUP_MIN_FRAMES)
+        } else {
+            max_frames
+        };
+        info!(target: "camera", "Warming up camera (up to {} frames)", max_frames);
+        let index = self.index;
+        let analysis = &self.analysis;
+        let (roi, exclude_center, weighting, metric) =
+            (analysis.roi, analysis.exclude_center, analysis.weighting, analysis.metric);
+        let session = match self.ensure_open() {
+            Ok(session) => session,
+            Err(e) => {
+                warn!(target: "camera", "Cannot warm up {}: {}", device_name(index), e);
+                return;
+            }
+        };
+        let format = session.format();
+        let geometry = Geometry::new(roi, exclude_center, weighting, &format);
+        let mut prev: Option<f32> = None;
+        let mut stable = 0;
+        let mut used = 0;
+        for _ in 0..max_frames {
+            used += 1;
+            let buf = match session.next_frame() {
+                Ok(buf) => buf,
+                Err(e) => {
+                    debug!(target: "camera", "warmup frame {} failed: {}", used, e);
+                    continue;
+                }
+            };
+            let luma = frame_luma(buf, &format, &geometry, true, metric, None, None);
+            trace!(target: "camera", "warmup frame {}: luma {:.4}", used, luma);
+            stable = match prev {
+                Some(p) if tolerance > 0.0 && (luma - p).abs() <= tolerance => stable + 1,
+                _ => 0,
+            };
+            prev = Some(luma);
+            if used >= WARMUP_MIN_FRAMES && stable >= WARMUP_STABLE_FRAMES {
+                break;
+            }
+        }
+        info!(
+            target: "camera",
+            "Camera ready after {} of {} frames (luma {:.4})",
+            used,
+            max_frames,
+            prev.unwrap_or(0.0)
+        );
+        WarmStamp::touch(self.warm_start);
+    }
+
+    pub fn measure_luma(&mut self, half_precision: bool) -> Result<f32, Box<dyn Error>> {
+        self.reconnect()?;
+        match self.mode {
+            CaptureMode::Continuous => {
+                let mut analysis = std::mem::take(&mut self.analysis);
+                let result = self.ensure_open().and_then(|session| {
+                    let format = session.format();
+                    let buf = session.next_frame()?;
+                    let sample = FrameSample::take(buf, &format, &analysis.geometry(&format).unread);
+                    let luma = analysis.process(buf, &format, half_precision);
+                    Ok((luma, sample, session.exposure()))
+                });
+                self.analysis = analysis;
+                self.record(result)
+            }
+            CaptureMode::Burst => {
+                let frames = self.burst_frames;
+                let mut analysis = std::mem::take(&mut self.analysis);
+                let result = self.ensure_open().and_then(|session| {
+                    // Later frames have had the most time for auto-exposure
+                    // to settle, so only the last one is measured.
+                    for _ in 1..frames {
+                        session.next_frame()?;
+                    }
+                    let format = session.format();
+                    let buf = session.next_frame()?;
+                    let sample = FrameSample::take(buf, &format, &analysis.geometry(&format).unread);
+                    let luma = analysis.process(buf, &format, half_precision);
+                    Ok((luma, sample, session.exposure()))
+                });
+                self.analysis = analysis;
+                self.close();
+                self.record(result)
+            }
+        }
+    }
+
+    /// Reopen a camera that disappeared once its backoff has passed, and
+    /// warm it up again; an error while it is still gone.
+    fn reconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(lost) = self.lost else {
+            return Ok(());
         };
-        Ok(avg.clamp(0.0, 1.0))
+        let now = Instant::now();
+        if now < lost.retry_at {
+            return Err(format!(
+                "{} is gone; reopening in {:.0?}",
+                device_name(self.index),
+                lost.retry_at - now
+            )
+            .into());
+        }
+        if let Err(e) = self.ensure_open() {
+            let attempts = lost.attempts + 1;
+            let delay = reconnect_delay(attempts);
+            debug!(
+                target: "camera",
+                "reopening {} failed ({}); next attempt in {:.0?}",
+                device_name(self.index),
+                e,
+                delay
+            );
+            self.lost = Some(Reconnect {
+                attempts,
+                retry_at: now + delay,
+            });
+            return Err(e);
+        }
+        self.lost = None;
+        info!(target: "camera", "{} is back", device_name(self.index));
+        let (frames, tolerance) = self.warmup;
+        self.warmup(frames, tolerance);
+        Ok(())
+    }
+
+    /// Drop the session of a camera that was unplugged and start trying
+    /// to reopen it.
+    fn lose(&mut self) {
+        warn!(
+            target: "camera",
+            "{} disappeared; reopening in {:.0?}",
+            device_name(self.index),
+            RECONNECT_MIN
+        );
+        self.close();
+        self.last_frame = None;
+        self.blank_frames = 0;
+        self.lost = Some(Reconnect {
+            attempts: 0,
+            retry_at: Instant::now() + RECONNECT_MIN,
+        });
+    }
+
+    fn record(
+        &mut self,
+        result: Result<Measured, Box<dyn Error>>,
+    ) -> Result<f32, Box<dyn Error>> {
+        let (luma, sample, exposure) = match result {
+            Ok(measured) => measured,
+            Err(e) => {
+                if is_unplugged(e.as_ref()) {
+                    self.lose();
+                }
+                return Err(classify_read_error(e));
+            }
+        };
+        let previous = std::mem::replace(&mut self.last_frame, sample.map(|s| s.hash));
+        let fault = sample.and_then(|s| s.fault(previous));
+        // A blank frame is never a reading: it would drive brightness to the
+        // minimum while the camera is merely covered.
+        if fault == Some(FrameFault::Blank) {
+            self.blank_frames += 1;
+            if self.blank_frames >= PRIVACY_BLANK_FRAMES {
+                return Err(Box::new(PrivacyBlocked));
+            }
+        } else {
+            self.blank_frames = 0;
+        }
+        if let Some(fault) = fault {
+            if fault == FrameFault::Frozen {
+                // Reopening is what usually gets a stalled driver going.
+                self.close();
+                self.last_frame = None;
+            }
+            return Err(Box::new(fault));
+        }
+        self.last_luma = luma;
+        self.last_exposure = exposure;
+        if let Some(e) = exposure {
+            trace!(
+                target: "camera",
+                "exposure {:.1} ms, gain {:.2}x, ~{:.0} lx",
+                e.time_secs * 1000.0,
+                e.gain,
+                self.lux().unwrap_or(0.0)
+            );
+        }
+        Ok(luma)
     }
 
     /// Legacy wrapper or for calibration (full precision, flat average)
@@ -123,3 +678,525 @@ impl Camera {
         Ok(acc / frames as f32)
     }
 }
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/smart-brightness/camera-warm`: the Unix time until
+/// which the camera counts as warm, written after warmup and on close.
+/// Wall-clock time, unlike `Instant`, moves on in suspend, after which the
+/// camera is cold again.
+struct WarmStamp;
+
+impl WarmStamp {
+    fn path() -> PathBuf {
+        runtime_dir().join("camera-warm")
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+
+    /// A stamp further ahead than `window` was written before the clock
+    /// was set back, and says nothing.
+    fn is_warm(window: Duration) -> bool {
+        let now = Self::now();
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .is_some_and(|until| now < until && until - now <= window.as_secs())
+    }
+
+    fn touch(window: Duration) {
+        if window.is_zero() {
+            return;
+        }
+        let dir = runtime_dir();
+        if fs::create_dir_all(&dir).is_ok() {
+            let _ = fs::write(Self::path(), format!("{}\n", Self::now() + window.as_secs()));
+        }
+    }
+}
+
+/// Optional per-frame analysis that needs region statistics.
+struct FrameAnalysis {
+    /// See `Unread`.
+    roi: [f32; 4],
+    exclude_center: f32,
+    weighting: LumaWeighting,
+    /// Built for the first frame of a format, i.e. once after opening;
+    /// dropped when a setting it depends on changes.
+    geometry: Option<(FrameFormat, Geometry)>,
+    metric: LumaMetric,
+    mask: Option<BrightMask>,
+    motion: Option<MotionDetector>,
+    last_motion: f32,
+}
+
+impl Default for FrameAnalysis {
+    fn default() -> Self {
+        Self {
+            roi: FULL_FRAME,
+            exclude_center: 0.0,
+            weighting: LumaWeighting::default(),
+            geometry: None,
+            metric: LumaMetric::default(),
+            mask: None,
+            motion: None,
+            last_motion: 0.0,
+        }
+    }
+}
+
+impl FrameAnalysis {
+    fn geometry(&mut self, format: &FrameFormat) -> &Geometry {
+        if self.geometry.as_ref().is_none_or(|(built, _)| built != format) {
+            let geometry = Geometry::new(self.roi, self.exclude_center, self.weighting, format);
+            self.geometry = Some((*format, geometry));
+        }
+        &self.geometry.as_ref().expect("built above").1
+    }
+
+    fn process(&mut self, buf: &[u8], format: &FrameFormat, half_precision: bool) -> f32 {
+        let mut grid = (self.mask.is_some() || self.motion.is_some()).then(CellGrid::new);
+        self.geometry(format);
+        // Taken from the field so the mask can be borrowed alongside.
+        let geometry = &self.geometry.as_ref().expect("built above").1;
+        let mask = self.mask.as_ref();
+        let luma = frame_luma(buf, format, geometry, half_precision, self.metric, mask, grid.as_mut());
+        if let Some(grid) = &grid {
+            if let Some(mask) = self.mask.as_mut() {
+                mask.update(grid);
+            }
+            if let Some(motion) = self.motion.as_mut() {
+                self.last_motion = motion.update(grid);
+            }
+        }
+        trace!(
+            target: "camera",
+            "frame luma {:.4}, motion {:.3}",
+            luma,
+            self.last_motion
+        );
+        luma
+    }
+}
+
+/// Access refused while streaming: privacy toggles that cut the camera off
+/// in firmware show up as EACCES.
+fn classify_read_error(err: Box<dyn Error>) -> Box<dyn Error> {
+    match err.downcast::<io::Error>() {
+        Ok(e) if e.kind() == io::ErrorKind::PermissionDenied => Box::new(PrivacyBlocked),
+        Ok(e) => e,
+        Err(other) => other,
+    }
+}
+
+/// A read or open error meaning the device node is gone.
+fn is_unplugged(err: &(dyn Error + 'static)) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.raw_os_error() == Some(ENODEV) || e.kind() == io::ErrorKind::NotFound)
+}
+
+/// Wait before reopening attempt `attempts` of a lost camera.
+fn reconnect_delay(attempts: u32) -> Duration {
+    RECONNECT_MIN
+        .saturating_mul(1 << attempts.min(16))
+        .min(RECONNECT_MAX)
+}
+
+/// Luma, sample and exposure settings of a measured frame.
+type Measured = (f32, Option<FrameSample>, Option<Exposure>);
+
+/// Range and hash of the Y values of every seventh pixel of a frame.
+#[derive(Debug, Clone, Copy)]
+struct FrameSample {
+    min: u8,
+    max: u8,
+    hash: u64,
+}
+
+impl FrameSample {
+    fn take(buf: &[u8], format: &FrameFormat, unread: &Unread) -> Option<Self> {
+        let w = format.width as usize;
+        let mut ys = (0..w * format.height as usize)
+            .step_by(7)
+            .filter(|i| !unread.contains(i % w, i / w))
+            .map_while(|i| buf.get(format.luma_index(i % w, i / w)));
+        let &first = ys.next()?;
+        // FNV-1a
+        let step = |hash: u64, y: u8| (hash ^ y as u64).wrapping_mul(0x0100_0000_01b3);
+        let init = Self {
+            min: first,
+            max: first,
+            hash: step(0xcbf2_9ce4_8422_2325, first),
+        };
+        Some(ys.fold(init, |s, &y| Self {
+            min: s.min.min(y),
+            max: s.max.max(y),
+            hash: step(s.hash, y),
+        }))
+    }
+
+    /// What is wrong with the frame, given the previous frame's hash. Sensor
+    /// noise keeps two live frames from ever being identical.
+    fn fault(&self, previous: Option<u64>) -> Option<FrameFault> {
+        if self.max <= BLANK_MAX_Y && self.max - self.min <= BLANK_MAX_SPREAD {
+            Some(FrameFault::Blank)
+        } else if self.min >= SATURATED_MIN_Y {
+            Some(FrameFault::Saturated)
+        } else if previous == Some(self.hash) {
+            Some(FrameFault::Frozen)
+        } else {
+            None
+        }
+    }
+}
+
+/// The pixels of a frame that are never read: those outside the region of
+/// interest (`camera_roi`), and the central part (`camera_exclude_center`),
+/// `fraction` of the frame's width and of its height, where a face in front
+/// of the screen usually is. The hole is empty for 0.
+struct Unread {
+    roi_x: Range<usize>,
+    roi_y: Range<usize>,
+    x: Range<usize>,
+    y: Range<usize>,
+}
+
+impl Unread {
+    fn new(roi: [f32; 4], fraction: f32, format: &FrameFormat) -> Self {
+        let span = |n: u32| {
+            let cut = (n as f32 * fraction).round() as usize;
+            let start = (n as usize - cut.min(n as usize)) / 2;
+            start..start + cut
+        };
+        let [x, y, w, h] = roi;
+        let part = |n: u32, from: f32, size: f32| {
+            let at = |share: f32| (n as f32 * share.clamp(0.0, 1.0)).round() as usize;
+            at(from)..at(from + size).max(at(from) + 1)
+        };
+        Self {
+            roi_x: part(format.width, x, w),
+            roi_y: part(format.height, y, h),
+            x: span(format.width),
+            y: span(format.height),
+        }
+    }
+
+    fn contains(&self, x: usize, y: usize) -> bool {
+        !(self.roi_x.contains(&x) && self.roi_y.contains(&y)) || (self.x.contains(&x) && self.y.contains(&y))
+    }
+}
+
+/// What reading a frame of one format takes that doesn't change from frame
+/// to frame: the pixels to skip, and the weight of every other one, worked
+/// out once rather than per pixel and frame.
+struct Geometry {
+    unread: Unread,
+    /// Row by row, `width` × `height`.
+    weights: Vec<f32>,
+}
+
+impl Geometry {
+    fn new(roi: [f32; 4], exclude_center: f32, weighting: LumaWeighting, format: &FrameFormat) -> Self {
+        let (w, h) = (format.width as usize, format.height as usize);
+        let (cx, cy) = ((w / 2) as f32, (h / 2) as f32);
+        let max_dist_sq = (cx * cx + cy * cy).max(1.0);
+        let bottom = h.saturating_sub(1).max(1) as f32;
+        let weight = |px: usize, py: usize| match weighting {
+            LumaWeighting::Center => {
+                let (dx, dy) = (px as f32 - cx, py as f32 - cy);
+                1.0 - 0.8 * ((dx * dx + dy * dy) / max_dist_sq).min(1.0)
+            }
+            LumaWeighting::Flat => 1.0,
+            LumaWeighting::Top => 1.0 - 0.8 * py as f32 / bottom,
+        };
+        Self {
+            unread: Unread::new(roi, exclude_center, format),
+            weights: (0..h)
+                .flat_map(|py| (0..w).map(move |px| (px, py)))
+                .map(|(px, py)| weight(px, py))
+                .collect(),
+        }
+    }
+}
+
+/// Weighted luma of a frame, normalized to [0, 1], each pixel counting by
+/// the weight `geometry` has for it. `metric` picks the mean or a
+/// percentile of the weighted histogram. Regions under the
+/// bright mask are down-weighted and pixels `geometry` leaves unread are
+/// skipped without being read; `grid` collects per-region luma. Half
+/// precision reads every other column. Formats whose rows are plain Y bytes
+/// (GREY, the Y plane of NV12) are walked row by row without per-pixel
+/// offsets.
+fn frame_luma(
+    buf: &[u8],
+    format: &FrameFormat,
+    geometry: &Geometry,
+    half_precision: bool,
+    metric: LumaMetric,
+    mask: Option<&BrightMask>,
+    mut grid: Option<&mut CellGrid>,
+) -> f32 {
+    let (w, h) = (format.width as usize, format.height as usize);
+    let unread = &geometry.unread;
+    let col_step = if half_precision { 2 } else { 1 };
+
+    let mut sum: f32 = 0.0;
+    let mut weight_sum: f32 = 0.0;
+    // Weight per Y value.
+    let mut histogram = [0.0f32; 256];
+    let mut add = |px: usize, py: usize, y: u8| {
+        let bin = y as usize;
+        let y = y as f32;
+        let mut weight = geometry.weights[py * w + px];
+        if let Some(g) = grid.as_deref_mut() {
+            let cell = CellGrid::index(px, py, w, h);
+            if let Some(m) = mask {
+                weight *= m.weight(cell);
+            }
+            g.add(cell, y);
+        }
+        sum += y * weight;
+        weight_sum += weight;
+        histogram[bin] += weight;
+    };
+    if format.pixel.luma_layout() == (0, 1) {
+        // A short buffer leaves the last rows short or missing.
+        let rows = buf.chunks(format.stride.max(1) as usize).take(h);
+        for (py, row) in rows.enumerate() {
+            let row = row.iter().take(w).enumerate().step_by(col_step);
+            for (px, &y) in row.filter(|(px, _)| !unread.contains(*px, py)) {
+                add(px, py, y);
+            }
+        }
+    } else {
+        'rows: for py in 0..h {
+            for px in (0..w).step_by(col_step) {
+                if unread.contains(px, py) {
+                    continue;
+                }
+                // A short buffer ends the frame early rather than panicking.
+                let Some(&y) = buf.get(format.luma_index(px, py)) else {
+                    break 'rows;
+                };
+                add(px, py, y);
+            }
+        }
+    }
+
+    if weight_sum <= 0.0 {
+        return 0.0;
+    }
+    let luma = match metric.quantile() {
+        None => sum / weight_sum,
+        Some(q) => {
+            let wanted = q * weight_sum;
+            let mut below = 0.0;
+            histogram
+                .iter()
+                .position(|w| {
+                    below += w;
+                    below >= wanted
+                })
+                .unwrap_or(255) as f32
+        }
+    };
+    (luma / 255.0).clamp(0.0, 1.0)
+}
+
+/// Whether a camera is powered, for `ctl status`.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the daemon has the webcam open right now.
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Publishes whether the webcam is powered to
+/// `$XDG_RUNTIME_DIR/smart-brightness/camera-active` ("1"/"0") so status
+/// bars and privacy indicators can show it, and to `is_active`.
+struct ActivityIndicator {
+    path: Option<PathBuf>,
+}
+
+impl ActivityIndicator {
+    fn new() -> Self {
+        let dir = runtime_dir();
+        let path = fs::create_dir_all(&dir)
+            .ok()
+            .map(|_| dir.join("camera-active"));
+        Self { path }
+    }
+
+    fn set(&self, active: bool) {
+        ACTIVE.store(active, Ordering::Relaxed);
+        if let Some(path) = &self.path {
+            let _ = fs::write(path, if active { "1\n" } else { "0\n" });
+        }
+    }
+}
+
+impl Drop for ActivityIndicator {
+    fn drop(&mut self) {
+        self.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A YUYV frame with the given Y for every pixel.
+    fn frame(w: usize, h: usize, y: impl Fn(usize) -> u8) -> Vec<u8> {
+        (0..w * h).flat_map(|i| [y(i), 128]).collect()
+    }
+
+    const FORMAT: FrameFormat = FrameFormat {
+        width: 64,
+        height: 48,
+        stride: 128,
+        pixel: PixelFormat::Yuyv,
+    };
+
+    fn geometry(roi: [f32; 4], exclude_center: f32, weighting: LumaWeighting) -> Geometry {
+        Geometry::new(roi, exclude_center, weighting, &FORMAT)
+    }
+
+    fn fault(buf: &[u8], previous: Option<&[u8]>) -> Option<FrameFault> {
+        let unread = Unread::new(FULL_FRAME, 0.0, &FORMAT);
+        let take = |buf: &[u8]| FrameSample::take(buf, &FORMAT, &unread);
+        let previous = previous.and_then(take).map(|s| s.hash);
+        take(buf)?.fault(previous)
+    }
+
+    #[test]
+    fn uniform_black_is_blank_but_a_dark_room_is_not() {
+        assert_eq!(fault(&frame(64, 48, |_| 16), None), Some(FrameFault::Blank));
+        assert_eq!(fault(&frame(64, 48, |_| 0), None), Some(FrameFault::Blank));
+        // Sensor noise in a dark room.
+        assert_eq!(fault(&frame(64, 48, |i| 16 + (i * 7 % 5) as u8), None), None);
+        // A dim but uniform scene is brighter than a blanked sensor.
+        assert_eq!(fault(&frame(64, 48, |_| 40), None), None);
+    }
+
+    #[test]
+    fn luma_is_read_from_the_negotiated_layout() {
+        let y = |i: usize| (i * 31 % 220) as u8;
+        let yuyv = frame(64, 48, y);
+        let grey: Vec<u8> = (0..64 * 48).map(y).collect();
+        // UYVY with each row padded to 136 bytes.
+        let uyvy: Vec<u8> = (0..48)
+            .flat_map(|row| {
+                (0..64)
+                    .flat_map(move |col| [128, y(row * 64 + col)])
+                    .chain([0; 8])
+            })
+            .collect();
+        let geometry = geometry(FULL_FRAME, 0.0, LumaWeighting::Center);
+        let luma = |buf: &[u8], format: FrameFormat, half: bool| {
+            frame_luma(buf, &format, &geometry, half, LumaMetric::Mean, None, None)
+        };
+        let grey_format = FrameFormat::new(64, 48, PixelFormat::Grey);
+        let expected = luma(&yuyv, FORMAT, false);
+        assert_eq!(luma(&grey, grey_format, false), expected);
+        assert_eq!(luma(&grey, grey_format, true), luma(&yuyv, FORMAT, true));
+        let padded = FrameFormat {
+            stride: 136,
+            ..FrameFormat::new(64, 48, PixelFormat::Uyvy)
+        };
+        assert_eq!(luma(&uyvy, padded, false), expected);
+    }
+
+    #[test]
+    fn excluded_center_contributes_nothing() {
+        let holed = geometry(FULL_FRAME, 0.5, LumaWeighting::Center);
+        let unread = &holed.unread;
+        assert_eq!((unread.x.clone(), unread.y.clone()), (16..48, 12..36));
+        let face = |i: usize| unread.contains(i % 64, i / 64);
+        let lit = frame(64, 48, |i| if face(i) { 250 } else { 60 });
+        let dark = frame(64, 48, |i| if face(i) { 5 } else { 60 });
+        let luma = |buf: &[u8], geometry: &Geometry| {
+            frame_luma(buf, &FORMAT, geometry, false, LumaMetric::Mean, None, None)
+        };
+        let outside = luma(&lit, &holed);
+        assert_eq!(outside, luma(&dark, &holed));
+        assert!((outside - 60.0 / 255.0).abs() < 1e-4);
+        assert!(luma(&lit, &geometry(FULL_FRAME, 0.0, LumaWeighting::Center)) > outside + 0.2);
+        let take = |buf: &[u8]| FrameSample::take(buf, &FORMAT, unread).unwrap().hash;
+        assert_eq!(take(&lit), take(&dark));
+    }
+
+    #[test]
+    fn only_the_region_of_interest_is_read() {
+        // Lit top third, a reflection from the keyboard below.
+        let buf = frame(64, 48, |i| if i / 64 < 16 { 90 } else { 240 });
+        let top = geometry([0.0, 0.0, 1.0, 1.0 / 3.0], 0.0, LumaWeighting::Center);
+        assert_eq!((top.unread.roi_x.clone(), top.unread.roi_y.clone()), (0..64, 0..16));
+        let luma = frame_luma(&buf, &FORMAT, &top, false, LumaMetric::Mean, None, None);
+        assert!((luma - 90.0 / 255.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn infrared_cameras_are_told_by_name_or_formats() {
+        let kind = CameraKind::classify;
+        assert_eq!(kind("Integrated_Webcam_HD: Integrate", &[*b"MJPG", *b"YUYV"]), CameraKind::Visible);
+        assert_eq!(kind("Integrated IR Camera", &[*b"YUYV"]), CameraKind::Infrared);
+        assert_eq!(kind("Intel(R) RealSense(TM) Depth Camera", &[]), CameraKind::Infrared);
+        assert_eq!(kind("HD Webcam", &[*b"GREY"]), CameraKind::Infrared);
+        assert_eq!(kind("Irwin HD Webcam", &[*b"YUYV"]), CameraKind::Visible);
+    }
+
+    #[test]
+    fn weighting_kernels_favor_their_part_of_the_frame() {
+        // Bright sky above, dark desk below.
+        let buf = frame(64, 48, |i| if i / 64 < 24 { 200 } else { 20 });
+        let luma = |weighting| {
+            let geometry = geometry(FULL_FRAME, 0.0, weighting);
+            frame_luma(&buf, &FORMAT, &geometry, false, LumaMetric::Mean, None, None)
+        };
+        let flat = luma(LumaWeighting::Flat);
+        assert!((flat - 110.0 / 255.0).abs() < 1e-4);
+        assert!(luma(LumaWeighting::Top) > flat + 0.05);
+        let corners = geometry(FULL_FRAME, 0.0, LumaWeighting::Center);
+        assert!((corners.weights[0] - 0.2).abs() < 1e-6);
+        assert_eq!(corners.weights[24 * 64 + 32], 1.0);
+    }
+
+    #[test]
+    fn percentiles_ignore_a_lamp_in_frame() {
+        let lamp = |i: usize| (24..40).contains(&(i % 64)) && (16..32).contains(&(i / 64));
+        let buf = frame(64, 48, |i| if lamp(i) { 250 } else { 60 });
+        let whole = geometry(FULL_FRAME, 0.0, LumaWeighting::Center);
+        let luma = |metric| frame_luma(&buf, &FORMAT, &whole, false, metric, None, None);
+        assert!(luma(LumaMetric::Mean) > 70.0 / 255.0);
+        assert_eq!(luma(LumaMetric::Median), 60.0 / 255.0);
+        assert_eq!(luma(LumaMetric::P25), 60.0 / 255.0);
+    }
+
+    #[test]
+    fn repeated_and_blown_out_frames_are_faults() {
+        let a = frame(64, 48, |i| (i * 13 % 200) as u8);
+        let b = frame(64, 48, |i| (i * 17 % 200) as u8);
+        assert_eq!(fault(&b, Some(&a)), None);
+        assert_eq!(fault(&a, Some(&a)), Some(FrameFault::Frozen));
+        assert_eq!(fault(&frame(64, 48, |i| 240 + (i % 16) as u8), None), Some(FrameFault::Saturated));
+    }
+
+    #[test]
+    fn unplugged_camera_is_retried_with_growing_waits() {
+        let gone: Box<dyn Error> = Box::new(io::Error::from_raw_os_error(ENODEV));
+        assert!(is_unplugged(gone.as_ref()));
+        let busy: Box<dyn Error> = Box::new(io::Error::other("busy"));
+        assert!(!is_unplugged(busy.as_ref()));
+        assert_eq!(reconnect_delay(0), RECONNECT_MIN);
+        assert_eq!(reconnect_delay(3), RECONNECT_MIN * 8);
+        assert_eq!(reconnect_delay(40), RECONNECT_MAX);
+    }
+}
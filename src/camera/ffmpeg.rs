@@ -0,0 +1,179 @@
+// src/camera/ffmpeg.rs
+use std::error::Error;
+use std::io::{self, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use super::{CameraKind, ExposureControl, FrameFormat};
+use crate::config::PixelFormat;
+use crate::lux::Exposure;
+
+#[cfg(target_os = "macos")]
+pub fn device_name(idx: usize) -> String {
+    format!("AVFoundation video device {}", idx)
+}
+
+#[cfg(target_os = "openbsd")]
+pub fn device_name(idx: usize) -> String {
+    format!("/dev/video{}", idx)
+}
+
+#[cfg(target_os = "windows")]
+pub fn device_name(idx: usize) -> String {
+    format!("DirectShow video device {}", idx)
+}
+
+/// How to get ffmpeg, for when it can't be found.
+#[cfg(target_os = "macos")]
+const INSTALL: &str = "brew install ffmpeg";
+#[cfg(target_os = "openbsd")]
+const INSTALL: &str = "pkg_add ffmpeg";
+#[cfg(target_os = "windows")]
+const INSTALL: &str = "winget install Gyan.FFmpeg, then open a new terminal";
+
+/// Error for an ffmpeg that couldn't be started to `what`.
+fn start_error(what: &str, e: io::Error) -> String {
+    if e.kind() == io::ErrorKind::NotFound {
+        format!("cannot {}: ffmpeg is not on PATH (install it: {})", what, INSTALL)
+    } else {
+        format!("cannot start ffmpeg to {}: {}", what, e)
+    }
+}
+
+/// ffmpeg doesn't tell infrared cameras apart; every index counts as one
+/// that sees the room.
+pub fn camera_kind(_idx: usize) -> CameraKind {
+    CameraKind::Visible
+}
+
+/// ffmpeg input options for camera `idx`.
+#[cfg(target_os = "macos")]
+fn input_args(idx: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(["-f", "avfoundation", "-framerate", "30", "-i"]
+        .map(String::from)
+        .into_iter()
+        .chain([format!("{}:none", idx)])
+        .collect())
+}
+
+/// video(4) speaks a V4L2 subset, which ffmpeg's v4l2 input handles.
+#[cfg(target_os = "openbsd")]
+fn input_args(idx: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(["-f", "v4l2", "-i"]
+        .map(String::from)
+        .into_iter()
+        .chain([device_name(idx)])
+        .collect())
+}
+
+/// DirectShow addresses cameras by name, so look up the `idx`-th one.
+#[cfg(target_os = "windows")]
+fn input_args(idx: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let out = Command::new("ffmpeg")
+        .args(["-hide_banner", "-list_devices", "true", "-f", "dshow", "-i", "dummy"])
+        .output()
+        .map_err(|e| start_error("list cameras", e))?;
+    // `[dshow @ 0000020f] "Integrated Camera" (video)`
+    let name = String::from_utf8_lossy(&out.stderr)
+        .lines()
+        .filter(|line| line.trim_end().ends_with("(video)"))
+        .filter_map(|line| line.split('"').nth(1).map(str::to_string))
+        .nth(idx)
+        .ok_or_else(|| format!("no DirectShow camera with index {}", idx))?;
+    Ok(["-f", "dshow", "-i"]
+        .map(String::from)
+        .into_iter()
+        .chain([format!("video={}", name)])
+        .collect())
+}
+
+/// Frames captured by an `ffmpeg` child process, which converts them to the
+/// layout V4L2 would deliver for `camera_fourcc`. The camera (and its LED) is
+/// powered for as long as the process runs.
+pub struct Session {
+    child: Child,
+    stdout: ChildStdout,
+    format: FrameFormat,
+    frame: Vec<u8>,
+}
+
+impl Session {
+    /// ffmpeg converts to whatever is asked, so the format is as requested.
+    pub fn open(idx: usize, w: u32, h: u32, pixel: PixelFormat) -> Result<Self, Box<dyn Error>> {
+        let pixels = w as usize * h as usize;
+        let (pix_fmt, frame_bytes) = match pixel {
+            PixelFormat::Yuyv => ("yuyv422", pixels * 2),
+            PixelFormat::Uyvy => ("uyvy422", pixels * 2),
+            PixelFormat::Grey => ("gray", pixels),
+            PixelFormat::Nv12 => ("nv12", pixels * 3 / 2),
+        };
+        let mut child = Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error"])
+            .args(["-video_size", &format!("{}x{}", w, h)])
+            .args(input_args(idx)?)
+            .args(["-pix_fmt", pix_fmt, "-f", "rawvideo", "pipe:1"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| start_error("capture from the camera", e))?;
+        let stdout = child.stdout.take().ok_or("ffmpeg stdout unavailable")?;
+        Ok(Self {
+            child,
+            stdout,
+            format: FrameFormat::new(w, h, pixel),
+            frame: vec![0; frame_bytes],
+        })
+    }
+
+    pub fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    /// Next frame, laid out as `format`.
+    pub fn next_frame(&mut self) -> Result<&[u8], Box<dyn Error>> {
+        if let Err(e) = self.stdout.read_exact(&mut self.frame) {
+            // Usually camera permission was denied or the size isn't supported.
+            return Err(match self.child.try_wait() {
+                Ok(Some(status)) => format!(
+                    "ffmpeg exited ({}); check camera permission and camera_resolution",
+                    status
+                )
+                .into(),
+                _ => e.into(),
+            });
+        }
+        Ok(&self.frame)
+    }
+
+    /// AVFoundation doesn't expose exposure settings through ffmpeg.
+    pub fn exposure(&self) -> Option<Exposure> {
+        None
+    }
+
+    pub fn exposure_control(&self) -> Option<ExposureControl> {
+        None
+    }
+
+    pub fn manual_exposure(&self) -> Option<i64> {
+        None
+    }
+
+    pub fn lock_exposure(&mut self, _value: i64) -> Result<(), Box<dyn Error>> {
+        Err("exposure can't be controlled through ffmpeg".into())
+    }
+
+    pub fn unlock_exposure(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, _value: i64) -> Result<(), Box<dyn Error>> {
+        Err("gain can't be controlled through ffmpeg".into())
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,217 @@
+// src/camera/v4l2.rs
+use std::error::Error;
+
+use v4l::buffer::Type;
+use v4l::control::{Control, Value};
+use v4l::device::Device;
+use v4l::format::FourCC;
+use v4l::io::traits::CaptureStream;
+use v4l::prelude::MmapStream;
+use v4l::video::Capture;
+
+use super::{CameraKind, ExposureControl, FrameFormat};
+use crate::config::PixelFormat;
+use crate::lux::Exposure;
+
+const V4L2_CID_AUTOGAIN: u32 = 0x0098_0912;
+const V4L2_CID_GAIN: u32 = 0x0098_0913;
+const V4L2_CID_EXPOSURE_AUTO: u32 = 0x009a_0901;
+const V4L2_CID_EXPOSURE_ABSOLUTE: u32 = 0x009a_0902;
+const V4L2_EXPOSURE_MANUAL: i64 = 1;
+
+pub fn device_name(idx: usize) -> String {
+    format!("/dev/video{}", idx)
+}
+
+/// What `/dev/video<idx>` is, without starting a stream.
+pub fn camera_kind(idx: usize) -> CameraKind {
+    let Ok(dev) = Device::new(idx) else {
+        return CameraKind::Other;
+    };
+    let Ok(caps) = dev.query_caps() else {
+        return CameraKind::Other;
+    };
+    if !caps.capabilities.contains(v4l::capability::Flags::VIDEO_CAPTURE) {
+        return CameraKind::Other;
+    }
+    let formats: Vec<[u8; 4]> = dev
+        .enum_formats()
+        .map(|formats| formats.iter().map(|f| f.fourcc.repr).collect())
+        .unwrap_or_default();
+    CameraKind::classify(&caps.card, &formats)
+}
+
+fn pixel_format(fourcc: FourCC) -> Option<PixelFormat> {
+    [PixelFormat::Yuyv, PixelFormat::Uyvy, PixelFormat::Grey, PixelFormat::Nv12]
+        .into_iter()
+        .find(|p| *p.fourcc() == fourcc.repr)
+}
+
+fn offers_grey(dev: &Device) -> bool {
+    dev.enum_formats().is_ok_and(|formats| {
+        formats
+            .iter()
+            .any(|f| f.fourcc.repr == *PixelFormat::Grey.fourcc())
+    })
+}
+
+/// An open device with a running capture stream. The webcam (and its LED)
+/// is powered for as long as a session exists. On FreeBSD the V4L2 devices
+/// are provided by webcamd.
+pub struct Session {
+    dev: Device,
+    stream: MmapStream<'static>,
+    format: FrameFormat,
+    /// Driver default for the gain control, taken as unity gain.
+    default_gain: Option<i64>,
+    /// Auto-exposure mode to restore after `lock_exposure`. UVC cameras
+    /// keep their controls after the device is closed.
+    saved_auto: Option<i64>,
+    /// Auto-gain switch and gain to restore after `set_gain`.
+    saved_gain: Option<(Option<i64>, Option<i64>)>,
+}
+
+impl Session {
+    /// Drivers substitute the nearest size they support; `format` is what
+    /// they settled on. A different pixel format can't be measured. GREY is
+    /// taken instead of `pixel` whenever the device offers it: the same luma
+    /// in half the bytes of YUYV.
+    pub fn open(idx: usize, w: u32, h: u32, pixel: PixelFormat) -> Result<Self, Box<dyn Error>> {
+        let dev = Device::new(idx)?;
+        let pixel = if offers_grey(&dev) { PixelFormat::Grey } else { pixel };
+        let mut fmt = dev.format()?;
+        fmt.width = w;
+        fmt.height = h;
+        fmt.fourcc = FourCC::new(pixel.fourcc());
+        let fmt = dev.set_format(&fmt)?;
+        let negotiated = pixel_format(fmt.fourcc).ok_or_else(|| {
+            format!(
+                "{} can't deliver {} (offers {}); try another camera_fourcc",
+                device_name(idx),
+                pixel,
+                String::from_utf8_lossy(&fmt.fourcc.repr)
+            )
+        })?;
+        let mut format = FrameFormat::new(fmt.width, fmt.height, negotiated);
+        // 0 when the driver doesn't report it.
+        format.stride = format.stride.max(fmt.stride);
+        let stream = MmapStream::with_buffers(&dev, Type::VideoCapture, 4)?;
+        let default_gain = dev.query_controls().ok().and_then(|controls| {
+            controls
+                .iter()
+                .find(|c| c.id == V4L2_CID_GAIN)
+                .map(|c| c.default)
+                .filter(|d| *d > 0)
+        });
+        Ok(Self {
+            dev,
+            stream,
+            format,
+            default_gain,
+            saved_auto: None,
+            saved_gain: None,
+        })
+    }
+
+    pub fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    /// Next frame, laid out as `format`.
+    pub fn next_frame(&mut self) -> Result<&[u8], Box<dyn Error>> {
+        let (buf, _) = self.stream.next()?;
+        Ok(buf)
+    }
+
+    fn read(&self, id: u32) -> Option<i64> {
+        match self.dev.control(id).ok()?.value {
+            Value::Integer(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn write(&self, id: u32, value: i64) -> Result<(), Box<dyn Error>> {
+        self.dev.set_control(Control {
+            id,
+            value: Value::Integer(value),
+        })?;
+        Ok(())
+    }
+
+    /// Range of the manual exposure time, in units of 100 µs.
+    pub fn exposure_control(&self) -> Option<ExposureControl> {
+        let controls = self.dev.query_controls().ok()?;
+        let c = controls.iter().find(|c| c.id == V4L2_CID_EXPOSURE_ABSOLUTE)?;
+        Some(ExposureControl {
+            min: c.minimum,
+            max: c.maximum,
+            default: c.default,
+        })
+    }
+
+    /// Manual exposure time, or None while auto-exposure is on.
+    pub fn manual_exposure(&self) -> Option<i64> {
+        (self.read(V4L2_CID_EXPOSURE_AUTO)? == V4L2_EXPOSURE_MANUAL)
+            .then(|| self.read(V4L2_CID_EXPOSURE_ABSOLUTE))
+            .flatten()
+    }
+
+    /// Switch auto-exposure off and fix the exposure time at `value`.
+    pub fn lock_exposure(&mut self, value: i64) -> Result<(), Box<dyn Error>> {
+        if self.saved_auto.is_none() {
+            self.saved_auto = self.read(V4L2_CID_EXPOSURE_AUTO);
+        }
+        self.write(V4L2_CID_EXPOSURE_AUTO, V4L2_EXPOSURE_MANUAL)?;
+        self.write(V4L2_CID_EXPOSURE_ABSOLUTE, value)
+    }
+
+    pub fn unlock_exposure(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.saved_auto.take() {
+            Some(mode) => self.write(V4L2_CID_EXPOSURE_AUTO, mode),
+            None => Ok(()),
+        }
+    }
+
+    /// Switch auto-gain off, where the driver has it, and fix the gain at
+    /// `value` for as long as the session lasts.
+    pub fn set_gain(&mut self, value: i64) -> Result<(), Box<dyn Error>> {
+        if self.saved_gain.is_none() {
+            self.saved_gain = Some((self.read(V4L2_CID_AUTOGAIN), self.read(V4L2_CID_GAIN)));
+        }
+        if self.read(V4L2_CID_AUTOGAIN).is_some() {
+            self.write(V4L2_CID_AUTOGAIN, 0)?;
+        }
+        self.write(V4L2_CID_GAIN, value)
+    }
+
+    fn restore_gain(&mut self) {
+        let Some((auto, gain)) = self.saved_gain.take() else {
+            return;
+        };
+        if let Some(gain) = gain {
+            let _ = self.write(V4L2_CID_GAIN, gain);
+        }
+        if let Some(auto) = auto {
+            let _ = self.write(V4L2_CID_AUTOGAIN, auto);
+        }
+    }
+
+    /// Current exposure settings, if the driver reports an absolute exposure
+    /// time. Cameras without a gain control are assumed to run at unity.
+    pub fn exposure(&self) -> Option<Exposure> {
+        // V4L2 exposure_absolute is in units of 100 µs.
+        let time_secs = self.read(V4L2_CID_EXPOSURE_ABSOLUTE).filter(|v| *v > 0)? as f32 * 1e-4;
+        let gain = match (self.default_gain, self.read(V4L2_CID_GAIN)) {
+            (Some(unity), Some(g)) => g.max(1) as f32 / unity as f32,
+            _ => 1.0,
+        };
+        Some(Exposure { time_secs, gain })
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.unlock_exposure();
+        self.restore_gain();
+    }
+}
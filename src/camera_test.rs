@@ -0,0 +1,172 @@
+// src/camera_test.rs
+//! `smart-brightness camera-test`: check that the camera can serve as a
+//! light meter.
+//!
+//! Luma is read at a handful of fixed exposure times, from short to long,
+//! with auto-exposure switched off. A usable sensor gets brighter with every
+//! step (until it saturates) and holds still while the exposure is locked;
+//! one that doesn't will keep fighting the daemon's readings.
+
+use std::error::Error;
+
+use crate::calibrate::print_banner;
+use crate::camera::{Camera, ExposureControl};
+use crate::config::Config;
+use crate::tr;
+
+/// Exposure times tried, spread evenly on a log scale.
+const STEPS: usize = 6;
+/// Longest exposure tried, in driver units (100 µs): longer ones slow the
+/// frame rate to a crawl.
+const MAX_EXPOSURE: i64 = 2000;
+/// Frames dropped after each change while the sensor settles.
+const SETTLE_FRAMES: usize = 4;
+/// Frames averaged per exposure.
+const SAMPLE_FRAMES: usize = 3;
+/// Frames watched while the exposure is locked.
+const LOCK_FRAMES: usize = 8;
+/// Luma drift that still counts as a held lock, and the dip between steps
+/// still put down to noise.
+const TOLERANCE: f32 = 0.02;
+/// Luma treated as clipped.
+const SATURATED: f32 = 0.98;
+
+pub fn run(cfg: &Config) -> Result<bool, Box<dyn Error>> {
+    let _span = tracing::info_span!("camera_test").entered();
+    let (w, h) = (cfg.resolution[0], cfg.resolution[1]);
+    print_banner(&[tr!("camtest-title")]);
+    println!();
+
+    let mut cam = Camera::open(cfg.camera_device, w, h, cfg.camera_fourcc)?;
+    cam.set_center_exclusion(cfg.camera_exclude_center);
+    println!("{}", tr!("calib-warming-up"));
+    cam.warmup(cfg.warmup_frames.max(30), cfg.warmup_tolerance);
+    let auto = cam.average_luma_over(SAMPLE_FRAMES)?;
+    let exposure = cam
+        .last_exposure()
+        .map(|e| format!("{:.1} ms × {:.2}", e.time_secs * 1000.0, e.gain))
+        .unwrap_or_else(|| "?".into());
+    println!("{}", tr!("camtest-auto", luma = format!("{:.3}", auto), exposure = exposure));
+
+    let Some(control) = cam.exposure_control() else {
+        println!("{}", tr!("camtest-no-control"));
+        return Ok(false);
+    };
+    println!(
+        "{}",
+        tr!(
+            "camtest-range",
+            min = ms(control.min),
+            max = ms(control.max),
+            default = ms(control.default)
+        )
+    );
+    println!();
+
+    let result = test_locked(&mut cam, control);
+    if let Err(e) = cam.unlock_exposure() {
+        println!("{}", tr!("camtest-restore-failed", error = e.to_string()));
+    }
+    let ok = result?;
+    println!();
+    println!("{}", if ok { tr!("camtest-verdict-ok") } else { tr!("camtest-verdict-bad") });
+    Ok(ok)
+}
+
+/// Sweep and lock checks; the caller restores auto-exposure.
+fn test_locked(cam: &mut Camera, control: ExposureControl) -> Result<bool, Box<dyn Error>> {
+    println!("{}", tr!("camtest-sweep-header"));
+    let mut sweep = Vec::with_capacity(STEPS);
+    for value in exposure_steps(control) {
+        cam.lock_exposure(value)?;
+        cam.average_luma_over(SETTLE_FRAMES)?;
+        let luma = cam.average_luma_over(SAMPLE_FRAMES)?;
+        let note = match cam.manual_exposure() {
+            Some(actual) if actual != value => tr!("camtest-adjusted", value = ms(actual)),
+            _ => String::new(),
+        };
+        println!("  {:>9}  {:.3}  {}", ms(value), luma, note);
+        sweep.push((value, luma));
+    }
+    println!();
+    let monotonic = check_monotonic(&sweep);
+
+    // Hold the middle exposure and watch for auto-exposure creeping back.
+    let (value, _) = sweep[sweep.len() / 2];
+    cam.lock_exposure(value)?;
+    cam.average_luma_over(SETTLE_FRAMES)?;
+    let mut samples = Vec::with_capacity(LOCK_FRAMES);
+    for _ in 0..LOCK_FRAMES {
+        samples.push(cam.measure_luma(false)?);
+    }
+    let (lo, hi) = samples
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), &l| (lo.min(l), hi.max(l)));
+    let held = match cam.manual_exposure() {
+        Some(actual) if actual == value && hi - lo <= TOLERANCE => {
+            println!(
+                "{}",
+                tr!("camtest-lock-held", value = ms(value), drift = format!("{:.3}", hi - lo))
+            );
+            true
+        }
+        Some(_) if hi - lo <= TOLERANCE => {
+            println!("{}", tr!("camtest-lock-moved", value = ms(value)));
+            false
+        }
+        Some(_) => {
+            println!("{}", tr!("camtest-lock-drift", drift = format!("{:.3}", hi - lo)));
+            false
+        }
+        None => {
+            println!("{}", tr!("camtest-lock-auto"));
+            false
+        }
+    };
+    Ok(monotonic && held)
+}
+
+/// `STEPS` exposure values from the shortest to `MAX_EXPOSURE`.
+fn exposure_steps(control: ExposureControl) -> Vec<i64> {
+    let lo = control.min.max(1) as f64;
+    let hi = control.max.min(MAX_EXPOSURE).max(control.min.max(1)) as f64;
+    let mut steps: Vec<i64> = (0..STEPS)
+        .map(|i| (lo * (hi / lo).powf(i as f64 / (STEPS - 1) as f64)).round() as i64)
+        .collect();
+    steps.dedup();
+    steps
+}
+
+fn check_monotonic(sweep: &[(i64, f32)]) -> bool {
+    for pair in sweep.windows(2) {
+        let ((_, a), (value, b)) = (pair[0], pair[1]);
+        if b < a - TOLERANCE {
+            println!(
+                "{}",
+                tr!(
+                    "camtest-not-monotonic",
+                    value = ms(value),
+                    from = format!("{:.3}", a),
+                    to = format!("{:.3}", b)
+                )
+            );
+            return false;
+        }
+    }
+    let (first, last) = (sweep[0].1, sweep[sweep.len() - 1].1);
+    if last - first < TOLERANCE * 2.0 {
+        println!("{}", tr!("camtest-flat"));
+        return false;
+    }
+    if last >= SATURATED {
+        println!("{}", tr!("camtest-monotonic-saturated"));
+    } else {
+        println!("{}", tr!("camtest-monotonic"));
+    }
+    true
+}
+
+/// Driver exposure units (100 µs) as milliseconds.
+fn ms(value: i64) -> String {
+    format!("{:.1} ms", value as f32 / 10.0)
+}
@@ -0,0 +1,318 @@
+// src/circadian.rs
+//! Circadian hours that fit the room, learned from the history store.
+//!
+//! Ambient luma is averaged per hour of the day over the last two weeks.
+//! Hours brighter than the midpoint between the darkest and brightest hour
+//! count as day; the longest such stretch gives `circadian_day_start_hour`
+//! and `circadian_night_start_hour`. The multipliers follow how much the
+//! room actually changes between day and night: a windowless office gets
+//! gentle ones, a sunlit room the full range.
+//!
+//! `smart-brightness circadian` prints the profile and the suggestion
+//! (`--apply` saves it); with `circadian_auto_tune` the daemon adopts it at
+//! startup.
+
+use std::io;
+
+use chrono::{Local, TimeZone, Timelike};
+use tracing::{info, warn};
+
+use crate::config::{save_config, Config};
+use crate::history::{self, HistoryRecord};
+use crate::time_adjust::TimeAdjuster;
+use crate::tr;
+
+/// History taken into account.
+pub const LOOKBACK_DAYS: i64 = 14;
+/// Samples an hour needs before its mean is trusted.
+const MIN_SAMPLES: usize = 6;
+/// Hours of the day that must be covered for a suggestion.
+const MIN_HOURS: usize = 20;
+/// Relative day/night difference below which there is no pattern to follow.
+const MIN_CONTRAST: f32 = 0.1;
+/// Multiplier swing at full contrast.
+const MAX_SWING: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Hour {
+    sum: f64,
+    count: usize,
+}
+
+/// Mean ambient luma for each hour of the day, in local time.
+#[derive(Debug, Clone, Default)]
+pub struct HourlyAmbient {
+    hours: [Hour; 24],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Suggestion {
+    pub day_start_hour: u8,
+    pub night_start_hour: u8,
+    pub day_multiplier: f32,
+    pub night_multiplier: f32,
+}
+
+impl Suggestion {
+    pub fn apply(&self, cfg: &mut Config) {
+        cfg.circadian_day_start_hour = self.day_start_hour;
+        cfg.circadian_night_start_hour = self.night_start_hour;
+        cfg.circadian_day_multiplier = self.day_multiplier;
+        cfg.circadian_night_multiplier = self.night_multiplier;
+    }
+}
+
+impl HourlyAmbient {
+    /// History records are stored after the circadian adjustment; passing the
+    /// adjuster in effect takes it back out.
+    pub fn from_records(records: &[HistoryRecord], adjuster: Option<&TimeAdjuster>) -> Self {
+        let mut profile = Self::default();
+        for rec in records {
+            let Some(time) = Local.timestamp_opt(rec.t, 0).single() else {
+                continue;
+            };
+            let hour = time.hour() as u8;
+            let luma = adjuster.map_or(rec.luma, |a| a.undo_at(hour, rec.luma));
+            let slot = &mut profile.hours[hour as usize];
+            slot.sum += luma as f64;
+            slot.count += 1;
+        }
+        profile
+    }
+
+    /// Profile of the last `LOOKBACK_DAYS` of the history store.
+    pub fn load(cfg: &Config) -> io::Result<Self> {
+        let since = chrono::Utc::now().timestamp() - LOOKBACK_DAYS * 86_400;
+        let records = history::load_since(&history::default_path(), since)?;
+        let adjuster = cfg.enable_circadian.then(|| TimeAdjuster::from_config(cfg));
+        Ok(Self::from_records(&records, adjuster.as_ref()))
+    }
+
+    /// Mean luma for `hour`, None with too few samples.
+    pub fn mean(&self, hour: u8) -> Option<f32> {
+        let h = self.hours[hour as usize];
+        (h.count >= MIN_SAMPLES).then(|| (h.sum / h.count as f64) as f32)
+    }
+
+    pub fn samples(&self, hour: u8) -> usize {
+        self.hours[hour as usize].count
+    }
+
+    /// Hours with a trusted mean.
+    pub fn covered(&self) -> usize {
+        (0..24).filter(|&h| self.mean(h).is_some()).count()
+    }
+
+    pub fn suggest(&self) -> Option<Suggestion> {
+        if self.covered() < MIN_HOURS {
+            return None;
+        }
+        let means: Vec<f32> = (0..24).filter_map(|h| self.mean(h)).collect();
+        let (lo, hi) = means
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(lo, hi), &m| (lo.min(m), hi.max(m)));
+        let threshold = (lo + hi) / 2.0;
+
+        // Uncovered hours go with the hour before them.
+        let last_known = (0..24u8).rev().find_map(|h| self.mean(h)).unwrap_or(lo);
+        let mut bright = [false; 24];
+        let mut prev = last_known > threshold;
+        for (h, b) in bright.iter_mut().enumerate() {
+            *b = self.mean(h as u8).map_or(prev, |m| m > threshold);
+            prev = *b;
+        }
+        let (start, len) = longest_run(&bright)?;
+        let end = (start + len) % 24;
+
+        let (mut day, mut night) = ((0.0, 0), (0.0, 0));
+        for h in 0..24u8 {
+            if let Some(m) = self.mean(h) {
+                let in_day = (h as usize + 24 - start) % 24 < len;
+                let acc = if in_day { &mut day } else { &mut night };
+                acc.0 += m;
+                acc.1 += 1;
+            }
+        }
+        if day.1 == 0 || night.1 == 0 {
+            return None;
+        }
+        let (day, night) = (day.0 / day.1 as f32, night.0 / night.1 as f32);
+        let contrast = ((day - night) / day.max(f32::EPSILON)).clamp(0.0, 1.0);
+        if contrast < MIN_CONTRAST {
+            return None;
+        }
+        let swing = round2(MAX_SWING * contrast);
+        Some(Suggestion {
+            day_start_hour: start as u8,
+            night_start_hour: end as u8,
+            day_multiplier: round2(1.0 + swing),
+            night_multiplier: round2(1.0 - swing),
+        })
+    }
+}
+
+/// Start and length of the longest circular run of `true`; None when all
+/// hours are alike.
+fn longest_run(bright: &[bool; 24]) -> Option<(usize, usize)> {
+    let dark = bright.iter().position(|b| !b)?;
+    bright.iter().position(|&b| b)?;
+    let mut best = (0, 0);
+    let mut run = None;
+    for i in 1..=24 {
+        let h = (dark + i) % 24;
+        match (bright[h], run) {
+            (true, None) => run = Some((h, 1)),
+            (true, Some((s, n))) => run = Some((s, n + 1)),
+            (false, Some((s, n))) => {
+                if n > best.1 {
+                    best = (s, n);
+                }
+                run = None;
+            }
+            (false, None) => {}
+        }
+    }
+    Some(best)
+}
+
+fn round2(v: f32) -> f32 {
+    (v * 100.0).round() / 100.0
+}
+
+/// `smart-brightness circadian`: print the hourly profile and the
+/// suggestion, saving it to the user's config with `apply`.
+pub fn report(mut cfg: Config, apply: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let profile = HourlyAmbient::load(&cfg)?;
+    let suggestion = profile.suggest();
+    println!("{}", tr!("circadian-header", days = LOOKBACK_DAYS.to_string()));
+    for h in 0..24u8 {
+        let day = suggestion.is_some_and(|s| {
+            (h as usize + 24 - s.day_start_hour as usize) % 24
+                < (s.night_start_hour as usize + 24 - s.day_start_hour as usize) % 24
+        });
+        match profile.mean(h) {
+            Some(m) => println!(
+                "  {:02}:00  {:.3}  {:<20} {}",
+                h,
+                m,
+                "█".repeat((m * 20.0).round() as usize),
+                if day { "☀" } else { "" }
+            ),
+            None => println!("  {:02}:00      –  ({})", h, profile.samples(h)),
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        tr!(
+            "circadian-current",
+            day = cfg.circadian_day_start_hour.to_string(),
+            night = cfg.circadian_night_start_hour.to_string(),
+            boost = format!("{:.2}", cfg.circadian_day_multiplier),
+            dim = format!("{:.2}", cfg.circadian_night_multiplier)
+        )
+    );
+    let Some(s) = suggestion else {
+        println!("{}", tr!("circadian-no-suggestion", hours = profile.covered().to_string()));
+        return Ok(());
+    };
+    println!(
+        "{}",
+        tr!(
+            "circadian-suggested",
+            day = s.day_start_hour.to_string(),
+            night = s.night_start_hour.to_string(),
+            boost = format!("{:.2}", s.day_multiplier),
+            dim = format!("{:.2}", s.night_multiplier)
+        )
+    );
+    if apply {
+        s.apply(&mut cfg);
+        save_config(&cfg)?;
+        println!("{}", tr!("circadian-applied"));
+    } else {
+        println!("{}", tr!("circadian-apply-hint"));
+    }
+    Ok(())
+}
+
+/// Adopt the suggestion for this run when `circadian_auto_tune` is set.
+pub fn auto_tune(cfg: &mut Config) {
+    if !cfg.circadian_auto_tune || !cfg.enable_circadian {
+        return;
+    }
+    let profile = match HourlyAmbient::load(cfg) {
+        Ok(p) => p,
+        Err(e) => {
+            warn!(target: "daemon", "Circadian auto-tune skipped: cannot read history: {}", e);
+            return;
+        }
+    };
+    match profile.suggest() {
+        Some(s) => {
+            info!(
+                target: "daemon",
+                "Circadian auto-tune: day {:02}:00 ×{:.2}, night {:02}:00 ×{:.2}",
+                s.day_start_hour,
+                s.day_multiplier,
+                s.night_start_hour,
+                s.night_multiplier
+            );
+            s.apply(cfg);
+        }
+        None => info!(
+            target: "daemon",
+            "Circadian auto-tune: not enough history ({}/24 hours) or no day/night pattern yet",
+            profile.covered()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(luma_at: impl Fn(u32) -> f32) -> Vec<HistoryRecord> {
+        let mut out = Vec::new();
+        for day in 1..=3 {
+            for hour in 0..24 {
+                for minute in (0..60).step_by(20) {
+                    let t = Local
+                        .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+                        .single()
+                        .unwrap()
+                        .timestamp();
+                    out.push(HistoryRecord {
+                        t,
+                        luma: luma_at(hour),
+                        brightness: 0,
+                        actual: None,
+                        variant: None,
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn suggests_the_bright_stretch_of_the_day() {
+        let recs = records(|h| if (8..19).contains(&h) { 0.6 } else { 0.1 });
+        let s = HourlyAmbient::from_records(&recs, None).suggest().unwrap();
+        assert_eq!((s.day_start_hour, s.night_start_hour), (8, 19));
+        assert!(s.day_multiplier > 1.0 && s.night_multiplier < 1.0);
+    }
+
+    #[test]
+    fn day_may_wrap_past_midnight() {
+        let recs = records(|h| if !(4..14).contains(&h) { 0.5 } else { 0.05 });
+        let s = HourlyAmbient::from_records(&recs, None).suggest().unwrap();
+        assert_eq!((s.day_start_hour, s.night_start_hour), (14, 4));
+    }
+
+    #[test]
+    fn no_suggestion_for_a_steady_room() {
+        let recs = records(|h| 0.4 + h as f32 * 0.001);
+        assert_eq!(HourlyAmbient::from_records(&recs, None).suggest(), None);
+    }
+}
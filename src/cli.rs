@@ -0,0 +1,247 @@
+// src/cli.rs
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::builder::PossibleValuesParser;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use crate::presets;
+
+/// Name of the installed binary (see PKGBUILD / install.sh).
+pub const BIN_NAME: &str = "smart-brightness";
+
+/// Help output is localized and printed by `print_help` in main.rs, so clap's
+/// generated help is disabled; subcommands keep theirs. Without a
+/// subcommand the daemon runs, as with `run`.
+#[derive(Parser, Debug)]
+#[command(name = BIN_NAME, version, disable_help_flag = true)]
+pub struct Cli {
+    /// Load and save this config file instead of looking for one
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+    /// Same as `configure`, from before there were subcommands
+    #[arg(long, hide = true)]
+    pub configure: bool,
+    /// Same as `calibrate`, from before there were subcommands
+    #[arg(long, hide = true)]
+    pub calibrate: bool,
+    #[command(flatten)]
+    pub run: RunArgs,
+    /// Display help
+    #[arg(short, long)]
+    pub help: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// How the daemon starts.
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Apply the named `[profiles.<name>]` overrides
+    #[arg(short, long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Start from a built-in parameter set (applied before --profile)
+    #[arg(long, value_name = "NAME", value_parser = PossibleValuesParser::new(presets::NAMES))]
+    pub preset: Option<String>,
+    /// Start from a state saved with `ctl dump-state`
+    #[arg(long, value_name = "FILE")]
+    pub load_state: Option<PathBuf>,
+    /// Alternate between two settings files, tagging history by file
+    #[arg(long, num_args = 2, value_names = ["A", "B"])]
+    pub ab_test: Vec<PathBuf>,
+    /// How long each --ab-test file stays in use, e.g. 10m
+    #[arg(long, value_name = "DURATION", default_value = "10m", value_parser = duration, requires = "ab_test")]
+    pub interval: Duration,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the daemon (the default without a subcommand)
+    Run(RunArgs),
+    /// Run the calibration wizard
+    Calibrate,
+    /// Edit the config in a terminal interface
+    Configure,
+    /// Print the running daemon's readings and brightness
+    Status {
+        /// Socket of the daemon (default: from the config)
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// Change a setting of the running daemon (same as `ctl config set`)
+    Set {
+        key: String,
+        /// TOML value, e.g. 0.2, true or "realtime"
+        value: String,
+        /// Also save it to the config file
+        #[arg(long)]
+        persist: bool,
+        /// Socket of the daemon (default: from the config)
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// List the backlights, light sensors and cameras found
+    Devices,
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// List profiles defined in the config and built-in presets
+    Profiles,
+    /// Check that the camera's luma follows its exposure time
+    CameraTest,
+    /// Suggest circadian hours from recorded ambient light
+    Circadian {
+        /// Save the suggestion to the config file
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Talk to the running daemon
+    Ctl {
+        /// Socket of the daemon (default: from the config)
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    /// Read or change settings of the running daemon
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Reach a brightness by a time of day, then follow ambient light again
+    Ramp {
+        /// Percent of the calibrated range
+        brightness: f32,
+        /// Local time, HH:MM
+        at: String,
+    },
+    /// Hold a brightness regardless of ambient light, until `ctl unlock`
+    Lock {
+        /// Percent of the calibrated range, e.g. 45%
+        #[arg(value_parser = percent)]
+        brightness: f32,
+        /// Unlock by itself after this many minutes
+        #[arg(long = "for", value_name = "MINUTES")]
+        minutes: Option<u64>,
+    },
+    /// Follow ambient light again after `ctl lock`
+    Unlock,
+    /// Print current readings and brightness
+    Status,
+    /// Print the run summary so far: brightness changes, luma and
+    /// brightness ranges, errors and backlight writes
+    Stats,
+    /// Stop adjusting brightness until `ctl resume`
+    Pause,
+    /// Adjust brightness again after `ctl pause`
+    Resume,
+    /// Print the daemon's internal state as JSON, for bug reports
+    DumpState,
+    /// Summarize today's history: time per brightness decile, manual
+    /// overrides, ambient light per hour
+    Report,
+    /// Turn the time-of-day adjustment off or back on until the daemon exits
+    Circadian { state: Switch },
+    /// Turn smoothing off to follow readings directly, or back on
+    Smoothing { state: Switch },
+}
+
+/// `45%` or `45`.
+fn percent(s: &str) -> Result<f32, String> {
+    s.trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("'{}' is not a percentage", s))
+}
+
+/// `90s`, `10m`, `1h`, or plain seconds.
+fn duration(s: &str) -> Result<Duration, String> {
+    let (n, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => return Err(format!("'{}' is not a duration (e.g. 90s, 10m, 1h)", s)),
+    };
+    match n.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(Duration::from_secs(n * secs)),
+        _ => Err(format!("'{}' is not a duration (e.g. 90s, 10m, 1h)", s)),
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Print the value the daemon is using
+    Get { key: String },
+    /// Validate and apply a value immediately
+    Set {
+        key: String,
+        /// TOML value, e.g. 0.2, true or "realtime"
+        value: String,
+        /// Also save it to the config file
+        #[arg(long)]
+        persist: bool,
+    },
+}
+
+/// Write the completion script for `shell`. Profile names are not baked in:
+/// bash, zsh and fish ask `smart-brightness profiles` at completion time.
+pub fn write_completions(shell: Shell, out: &mut dyn Write) -> io::Result<()> {
+    let mut cmd = Cli::command();
+    clap_complete::generate(shell, &mut cmd, BIN_NAME, out);
+    match shell {
+        Shell::Bash => out.write_all(BASH_PROFILES.as_bytes()),
+        Shell::Zsh => out.write_all(ZSH_PROFILES.as_bytes()),
+        Shell::Fish => out.write_all(FISH_PROFILES.as_bytes()),
+        _ => Ok(()),
+    }
+}
+
+// The wrappers look up the function clap_complete registered instead of
+// hard-coding its name, then take over the registration.
+const BASH_PROFILES: &str = r#"
+_smart_brightness_generated="$(complete -p smart-brightness 2>/dev/null | sed -E 's/.*-F ([^ ]+).*/\1/')"
+_smart_brightness_dynamic() {
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "$prev" == "--profile" || "$prev" == "-p" ]]; then
+        COMPREPLY=( $(compgen -W "$(smart-brightness profiles 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}") )
+        return 0
+    fi
+    "$_smart_brightness_generated" "$@"
+}
+complete -F _smart_brightness_dynamic -o bashdefault -o default smart-brightness
+"#;
+
+const ZSH_PROFILES: &str = r#"
+_smart_brightness_generated=${_comps[smart-brightness]:-_smart-brightness}
+_smart_brightness_dynamic() {
+    if [[ "$words[CURRENT-1]" == (--profile|-p) ]]; then
+        local -a profiles
+        profiles=(${(f)"$(smart-brightness profiles 2>/dev/null)"})
+        compadd -a profiles
+        return
+    fi
+    $_smart_brightness_generated "$@"
+}
+compdef _smart_brightness_dynamic smart-brightness
+"#;
+
+const FISH_PROFILES: &str = r#"
+complete -c smart-brightness -s p -l profile -x -a '(smart-brightness profiles 2>/dev/null)'
+"#;
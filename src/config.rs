@@ -1,7 +1,22 @@
 // src/config.rs
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::correction;
+use crate::curve::{Curve, CurveUnit};
+use crate::displays::{self, LinkedDisplay};
+use crate::export::Endpoint;
+use crate::expression::Expression;
+use crate::integrations::{DesktopAutoBrightness, SessionType};
+use crate::presets;
+use crate::ramps::{self, Ramp};
+use crate::rules::{Rule, RuleSet};
+use crate::smooth_transition::Easing;
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 #[serde(rename_all = "lowercase")]
@@ -24,32 +39,258 @@ pub enum DaemonMode {
     Realtime,
 }
 
+impl DaemonMode {
+    pub const NAMES: [&'static str; 3] = ["boot", "interval", "realtime"];
+
+    /// As written in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            DaemonMode::Boot => "boot",
+            DaemonMode::Interval => "interval",
+            DaemonMode::Realtime => "realtime",
+        }
+    }
+}
+
+/// Where ambient readings come from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AmbientSensorKind {
+    #[default]
+    Camera,
+    /// IIO ambient light sensor (`/sys/bus/iio/devices/*/in_illuminance_*`).
+    Als,
+    /// External process started from `sensor_command`.
+    Command,
+    /// File kept up to date by another program (`sensor_file`).
+    File,
+    /// UDP datagrams received on `sensor_listen`.
+    Network,
+}
+
+/// How the webcam is driven between measurements.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CameraCaptureMode {
+    /// Keep the stream open and sample every `capture_interval_ms`.
+    #[default]
+    Continuous,
+    /// Open, grab `camera_burst_frames`, close; repeat every
+    /// `camera_burst_interval_ms`. Keeps the webcam LED off most of the time.
+    Burst,
+}
+
+/// How much each pixel of a frame counts towards a reading
+/// (`luma_weighting`).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LumaWeighting {
+    /// Fully at the center, 0.2 at the corners.
+    #[default]
+    Center,
+    /// Every pixel alike.
+    Flat,
+    /// Fully along the top row, 0.2 along the bottom one: the room above
+    /// the screen over the desk below it.
+    Top,
+}
+
+/// `camera_roi` that reads the whole frame.
+pub const FULL_FRAME: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+/// Statistic of a frame's pixels a camera reading is (`luma_metric`).
+/// Every statistic keeps `luma_weighting` and the bright-source mask.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LumaMetric {
+    #[default]
+    Mean,
+    Median,
+    /// 25th percentile: a lamp or window in frame hardly moves it.
+    P25,
+    P75,
+}
+
+impl LumaMetric {
+    /// Share of the frame's weight at or below the reading; None for the
+    /// mean.
+    pub fn quantile(self) -> Option<f32> {
+        match self {
+            LumaMetric::Mean => None,
+            LumaMetric::Median => Some(0.5),
+            LumaMetric::P25 => Some(0.25),
+            LumaMetric::P75 => Some(0.75),
+        }
+    }
+}
+
+/// Pixel format requested from the camera (`camera_fourcc`). Only formats
+/// with a plain luma channel can be measured.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PixelFormat {
+    #[default]
+    Yuyv,
+    Uyvy,
+    Grey,
+    Nv12,
+}
+
+/// One knob for how quickly brightness follows the room
+/// (`responsiveness`); see `presets::responsiveness`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Responsiveness {
+    Relaxed,
+    Balanced,
+    Snappy,
+}
+
+/// What to do with a camera reading taken during heavy motion.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MotionGating {
+    /// Drop the reading.
+    #[default]
+    Skip,
+    /// Keep it, but let it move the smoothed value less.
+    Weight,
+}
+
+pub const SYSFS_ROOT_ENV: &str = "SMART_BRIGHTNESS_SYSFS_ROOT";
+/// Settings an administrator enforces; see `read_config`.
+pub const MANAGED_PATH: &str = "/etc/smart-brightness/managed.toml";
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
-    // Daemon configuration
+    // Daemon configuration; read at startup only.
     #[serde(default)]
     pub mode: DaemonMode,
+    /// Seconds of each active phase in boot and interval mode.
     #[serde(default = "default_run_duration")]
     pub run_duration: f64,
+    /// Seconds between active phases in interval mode.
     #[serde(default = "default_pause_interval")]
     pub pause_interval: f64,
+    /// Run in interval mode whatever `mode` says.
     #[serde(default)]
     pub interval_boot: bool,
 
+    /// Where sysfs is mounted; `SMART_BRIGHTNESS_SYSFS_ROOT` overrides it.
+    /// Backlights are looked up under `class/backlight`, light sensors
+    /// under `bus/iio/devices`.
+    #[serde(default = "default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+
+    #[serde(default)]
+    pub ambient_sensor: AmbientSensorKind,
+    #[serde(default = "default_als_max_value")]
+    pub als_max_value: f32,
+    /// Program (and arguments) speaking the line protocol in
+    /// `external_sensor`, used when `ambient_sensor = "command"`.
+    #[serde(default)]
+    pub sensor_command: Option<String>,
+    #[serde(default = "default_sensor_command_timeout_ms")]
+    pub sensor_command_timeout_ms: u64,
+    #[serde(default)]
+    pub sensor_file: Option<PathBuf>,
+    /// UDP address readings arrive on when `ambient_sensor = "network"`.
+    #[serde(default)]
+    pub sensor_listen: Option<String>,
+    /// Age after which a file or network reading no longer counts.
+    #[serde(default = "default_sensor_stale_secs")]
+    pub sensor_stale_secs: u64,
+    /// Sensors tried in order when `ambient_sensor` can't be opened or keeps
+    /// failing; see `sensor`.
+    #[serde(default)]
+    pub sensor_fallbacks: Vec<AmbientSensorKind>,
+    #[serde(default = "default_sensor_failover_after")]
+    pub sensor_failover_after: u32,
+
     #[serde(rename = "camera_index", alias = "camera_device")]
     pub camera_device: usize,
     #[serde(rename = "camera_resolution", alias = "resolution")]
     pub resolution: [u32; 2],
+    #[serde(default)]
+    pub camera_fourcc: PixelFormat,
     #[serde(
         rename = "camera_warmup_frames",
         alias = "warmup_frames"
     )]
     pub warmup_frames: usize,
+    #[serde(
+        default = "default_warmup_tolerance",
+        rename = "camera_warmup_tolerance",
+        alias = "warmup_tolerance"
+    )]
+    pub warmup_tolerance: f32,
+    /// Shorten warmup when the camera was streaming this recently (0 = off).
+    #[serde(default = "default_camera_warm_start_secs")]
+    pub camera_warm_start_secs: u64,
+    /// Also read as `camera_keep_open`, a switch for the same choice.
+    #[serde(
+        default,
+        alias = "camera_keep_open",
+        deserialize_with = "capture_mode_or_keep_open"
+    )]
+    pub camera_capture_mode: CameraCaptureMode,
+    #[serde(default = "default_camera_burst_frames")]
+    pub camera_burst_frames: usize,
+    #[serde(default = "default_camera_burst_interval_ms")]
+    pub camera_burst_interval_ms: u64,
+    /// Fixed `exposure_time_absolute` in 100 µs units; turns auto-exposure
+    /// off while the camera is open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_exposure: Option<i64>,
+    /// Fixed gain in driver units; turns auto-gain off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_gain: Option<i64>,
+    #[serde(default = "default_camera_aperture")]
+    pub camera_aperture: f32,
+    #[serde(default = "default_lux_scale")]
+    pub lux_scale: f32,
+    /// Part of the frame that is read, `[x, y, w, h]` in shares of its
+    /// width and height.
+    #[serde(default = "default_camera_roi")]
+    pub camera_roi: [f32; 4],
+    #[serde(default)]
+    pub camera_exclude_center: f32,
+    #[serde(default)]
+    pub luma_metric: LumaMetric,
+    #[serde(default)]
+    pub luma_weighting: LumaWeighting,
+    #[serde(default = "default_bright_mask_enabled")]
+    pub bright_mask_enabled: bool,
+    #[serde(default = "default_bright_mask_threshold")]
+    pub bright_mask_threshold: f32,
+    #[serde(
+        default = "default_bright_mask_adapt_secs",
+        rename = "bright_mask_adapt_seconds",
+        alias = "bright_mask_adapt_secs"
+    )]
+    pub bright_mask_adapt_secs: u64,
+    #[serde(default = "default_bright_mask_weight")]
+    pub bright_mask_weight: f32,
+    #[serde(default)]
+    pub motion_gate_enabled: bool,
+    #[serde(default = "default_motion_gate_threshold")]
+    pub motion_gate_threshold: f32,
+    #[serde(default)]
+    pub motion_gate_mode: MotionGating,
+    /// Stands for smoothing, capture and step settings not set one by one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub responsiveness: Option<Responsiveness>,
     #[serde(
         rename = "ambient_smoothing_strength",
         alias = "smoothing_factor"
     )]
     pub smoothing_factor: f32,
+    /// Readings taken at startup before the first brightness target; see
+    /// `controller`.
+    #[serde(default = "default_startup_grace_captures")]
+    pub startup_grace_captures: u32,
+    #[serde(default)]
+    pub startup_grace_secs: f32,
     #[serde(
         rename = "screen_brightness_min",
         alias = "real_min_brightness"
@@ -62,6 +303,14 @@ pub struct Config {
     pub real_max_brightness: u32,
     #[serde(rename = "capture_interval_ms")]
     pub capture_interval_ms: u64,
+    /// Interval used for `capture_fast_secs` after the raw reading jumps by
+    /// at least `capture_fast_luma_delta` (0 = off).
+    #[serde(default)]
+    pub capture_fast_interval_ms: u64,
+    #[serde(default = "default_capture_fast_luma_delta")]
+    pub capture_fast_luma_delta: f32,
+    #[serde(default = "default_capture_fast_secs")]
+    pub capture_fast_secs: f64,
     #[serde(
         rename = "brightness_step_interval_ms",
         alias = "smooth_interval_ms"
@@ -77,6 +326,18 @@ pub struct Config {
         alias = "smooth_max_step"
     )]
     pub smooth_max_step: u32,
+    #[serde(default = "default_override_return_secs")]
+    pub override_return_secs: f32,
+    #[serde(default)]
+    pub backlight_min_write_interval_ms: u64,
+    #[serde(default = "default_backlight_min_change")]
+    pub backlight_min_change: u32,
+    /// Percent of the hardware maximum the backlight is never set below.
+    #[serde(default)]
+    pub backlight_floor_percent: f32,
+    /// Re-write the target right after the display wakes from DPMS off.
+    #[serde(default = "default_backlight_restore_on_wake")]
+    pub backlight_restore_on_wake: bool,
     #[serde(
         rename = "ambient_luma_min",
         alias = "camera_min_luma"
@@ -87,6 +348,13 @@ pub struct Config {
         alias = "camera_max_luma"
     )]
     pub camera_max_luma: Option<f32>,
+    /// Camera luma mapped onto the light sensor's scale; see `correction`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera_correction: Vec<[f32; 2]>,
+    /// Readings clipped at one end of the calibrated range for this long
+    /// switch to the full raw range until they fit again; 0 never.
+    #[serde(default = "default_safe_mode_after_secs")]
+    pub safe_mode_after_secs: u64,
     #[serde(default)]
     pub calibrated: bool,
     #[serde(default)]
@@ -119,36 +387,37 @@ pub struct Config {
     pub circadian_day_start_hour: u8,
     #[serde(default = "default_night_start_hour")]
     pub circadian_night_start_hour: u8,
-    #[serde(
-        default = "default_status_interval_secs",
-        rename = "status_interval_seconds",
-        alias = "status_interval_secs"
-    )]
-    pub status_interval_secs: u64,
+    /// Share of a pure time-of-day schedule in the adjusted reading; see
+    /// `time_adjust`.
+    #[serde(default)]
+    pub circadian_weight: f32,
+    #[serde(default = "default_circadian_day_level")]
+    pub circadian_day_level: f32,
+    #[serde(default = "default_circadian_night_level")]
+    pub circadian_night_level: f32,
+    /// Replace the circadian hours and multipliers with ones learned from
+    /// history at startup; see `circadian`.
+    #[serde(default)]
+    pub circadian_auto_tune: bool,
     #[serde(
         default = "default_status_threshold",
         rename = "status_min_brightness_change",
         alias = "status_threshold"
     )]
     pub status_threshold: u32,
-    #[serde(
-        default = "default_status_fast_interval_secs",
-        rename = "status_fast_interval_seconds",
-        alias = "status_fast_interval_secs"
-    )]
-    pub status_fast_interval_secs: f64,
-    #[serde(
-        default = "default_status_fast_threshold",
-        rename = "status_fast_change_threshold",
-        alias = "status_fast_threshold"
-    )]
-    pub status_fast_threshold: u32,
     #[serde(
         default = "default_error_throttle_secs",
         rename = "error_throttle_seconds",
         alias = "error_throttle_secs"
     )]
     pub error_throttle_secs: u64,
+    /// Readings failing for this long show a desktop notification; 0 never.
+    #[serde(default = "default_error_notify_after_secs")]
+    pub error_notify_after_secs: u64,
+    /// Readings failing for this long switch to a fallback sensor, or hold
+    /// the brightness; 0 never.
+    #[serde(default = "default_error_fallback_after_secs")]
+    pub error_fallback_after_secs: u64,
     #[serde(
         default = "default_min_luma_delta",
         rename = "ambient_luma_min_change",
@@ -161,14 +430,144 @@ pub struct Config {
         alias = "log_target_brightness"
     )]
     pub log_target_brightness: bool,
+    /// Look at every other pixel column of camera frames.
+    #[serde(default)]
+    pub half_precision: bool,
+    #[serde(default = "default_history_enabled")]
+    pub history_enabled: bool,
     #[serde(
-        default = "default_status_log_only_on_change",
-        rename = "status_only_when_changed",
-        alias = "status_log_only_on_change"
+        default = "default_history_sample_secs",
+        rename = "history_sample_seconds",
+        alias = "history_sample_secs"
     )]
-    pub status_log_only_on_change: bool,
+    pub history_sample_secs: u64,
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u32,
+    /// Record every backlight write with its reason; see `audit`.
     #[serde(default)]
-    pub half_precision: bool,
+    pub audit_log: bool,
+    #[serde(default)]
+    pub audit_log_path: Option<PathBuf>,
+    /// Keep the last minutes of the loop in memory for SIGQUIT and panics;
+    /// see `flight_recorder`.
+    #[serde(default = "default_flight_recorder")]
+    pub flight_recorder: bool,
+
+    /// Measured luminance at `screen_brightness_min` / `screen_brightness_max`,
+    /// for a curve in nits. Levels in between are assumed nits-linear.
+    #[serde(default)]
+    pub panel_min_nits: Option<f32>,
+    #[serde(default)]
+    pub panel_max_nits: Option<f32>,
+
+    // Event hooks; see `hooks`.
+    #[serde(default)]
+    pub on_change: Option<String>,
+    #[serde(default)]
+    pub on_pause: Option<String>,
+    #[serde(default)]
+    pub on_error: Option<String>,
+    #[serde(default = "default_hook_min_interval_ms")]
+    pub hook_min_interval_ms: u64,
+
+    /// Influx line protocol / statsd endpoint; see `export`.
+    #[serde(default)]
+    pub metrics_export_url: Option<String>,
+    #[serde(default = "default_metrics_export_interval_secs")]
+    pub metrics_export_interval_secs: u64,
+    #[serde(default)]
+    pub metrics_export_token: Option<String>,
+
+    /// Overrides session detection for `integrations`.
+    #[serde(default)]
+    pub session_type: SessionType,
+    /// Switch GNOME's own automatic brightness off while running.
+    #[serde(default)]
+    pub desktop_auto_brightness: DesktopAutoBrightness,
+    /// Stop capturing once the user has been idle this long (0 = never).
+    #[serde(default)]
+    pub idle_pause_secs: u64,
+    /// Keep brightness fixed while a fullscreen window has focus.
+    #[serde(default)]
+    pub fullscreen_hold: bool,
+    /// Dim the picture when there is no backlight device (external monitors).
+    #[serde(default)]
+    pub software_dimming: bool,
+    /// Below the lowest backlight level, dim the picture as well.
+    #[serde(default)]
+    pub pixel_dimming: bool,
+    /// Picture brightness at the bottom of the range, as a factor.
+    #[serde(default = "default_pixel_dimming_min")]
+    pub pixel_dimming_min: f32,
+    /// Share of the brightness range given to picture dimming.
+    #[serde(default = "default_pixel_dimming_share")]
+    pub pixel_dimming_share: f32,
+    /// How much of the screen's own light reaches the sensor: readings are
+    /// reduced by this × screen luma × backlight level (0 = off).
+    #[serde(default)]
+    pub screen_compensation: f32,
+
+    /// Address for the web dashboard, e.g. `127.0.0.1:8787`. Needs the
+    /// `dashboard` feature.
+    #[serde(default)]
+    pub dashboard_listen: Option<String>,
+    /// Accept `ctl` commands on a Unix socket.
+    #[serde(default = "default_ipc_enabled")]
+    pub ipc_enabled: bool,
+    /// Socket for `ctl`; `<runtime dir>/smart-brightness/control.sock` if unset.
+    #[serde(default)]
+    pub ipc_socket: Option<PathBuf>,
+    /// Let other tools take over through an override file; see `override_file`.
+    #[serde(default = "default_override_enabled")]
+    pub override_enabled: bool,
+    /// `<runtime dir>/smart-brightness/override` if unset.
+    #[serde(default)]
+    pub override_file: Option<PathBuf>,
+
+    /// Replaces `[curve]` when set: brightness level as a formula of `luma`,
+    /// `min` and `max` (the calibrated brightness range).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mapping_expression: Option<Expression>,
+
+    // Conditional overrides (`[[rules]]`); see `rules`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+
+    // Brightness ramps by time of day (`[[ramps]]`); see `ramps`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ramps: Vec<Ramp>,
+
+    // Displays that follow the primary backlight (`[[displays]]`); see
+    // `displays`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub displays: Vec<LinkedDisplay>,
+
+    // Transition paces by what set the target (`[easing.<cause>]`).
+    #[serde(default, skip_serializing_if = "Easing::is_default")]
+    pub easing: Easing,
+
+    // Luma → brightness mapping (`[curve]`); linear unless edited.
+    #[serde(default, skip_serializing_if = "Curve::is_linear")]
+    pub curve: Curve,
+
+    // Overrides for one daemon mode (`[modes.<mode>]`), applied when the
+    // daemon runs in it. Kept last with `profiles`: TOML needs tables after
+    // plain keys when serializing.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modes: BTreeMap<String, toml::Table>,
+
+    // Named override sets (`[profiles.<name>]`), applied with `--profile`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+
+    /// Name of the profile or preset applied at startup, for rules.
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+
+    /// Settings `managed.toml` sets, which nothing else may change, with
+    /// the user's own value each replaced.
+    #[serde(skip)]
+    pub managed: BTreeMap<String, Option<toml::Value>>,
 }
 
 impl Default for Config {
@@ -178,18 +577,62 @@ impl Default for Config {
             run_duration: default_run_duration(),
             pause_interval: default_pause_interval(),
             interval_boot: false,
+            sysfs_root: default_sysfs_root(),
+            ambient_sensor: AmbientSensorKind::Camera,
+            als_max_value: default_als_max_value(),
+            sensor_command: None,
+            sensor_command_timeout_ms: default_sensor_command_timeout_ms(),
+            sensor_file: None,
+            sensor_listen: None,
+            sensor_stale_secs: default_sensor_stale_secs(),
+            sensor_fallbacks: Vec::new(),
+            sensor_failover_after: default_sensor_failover_after(),
             camera_device: 0,
             resolution: [640, 400],
+            camera_fourcc: PixelFormat::Yuyv,
             warmup_frames: 30,
+            warmup_tolerance: default_warmup_tolerance(),
+            camera_warm_start_secs: default_camera_warm_start_secs(),
+            camera_capture_mode: CameraCaptureMode::Continuous,
+            camera_burst_frames: default_camera_burst_frames(),
+            camera_burst_interval_ms: default_camera_burst_interval_ms(),
+            camera_exposure: None,
+            camera_gain: None,
+            camera_aperture: default_camera_aperture(),
+            lux_scale: default_lux_scale(),
+            camera_roi: FULL_FRAME,
+            camera_exclude_center: 0.0,
+            luma_metric: LumaMetric::Mean,
+            luma_weighting: LumaWeighting::Center,
+            bright_mask_enabled: default_bright_mask_enabled(),
+            bright_mask_threshold: default_bright_mask_threshold(),
+            bright_mask_adapt_secs: default_bright_mask_adapt_secs(),
+            bright_mask_weight: default_bright_mask_weight(),
+            motion_gate_enabled: false,
+            motion_gate_threshold: default_motion_gate_threshold(),
+            motion_gate_mode: MotionGating::Skip,
+            responsiveness: None,
             smoothing_factor: 0.15,
+            startup_grace_captures: default_startup_grace_captures(),
+            startup_grace_secs: 0.0,
             real_min_brightness: 47,
             real_max_brightness: 937,
             capture_interval_ms: 150, // More responsive
+            capture_fast_interval_ms: 0,
+            capture_fast_luma_delta: default_capture_fast_luma_delta(),
+            capture_fast_secs: default_capture_fast_secs(),
             smooth_interval_ms: 20,   // Faster updates
             smooth_step_divisor: 10,  // Faster transition
             smooth_max_step: 100,     // Allow larger jumps
+            override_return_secs: default_override_return_secs(),
+            backlight_min_write_interval_ms: 0,
+            backlight_min_change: default_backlight_min_change(),
+            backlight_floor_percent: 0.0,
+            backlight_restore_on_wake: default_backlight_restore_on_wake(),
             camera_min_luma: Some(0.05),
             camera_max_luma: Some(0.8),
+            camera_correction: Vec::new(),
+            safe_mode_after_secs: default_safe_mode_after_secs(),
             calibrated: true,
             logging: LogLevel::Low,
             logging_path: None,
@@ -198,15 +641,56 @@ impl Default for Config {
             circadian_night_multiplier: default_night_multiplier(),
             circadian_day_start_hour: default_day_start_hour(),
             circadian_night_start_hour: default_night_start_hour(),
-            status_interval_secs: default_status_interval_secs(),
+            circadian_weight: 0.0,
+            circadian_day_level: default_circadian_day_level(),
+            circadian_night_level: default_circadian_night_level(),
+            circadian_auto_tune: false,
             status_threshold: default_status_threshold(),
-            status_fast_interval_secs: default_status_fast_interval_secs(),
-            status_fast_threshold: default_status_fast_threshold(),
             error_throttle_secs: default_error_throttle_secs(),
+            error_notify_after_secs: default_error_notify_after_secs(),
+            error_fallback_after_secs: default_error_fallback_after_secs(),
             min_luma_delta: default_min_luma_delta(),
             log_target_brightness: default_log_target_brightness(),
-            status_log_only_on_change: default_status_log_only_on_change(),
             half_precision: false,
+            on_change: None,
+            on_pause: None,
+            on_error: None,
+            hook_min_interval_ms: default_hook_min_interval_ms(),
+            history_enabled: default_history_enabled(),
+            history_sample_secs: default_history_sample_secs(),
+            history_retention_days: default_history_retention_days(),
+            audit_log: false,
+            audit_log_path: None,
+            flight_recorder: default_flight_recorder(),
+            panel_min_nits: None,
+            panel_max_nits: None,
+            metrics_export_url: None,
+            metrics_export_interval_secs: default_metrics_export_interval_secs(),
+            metrics_export_token: None,
+            session_type: SessionType::Auto,
+            desktop_auto_brightness: DesktopAutoBrightness::Keep,
+            idle_pause_secs: 0,
+            fullscreen_hold: false,
+            software_dimming: false,
+            pixel_dimming: false,
+            pixel_dimming_min: default_pixel_dimming_min(),
+            pixel_dimming_share: default_pixel_dimming_share(),
+            screen_compensation: 0.0,
+            dashboard_listen: None,
+            ipc_enabled: default_ipc_enabled(),
+            ipc_socket: None,
+            override_enabled: default_override_enabled(),
+            override_file: None,
+            mapping_expression: None,
+            rules: Vec::new(),
+            ramps: Vec::new(),
+            displays: Vec::new(),
+            easing: Easing::default(),
+            curve: Curve::default(),
+            modes: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            managed: BTreeMap::new(),
         }
     }
 }
@@ -219,6 +703,142 @@ fn default_pause_interval() -> f64 {
     60.0 // 1 minute
 }
 
+fn default_ipc_enabled() -> bool {
+    true
+}
+
+fn default_override_enabled() -> bool {
+    true
+}
+
+fn default_pixel_dimming_min() -> f32 {
+    0.5
+}
+
+fn default_pixel_dimming_share() -> f32 {
+    0.25
+}
+
+fn default_sysfs_root() -> PathBuf {
+    PathBuf::from("/sys")
+}
+
+fn default_als_max_value() -> f32 {
+    1000.0
+}
+
+fn default_startup_grace_captures() -> u32 {
+    3
+}
+
+fn default_sensor_stale_secs() -> u64 {
+    30
+}
+
+fn default_sensor_failover_after() -> u32 {
+    5
+}
+
+fn default_sensor_command_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_capture_fast_luma_delta() -> f32 {
+    0.05
+}
+
+fn default_capture_fast_secs() -> f64 {
+    5.0
+}
+
+fn default_hook_min_interval_ms() -> u64 {
+    1000
+}
+
+fn default_metrics_export_interval_secs() -> u64 {
+    10
+}
+
+fn default_warmup_tolerance() -> f32 {
+    0.005
+}
+
+fn default_camera_warm_start_secs() -> u64 {
+    60
+}
+
+fn default_override_return_secs() -> f32 {
+    5.0
+}
+
+fn default_backlight_min_change() -> u32 {
+    1
+}
+
+fn default_safe_mode_after_secs() -> u64 {
+    600
+}
+
+fn default_backlight_restore_on_wake() -> bool {
+    true
+}
+
+fn default_camera_aperture() -> f32 {
+    2.0
+}
+
+fn default_lux_scale() -> f32 {
+    1.0
+}
+
+/// `camera_capture_mode` by name, or `camera_keep_open = false` for burst
+/// capture.
+fn capture_mode_or_keep_open<'de, D: Deserializer<'de>>(d: D) -> Result<CameraCaptureMode, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        KeepOpen(bool),
+        Mode(CameraCaptureMode),
+    }
+    Ok(match Repr::deserialize(d)? {
+        Repr::KeepOpen(true) => CameraCaptureMode::Continuous,
+        Repr::KeepOpen(false) => CameraCaptureMode::Burst,
+        Repr::Mode(mode) => mode,
+    })
+}
+
+fn default_camera_roi() -> [f32; 4] {
+    FULL_FRAME
+}
+
+fn default_camera_burst_frames() -> usize {
+    3
+}
+
+fn default_camera_burst_interval_ms() -> u64 {
+    10_000
+}
+
+fn default_bright_mask_enabled() -> bool {
+    true
+}
+
+fn default_bright_mask_threshold() -> f32 {
+    0.9
+}
+
+fn default_bright_mask_adapt_secs() -> u64 {
+    300
+}
+
+fn default_bright_mask_weight() -> f32 {
+    0.1
+}
+
+fn default_motion_gate_threshold() -> f32 {
+    0.1
+}
+
 fn default_enable_circadian() -> bool {
     true
 }
@@ -239,24 +859,28 @@ fn default_night_start_hour() -> u8 {
     18
 }
 
-fn default_status_interval_secs() -> u64 {
-    5
+fn default_circadian_day_level() -> f32 {
+    0.6
+}
+
+fn default_circadian_night_level() -> f32 {
+    0.1
 }
 
 fn default_status_threshold() -> u32 {
     8
 }
 
-fn default_status_fast_interval_secs() -> f64 {
-    0.25
+fn default_error_throttle_secs() -> u64 {
+    2
 }
 
-fn default_status_fast_threshold() -> u32 {
-    40
+fn default_error_notify_after_secs() -> u64 {
+    60
 }
 
-fn default_error_throttle_secs() -> u64 {
-    2
+fn default_error_fallback_after_secs() -> u64 {
+    300
 }
 
 fn default_min_luma_delta() -> f32 {
@@ -267,12 +891,266 @@ fn default_log_target_brightness() -> bool {
     true
 }
 
-fn default_status_log_only_on_change() -> bool {
+fn default_history_enabled() -> bool {
+    true
+}
+
+fn default_history_sample_secs() -> u64 {
+    60
+}
+
+fn default_history_retention_days() -> u32 {
+    7
+}
+
+fn default_flight_recorder() -> bool {
     true
 }
 
 impl Config {
+    /// `sysfs_root`, unless the environment points elsewhere (test
+    /// fixtures, containers with sysfs bind-mounted under another path).
+    pub fn sysfs_root(&self) -> PathBuf {
+        std::env::var_os(SYSFS_ROOT_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.sysfs_root.clone())
+    }
+
+    /// Time between ambient measurements for the configured capture mode.
+    pub fn capture_interval(&self) -> Duration {
+        match self.camera_capture_mode {
+            CameraCaptureMode::Continuous => Duration::from_millis(self.capture_interval_ms),
+            CameraCaptureMode::Burst => Duration::from_millis(self.camera_burst_interval_ms),
+        }
+    }
+
+    /// Curve (or `mapping_expression`) output for `luma` as a fraction of
+    /// the calibrated brightness range. Nits beyond what the panel can show
+    /// are capped at its ends.
+    pub fn brightness_fraction(&self, luma: f32) -> f32 {
+        if let Some(expr) = &self.mapping_expression {
+            let min = self.real_min_brightness as f32;
+            let max = self.real_max_brightness as f32;
+            let level = expr.eval([luma, min, max]);
+            if max <= min || !level.is_finite() {
+                return 0.0;
+            }
+            return ((level - min) / (max - min)).clamp(0.0, 1.0);
+        }
+        let y = self.curve.eval(luma);
+        match (self.curve.unit, self.panel_min_nits, self.panel_max_nits) {
+            (CurveUnit::Nits, Some(min), Some(max)) if max > min => {
+                ((y - min) / (max - min)).clamp(0.0, 1.0)
+            }
+            _ => y.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Config with the keys of `[profiles.<name>]` laid over the top-level
+    /// values. Nested tables are replaced wholesale, not merged. Names not
+    /// defined in the config fall back to the built-in presets.
+    pub fn with_profile(&self, name: &str) -> Result<Config, String> {
+        match self.profiles.get(name) {
+            Some(overrides) => self.with_overrides(&format!("profile '{}'", name), overrides),
+            None => self
+                .with_preset(name)
+                .map_err(|_| format!("unknown profile '{}'", name)),
+        }
+    }
+
+    /// Config with a built-in preset applied, unless the config defines a
+    /// profile of that name, which then takes its place.
+    pub fn with_preset(&self, name: &str) -> Result<Config, String> {
+        if self.profiles.contains_key(name) {
+            return self.with_profile(name);
+        }
+        let overrides = presets::get(name).ok_or_else(|| format!("unknown preset '{}'", name))?;
+        self.with_overrides(&format!("preset '{}'", name), &overrides)
+    }
+
+    /// The mode the daemon runs in: `mode`, or interval with
+    /// `interval_boot`.
+    pub fn effective_mode(&self) -> DaemonMode {
+        if self.interval_boot {
+            DaemonMode::Interval
+        } else {
+            self.mode
+        }
+    }
+
+    /// Config with the keys of `[modes.<mode>]` laid over the top-level
+    /// values, as a profile's would be; profiles and presets go on top.
+    pub fn with_mode(&self, mode: DaemonMode) -> Result<Config, String> {
+        match self.modes.get(mode.name()) {
+            Some(overrides) => self.with_overrides(&format!("[modes.{}]", mode.name()), overrides),
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// Config with the settings in the TOML file at `path` laid over the
+    /// top-level values, as a profile's would be.
+    pub fn with_file(&self, path: &Path) -> Result<Config, String> {
+        let overrides = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| toml::from_str::<toml::Table>(&data).map_err(|e| e.to_string()))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.with_overrides(&path.display().to_string(), &overrides)
+    }
+
+    /// Config with `overrides` laid over the top-level values. Managed
+    /// settings keep their value.
+    fn with_overrides(&self, what: &str, overrides: &toml::Table) -> Result<Config, String> {
+        let table = self.to_table()?;
+        let mut merged = table.clone();
+        let mut overrides = overrides.clone();
+        expand_responsiveness(&mut overrides)?;
+        for (key, value) in &overrides {
+            let name = setting_name(key);
+            if !self.managed.contains_key(name) {
+                merged.insert(name.to_string(), value.clone());
+            }
+        }
+        let mut cfg: Config = toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e: toml::de::Error| format!("{}: {}", what, e))?;
+        cfg.managed = self.managed.clone();
+        Ok(cfg)
+    }
+
+    /// Config with the settings of `managed.toml` laid over it and locked.
+    fn with_managed(&self, managed: &toml::Table) -> Result<Config, String> {
+        let table = self.to_table()?;
+        let own = managed
+            .keys()
+            .map(|key| {
+                let name = setting_name(key);
+                (name.to_string(), table.get(name).cloned())
+            })
+            .collect();
+        let mut cfg = self.with_overrides(MANAGED_PATH, managed)?;
+        cfg.validate()?;
+        cfg.managed = own;
+        Ok(cfg)
+    }
+
+    /// Whether `managed.toml` sets the setting serialized as `name`.
+    pub fn is_managed(&self, name: &str) -> bool {
+        self.managed.contains_key(name)
+    }
+
+    /// The config as the user's file has it: managed settings back at the
+    /// user's own values.
+    fn without_managed(&self) -> Result<Config, String> {
+        let mut table = self.to_table()?;
+        for (name, own) in &self.managed {
+            match own {
+                Some(value) => table.insert(name.clone(), value.clone()),
+                None => table.remove(name),
+            };
+        }
+        toml::Value::Table(table).try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
+
+    /// Config with one top-level setting changed, as by `ctl config set`,
+    /// and the name the setting is saved under. `value` is TOML (`0.2`,
+    /// `true`, `"auto"`); anything that doesn't parse as TOML is taken as a
+    /// string. Tables (`curve`, `profiles`, `modes`, ...) can't be set this
+    /// way.
+    pub fn with_key(&self, key: &str, value: &str) -> Result<(Config, String), String> {
+        let value = toml::from_str::<toml::Table>(&format!("v = {}", value))
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+        if value.is_table() {
+            return Err(format!("'{}' takes a table; edit the config file instead", key));
+        }
+        let name = setting_name(key).to_string();
+        let mut overrides = toml::Table::new();
+        overrides.insert(name.clone(), value);
+        let cfg = self.with_overrides(key, &overrides)?;
+        // Unknown keys are ignored when deserializing, so a setting is one
+        // that shows up afterwards.
+        if !cfg.to_table()?.contains_key(&name) {
+            return Err(format!("unknown setting '{}'", key));
+        }
+        if self.managed.contains_key(&name) {
+            return Err(format!("'{}' is set by {} and can't be changed", name, MANAGED_PATH));
+        }
+        cfg.validate()?;
+        Ok((cfg, name))
+    }
+
+    /// Whether going from `self` to `next` needs the ambient sensor opened
+    /// again: the device, its format, or the sensor kind changed.
+    pub fn sensor_changed(&self, next: &Config) -> bool {
+        self.ambient_sensor != next.ambient_sensor
+            || self.sensor_command != next.sensor_command
+            || self.sensor_command_timeout_ms != next.sensor_command_timeout_ms
+            || self.sensor_file != next.sensor_file
+            || self.sensor_listen != next.sensor_listen
+            || self.sensor_stale_secs != next.sensor_stale_secs
+            || self.sensor_fallbacks != next.sensor_fallbacks
+            || self.sensor_failover_after != next.sensor_failover_after
+            || self.sysfs_root != next.sysfs_root
+            || self.als_max_value != next.als_max_value
+            || self.camera_device != next.camera_device
+            || self.resolution != next.resolution
+            || self.camera_fourcc != next.camera_fourcc
+            || self.camera_warm_start_secs != next.camera_warm_start_secs
+            || self.camera_capture_mode != next.camera_capture_mode
+            || self.camera_burst_frames != next.camera_burst_frames
+            || self.camera_burst_interval_ms != next.camera_burst_interval_ms
+            || self.camera_exposure != next.camera_exposure
+            || self.camera_gain != next.camera_gain
+            || self.camera_roi != next.camera_roi
+            || self.camera_exclude_center != next.camera_exclude_center
+            || self.luma_metric != next.luma_metric
+            || self.luma_weighting != next.luma_weighting
+            || self.camera_correction != next.camera_correction
+    }
+
+    /// A top-level setting as TOML, e.g. `0.3` or `"realtime"`.
+    pub fn get_key(&self, key: &str) -> Option<String> {
+        let mut table = self.to_table().ok()?;
+        table.remove(setting_name(key)).map(|v| match v {
+            // Settings are f32; print them without f64 rounding noise.
+            toml::Value::Float(f) => (f as f32).to_string(),
+            v => v.to_string(),
+        })
+    }
+
+    fn to_table(&self) -> Result<toml::Table, String> {
+        match toml::Value::try_from(self).map_err(|e| e.to_string())? {
+            toml::Value::Table(t) => Ok(t),
+            _ => unreachable!("Config serializes to a table"),
+        }
+    }
+
+    /// Profiles defined in the config plus the built-in presets.
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        names.extend(presets::NAMES);
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
     pub fn validate(&self) -> Result<(), String> {
+        for (name, overrides) in &self.modes {
+            if !DaemonMode::NAMES.contains(&name.as_str()) {
+                return Err(format!(
+                    "[modes.{}]: unknown mode (use {})",
+                    name,
+                    DaemonMode::NAMES.join(", ")
+                ));
+            }
+            if let Some(key) = ["mode", "interval_boot", "modes"]
+                .into_iter()
+                .find(|key| overrides.contains_key(*key))
+            {
+                return Err(format!("[modes.{}] can't set '{}'", name, key));
+            }
+        }
         if self.real_max_brightness <= self.real_min_brightness {
             return Err("real_max_brightness must be greater than real_min_brightness".into());
         }
@@ -282,9 +1160,18 @@ impl Config {
         if self.capture_interval_ms == 0 {
             return Err("capture_interval_ms must be greater than 0".into());
         }
+        if !(self.capture_fast_luma_delta > 0.0 && self.capture_fast_luma_delta <= 1.0) {
+            return Err("capture_fast_luma_delta must be in the range (0, 1]".into());
+        }
+        if !(self.capture_fast_secs.is_finite() && self.capture_fast_secs >= 0.0) {
+            return Err("capture_fast_secs must be 0 or greater".into());
+        }
         if self.smooth_interval_ms == 0 {
             return Err("smooth_interval_ms must be greater than 0".into());
         }
+        if !self.startup_grace_secs.is_finite() || self.startup_grace_secs < 0.0 {
+            return Err("startup_grace_secs must be 0 or more".into());
+        }
         if !(0.0..=1.0).contains(&self.smoothing_factor) {
             return Err("smoothing_factor must be in the range [0, 1]".into());
         }
@@ -297,35 +1184,124 @@ impl Config {
         if self.smooth_max_step == 0 {
             return Err("smooth_max_step must be greater than 0".into());
         }
+        if !(0.0..=600.0).contains(&self.override_return_secs) {
+            return Err("override_return_secs must be in the range [0, 600]".into());
+        }
+        if self.backlight_min_change == 0 {
+            return Err("backlight_min_change must be greater than 0".into());
+        }
+        if !(0.0..100.0).contains(&self.backlight_floor_percent) {
+            return Err("backlight_floor_percent must be at least 0 and below 100".into());
+        }
         if self.warmup_frames == 0 {
             return Err("warmup_frames must be greater than 0".into());
         }
+        if !(0.0..=1.0).contains(&self.warmup_tolerance) {
+            return Err("camera_warmup_tolerance must be within 0.0..=1.0".into());
+        }
+        if self.als_max_value <= 0.0 {
+            return Err("als_max_value must be greater than 0".into());
+        }
+        let sensors = || std::iter::once(&self.ambient_sensor).chain(&self.sensor_fallbacks);
+        if sensors().any(|k| *k == AmbientSensorKind::Command)
+            && self.sensor_command.as_deref().is_none_or(|c| c.trim().is_empty())
+        {
+            return Err("ambient_sensor = \"command\" requires sensor_command".into());
+        }
+        if sensors().any(|k| *k == AmbientSensorKind::File) && self.sensor_file.is_none() {
+            return Err("ambient_sensor = \"file\" requires sensor_file".into());
+        }
+        if sensors().any(|k| *k == AmbientSensorKind::Network)
+            && self
+                .sensor_listen
+                .as_deref()
+                .is_none_or(|a| a.parse::<std::net::SocketAddr>().is_err())
+        {
+            return Err("ambient_sensor = \"network\" requires sensor_listen as host:port".into());
+        }
+        if self.sensor_command_timeout_ms == 0 {
+            return Err("sensor_command_timeout_ms must be greater than 0".into());
+        }
+        if self.sensor_stale_secs == 0 {
+            return Err("sensor_stale_secs must be greater than 0".into());
+        }
+        if self.sensor_failover_after == 0 {
+            return Err("sensor_failover_after must be greater than 0".into());
+        }
+        if self.camera_exposure.is_some_and(|v| v <= 0) {
+            return Err("camera_exposure must be greater than 0".into());
+        }
+        if self.camera_gain.is_some_and(|v| v < 0) {
+            return Err("camera_gain must not be negative".into());
+        }
+        if self.camera_aperture <= 0.0 {
+            return Err("camera_aperture must be greater than 0".into());
+        }
+        if self.lux_scale <= 0.0 {
+            return Err("lux_scale must be greater than 0".into());
+        }
+        if self.camera_burst_frames == 0 {
+            return Err("camera_burst_frames must be greater than 0".into());
+        }
+        if self.camera_burst_interval_ms == 0 {
+            return Err("camera_burst_interval_ms must be greater than 0".into());
+        }
+        let [x, y, w, h] = self.camera_roi;
+        if !self.camera_roi.iter().all(|v| (0.0..=1.0).contains(v)) || w <= 0.0 || h <= 0.0 {
+            return Err("camera_roi must be [x, y, w, h] with values in [0, 1] and w, h > 0".into());
+        }
+        if x + w > 1.0 + f32::EPSILON || y + h > 1.0 + f32::EPSILON {
+            return Err("camera_roi must lie within the frame (x + w and y + h at most 1)".into());
+        }
+        if !(0.0..=0.9).contains(&self.camera_exclude_center) {
+            return Err("camera_exclude_center must be in the range [0, 0.9]".into());
+        }
+        if !(0.0..=1.0).contains(&self.bright_mask_threshold) {
+            return Err("bright_mask_threshold must be in the range [0, 1]".into());
+        }
+        if self.bright_mask_adapt_secs == 0 {
+            return Err("bright_mask_adapt_seconds must be greater than 0".into());
+        }
+        if !(0.0..=1.0).contains(&self.bright_mask_weight) {
+            return Err("bright_mask_weight must be in the range [0, 1]".into());
+        }
+        if self.motion_gate_threshold <= 0.0 {
+            return Err("motion_gate_threshold must be greater than 0".into());
+        }
+        if [self.camera_min_luma, self.camera_max_luma]
+            .iter()
+            .flatten()
+            .any(|l| !l.is_finite())
+        {
+            return Err("ambient_luma_min and ambient_luma_max must be finite numbers".into());
+        }
         if let (Some(min), Some(max)) = (self.camera_min_luma, self.camera_max_luma) {
             if max <= min {
                 return Err("ambient_luma_max must be greater than ambient_luma_min".into());
             }
         }
-        if self.status_interval_secs == 0 {
-            return Err("status_interval_seconds must be greater than 0".into());
-        }
-        if self.status_fast_interval_secs <= 0.0 {
-            return Err("status_fast_interval_seconds must be greater than 0".into());
-        }
+        correction::check(&self.camera_correction)?;
         if self.status_threshold == 0 {
             return Err("status_min_brightness_change must be greater than 0".into());
         }
-        if self.status_fast_threshold == 0 {
-            return Err("status_fast_change_threshold must be greater than 0".into());
-        }
         if self.error_throttle_secs == 0 {
             return Err("error_throttle_seconds must be greater than 0".into());
         }
-        if self.run_duration <= 0.0 {
+        if !(self.run_duration.is_finite() && self.run_duration > 0.0) {
             return Err("run_duration must be greater than 0".into());
         }
-        if self.pause_interval < 0.0 {
+        if !(self.pause_interval.is_finite() && self.pause_interval >= 0.0) {
             return Err("pause_interval must be non-negative".into());
         }
+        if !(0.2..=1.0).contains(&self.pixel_dimming_min) {
+            return Err("pixel_dimming_min must be within 0.2..=1.0".into());
+        }
+        if !(0.05..=0.9).contains(&self.pixel_dimming_share) {
+            return Err("pixel_dimming_share must be within 0.05..=0.9".into());
+        }
+        if !(0.0..=1.0).contains(&self.screen_compensation) {
+            return Err("screen_compensation must be within 0.0..=1.0".into());
+        }
         // Validate circadian hours are in valid range
         if self.circadian_day_start_hour > 23 {
             return Err("circadian_day_start_hour must be between 0 and 23".into());
@@ -333,92 +1309,414 @@ impl Config {
         if self.circadian_night_start_hour > 23 {
             return Err("circadian_night_start_hour must be between 0 and 23".into());
         }
+        if !(0.0..=1.0).contains(&self.circadian_weight) {
+            return Err("circadian_weight must be between 0 and 1".into());
+        }
+        if !(0.0..=1.0).contains(&self.circadian_day_level)
+            || !(0.0..=1.0).contains(&self.circadian_night_level)
+        {
+            return Err("circadian_day_level and circadian_night_level must be between 0 and 1".into());
+        }
+        if self.history_sample_secs == 0 {
+            return Err("history_sample_seconds must be greater than 0".into());
+        }
+        if self.history_retention_days == 0 {
+            return Err("history_retention_days must be greater than 0".into());
+        }
+        self.curve.validate()?;
+        RuleSet::compile(&self.rules)?;
+        ramps::validate(&self.ramps)?;
+        displays::validate(&self.displays)?;
+        if let Some(url) = &self.metrics_export_url {
+            Endpoint::parse(url).map_err(|e| format!("metrics_export_url: {}", e))?;
+        }
+        if self.metrics_export_interval_secs == 0 {
+            return Err("metrics_export_interval_secs must be greater than 0".into());
+        }
+        if let Some(addr) = &self.dashboard_listen
+            && !is_loopback_listen(addr)
+        {
+            return Err(format!(
+                "dashboard_listen '{}' must be a loopback address and port, \
+                 e.g. 127.0.0.1:8787 (the dashboard has no authentication)",
+                addr
+            ));
+        }
+        if let Some(expr) = &self.mapping_expression {
+            let (min, max) = (self.real_min_brightness as f32, self.real_max_brightness as f32);
+            for i in 0..=10 {
+                let luma = i as f32 / 10.0;
+                if !expr.eval([luma, min, max]).is_finite() {
+                    return Err(format!(
+                        "mapping_expression '{}' is not a finite number at luma = {}",
+                        expr, luma
+                    ));
+                }
+            }
+        }
+        match (self.panel_min_nits, self.panel_max_nits) {
+            (Some(min), Some(max)) => {
+                if min < 0.0 || max <= min {
+                    return Err("panel_max_nits must be greater than panel_min_nits (>= 0)".into());
+                }
+            }
+            (None, None) => {
+                if self.curve.unit == CurveUnit::Nits {
+                    return Err("a curve in nits needs panel_min_nits and panel_max_nits".into());
+                }
+            }
+            _ => return Err("set both panel_min_nits and panel_max_nits, or neither".into()),
+        }
         Ok(())
     }
 }
 
-pub fn read_config() -> Config {
-    // 1. Check ~/.config/smart-brightness/config.toml
-    if let Some(mut path) = dirs::config_dir() {
-        path.push("smart-brightness");
-        path.push("config.toml");
-        if path.exists() {
-            return load_from_path(&path);
+/// Old names of renamed settings, as (alias, setting); the `alias`
+/// attributes above, which serde doesn't expose. A test checks that each
+/// pair names the same field.
+const ALIASES: [(&str, &str); 23] = [
+    ("camera_device", "camera_index"),
+    ("resolution", "camera_resolution"),
+    ("warmup_frames", "camera_warmup_frames"),
+    ("warmup_tolerance", "camera_warmup_tolerance"),
+    ("camera_keep_open", "camera_capture_mode"),
+    ("bright_mask_adapt_secs", "bright_mask_adapt_seconds"),
+    ("smoothing_factor", "ambient_smoothing_strength"),
+    ("real_min_brightness", "screen_brightness_min"),
+    ("real_max_brightness", "screen_brightness_max"),
+    ("smooth_interval_ms", "brightness_step_interval_ms"),
+    ("smooth_step_divisor", "brightness_step_divisor"),
+    ("smooth_max_step", "brightness_step_max"),
+    ("camera_min_luma", "ambient_luma_min"),
+    ("camera_max_luma", "ambient_luma_max"),
+    ("logging_path", "log_directory"),
+    ("enable_circadian", "circadian_enabled"),
+    ("circadian_day_multiplier", "circadian_day_boost"),
+    ("circadian_night_multiplier", "circadian_night_dim"),
+    ("status_threshold", "status_min_brightness_change"),
+    ("error_throttle_secs", "error_throttle_seconds"),
+    ("min_luma_delta", "ambient_luma_min_change"),
+    ("log_target_brightness", "status_show_target_brightness"),
+    ("history_sample_secs", "history_sample_seconds"),
+];
+
+/// The name `key` is serialized under: the setting it is an alias of
+/// (`smoothing_factor` → `ambient_smoothing_strength`), or itself.
+fn setting_name(key: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, name)| name)
+}
+
+/// Put in the settings `responsiveness` in `table` stands for. A setting
+/// `table` has at other than its default, under any of its names, was
+/// tuned by hand and stays.
+fn expand_responsiveness(table: &mut toml::Table) -> Result<(), String> {
+    let Some(level) = table.get("responsiveness") else {
+        return Ok(());
+    };
+    let level: Responsiveness = level
+        .clone()
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("responsiveness: {}", e))?;
+    let defaults = Config::default();
+    let default_table = defaults.to_table()?;
+    // (setting, name it has in `table`)
+    let names: Vec<(String, String)> = table
+        .keys()
+        .map(|key| (setting_name(key).to_string(), key.clone()))
+        .collect();
+    for (key, value) in presets::responsiveness(level) {
+        if let Some((_, own)) = names.iter().find(|(name, _)| *name == key) {
+            let single = toml::Table::from_iter([(key.clone(), table[own].clone())]);
+            if defaults.with_overrides(&key, &single)?.to_table()? != default_table {
+                continue;
+            }
+            table.remove(own);
         }
+        table.insert(key, value);
     }
+    Ok(())
+}
 
-    // 2. Check /etc/smart-brightness/config.toml (System-wide fallback)
-    let sys_path = Path::new("/etc/smart-brightness/config.toml");
-    if sys_path.exists() {
-        return load_from_path(sys_path);
+/// The user's config with the administrator's `managed.toml`, if any, laid
+/// over it. Its settings win over the config file, profiles, presets,
+/// `ctl config set` and the configurator, and aren't saved to the user's
+/// file.
+pub fn read_config() -> Config {
+    let cfg = read_user_config();
+    let path = Path::new(MANAGED_PATH);
+    if !path.exists() {
+        return cfg;
     }
-
-    // 3. Check current directory (fallback)
-    let cwd_path = Path::new("config.toml");
-    if cwd_path.exists() {
-        println!("Found config.toml in current directory, using it.");
-        return load_from_path(cwd_path);
+    match with_managed_file(&cfg, path) {
+        Ok(managed) => managed,
+        Err(e) => {
+            eprintln!("Ignoring managed config ({}): {}", path.display(), e);
+            cfg
+        }
     }
+}
 
-    // Default
-    println!("No config found in standard locations. Using defaults.");
-    Config::default()
+fn with_managed_file(cfg: &Config, path: &Path) -> Result<Config, String> {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| toml::from_str::<toml::Table>(&data).map_err(|e| e.to_string()))
+        .and_then(|table| cfg.with_managed(&table))
 }
 
-fn load_from_path(path: &Path) -> Config {
-    let data = match fs::read_to_string(path) {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!(
-                "Failed to read config file ({}): {}. Falling back to defaults.",
-                path.display(),
-                e
-            );
-            return Config::default();
+/// Set by `--config`; replaces the lookup in `config_path`.
+static CONFIG_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Load and save the config at `path` only, for the rest of the process.
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_OVERRIDE.set(path);
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("smart-brightness").join("config.toml"))
+}
+
+/// The config file `read_config` loads: the `--config` file, or the first
+/// that exists in the order listed in `--help`.
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = CONFIG_OVERRIDE.get() {
+        return Some(path.clone());
+    }
+    [
+        user_config_path(),
+        Some(PathBuf::from("/etc/smart-brightness/config.toml")),
+        Some(PathBuf::from("config.toml")),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|path| path.exists())
+}
+
+fn read_user_config() -> Config {
+    match config_path().filter(|path| path.exists()) {
+        Some(path) => {
+            if path.is_relative() {
+                eprintln!("Found config.toml in current directory, using it.");
+            }
+            load_from_path(&path)
         }
-    };
-    match toml::from_str(&data) {
-        Ok(cfg) => cfg,
-        Err(e) => {
-            eprintln!(
-                "Failed to parse config file ({}): {}. Falling back to defaults.",
-                path.display(),
-                e
-            );
+        None => {
+            eprintln!("No config found in standard locations. Using defaults.");
             Config::default()
         }
     }
 }
 
-pub fn autodetect_backlight_file(name: &str) -> Option<PathBuf> {
-    let dir = Path::new("/sys/class/backlight");
-    if !dir.exists() {
-        return None;
+/// The config at `path` with the managed settings applied, as
+/// `read_config` would load it, except that errors are returned instead
+/// of falling back to defaults: a half-written file must not reset a
+/// running daemon.
+pub fn reload_config(path: &Path) -> Result<Config, String> {
+    let cfg = parse_config(path)?;
+    let managed = Path::new(MANAGED_PATH);
+    if !managed.exists() {
+        return Ok(cfg);
     }
-    fs::read_dir(dir).ok()?.flatten().find_map(|e| {
-        let p = e.path().join(name);
-        p.exists().then(|| p)
+    with_managed_file(&cfg, managed).map_err(|e| format!("{}: {}", managed.display(), e))
+}
+
+fn parse_config(path: &Path) -> Result<Config, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    toml::from_str::<toml::Table>(&data)
+        .map_err(|e| e.to_string())
+        .and_then(|mut table| {
+            expand_responsiveness(&mut table)?;
+            toml::Value::Table(table)
+                .try_into()
+                .map_err(|e: toml::de::Error| e.to_string())
+        })
+        .map_err(|e| format!("cannot parse {}: {}", path.display(), e))
+}
+
+fn load_from_path(path: &Path) -> Config {
+    parse_config(path).unwrap_or_else(|e| {
+        eprintln!("Failed to load config file: {}. Falling back to defaults.", e);
+        Config::default()
     })
 }
 
-pub fn save_config(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
-    // Save to user config directory, creating it if needed
-    let config_path = if let Some(mut path) = dirs::config_dir() {
-        path.push("smart-brightness");
-        // Create directory if it doesn't exist
-        if !path.exists() {
-            fs::create_dir_all(&path)?;
-        }
-        path.push("config.toml");
-        path
-    } else {
-        // Fallback to current directory if config_dir is not available
+/// Per-user runtime state directory (`$XDG_RUNTIME_DIR/smart-brightness`).
+pub fn runtime_dir() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("smart-brightness")
+}
+
+/// Where `save_config` writes: back to the file that was loaded, unless
+/// that can't be written (the system-wide one, for a user); then to the
+/// user's config, which is looked at first from then on.
+fn save_path() -> PathBuf {
+    if let Some(path) = CONFIG_OVERRIDE.get() {
+        return path.clone();
+    }
+    let loaded = config_path()
+        .filter(|path| fs::OpenOptions::new().write(true).open(path).is_ok());
+    loaded.or_else(user_config_path).unwrap_or_else(|| {
         eprintln!("Warning: Could not determine config directory, saving to current directory");
         PathBuf::from("config.toml")
-    };
+    })
+}
+
+pub fn save_config(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = save_path();
+    if let Some(dir) = config_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
 
-    let s = toml::to_string_pretty(cfg)?;
+    let cfg = cfg.without_managed()?;
+    let s = match cfg.responsiveness {
+        // Settings at the level's values come from it again on loading,
+        // so a different level later still moves them.
+        Some(level) => {
+            let mut table = cfg.to_table()?;
+            let own = presets::responsiveness(level);
+            let implied = Config::default().with_overrides("responsiveness", &own)?.to_table()?;
+            for key in own.keys() {
+                if table.get(key) == implied.get(key) {
+                    table.remove(key);
+                }
+            }
+            toml::to_string_pretty(&table)?
+        }
+        None => toml::to_string_pretty(&cfg)?,
+    };
     fs::write(&config_path, s)?;
     println!("Configuration saved to: {}", config_path.display());
     Ok(())
 }
+
+/// Whether `addr` is `host:port` with a loopback host: `localhost`, an
+/// address in 127.0.0.0/8, or `[::1]`.
+fn is_loopback_listen(addr: &str) -> bool {
+    let Some((host, port)) = addr.rsplit_once(':') else {
+        return false;
+    };
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    port.parse::<u16>().is_ok()
+        && (host.eq_ignore_ascii_case("localhost")
+            || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn managed_settings_cannot_be_overridden() {
+        let mut user = Config::default();
+        user.profiles.insert(
+            "dim".into(),
+            toml::from_str("real_max_brightness = 50\nsmoothing_factor = 0.5").unwrap(),
+        );
+        let managed = toml::from_str("real_max_brightness = 80\nambient_smoothing_strength = 0.3").unwrap();
+        let cfg = user.with_managed(&managed).unwrap();
+        assert_eq!(cfg.real_max_brightness, 80);
+
+        let dim = cfg.with_profile("dim").unwrap();
+        assert_eq!(dim.real_max_brightness, 80);
+        assert_eq!(dim.smoothing_factor, 0.3);
+        assert!(cfg.with_key("smoothing_factor", "0.9").is_err());
+        assert!(cfg.with_key("real_min_brightness", "5").is_ok());
+
+        let own = cfg.without_managed().unwrap();
+        assert_eq!(own.real_max_brightness, user.real_max_brightness);
+        assert_eq!(own.smoothing_factor, user.smoothing_factor);
+    }
+
+    #[test]
+    fn mode_section_lies_under_profiles() {
+        let cfg: Config = toml::from_str(
+            "smoothing_factor = 0.2\n\
+             [modes.boot]\n\
+             smoothing_factor = 0.6\n\
+             enable_circadian = false\n\
+             [profiles.calm]\n\
+             smoothing_factor = 0.1\n",
+        )
+        .unwrap();
+        cfg.validate().unwrap();
+        let boot = cfg.with_mode(DaemonMode::Boot).unwrap();
+        assert_eq!(boot.smoothing_factor, 0.6);
+        assert!(!boot.enable_circadian);
+        assert_eq!(boot.with_profile("calm").unwrap().smoothing_factor, 0.1);
+        assert_eq!(cfg.with_mode(DaemonMode::Realtime).unwrap().smoothing_factor, 0.2);
+
+        let bad: Config = toml::from_str("[modes.boot]\nmode = \"realtime\"\n").unwrap();
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn keep_open_switch_picks_the_capture_mode() {
+        let parse = |text: &str| toml::from_str::<Config>(text).unwrap().camera_capture_mode;
+        assert_eq!(parse("camera_keep_open = false"), CameraCaptureMode::Burst);
+        assert_eq!(parse("camera_keep_open = true"), CameraCaptureMode::Continuous);
+        assert_eq!(parse("camera_capture_mode = \"burst\""), CameraCaptureMode::Burst);
+        assert!(toml::from_str::<Config>("camera_capture_mode = \"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn dashboard_listens_on_loopback_only() {
+        let listen = |addr: &str| Config {
+            dashboard_listen: Some(addr.into()),
+            ..Config::default()
+        };
+        for addr in ["127.0.0.1:8787", "127.0.0.2:80", "[::1]:8787", "localhost:8787"] {
+            assert!(listen(addr).validate().is_ok(), "{}", addr);
+        }
+        let open = ["0.0.0.0:8787", "192.168.1.5:8787", "[::]:8787", "example.com:80", "127.0.0.1"];
+        for addr in open {
+            assert!(listen(addr).validate().is_err(), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn aliases_name_the_setting_they_stand_for() {
+        let table = Config::default().to_table().unwrap();
+        for (alias, name) in ALIASES {
+            // serde refuses a setting given under two of its names.
+            let value = table
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| toml::Value::String("x".into()));
+            let both = toml::Table::from_iter([
+                (alias.to_string(), value.clone()),
+                (name.to_string(), value),
+            ]);
+            let err = toml::Value::Table(both).try_into::<Config>().unwrap_err();
+            let err = err.to_string();
+            assert!(err.contains("duplicate field"), "{} → {}: {}", alias, name, err);
+        }
+        let cfg = Config::default().with_key("smoothing_factor", "0.4").unwrap();
+        assert_eq!(cfg.1, "ambient_smoothing_strength");
+        assert_eq!(cfg.0.get_key("smoothing_factor").as_deref(), Some("0.4"));
+        assert!(Config::default().with_key("smoothing", "0.4").is_err());
+    }
+
+    #[test]
+    fn responsiveness_fills_in_what_is_not_set_by_hand() {
+        // A saved config has every setting, most at their defaults.
+        let mut table = Config::default().to_table().unwrap();
+        table.remove("ambient_smoothing_strength");
+        table.insert("smoothing_factor".into(), toml::Value::Float(0.2));
+        table.insert("responsiveness".into(), "snappy".into());
+        expand_responsiveness(&mut table).unwrap();
+        let cfg: Config = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(cfg.smoothing_factor, 0.2);
+        assert_eq!(cfg.capture_interval_ms, 80);
+        assert_eq!(cfg.smooth_step_divisor, 3);
+
+        let (relaxed, _) = cfg.with_key("responsiveness", "relaxed").unwrap();
+        assert_eq!(relaxed.capture_interval_ms, 400);
+        assert_eq!(relaxed.smoothing_factor, 0.06);
+    }
+}
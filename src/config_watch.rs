@@ -0,0 +1,48 @@
+// src/config_watch.rs
+//! Reload of the config file when it is saved while the daemon runs.
+//!
+//! The file's modification time is polled; editors that replace the file
+//! rather than write into it are covered as well. A file that doesn't
+//! parse or validate is ignored until the next save, so the daemon keeps
+//! running on what it had.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file is looked at.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct ConfigWatch {
+    path: PathBuf,
+    last_poll: Option<Instant>,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatch {
+    /// Watch `path`, taking its current contents as already loaded.
+    pub fn new(path: PathBuf) -> Self {
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        Self {
+            path,
+            last_poll: None,
+            modified,
+        }
+    }
+
+    /// Look at the file if `POLL_INTERVAL` has passed; the path to load
+    /// again when it changed since the last look.
+    pub fn poll(&mut self, now: Instant) -> Option<&PathBuf> {
+        if self.last_poll.is_some_and(|t| now.duration_since(t) < POLL_INTERVAL) {
+            return None;
+        }
+        self.last_poll = Some(now);
+        // Missing for a moment while an editor replaces it.
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok()?;
+        if Some(modified) == self.modified {
+            return None;
+        }
+        self.modified = Some(modified);
+        Some(&self.path)
+    }
+}
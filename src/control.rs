@@ -0,0 +1,447 @@
+// src/control.rs
+//! State shared between the control loop and remote front ends: live
+//! readings flow out, pause / boost / profile / setting / ramp / bypass /
+//! lock requests flow in.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::camera;
+use crate::config::{self, Config};
+use crate::config_watch::ConfigWatch;
+use crate::report::DailyReport;
+use crate::state_dump::{Devices, LoopState, StateDump};
+use crate::stats::RunStats;
+
+/// History kept for charts: five minutes at one sample per second.
+const SAMPLE_CAPACITY: usize = 300;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sample {
+    /// Unix time in seconds.
+    pub time: f64,
+    pub luma: f32,
+    pub brightness: u32,
+}
+
+/// Everything a front end shows, serialized as-is by the dashboard.
+#[cfg(feature = "dashboard")]
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub paused: bool,
+    pub boost_percent: f32,
+    pub boost_remaining_secs: u64,
+    pub profile: Option<String>,
+    pub profiles: Vec<String>,
+    pub luma: f32,
+    pub lux: Option<f32>,
+    pub brightness: u32,
+    pub target: u32,
+    pub brightness_min: u32,
+    pub brightness_max: u32,
+    pub samples: Vec<Sample>,
+}
+
+/// A stage of the pipeline that `ctl` can switch off for the running
+/// daemon, to tell whether it causes what the brightness does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Feature {
+    Circadian,
+    Smoothing,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Feature::Circadian => "circadian",
+            Feature::Smoothing => "smoothing",
+        })
+    }
+}
+
+/// Stages switched off until the daemon exits, whatever the config says.
+/// Without smoothing, readings and targets pass straight through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bypass {
+    pub circadian: bool,
+    pub smoothing: bool,
+}
+
+#[derive(Default)]
+struct State {
+    /// Config before any profile, so switching starts from a clean slate.
+    base: Config,
+    /// The config file, reloaded when it changes.
+    watch: Option<ConfigWatch>,
+    paused: bool,
+    bypass: Bypass,
+    boost: Option<(f32, Instant)>,
+    /// Brightness % held by `ctl lock`, and when the lock runs out.
+    lock: Option<(f32, Option<Instant>)>,
+    profile: Option<String>,
+    switch_to: Option<String>,
+    /// Config the loop should run with instead of its startup one, after a
+    /// profile switch or `set_config_key`.
+    switched: Option<Config>,
+    reconfigured: bool,
+    /// One-off ramp waiting to be started, as (brightness %, time left).
+    ramp: Option<(f32, Duration)>,
+    luma: f32,
+    lux: Option<f32>,
+    brightness: u32,
+    target: u32,
+    last_sample: Option<Instant>,
+    samples: VecDeque<Sample>,
+    /// For `dump_state`.
+    loop_state: LoopState,
+    /// For `stats`; None until the loop has done anything.
+    stats: Option<RunStats>,
+    devices: Devices,
+}
+
+pub struct Control {
+    /// Config the daemon started with, profile applied.
+    startup: Config,
+    state: Mutex<State>,
+}
+
+impl Control {
+    pub fn new(base: Config, startup: Config) -> Self {
+        Self {
+            state: Mutex::new(State {
+                base,
+                profile: startup.active_profile.clone(),
+                ..State::default()
+            }),
+            startup,
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panicking front end must not take the control loop down with it.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Control loop side.
+
+    pub fn paused(&self) -> bool {
+        self.state().paused
+    }
+
+    pub fn bypass(&self) -> Bypass {
+        self.state().bypass
+    }
+
+    /// Active boost as a fraction of the brightness range; 0 once expired.
+    pub fn boost(&self) -> f32 {
+        let mut state = self.state();
+        match state.boost {
+            Some((amount, until)) if Instant::now() < until => amount,
+            Some(_) => {
+                state.boost = None;
+                0.0
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Brightness % held by `ctl lock`; None once unlocked or run out.
+    pub fn lock(&self) -> Option<f32> {
+        let mut state = self.state();
+        match state.lock {
+            Some((_, Some(until))) if Instant::now() >= until => {
+                state.lock = None;
+                None
+            }
+            lock => lock.map(|(brightness, _)| brightness),
+        }
+    }
+
+    pub fn publish(&self, luma: f32, lux: Option<f32>, brightness: u32, target: u32) {
+        let mut state = self.state();
+        state.luma = luma;
+        state.lux = lux;
+        state.brightness = brightness;
+        state.target = target;
+        let now = Instant::now();
+        if state.last_sample.is_some_and(|t| now.duration_since(t) < SAMPLE_INTERVAL) {
+            return;
+        }
+        state.last_sample = Some(now);
+        if state.samples.len() == SAMPLE_CAPACITY {
+            state.samples.pop_front();
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        state.samples.push_back(Sample {
+            time,
+            luma,
+            brightness,
+        });
+    }
+
+    /// The loop's internal state, for `dump_state`.
+    pub fn publish_state(&self, state: LoopState) {
+        self.state().loop_state = state;
+    }
+
+    /// Run statistics so far, with `writes` to the backlight not yet
+    /// counted in them.
+    pub fn publish_stats(&self, stats: &RunStats, writes: u64) {
+        let mut stats = stats.clone();
+        stats.backlight_writes += writes;
+        self.state().stats = Some(stats);
+    }
+
+    pub fn set_devices(&self, devices: Devices) {
+        self.state().devices = devices;
+    }
+
+    /// Config for a requested profile switch, if one is waiting.
+    pub fn take_profile_switch(&self) -> Option<Result<Config, String>> {
+        let (name, base) = {
+            let mut state = self.state();
+            (state.switch_to.take()?, state.base.clone())
+        };
+        let cfg = base.with_profile(&name).and_then(|mut cfg| {
+            cfg.validate()?;
+            cfg.active_profile = Some(name.clone());
+            Ok(cfg)
+        });
+        if let Ok(cfg) = &cfg {
+            let mut state = self.state();
+            state.profile = Some(name);
+            state.switched = Some(cfg.clone());
+            state.reconfigured = false;
+        }
+        Some(cfg)
+    }
+
+    /// Reload the config file when it changes; see `config_watch`.
+    pub fn watch_config(&self, watch: ConfigWatch) {
+        self.state().watch = Some(watch);
+    }
+
+    /// Config from the config file if it changed since the last call, with
+    /// the profile in use applied again. Settings changed with
+    /// `set_config_key` give way to the file.
+    pub fn take_reloaded(&self) -> Option<Result<Config, String>> {
+        let (path, profile) = {
+            let mut state = self.state();
+            let path = state.watch.as_mut()?.poll(Instant::now())?.clone();
+            (path, state.profile.clone())
+        };
+        // Read and parse without the lock; front ends wait on it.
+        let base = match config::reload_config(&path).and_then(|cfg| cfg.with_mode(self.startup.mode)) {
+            Ok(base) => base,
+            Err(e) => return Some(Err(e)),
+        };
+        let next = match profile {
+            Some(name) => base.with_profile(&name).map(|mut cfg| {
+                cfg.active_profile = Some(name);
+                cfg
+            }),
+            None => Ok(base.clone()),
+        }
+        .and_then(|cfg| cfg.validate().map(|()| cfg))
+        .map_err(|e| format!("{}: {}", path.display(), e));
+        if let Ok(next) = &next {
+            let mut state = self.state();
+            state.base = base;
+            state.switched = Some(next.clone());
+            state.reconfigured = false;
+        }
+        Some(next)
+    }
+
+    /// Config after a `set_config_key`, if one is waiting.
+    pub fn take_reconfigured(&self) -> Option<Config> {
+        let mut state = self.state();
+        if !std::mem::take(&mut state.reconfigured) {
+            return None;
+        }
+        state.switched.clone()
+    }
+
+    pub fn take_ramp(&self) -> Option<(f32, Duration)> {
+        self.state().ramp.take()
+    }
+
+    /// Config of the last successful profile switch or setting change.
+    pub fn switched_config(&self) -> Option<Config> {
+        self.state().switched.clone()
+    }
+
+    // Front end side.
+
+    pub fn set_paused(&self, paused: bool) {
+        self.state().paused = paused;
+    }
+
+    /// One-line summary for `ctl status`.
+    pub fn status(&self) -> String {
+        let lock = self.lock();
+        let state = self.state();
+        let mut out = format!(
+            "brightness {} (target {}), ambient luma {:.3}",
+            state.brightness, state.target, state.luma
+        );
+        if let Some(lux) = state.lux {
+            out += &format!(" (~{:.0} lux)", lux);
+        }
+        if let Some(profile) = &state.profile {
+            out += &format!(", profile '{}'", profile);
+        }
+        if state.paused {
+            out += ", paused";
+        }
+        if let Some(brightness) = lock {
+            out += &format!(", locked at {}%", brightness);
+        }
+        out += if camera::is_active() { ", camera on" } else { ", camera off" };
+        out
+    }
+
+    /// Raise brightness by `percent` of the range for `duration`; 0 cancels.
+    /// Only the dashboard asks for a boost.
+    #[cfg(feature = "dashboard")]
+    pub fn set_boost(&self, percent: f32, duration: Duration) {
+        self.state().boost =
+            (percent != 0.0).then(|| (percent.clamp(-100.0, 100.0) / 100.0, Instant::now() + duration));
+    }
+
+    /// Hold `brightness`% of the calibrated range regardless of ambient
+    /// light, until `unlock` or for `duration`.
+    pub fn set_lock(&self, brightness: f32, duration: Option<Duration>) -> Result<String, String> {
+        if !(0.0..=100.0).contains(&brightness) {
+            return Err("brightness must be between 0 and 100".into());
+        }
+        self.state().lock = Some((brightness, duration.map(|d| Instant::now() + d)));
+        Ok(match duration {
+            Some(d) => format!("locked at {}% for {} min", brightness, d.as_secs().div_ceil(60)),
+            None => format!("locked at {}% until unlocked", brightness),
+        })
+    }
+
+    pub fn unlock(&self) -> String {
+        match self.state().lock.take() {
+            Some(_) => "unlocked".into(),
+            None => "not locked".into(),
+        }
+    }
+
+    /// Switch `feature` off, or back on; returns how it now stands.
+    pub fn set_bypass(&self, feature: Feature, on: bool) -> String {
+        let mut state = self.state();
+        match feature {
+            Feature::Circadian => state.bypass.circadian = !on,
+            Feature::Smoothing => state.bypass.smoothing = !on,
+        }
+        format!("{} {}", feature, if on { "on" } else { "off (until restart)" })
+    }
+
+    /// Switch to profile `name`; only the dashboard asks for a switch.
+    #[cfg(feature = "dashboard")]
+    pub fn request_profile(&self, name: &str) -> Result<(), String> {
+        if !self.state().base.profile_names().contains(&name) {
+            return Err(format!("unknown profile '{}'", name));
+        }
+        self.state().switch_to = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Ramp to `brightness`% of the calibrated range by the next `at`
+    /// (`HH:MM`); see `ramps`.
+    pub fn request_ramp(&self, brightness: f32, at: &str) -> Result<String, String> {
+        if !(0.0..=100.0).contains(&brightness) {
+            return Err("brightness must be between 0 and 100".into());
+        }
+        let left = crate::ramps::until_next(at)?;
+        self.state().ramp = Some((brightness, left));
+        Ok(format!("{}% by {} (in {} min)", brightness, at.trim(), left.as_secs().div_ceil(60)))
+    }
+
+    /// Change one setting of the running config; see `Config::with_key`.
+    pub fn set_config_key(&self, key: &str, value: &str) -> Result<String, String> {
+        let mut state = self.state();
+        let current = state.switched.as_ref().unwrap_or(&self.startup);
+        let (next, name) = current.with_key(key, value)?;
+        let applied = next.get_key(&name).unwrap_or_default();
+        state.switched = Some(next);
+        state.reconfigured = true;
+        Ok(applied)
+    }
+
+    pub fn config_key(&self, key: &str) -> Result<String, String> {
+        let state = self.state();
+        let current = state.switched.as_ref().unwrap_or(&self.startup);
+        current
+            .get_key(key)
+            .ok_or_else(|| format!("unknown or unset setting '{}'", key))
+    }
+
+    /// Today so far, from the history store; see `report`.
+    pub fn report(&self) -> Result<String, String> {
+        let state = self.state();
+        let cfg = state.switched.as_ref().unwrap_or(&self.startup);
+        if !cfg.history_enabled {
+            return Err("history_enabled is off: there is nothing to report".into());
+        }
+        let range = (cfg.real_min_brightness, cfg.real_max_brightness);
+        DailyReport::load(chrono::Local::now().date_naive(), range, cfg.history_sample_secs)
+            .map(|report| report.to_string())
+            .map_err(|e| format!("cannot read history: {}", e))
+    }
+
+    /// The run summary the daemon logs when it stops, as of now.
+    pub fn stats(&self) -> Result<String, String> {
+        match &self.state().stats {
+            Some(stats) => Ok(stats.lines().join("\n")),
+            None => Err("no statistics yet: the daemon hasn't taken a reading".into()),
+        }
+    }
+
+    /// Everything needed to look into, or reproduce, what the loop is doing;
+    /// see `state_dump`.
+    pub fn dump_state(&self) -> StateDump {
+        let state = self.state();
+        StateDump {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            time: chrono::Local::now().to_rfc3339(),
+            config: state.switched.clone().unwrap_or_else(|| self.startup.clone()),
+            state: state.loop_state.clone(),
+            samples: state.samples.iter().copied().collect(),
+            devices: state.devices.clone(),
+        }
+    }
+
+    #[cfg(feature = "dashboard")]
+    pub fn snapshot(&self) -> Snapshot {
+        let boost = self.boost();
+        let state = self.state();
+        let boost_remaining_secs = state
+            .boost
+            .map_or(0, |(_, until)| until.saturating_duration_since(Instant::now()).as_secs());
+        Snapshot {
+            paused: state.paused,
+            boost_percent: boost * 100.0,
+            boost_remaining_secs,
+            profile: state.profile.clone(),
+            profiles: state.base.profile_names().into_iter().map(str::to_string).collect(),
+            luma: state.luma,
+            lux: state.lux,
+            brightness: state.brightness,
+            target: state.target,
+            brightness_min: state.base.real_min_brightness,
+            brightness_max: state.base.real_max_brightness,
+            samples: state.samples.iter().copied().collect(),
+        }
+    }
+}
@@ -0,0 +1,1807 @@
+// src/controller.rs
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use tracing::{debug, error, info, warn};
+
+use crate::audit::{AuditLog, Entry, Reason};
+use crate::backlight::Backlight;
+use crate::camera::PrivacyBlocked;
+use crate::config::Config;
+use crate::control::{Bypass, Control};
+use crate::export::{Metrics, MetricsExporter};
+use crate::flight_recorder::{self, Kind};
+use crate::history::HistoryRecorder;
+use crate::integrations::{self, Desktop};
+use crate::hooks::{Event, Hooks};
+use crate::logging;
+use crate::masking;
+use crate::motion::MotionGate;
+use crate::override_file::{Override, OverrideFile};
+use crate::ramps;
+use crate::rules::{Context, Effects, RuleSet};
+use crate::sensor::AmbientInput;
+use crate::smooth_transition::{Cause, SmoothTransition};
+use crate::smoothing::Ema;
+use crate::state_dump::LoopState;
+use crate::stats::RunStats;
+use crate::telemetry::LoopTelemetry;
+use crate::time_adjust::{ClockWatch, TimeAdjuster};
+use crate::tr;
+
+mod state;
+
+pub use state::{Inhibition, State};
+
+/// How often a sensor is retried while brightness is held after
+/// `error_fallback_after_secs`.
+const HOLD_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// How long readings must fit the calibrated range again to leave safe mode.
+const SAFE_MODE_EXIT: Duration = Duration::from_secs(60);
+/// Time asleep after which the room may have changed: readings start over.
+const RESUME_MIN_SLEEP: Duration = Duration::from_secs(5);
+/// After a resume, readings are taken at least this often for a while.
+const RESUME_CAPTURE_INTERVAL: Duration = Duration::from_millis(250);
+const RESUME_WINDOW: Duration = Duration::from_secs(2);
+
+/// Who sets the brightness besides ambient light, from `ctl`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OverrideState {
+    Ambient,
+    /// `ctl lock`: held at this % of the calibrated range, unlike a pause
+    /// which leaves the brightness wherever it was.
+    Locked(f32),
+}
+
+/// Time source for capture scheduling and run-duration expiry.
+///
+/// Intervals, throttles, ramps and status timings run on `now`, which is
+/// monotonic: it ignores changes to the wall clock and, on Linux, stands
+/// still in suspend, so no interval comes due all at once after a resume.
+/// Time-of-day decisions use `wall`. `tick` looks at both to notice a
+/// resume or a clock change and start over from fresh readings.
+pub trait Clock {
+    fn now(&self) -> Instant;
+
+    /// Total time the machine has been suspended; `now` stands still then.
+    fn suspended(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Local wall-clock time, for the time of day.
+    fn wall(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// `CLOCK_BOOTTIME` counts time in suspend, `CLOCK_MONOTONIC` doesn't.
+    #[cfg(target_os = "linux")]
+    fn suspended(&self) -> Duration {
+        use nix::time::{clock_gettime, ClockId};
+        let read = |id| clock_gettime(id).map(|t| Duration::new(t.tv_sec() as u64, t.tv_nsec() as u32));
+        match (read(ClockId::CLOCK_BOOTTIME), read(ClockId::CLOCK_MONOTONIC)) {
+            (Ok(boot), Ok(monotonic)) => boot.saturating_sub(monotonic),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Where ambient readings come from.
+pub trait LumaSource {
+    fn measure(&mut self, half_precision: bool) -> Result<f32, Box<dyn Error>>;
+
+    /// Motion score of the last reading; 0 if the source can't tell.
+    fn motion(&self) -> f32 {
+        0.0
+    }
+
+    /// Frame regions currently down-weighted as bright sources.
+    fn masked_cells(&self) -> usize {
+        0
+    }
+
+    /// Illuminance of the last reading in lux, if known.
+    fn lux(&self) -> Option<f32> {
+        None
+    }
+
+    /// Switch to another sensor after readings have failed for long; false
+    /// if there is none.
+    fn fall_back(&mut self) -> bool {
+        false
+    }
+}
+
+impl LumaSource for AmbientInput {
+    fn measure(&mut self, half_precision: bool) -> Result<f32, Box<dyn Error>> {
+        AmbientInput::measure(self, half_precision)
+    }
+
+    fn motion(&self) -> f32 {
+        AmbientInput::motion(self)
+    }
+
+    fn masked_cells(&self) -> usize {
+        AmbientInput::masked_cells(self)
+    }
+
+    fn lux(&self) -> Option<f32> {
+        AmbientInput::lux(self)
+    }
+
+    fn fall_back(&mut self) -> bool {
+        AmbientInput::fall_back(self)
+    }
+}
+
+/// Where brightness values go.
+pub trait BacklightSink {
+    fn set(&self, value: u32) -> std::io::Result<()>;
+
+    /// Write out anything `set` held back; called once the transition has
+    /// reached its target.
+    fn flush(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Brightness the panel reports, if it can be read back.
+    fn actual(&self) -> Option<u32>;
+
+    /// The display has come back on and may have lost its level.
+    fn woke(&self) -> bool {
+        false
+    }
+
+    /// The display is powered off, as far as the sink can tell.
+    fn display_off(&self) -> bool {
+        false
+    }
+
+    /// Attach and detach linked displays that came or went.
+    fn hotplug(&self) {}
+
+    /// Write `value` even if it is the last value written.
+    fn reassert(&self, value: u32) -> std::io::Result<()> {
+        self.set(value)
+    }
+
+    /// The last value that reached the device, for the audit log.
+    fn written(&self) -> Option<u32> {
+        None
+    }
+
+    /// Levels above the calibrated range that the sink adds by dimming the
+    /// picture below the backlight floor.
+    fn pixel_levels(&self) -> u32 {
+        0
+    }
+
+    /// Values that reached the device so far, for `ctl stats`.
+    fn writes(&self) -> u64 {
+        0
+    }
+}
+
+impl BacklightSink for Backlight {
+    fn set(&self, value: u32) -> std::io::Result<()> {
+        Backlight::set(self, value)
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        Backlight::flush(self)
+    }
+
+    fn actual(&self) -> Option<u32> {
+        Backlight::actual(self)
+    }
+
+    fn woke(&self) -> bool {
+        Backlight::woke(self)
+    }
+
+    fn display_off(&self) -> bool {
+        Backlight::display_off(self)
+    }
+
+    fn hotplug(&self) {
+        Backlight::hotplug(self)
+    }
+
+    fn writes(&self) -> u64 {
+        Backlight::writes(self)
+    }
+
+    fn reassert(&self, value: u32) -> std::io::Result<()> {
+        Backlight::reassert(self, value)
+    }
+
+    fn written(&self) -> Option<u32> {
+        Backlight::written(self)
+    }
+
+    fn pixel_levels(&self) -> u32 {
+        Backlight::pixel_levels(self)
+    }
+}
+
+/// Outcome of one [`Controller::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tick {
+    /// `max_duration` has elapsed; the run is over.
+    Expired,
+    /// A capture or brightness step happened; tick again straight away.
+    Busy,
+    /// Nothing was due; the next event is this far away.
+    Idle(Duration),
+}
+
+/// One run of the capture → smooth → map → step pipeline.
+pub struct Controller<'a, S, B, C> {
+    cfg: &'a Config,
+    sensor: S,
+    backlight: B,
+    clock: C,
+    started: Instant,
+    max_duration: Option<Duration>,
+    capture_interval: Duration,
+    fast_capture: Option<FastCapture>,
+    last_capture: Option<Instant>,
+    /// `Clock::suspended` as of the last tick, to notice a resume.
+    suspended: Duration,
+    /// End of the quick captures that follow a resume.
+    resumed_until: Option<Instant>,
+    clock_watch: ClockWatch,
+    ema: Ema,
+    transition: SmoothTransition,
+    status: StatusReporter,
+    circadian: TimeAdjuster,
+    motion_gate: MotionGate,
+    telemetry: LoopTelemetry,
+    capture_errors: ErrorThrottle,
+    safe_mode: SafeMode,
+    hooks: Hooks,
+    exporter: MetricsExporter,
+    audit: AuditLog,
+    /// What set the current target, for the audit log.
+    reason: Reason,
+    /// Smoothed luma, before the circadian adjustment, when the current
+    /// target was set from a reading.
+    target_luma: Option<f32>,
+    rules: RuleSet,
+    effects: Effects,
+    control: Option<Arc<Control>>,
+    boost: f32,
+    /// Stages switched off with `ctl circadian off` / `ctl smoothing off`.
+    bypass: Bypass,
+    desktop: Desktop,
+    /// What holds the brightness, if anything; see `state`.
+    state: State,
+    /// The camera is covered by a privacy switch; brightness stays put.
+    privacy: bool,
+    override_file: OverrideFile,
+    override_state: OverrideState,
+    /// End of the ramp running now; ambient readings wait until then.
+    ramp_end: Option<Instant>,
+    /// Startup grace period: readings still to take, and the time to wait
+    /// for, before the first target is set. The EMA settles meanwhile.
+    grace: Option<(u32, Instant)>,
+    last_adjusted_luma: f32,
+    last_lux: Option<f32>,
+    has_luma: bool,
+    masked_cells: usize,
+    hardware_max: u32,
+    /// Top of the calibrated range, moved up by the sink's pixel levels.
+    real_max: u32,
+}
+
+impl<'a, S: LumaSource, B: BacklightSink, C: Clock> Controller<'a, S, B, C> {
+    /// `start_value` is the brightness the panel is at now; `hardware_max`
+    /// caps every target regardless of calibration.
+    pub fn new(
+        cfg: &'a Config,
+        sensor: S,
+        backlight: B,
+        clock: C,
+        start_value: u32,
+        hardware_max: u32,
+        max_duration: Option<Duration>,
+    ) -> Self {
+        let started = clock.now();
+        let real_max = cfg.real_max_brightness + backlight.pixel_levels();
+        Self {
+            cfg,
+            sensor,
+            backlight,
+            clock,
+            started,
+            max_duration,
+            capture_interval: cfg.capture_interval(),
+            fast_capture: FastCapture::from_config(cfg),
+            last_capture: None,
+            suspended: clock.suspended(),
+            resumed_until: None,
+            clock_watch: ClockWatch::default(),
+            ema: Ema::new(cfg.smoothing_factor),
+            transition: SmoothTransition::new(
+                start_value,
+                cfg.smooth_interval_ms,
+                cfg.smooth_step_divisor,
+                cfg.smooth_max_step,
+            )
+            .with_easing(cfg.easing.clone())
+            .with_return(Duration::from_secs_f32(cfg.override_return_secs)),
+            status: StatusReporter::new(cfg.status_threshold, cfg.log_target_brightness),
+            circadian: TimeAdjuster::from_config(cfg),
+            motion_gate: MotionGate::from_config(cfg),
+            telemetry: LoopTelemetry::new(),
+            capture_errors: ErrorThrottle::from_config(cfg),
+            safe_mode: SafeMode::from_config(cfg),
+            hooks: Hooks::from_config(cfg),
+            exporter: MetricsExporter::from_config(cfg),
+            audit: AuditLog::from_config(cfg),
+            reason: Reason::Ambient,
+            target_luma: None,
+            rules: RuleSet::compile(&cfg.rules).unwrap_or_else(|e| {
+                warn!(target: "daemon", "Ignoring rules: {}", e);
+                RuleSet::default()
+            }),
+            effects: Effects::default(),
+            control: None,
+            boost: 0.0,
+            bypass: Bypass::default(),
+            desktop: Desktop::from_config(cfg),
+            state: State::Running,
+            privacy: false,
+            override_file: OverrideFile::from_config(cfg),
+            override_state: OverrideState::Ambient,
+            ramp_end: None,
+            grace: (cfg.startup_grace_captures > 0 || cfg.startup_grace_secs > 0.0).then(|| {
+                (
+                    cfg.startup_grace_captures,
+                    started + Duration::from_secs_f32(cfg.startup_grace_secs),
+                )
+            }),
+            last_adjusted_luma: 0.0,
+            last_lux: None,
+            has_luma: false,
+            masked_cells: 0,
+            hardware_max,
+            real_max,
+        }
+    }
+
+    /// Take pause / boost requests from, and report readings to, a front end.
+    pub fn set_control(&mut self, control: Arc<Control>) {
+        self.control = Some(control);
+    }
+
+    /// Set targets from the first reading, e.g. when taking over from a
+    /// controller for a different config.
+    pub fn skip_grace(&mut self) {
+        self.grace = None;
+    }
+
+    /// Sensor, backlight and current brightness, to carry over into a
+    /// controller for a different config.
+    pub fn into_parts(self) -> (S, B, u32) {
+        let value = self.transition.current_value();
+        (self.sensor, self.backlight, value)
+    }
+
+    /// What `ctl dump-state` reports of the loop.
+    pub fn state(&self) -> LoopState {
+        let now = self.clock.now();
+        LoopState {
+            ema: self.ema.value(),
+            adjusted_luma: self.last_adjusted_luma,
+            lux: self.last_lux,
+            brightness: self.transition.current_value(),
+            target: self.transition.target_value(),
+            step: self.transition.step_size(),
+            ramp_remaining_secs: self
+                .ramp_end
+                .map(|end| end.saturating_duration_since(now).as_secs_f64()),
+            held: self.capture_errors.holding(),
+            privacy: self.privacy,
+            state: self.state,
+        }
+    }
+
+    /// Pick up where a dumped loop left off (`--load-state`). A failure
+    /// hold isn't carried over; it builds up again if readings still fail.
+    pub fn restore(&mut self, state: &LoopState) {
+        let now = self.clock.now();
+        self.grace = None;
+        if let Some(ema) = state.ema {
+            self.ema.seed(ema);
+            self.target_luma = Some(ema);
+            self.last_adjusted_luma = state.adjusted_luma;
+            self.has_luma = true;
+        }
+        self.last_lux = state.lux;
+        self.privacy = state.privacy;
+        match state.ramp_remaining_secs {
+            Some(left) => {
+                let end = now + Duration::from_secs_f64(left.max(0.0));
+                self.transition.jump_to(state.brightness, self.hardware_max);
+                self.transition.ramp_to(state.target, self.hardware_max, now, end);
+                self.ramp_end = Some(end);
+                self.reason = Reason::Ramp;
+            }
+            None => self
+                .transition
+                .resume(state.brightness, state.target, state.step, self.hardware_max),
+        }
+        let _ = self.backlight.set(self.transition.current_value());
+        info!(
+            target: "daemon",
+            "Resuming from saved state: brightness {} → {}",
+            self.transition.current_value(),
+            self.transition.target_value()
+        );
+    }
+
+    /// Do whatever is due: capture a reading at the configured rate, then
+    /// step the transition towards its target.
+    pub fn tick(&mut self, stats: &mut RunStats, history: &mut HistoryRecorder) -> Tick {
+        let now = self.clock.now();
+        if let Some(limit) = self.max_duration
+            && now.duration_since(self.started) >= limit
+        {
+            return Tick::Expired;
+        }
+        let suspended = self.clock.suspended();
+        let asleep = suspended.saturating_sub(self.suspended);
+        self.suspended = suspended;
+        if asleep >= RESUME_MIN_SLEEP {
+            self.soft_start(asleep, now);
+            self.retime_ramp();
+        }
+        if let Some(change) = self.clock_watch.poll(now, suspended, self.clock.wall()) {
+            // Day or night may have changed with it: set a target from the
+            // next reading even if the room stays the same.
+            info!(target: "daemon", "{}; taking the time of day afresh", change);
+            self.has_luma = false;
+            self.last_capture = None;
+            self.retime_ramp();
+        }
+
+        // Paused from a front end: leave the backlight where it is.
+        if self.control.as_ref().is_some_and(|c| c.paused()) {
+            self.enter(State::Paused);
+            self.last_capture = None;
+            return Tick::Idle(self.capture_interval_at(now));
+        }
+
+        self.apply_bypass();
+
+        // A third-party tool has taken over.
+        let overridden = self.apply_override(now);
+        if overridden == Some(Override::Pause) {
+            self.enter(State::Paused);
+            self.last_capture = None;
+            return Tick::Idle(self.capture_interval_at(now));
+        }
+
+        let mut work_done = false;
+        let locked = overridden.is_none() && self.apply_lock();
+        if overridden.is_none() && !locked {
+            self.update_ramp(now);
+        }
+        let state = self.next_state(overridden.is_some() || locked);
+        self.enter(state);
+
+        // 1. Capture new frame at configured rate
+        // Unless the state says otherwise or a ramp runs; a running
+        // transition still finishes.
+        let idle = !self.state.captures() || self.ramp_end.is_some();
+        let interval = self.capture_interval_at(now);
+        let since_capture = self.last_capture.map(|t| now.duration_since(t));
+        if !idle && since_capture.is_none_or(|d| d >= interval) {
+            if let Some(gap) = since_capture {
+                self.telemetry.capture_gap(gap, interval);
+            }
+            self.capture(stats, history);
+            self.last_capture = Some(self.clock.now());
+            work_done = true;
+        }
+
+        // 2. Apply smooth step
+        self.backlight.hotplug();
+        let written = self.backlight.written();
+        if self.backlight.woke() {
+            // The driver may have reset the panel to full brightness: go
+            // straight back rather than stepping down from there. A ramp
+            // keeps its pace from where it is.
+            let value = if self.ramp_end.is_some() {
+                self.transition.current_value()
+            } else {
+                let target = self.transition.target_value();
+                self.transition.jump_to(target, self.hardware_max);
+                target
+            };
+            info!(target: "backlight", "Display woke up; restoring brightness {}", value);
+            self.record(Kind::Woke, None);
+            let _ = self.backlight.reassert(value);
+            work_done = true;
+        }
+        if let Some(val) = self.transition.update(self.clock.now()) {
+            let write_start = Instant::now();
+            let _ = self.backlight.set(val);
+            self.record(Kind::Write, None);
+            self.telemetry.write(write_start.elapsed());
+            if val == self.transition.target_value() {
+                if let Some(line) = self.status.finish(val, self.clock.now()) {
+                    line.log();
+                }
+            }
+            work_done = true;
+        } else if self.transition.current_value() == self.transition.target_value() {
+            let _ = self.backlight.flush();
+        }
+        self.audit_write(written);
+        if work_done && let Some(control) = &self.control {
+            control.publish_state(self.state());
+            control.publish_stats(stats, self.backlight.writes());
+        }
+        self.telemetry.maybe_report();
+        self.hooks.poll();
+        if self.has_luma {
+            let (transition, luma, lux) = (&self.transition, self.last_adjusted_luma, self.last_lux);
+            self.exporter.maybe_push(|| Metrics {
+                luma,
+                lux,
+                brightness: transition.current_value(),
+                target: transition.target_value(),
+                brightness_changes: stats.brightness_changes,
+                capture_errors: stats.capture_errors,
+                motion_skipped: stats.motion_skipped,
+            });
+        }
+
+        if work_done {
+            return Tick::Busy;
+        }
+        // 3. Report how long until the next due event
+        let since_capture = self
+            .last_capture
+            .map_or(Duration::MAX, |t| self.clock.now().duration_since(t));
+        let interval = self.capture_interval_at(self.clock.now());
+        let capture_wait = if idle {
+            interval
+        } else {
+            interval.saturating_sub(since_capture)
+        };
+        let step_wait = self.transition.time_until_next_step(self.clock.now());
+        Tick::Idle(capture_wait.min(step_wait))
+    }
+
+    fn capture(&mut self, stats: &mut RunStats, history: &mut HistoryRecorder) {
+        let capture_start = Instant::now();
+        // A NaN reading would stick in the average for good; it is a
+        // failed capture like any other.
+        let reading = self
+            .sensor
+            .measure(self.cfg.half_precision)
+            .and_then(|luma| {
+                if luma.is_finite() {
+                    Ok(luma)
+                } else {
+                    Err(format!("sensor returned a non-finite reading ({})", luma).into())
+                }
+            })
+            .map(|luma| self.compensate_screen(luma));
+        self.telemetry.capture(capture_start.elapsed());
+        if let (Ok(raw), Some(fast)) = (&reading, &mut self.fast_capture) {
+            fast.observe(*raw, self.clock.now());
+        }
+        if reading.is_ok() {
+            self.last_lux = self.sensor.lux();
+            if let Some(lux) = self.last_lux {
+                stats.lux.add(lux as f64);
+            }
+        }
+        let processing_start = Instant::now();
+        let reading = reading.map(|luma| (luma, self.motion_gate.weight(self.sensor.motion())));
+        match reading {
+            Ok((_, 0.0)) => {
+                stats.motion_skipped += 1;
+            }
+            Ok((raw_luma, weight)) => {
+                if std::mem::take(&mut self.privacy) {
+                    info!(target: "daemon", "Camera privacy switch off; following ambient light again");
+                }
+                if let Some(failed) = self.capture_errors.recover(self.clock.now()) {
+                    info!(target: "daemon", "Ambient readings are back after {:.0?}", failed);
+                }
+                let switched = self.safe_mode.observe(self.cfg, raw_luma, self.clock.now());
+                let normalized = if self.safe_mode.active {
+                    raw_luma.clamp(0.0, 1.0)
+                } else {
+                    normalize_luma(self.cfg, raw_luma)
+                };
+                if let Some(entered) = switched {
+                    self.switch_safe_mode(entered, normalized);
+                }
+                let smoothed = self.ema.update_weighted(normalized, weight);
+                let smoothed = if self.bypass.smoothing { normalized } else { smoothed };
+                let rules_changed = self.apply_rules(smoothed);
+                let boost_changed = self.apply_boost();
+                let adjusted = if self.effects.disable_circadian || self.bypass.circadian {
+                    smoothed
+                } else {
+                    apply_circadian(self.cfg, &self.circadian, smoothed)
+                };
+                stats.record_sample(adjusted, self.transition.current_value());
+                let backlight = &self.backlight;
+                history.maybe_record(adjusted, self.transition.current_value(), || {
+                    backlight.actual()
+                });
+                // In fullscreen the picture itself lights the room; keep the target.
+                if !self.in_grace()
+                    && self.state != State::Inhibited(Inhibition::Fullscreen)
+                    && let Some(target) = self.update_brightness(adjusted)
+                {
+                    let changed = target != self.transition.target_value();
+                    if changed {
+                        stats.brightness_changes += 1;
+                        self.fire_change(target, adjusted);
+                        self.reason = if boost_changed {
+                            Reason::Manual
+                        } else if rules_changed {
+                            Reason::Rule
+                        } else if adjusted != smoothed
+                            && self
+                                .target_luma
+                                .is_some_and(|luma| (smoothed - luma).abs() < self.cfg.min_luma_delta)
+                        {
+                            // The room is as it was; the time of day moved the target.
+                            Reason::Circadian
+                        } else {
+                            Reason::Ambient
+                        };
+                    }
+                    self.target_luma = Some(smoothed);
+                    self.transition.set_target_by(target, self.hardware_max, self.cause());
+                    if changed {
+                        let (from, to) = (self.transition.current_value(), self.transition.target_value());
+                        if let Some(line) =
+                            self.status.target(from, to, adjusted, self.last_lux, self.clock.now())
+                        {
+                            line.log();
+                        }
+                    }
+                }
+                self.record(Kind::Reading, Some(adjusted));
+                if let Some(control) = &self.control {
+                    control.publish(
+                        adjusted,
+                        self.last_lux,
+                        self.transition.current_value(),
+                        self.transition.target_value(),
+                    );
+                }
+            }
+            // Not a failure: hold the brightness until the camera is uncovered.
+            Err(err) if err.is::<PrivacyBlocked>() => {
+                if !self.privacy {
+                    self.privacy = true;
+                    self.record(Kind::Privacy, None);
+                    info!(
+                        target: "daemon",
+                        "Camera blocked by a privacy switch; holding brightness at {}",
+                        self.transition.target_value()
+                    );
+                }
+            }
+            Err(err) => {
+                stats.capture_errors += 1;
+                self.record(Kind::CaptureError, None);
+                if self.hooks.wants(Event::Error) {
+                    self.hooks.fire(Event::Error, vec![("SB_ERROR", err.to_string())]);
+                }
+                let now = self.clock.now();
+                match self.capture_errors.fail("Ambient capture failed", &err, now) {
+                    Some(Escalation::Notify) => integrations::notify(
+                        &tr!("notify-sensor-failing"),
+                        &tr!(
+                            "notify-sensor-failing-body",
+                            secs = self.cfg.error_notify_after_secs.to_string(),
+                            error = err.to_string()
+                        ),
+                    ),
+                    Some(Escalation::Fallback) => self.fall_back(now),
+                    _ => {}
+                }
+            }
+        }
+        self.telemetry.processing(processing_start.elapsed());
+
+        let masked = self.sensor.masked_cells();
+        if masked != self.masked_cells {
+            self.masked_cells = masked;
+            info!(
+                target: "camera",
+                "Bright-source mask: {} of {} frame regions down-weighted",
+                masked,
+                masking::GRID_COLS * masking::GRID_ROWS
+            );
+        }
+    }
+
+    /// Start over from fresh readings after the machine slept: the average
+    /// still holds the room it went to sleep in, and the laptop may have
+    /// moved since. The first reading sets the average outright, and a few
+    /// quick ones follow to settle it.
+    fn soft_start(&mut self, asleep: Duration, now: Instant) {
+        info!(target: "daemon", "Resumed after {:.0?} asleep; taking new readings", asleep);
+        self.ema = Ema::new(self.cfg.smoothing_factor);
+        self.has_luma = false;
+        self.last_capture = None;
+        self.resumed_until = Some(now + RESUME_WINDOW);
+        if let Some(fast) = &mut self.fast_capture {
+            fast.last_raw = None;
+        }
+    }
+
+    /// Take up stages switched off or back on from `ctl`.
+    fn apply_bypass(&mut self) {
+        let bypass = self.control.as_ref().map_or(Bypass::default(), |c| c.bypass());
+        if bypass == self.bypass {
+            return;
+        }
+        info!(
+            target: "daemon",
+            "Circadian adjustment {}, smoothing {}",
+            if bypass.circadian { "off" } else { "on" },
+            if bypass.smoothing { "off" } else { "on" }
+        );
+        self.bypass = bypass;
+        self.transition.set_instant(bypass.smoothing);
+        // Re-map the next reading even if luma hasn't moved.
+        self.has_luma = false;
+    }
+
+    /// Re-evaluate `[[rules]]` for `luma`; true if their effects changed.
+    fn apply_rules(&mut self, luma: f32) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let ctx = Context::now(luma, self.last_lux, self.cfg.active_profile.as_deref());
+        let effects = self.rules.evaluate(&ctx);
+        if effects != self.effects {
+            info!(
+                target: "daemon",
+                "Rules: cap {:.0}%, floor {:.0}%, circadian {}",
+                effects.cap * 100.0,
+                effects.floor * 100.0,
+                if effects.disable_circadian { "off" } else { "on" }
+            );
+            self.effects = effects;
+            // Re-map even if luma hasn't moved enough to pass the delta gate.
+            self.has_luma = false;
+            return true;
+        }
+        false
+    }
+
+    /// Count a reading against the startup grace period; true while it
+    /// lasts.
+    fn in_grace(&mut self) -> bool {
+        let Some((captures, until)) = &mut self.grace else {
+            return false;
+        };
+        if *captures > 0 {
+            *captures -= 1;
+            return true;
+        }
+        if self.clock.now() < *until {
+            return true;
+        }
+        self.grace = None;
+        debug!(target: "daemon", "Startup grace period over");
+        false
+    }
+
+    /// Follow changes of the override file; returns the override in effect.
+    fn apply_override(&mut self, now: Instant) -> Option<Override> {
+        if !self.override_file.poll(now) {
+            return self.override_file.current();
+        }
+        let current = self.override_file.current();
+        let target = match current {
+            Some(Override::Pause) => {
+                info!(target: "daemon", "Override file: leaving brightness alone");
+                self.record(Kind::Override, None);
+                return current;
+            }
+            Some(Override::Percent(percent)) => self.percent_value(percent),
+            // A hardware value, with the picture undimmed.
+            Some(Override::Raw(value)) => value
+                .saturating_add(self.backlight.pixel_levels())
+                .min(self.hardware_max),
+            None => {
+                info!(target: "daemon", "Override file gone; following ambient light again");
+                self.has_luma = false;
+                // A `ctl lock` still in place takes over again.
+                self.override_state = OverrideState::Ambient;
+                return None;
+            }
+        };
+        info!(target: "daemon", "Override file: brightness {}", target);
+        self.transition.set_target_by(target, self.hardware_max, Cause::Manual);
+        self.record(Kind::Override, None);
+        self.reason = Reason::Override;
+        self.ramp_end = None;
+        current
+    }
+
+    /// Follow `ctl lock` and `ctl unlock`; true while locked.
+    fn apply_lock(&mut self) -> bool {
+        let wanted = match self.control.as_ref().and_then(|c| c.lock()) {
+            Some(percent) => OverrideState::Locked(percent),
+            None => OverrideState::Ambient,
+        };
+        if wanted == self.override_state {
+            return wanted != OverrideState::Ambient;
+        }
+        self.override_state = wanted;
+        match wanted {
+            OverrideState::Locked(percent) => {
+                let target = self.percent_value(percent);
+                info!(target: "daemon", "Brightness locked at {}% ({})", percent, target);
+                self.transition.set_target_by(target, self.hardware_max, Cause::Manual);
+                self.record(Kind::Override, None);
+                self.reason = Reason::Manual;
+                self.ramp_end = None;
+                true
+            }
+            OverrideState::Ambient => {
+                info!(target: "daemon", "Brightness unlocked; following ambient light again");
+                self.has_luma = false;
+                false
+            }
+        }
+    }
+
+    /// `percent` of the calibrated range as a backlight value.
+    fn percent_value(&self, percent: f32) -> u32 {
+        let (min, max) = (self.cfg.real_min_brightness, self.real_max);
+        let value = (percent / 100.0)
+            .mul_add(max.saturating_sub(min) as f32, min as f32)
+            .round() as u32;
+        value.clamp(min, max).min(self.hardware_max)
+    }
+
+    /// Start a ramp requested from a front end or due by `[[ramps]]`, and
+    /// go back to ambient tracking once the running one has ended.
+    fn update_ramp(&mut self, now: Instant) {
+        if let Some(end) = self.ramp_end {
+            if now >= end && self.transition.current_value() == self.transition.target_value() {
+                info!(target: "daemon", "Ramp finished; following ambient light again");
+                self.ramp_end = None;
+                self.has_luma = false;
+            }
+            return;
+        }
+        let requested = self.control.as_ref().and_then(|c| c.take_ramp());
+        let reason = if requested.is_some() { Reason::Manual } else { Reason::Ramp };
+        let Some((brightness, left)) = requested.or_else(|| ramps::due(&self.cfg.ramps)) else {
+            return;
+        };
+        self.reason = reason;
+        let target = self.percent_value(brightness);
+        let end = now + left;
+        self.transition.ramp_to(target, self.hardware_max, now, end);
+        self.ramp_end = Some(end);
+        self.record(Kind::Ramp, None);
+        info!(
+            target: "daemon",
+            "Ramping from {} to {} over {} min",
+            self.transition.current_value(),
+            target,
+            left.as_secs().div_ceil(60)
+        );
+    }
+
+    /// A scheduled ramp is timed by the wall clock, but runs to a monotonic
+    /// end that stands still in suspend and ignores clock changes. Drop it;
+    /// `update_ramp` takes it up again, from the current brightness and
+    /// with the time the schedule has left, if it is still due.
+    fn retime_ramp(&mut self) {
+        if self.ramp_end.is_some() && self.reason == Reason::Ramp {
+            debug!(target: "daemon", "Re-timing the scheduled ramp");
+            self.ramp_end = None;
+            self.has_luma = false;
+        }
+    }
+
+    /// Take out the screen's own light: `screen_compensation` × screen luma ×
+    /// the backlight level as a fraction of the hardware range.
+    fn compensate_screen(&self, luma: f32) -> f32 {
+        let Some(screen) = self.desktop.screen_luma() else {
+            return luma;
+        };
+        let level = self.transition.current_value() as f32 / self.hardware_max.max(1) as f32;
+        (luma - self.cfg.screen_compensation * screen * level).max(0.0)
+    }
+
+    /// Start the EMA over from `normalized`, which is on the new scale, and
+    /// re-map at once.
+    fn switch_safe_mode(&mut self, entered: bool, normalized: f32) {
+        self.ema.seed(normalized);
+        self.has_luma = false;
+        if !entered {
+            info!(target: "daemon", "Ambient readings fit the calibrated range again; leaving safe mode");
+            return;
+        }
+        let minutes = (self.cfg.safe_mode_after_secs / 60).max(1).to_string();
+        warn!(
+            target: "daemon",
+            "Ambient readings have been outside the calibrated range for {} min; \
+             safe mode: using the full camera range until they fit again",
+            minutes
+        );
+        self.record(Kind::Fallback, Some(normalized));
+        integrations::notify(
+            &tr!("notify-safe-mode"),
+            &tr!("notify-safe-mode-body", minutes = minutes),
+        );
+    }
+
+    /// Readings have failed for `error_fallback_after_secs`: switch to
+    /// another sensor, or stop where the brightness is.
+    fn fall_back(&mut self, now: Instant) {
+        self.record(Kind::Fallback, None);
+        if self.sensor.fall_back() {
+            self.capture_errors.restart(now);
+            return;
+        }
+        let current = self.transition.current_value();
+        self.transition.set_target(current, self.hardware_max);
+        error!(
+            target: "daemon",
+            "No other sensor to switch to; holding brightness at {} and retrying every {:?}",
+            current,
+            HOLD_RETRY_INTERVAL
+        );
+    }
+
+    /// What set the current target, as far as its pace goes.
+    fn cause(&self) -> Cause {
+        match self.reason {
+            Reason::Manual => Cause::Boost,
+            Reason::Circadian => Cause::Circadian,
+            Reason::Override => Cause::Manual,
+            Reason::Ambient | Reason::Rule | Reason::Ramp => Cause::Ambient,
+        }
+    }
+
+    /// Note the moment in the flight recorder.
+    fn record(&self, kind: Kind, luma: Option<f32>) {
+        flight_recorder::record(kind, luma, self.transition.target_value(), self.backlight.written());
+    }
+
+    /// The capture interval, shortened while a fast-capture window is open
+    /// or just after a resume, and lengthened while holding after
+    /// persistent failures.
+    fn capture_interval_at(&self, now: Instant) -> Duration {
+        if self.capture_errors.holding() {
+            return self.capture_interval.max(HOLD_RETRY_INTERVAL);
+        }
+        if self.resumed_until.is_some_and(|t| now < t) {
+            return self.capture_interval.min(RESUME_CAPTURE_INTERVAL);
+        }
+        match &self.fast_capture {
+            Some(fast) if fast.active(now) => fast.interval.min(self.capture_interval),
+            _ => self.capture_interval,
+        }
+    }
+
+    /// What holds the brightness now, the most forceful first. Pauses are
+    /// taken care of before.
+    fn next_state(&mut self, overridden: bool) -> State {
+        if overridden {
+            State::Overridden
+        } else if self.backlight.display_off() {
+            State::DisplayOff
+        } else if self.desktop.idle() {
+            State::Inhibited(Inhibition::Idle)
+        } else if self.capture_errors.holding() {
+            State::SensorFailed
+        } else if self.privacy {
+            State::Inhibited(Inhibition::Privacy)
+        } else if self.desktop.fullscreen() {
+            State::Inhibited(Inhibition::Fullscreen)
+        } else {
+            State::Running
+        }
+    }
+
+    fn enter(&mut self, state: State) {
+        if state == self.state {
+            return;
+        }
+        info!(target: "daemon", "State: {} → {}", self.state, state);
+        match self.state {
+            // Targets were kept through the fullscreen hold; re-map at once.
+            State::Inhibited(Inhibition::Fullscreen) => self.has_luma = false,
+            // Blend back from the held level rather than snapping.
+            State::Paused | State::Overridden => {
+                self.transition.release();
+                self.has_luma = false;
+            }
+            _ => {}
+        }
+        self.state = state;
+    }
+
+    fn apply_boost(&mut self) -> bool {
+        let boost = self.control.as_ref().map_or(0.0, |c| c.boost());
+        if boost == self.boost {
+            return false;
+        }
+        if boost == 0.0 {
+            self.transition.release();
+        }
+        self.boost = boost;
+        self.has_luma = false;
+        true
+    }
+
+    /// Record a value that reached the device since `before` was.
+    fn audit_write(&mut self, before: Option<u32>) {
+        let Some(new) = self.backlight.written() else {
+            return;
+        };
+        if before == Some(new) {
+            return;
+        }
+        self.audit.record(Entry {
+            old: before,
+            new,
+            target: self.transition.target_value(),
+            reason: self.reason,
+            luma: self.has_luma.then_some(self.last_adjusted_luma),
+            lux: self.last_lux,
+        });
+    }
+
+    fn fire_change(&mut self, target: u32, luma: f32) {
+        if !self.hooks.wants(Event::Change) {
+            return;
+        }
+        let (min, max) = (self.cfg.real_min_brightness, self.real_max);
+        let percent = target.saturating_sub(min) as f32 * 100.0 / max.saturating_sub(min).max(1) as f32;
+        let mut env = vec![
+            ("SB_BRIGHTNESS", target.to_string()),
+            ("SB_BRIGHTNESS_MAX", self.hardware_max.to_string()),
+            ("SB_PERCENT", format!("{:.0}", percent)),
+            ("SB_LUMA", format!("{:.3}", luma)),
+        ];
+        if let Some(lux) = self.last_lux {
+            env.push(("SB_LUX", format!("{:.0}", lux)));
+        }
+        self.hooks.fire(Event::Change, env);
+    }
+
+    /// New brightness target for `adjusted`, or None while it stays within
+    /// `min_luma_delta` of the previous reading.
+    fn update_brightness(&mut self, adjusted: f32) -> Option<u32> {
+        let luma_delta = if self.has_luma {
+            (adjusted - self.last_adjusted_luma).abs()
+        } else {
+            f32::MAX
+        };
+        self.last_adjusted_luma = adjusted;
+        if self.has_luma && luma_delta < self.cfg.min_luma_delta {
+            return None;
+        }
+        self.has_luma = true;
+        let real_min = self.cfg.real_min_brightness;
+        let real_max = self.real_max;
+        let range = real_max.saturating_sub(real_min) as f32;
+        let fraction = self.effects.clamp(self.cfg.brightness_fraction(adjusted));
+        let fraction = (fraction + self.boost).clamp(0.0, 1.0);
+        // `as u32` turns NaN into 0: better to hold than to go dark.
+        if !fraction.is_finite() {
+            warn!(
+                target: "daemon",
+                "Curve gave no brightness for luma {}; holding the current target",
+                adjusted
+            );
+            return None;
+        }
+        let mapped = fraction
+            .mul_add(range, real_min as f32)
+            .round() as u32;
+        Some(mapped.clamp(real_min, real_max).min(self.hardware_max))
+    }
+}
+
+/// Samples quickly for a while after a sudden change in the raw reading, so
+/// a long `capture_interval_ms` doesn't make the display slow to follow.
+struct FastCapture {
+    interval: Duration,
+    luma_delta: f32,
+    duration: Duration,
+    until: Option<Instant>,
+    last_raw: Option<f32>,
+}
+
+impl FastCapture {
+    fn from_config(cfg: &Config) -> Option<Self> {
+        (cfg.capture_fast_interval_ms > 0).then(|| Self {
+            interval: Duration::from_millis(cfg.capture_fast_interval_ms),
+            luma_delta: cfg.capture_fast_luma_delta,
+            duration: Duration::from_secs_f64(cfg.capture_fast_secs),
+            until: None,
+            last_raw: None,
+        })
+    }
+
+    fn observe(&mut self, raw: f32, now: Instant) {
+        if let Some(last) = self.last_raw
+            && (raw - last).abs() >= self.luma_delta
+        {
+            if !self.active(now) {
+                debug!(target: "daemon", "Light changed by {:.3}: capturing fast", raw - last);
+            }
+            self.until = Some(now + self.duration);
+        }
+        self.last_raw = Some(raw);
+    }
+
+    fn active(&self, now: Instant) -> bool {
+        self.until.is_some_and(|t| now < t)
+    }
+}
+
+/// Reports each brightness transition twice: when its target is committed
+/// and when a write reaches it. Steps in between, and a new target arriving
+/// while one is underway, aren't reported; the completion line covers them.
+struct StatusReporter {
+    min_change: u32,
+    enabled: bool,
+    /// Level and time the transition underway started from.
+    underway: Option<(u32, Instant)>,
+}
+
+/// A line for the status log; see `StatusReporter`.
+#[derive(Debug, Clone, PartialEq)]
+enum StatusLine {
+    Start {
+        from: u32,
+        to: u32,
+        luma: f32,
+        lux: Option<f32>,
+    },
+    Done {
+        from: u32,
+        to: u32,
+        took: Duration,
+    },
+}
+
+impl StatusLine {
+    fn log(&self) {
+        let line = match *self {
+            StatusLine::Start { from, to, luma, lux } => {
+                let (from, to, luma) = (from.to_string(), to.to_string(), format!("{:.3}", luma));
+                match lux {
+                    Some(lux) => tr!(
+                        "status-transition-start-lux",
+                        from = from,
+                        to = to,
+                        luma = luma,
+                        lux = format!("{:.0}", lux)
+                    ),
+                    None => tr!("status-transition-start", from = from, to = to, luma = luma),
+                }
+            }
+            StatusLine::Done { from, to, took } => tr!(
+                "status-transition-done",
+                from = from.to_string(),
+                to = to.to_string(),
+                secs = format!("{:.1}", took.as_secs_f32())
+            ),
+        };
+        info!(target: logging::STATUS, "{}", line);
+    }
+}
+
+impl StatusReporter {
+    fn new(min_change: u32, enabled: bool) -> Self {
+        Self {
+            min_change: min_change.max(1),
+            enabled,
+            underway: None,
+        }
+    }
+
+    /// A new target `to` was committed while the panel is at `from`.
+    fn target(
+        &mut self,
+        from: u32,
+        to: u32,
+        luma: f32,
+        lux: Option<f32>,
+        now: Instant,
+    ) -> Option<StatusLine> {
+        if self.underway.is_some() {
+            // Sent back to where the panel already is.
+            return if from == to { self.finish(to, now) } else { None };
+        }
+        if from == to {
+            return None;
+        }
+        self.underway = Some((from, now));
+        (self.enabled && from.abs_diff(to) >= self.min_change)
+            .then_some(StatusLine::Start { from, to, luma, lux })
+    }
+
+    /// A write brought the panel to the target at `value`.
+    fn finish(&mut self, value: u32, now: Instant) -> Option<StatusLine> {
+        let (from, started) = self.underway.take()?;
+        (self.enabled && from.abs_diff(value) >= self.min_change).then(|| StatusLine::Done {
+            from,
+            to: value,
+            took: now.duration_since(started),
+        })
+    }
+}
+
+/// How far a run of failing readings has been escalated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Escalation {
+    Warn,
+    Notify,
+    Fallback,
+}
+
+/// Logs failures at most every `interval` and escalates while they go on
+/// without a good reading in between: an error and a notification after
+/// `notify_after`, a sensor switch or hold after `fallback_after`.
+struct ErrorThrottle {
+    last_log: Option<Instant>,
+    interval: Duration,
+    notify_after: Option<Duration>,
+    fallback_after: Option<Duration>,
+    /// Start of the current run of failures, and its length.
+    failing_since: Option<Instant>,
+    consecutive: u32,
+    escalation: Escalation,
+}
+
+impl ErrorThrottle {
+    fn from_config(cfg: &Config) -> Self {
+        let after = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        Self {
+            last_log: None,
+            interval: Duration::from_secs(cfg.error_throttle_secs),
+            notify_after: after(cfg.error_notify_after_secs),
+            fallback_after: after(cfg.error_fallback_after_secs),
+            failing_since: None,
+            consecutive: 0,
+            escalation: Escalation::Warn,
+        }
+    }
+
+    /// Note a failure at `now`; returns the escalation it newly reached.
+    fn fail<E: std::fmt::Display>(&mut self, context: &str, err: E, now: Instant) -> Option<Escalation> {
+        self.consecutive += 1;
+        let since = *self.failing_since.get_or_insert(now);
+        let failing = now.duration_since(since);
+        let reached = if self.fallback_after.is_some_and(|d| failing >= d) {
+            Escalation::Fallback
+        } else if self.notify_after.is_some_and(|d| failing >= d) {
+            Escalation::Notify
+        } else {
+            Escalation::Warn
+        };
+        if reached > self.escalation {
+            self.escalation = reached;
+            self.last_log = Some(now);
+            error!(
+                target: "daemon",
+                "{}: {} ({} in a row over {:.0?})",
+                context,
+                err,
+                self.consecutive,
+                failing
+            );
+            return Some(reached);
+        }
+        if self.last_log.is_none_or(|t| now.duration_since(t) >= self.interval) {
+            warn!(target: "daemon", "{}: {} ({} in a row)", context, err, self.consecutive);
+            self.last_log = Some(now);
+        }
+        None
+    }
+
+    /// Start over after a switch to another sensor, which gets the full
+    /// time before falling back again.
+    fn restart(&mut self, now: Instant) {
+        self.failing_since = Some(now);
+        self.escalation = self.escalation.min(Escalation::Notify);
+    }
+
+    /// Note a good reading; returns how long readings had failed if that
+    /// had been escalated.
+    fn recover(&mut self, now: Instant) -> Option<Duration> {
+        let since = self.failing_since.take()?;
+        self.consecutive = 0;
+        let escalated = std::mem::replace(&mut self.escalation, Escalation::Warn) > Escalation::Warn;
+        escalated.then(|| now.duration_since(since))
+    }
+
+    fn holding(&self) -> bool {
+        self.escalation == Escalation::Fallback
+    }
+}
+
+/// Watches for a calibration that no longer fits the room: readings
+/// clipped at one end of it for `after` switch to the full raw range, and
+/// readings back inside it for `SAFE_MODE_EXIT` switch back.
+struct SafeMode {
+    after: Option<Duration>,
+    /// Start of the current run of readings that would flip the mode.
+    since: Option<Instant>,
+    active: bool,
+}
+
+impl SafeMode {
+    fn from_config(cfg: &Config) -> Self {
+        Self {
+            after: (cfg.safe_mode_after_secs > 0)
+                .then(|| Duration::from_secs(cfg.safe_mode_after_secs)),
+            since: None,
+            active: false,
+        }
+    }
+
+    /// Note a raw reading at `now`; returns true on entering safe mode and
+    /// false on leaving it.
+    fn observe(&mut self, cfg: &Config, raw: f32, now: Instant) -> Option<bool> {
+        let (Some(after), Some(min), Some(max)) =
+            (self.after, cfg.camera_min_luma, cfg.camera_max_luma)
+        else {
+            return None;
+        };
+        let clipped = raw <= min || raw >= max;
+        let (flips, wait) = if self.active {
+            (!clipped, SAFE_MODE_EXIT)
+        } else {
+            (clipped, after)
+        };
+        if !flips {
+            self.since = None;
+            return None;
+        }
+        let since = *self.since.get_or_insert(now);
+        if now.duration_since(since) < wait {
+            return None;
+        }
+        self.since = None;
+        self.active = !self.active;
+        Some(self.active)
+    }
+}
+
+fn normalize_luma(cfg: &Config, raw: f32) -> f32 {
+    if let (Some(min), Some(max)) = (cfg.camera_min_luma, cfg.camera_max_luma)
+        && max > min
+    {
+        return ((raw - min) / (max - min)).clamp(0.0, 1.0);
+    }
+    raw
+}
+
+fn apply_circadian(cfg: &Config, circadian: &TimeAdjuster, smoothed: f32) -> f32 {
+    if cfg.enable_circadian {
+        circadian.adjust(smoothed)
+    } else {
+        smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::FakeClock;
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+
+    struct FakeSensor(VecDeque<f32>);
+
+    impl LumaSource for FakeSensor {
+        fn measure(&mut self, _half_precision: bool) -> Result<f32, Box<dyn Error>> {
+            self.0.pop_front().ok_or_else(|| "no more readings".into())
+        }
+    }
+
+    /// Values written, and whether the display has just woken.
+    #[derive(Default)]
+    struct FakeBacklight(RefCell<Vec<u32>>, Cell<bool>);
+
+    impl BacklightSink for FakeBacklight {
+        fn set(&self, value: u32) -> std::io::Result<()> {
+            self.0.borrow_mut().push(value);
+            Ok(())
+        }
+
+        fn actual(&self) -> Option<u32> {
+            self.0.borrow().last().copied()
+        }
+
+        fn woke(&self) -> bool {
+            self.1.take()
+        }
+    }
+
+    fn test_config() -> Config {
+        Config {
+            smoothing_factor: 1.0,
+            real_min_brightness: 0,
+            real_max_brightness: 100,
+            camera_min_luma: None,
+            camera_max_luma: None,
+            enable_circadian: false,
+            min_luma_delta: 0.05,
+            history_enabled: false,
+            log_target_brightness: false,
+            startup_grace_captures: 0,
+            // Not the real runtime dir's override file.
+            override_enabled: false,
+            ..Config::default()
+        }
+    }
+
+    struct Harness<'a> {
+        ctl: Controller<'a, FakeSensor, FakeBacklight, FakeClock>,
+        clock: FakeClock,
+        stats: RunStats,
+        history: HistoryRecorder,
+    }
+
+    impl<'a> Harness<'a> {
+        fn new(
+            cfg: &'a Config,
+            readings: &[f32],
+            hardware_max: u32,
+            max_duration: Option<Duration>,
+        ) -> Self {
+            let clock = FakeClock::new();
+            let sensor = FakeSensor(readings.iter().copied().collect());
+            Self {
+                ctl: Controller::new(
+                    cfg,
+                    sensor,
+                    FakeBacklight::default(),
+                    clock.clone(),
+                    cfg.real_min_brightness,
+                    hardware_max,
+                    max_duration,
+                ),
+                clock,
+                stats: RunStats::new(),
+                history: HistoryRecorder::from_config(cfg),
+            }
+        }
+
+        /// Tick once a capture is due and return the resulting target.
+        fn capture(&mut self) -> u32 {
+            self.ctl.tick(&mut self.stats, &mut self.history);
+            self.clock.advance(self.ctl.capture_interval);
+            self.ctl.transition.target_value()
+        }
+    }
+
+    #[test]
+    fn small_luma_changes_do_not_move_the_target() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.5, 0.52, 0.54, 0.6], 100, None);
+        assert_eq!(h.capture(), 50);
+        assert_eq!(h.capture(), 50);
+        // Compared against the previous reading, not the last applied one.
+        assert_eq!(h.capture(), 50);
+        assert_eq!(h.capture(), 60);
+        assert_eq!(h.stats.brightness_changes, 2);
+    }
+
+    #[test]
+    fn first_reading_always_sets_a_target() {
+        let cfg = Config {
+            min_luma_delta: 1.0,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.3], 100, None);
+        assert_eq!(h.capture(), 30);
+    }
+
+    #[test]
+    fn target_is_clamped_to_calibrated_and_hardware_range() {
+        let cfg = Config {
+            real_min_brightness: 20,
+            real_max_brightness: 80,
+            curve: crate::curve::Curve {
+                points: vec![[0.0, 0.0], [0.5, 1.0]],
+                ..Default::default()
+            },
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.0, 1.0], 70, None);
+        assert_eq!(h.capture(), 20);
+        assert_eq!(h.capture(), 70);
+    }
+
+    #[test]
+    fn raw_override_beyond_the_hardware_is_clamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("override");
+        std::fs::write(&path, "4294967295\n").unwrap();
+        let cfg = Config {
+            override_enabled: true,
+            override_file: Some(path),
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.5], 70, None);
+        assert_eq!(h.capture(), 70);
+    }
+
+    #[test]
+    fn capture_waits_for_interval() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.5, 0.9], 100, None);
+        assert_eq!(h.ctl.tick(&mut h.stats, &mut h.history), Tick::Busy);
+        assert_eq!(h.ctl.sensor.0.len(), 1);
+        h.clock.advance(h.ctl.capture_interval / 2);
+        h.ctl.tick(&mut h.stats, &mut h.history);
+        assert_eq!(h.ctl.sensor.0.len(), 1);
+        h.clock.advance(h.ctl.capture_interval / 2);
+        h.ctl.tick(&mut h.stats, &mut h.history);
+        assert!(h.ctl.sensor.0.is_empty());
+    }
+
+    #[test]
+    fn capture_errors_are_counted_and_keep_the_target() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.4], 100, None);
+        assert_eq!(h.capture(), 40);
+        assert_eq!(h.capture(), 40);
+        assert_eq!(h.stats.capture_errors, 1);
+    }
+
+    #[test]
+    fn status_reports_start_and_completion_of_a_transition() {
+        let t0 = Instant::now();
+        let mut status = StatusReporter::new(5, true);
+        assert_eq!(
+            status.target(10, 50, 0.5, None, t0),
+            Some(StatusLine::Start {
+                from: 10,
+                to: 50,
+                luma: 0.5,
+                lux: None
+            })
+        );
+        // Retargeted while underway: the completion line covers it.
+        assert_eq!(status.target(30, 60, 0.6, None, t0), None);
+        assert_eq!(
+            status.finish(60, t0 + Duration::from_secs(2)),
+            Some(StatusLine::Done {
+                from: 10,
+                to: 60,
+                took: Duration::from_secs(2)
+            })
+        );
+        assert_eq!(status.finish(60, t0), None);
+    }
+
+    #[test]
+    fn status_skips_small_and_reversed_transitions() {
+        let t0 = Instant::now();
+        let mut status = StatusReporter::new(5, true);
+        assert_eq!(status.target(10, 12, 0.1, None, t0), None);
+        assert_eq!(status.finish(12, t0), None);
+
+        assert!(status.target(10, 50, 0.5, None, t0).is_some());
+        // Sent back to where the panel already is.
+        assert_eq!(
+            status.target(40, 40, 0.4, None, t0),
+            Some(StatusLine::Done {
+                from: 10,
+                to: 40,
+                took: Duration::ZERO
+            })
+        );
+        assert_eq!(status.finish(40, t0), None);
+
+        let mut quiet = StatusReporter::new(5, false);
+        assert_eq!(quiet.target(10, 50, 0.5, None, t0), None);
+        assert_eq!(quiet.finish(50, t0), None);
+    }
+
+    #[test]
+    fn non_finite_readings_count_as_capture_errors() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.4, f32::NAN, f32::INFINITY, 0.6], 100, None);
+        assert_eq!(h.capture(), 40);
+        assert_eq!(h.capture(), 40);
+        assert_eq!(h.capture(), 40);
+        assert_eq!(h.stats.capture_errors, 2);
+        assert_eq!(h.capture(), 60);
+    }
+
+    #[test]
+    fn persistent_capture_errors_hold_brightness_until_readings_return() {
+        let cfg = Config {
+            error_notify_after_secs: 0,
+            error_fallback_after_secs: 10,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.4], 100, None);
+        assert_eq!(h.capture(), 40);
+        h.capture();
+        assert!(!h.ctl.capture_errors.holding());
+        h.clock.advance(Duration::from_secs(10));
+        h.capture();
+        assert!(h.ctl.capture_errors.holding());
+        assert_eq!(h.ctl.capture_interval_at(h.clock.now()), HOLD_RETRY_INTERVAL);
+        h.ctl.sensor.0.push_back(0.7);
+        h.clock.advance(HOLD_RETRY_INTERVAL);
+        assert_eq!(h.capture(), 70);
+        assert!(!h.ctl.capture_errors.holding());
+    }
+
+    #[test]
+    fn smoothing_switched_off_passes_readings_straight_through() {
+        let cfg = Config {
+            smoothing_factor: 0.5,
+            smooth_step_divisor: 10,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.2, 0.8], 100, None);
+        let control = Arc::new(Control::new(cfg.clone(), cfg.clone()));
+        h.ctl.set_control(control.clone());
+        assert_eq!(h.capture(), 20);
+        control.set_bypass(crate::control::Feature::Smoothing, false);
+        assert_eq!(h.capture(), 80);
+        assert_eq!(h.ctl.transition.current_value(), 80);
+    }
+
+    #[test]
+    fn lock_holds_brightness_until_unlocked() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.2, 0.8, 0.8, 0.6], 100, None);
+        let control = Arc::new(Control::new(cfg.clone(), cfg.clone()));
+        h.ctl.set_control(control.clone());
+        assert_eq!(h.capture(), 20);
+        control.set_lock(45.0, None).unwrap();
+        assert_eq!(h.capture(), 45);
+        assert_eq!(h.capture(), 45);
+        control.unlock();
+        assert_eq!(h.capture(), 80);
+        control.set_lock(10.0, Some(Duration::ZERO)).unwrap();
+        assert_eq!(h.capture(), 80);
+    }
+
+    #[test]
+    fn state_follows_what_holds_the_brightness() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.2, 0.6], 100, None);
+        let control = Arc::new(Control::new(cfg.clone(), cfg.clone()));
+        h.ctl.set_control(control.clone());
+        assert_eq!(h.capture(), 20);
+        assert_eq!(h.ctl.state, State::Running);
+        control.set_paused(true);
+        assert_eq!(h.capture(), 20);
+        assert_eq!(h.ctl.state, State::Paused);
+        control.set_paused(false);
+        control.set_lock(30.0, None).unwrap();
+        assert_eq!(h.capture(), 30);
+        assert_eq!(h.ctl.state, State::Overridden);
+        control.unlock();
+        assert_eq!(h.capture(), 60);
+        assert_eq!(h.ctl.state, State::Running);
+    }
+
+    #[test]
+    fn readings_pinned_outside_the_calibration_switch_to_safe_mode() {
+        let cfg = Config {
+            camera_min_luma: Some(0.2),
+            camera_max_luma: Some(0.6),
+            safe_mode_after_secs: 10,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.9, 0.9, 0.4, 0.4], 100, None);
+        assert_eq!(h.capture(), 100);
+        h.clock.advance(Duration::from_secs(10));
+        assert_eq!(h.capture(), 90);
+        assert!(h.ctl.safe_mode.active);
+        assert_eq!(h.capture(), 40);
+        h.clock.advance(SAFE_MODE_EXIT);
+        assert_eq!(h.capture(), 50);
+        assert!(!h.ctl.safe_mode.active);
+    }
+
+    #[test]
+    fn waking_display_jumps_to_the_target() {
+        let cfg = Config {
+            smooth_step_divisor: 10,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.8], 100, None);
+        assert_eq!(h.capture(), 80);
+        assert!(h.ctl.transition.current_value() < 80);
+        h.ctl.backlight.1.set(true);
+        h.ctl.tick(&mut h.stats, &mut h.history);
+        assert_eq!(h.ctl.transition.current_value(), 80);
+        assert_eq!(h.ctl.backlight.actual(), Some(80));
+    }
+
+    #[test]
+    fn expires_after_max_duration() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.5; 8], 100, Some(Duration::from_secs(1)));
+        assert_ne!(h.ctl.tick(&mut h.stats, &mut h.history), Tick::Expired);
+        h.clock.advance(Duration::from_millis(999));
+        assert_ne!(h.ctl.tick(&mut h.stats, &mut h.history), Tick::Expired);
+        h.clock.advance(Duration::from_millis(1));
+        assert_eq!(h.ctl.tick(&mut h.stats, &mut h.history), Tick::Expired);
+    }
+
+    #[test]
+    fn runs_forever_without_max_duration() {
+        let cfg = test_config();
+        let mut h = Harness::new(&cfg, &[0.5; 8], 100, None);
+        h.clock.advance(Duration::from_secs(86_400));
+        assert_ne!(h.ctl.tick(&mut h.stats, &mut h.history), Tick::Expired);
+    }
+
+    #[test]
+    fn sudden_change_shortens_the_capture_interval_for_a_while() {
+        let cfg = Config {
+            capture_interval_ms: 1000,
+            capture_fast_interval_ms: 100,
+            capture_fast_luma_delta: 0.1,
+            capture_fast_secs: 2.0,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.5, 0.52, 0.9, 0.9], 100, None);
+        let slow = Duration::from_millis(1000);
+        let fast = Duration::from_millis(100);
+        h.capture();
+        h.capture();
+        assert_eq!(h.ctl.capture_interval_at(h.clock.now()), slow);
+        h.capture();
+        assert_eq!(h.ctl.capture_interval_at(h.clock.now()), fast);
+        h.clock.advance(Duration::from_millis(1000));
+        assert_eq!(h.ctl.capture_interval_at(h.clock.now()), slow);
+    }
+
+    #[test]
+    fn resume_starts_the_average_over_and_captures_quickly() {
+        let cfg = Config {
+            smoothing_factor: 0.1,
+            capture_interval_ms: 5000,
+            ..test_config()
+        };
+        let mut h = Harness::new(&cfg, &[0.2, 0.2, 0.9], 100, None);
+        assert_eq!(h.capture(), 20);
+        assert_eq!(h.capture(), 20);
+        h.clock.suspend(Duration::from_secs(3600));
+        h.ctl.tick(&mut h.stats, &mut h.history);
+        assert_eq!(h.ctl.transition.target_value(), 90);
+        assert_eq!(h.ctl.capture_interval_at(h.clock.now()), RESUME_CAPTURE_INTERVAL);
+    }
+}
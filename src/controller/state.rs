@@ -0,0 +1,71 @@
+// src/controller/state.rs
+//! What the control loop is doing about the brightness, as one state
+//! instead of a flag per feature that can hold it.
+//!
+//! Each tick the loop works out the state from what holds the brightness,
+//! most forceful first: a pause, an override or lock, the display being
+//! off, the user being idle, failing readings, the camera being covered, a
+//! fullscreen window. Every change is logged.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    /// Following ambient light.
+    #[default]
+    Running,
+    /// Paused from a front end or by `pause` in the override file; the
+    /// backlight is left alone.
+    Paused,
+    /// A level from the override file or `ctl lock`.
+    Overridden,
+    /// Something outside the daemon says to hold the brightness.
+    Inhibited(Inhibition),
+    /// Readings have failed for long; brightness is held while the sensor
+    /// is retried.
+    SensorFailed,
+    /// The display is powered off; nobody sees the brightness.
+    DisplayOff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Inhibition {
+    /// The user has been idle for `idle_pause_secs`.
+    Idle,
+    /// The camera is covered by a privacy switch.
+    Privacy,
+    /// A fullscreen window has focus (`fullscreen_hold`).
+    Fullscreen,
+}
+
+impl State {
+    /// Whether readings are taken. A covered camera and a failing sensor
+    /// are still tried, to notice when they are back; a fullscreen hold
+    /// keeps recording readings without acting on them.
+    pub fn captures(self) -> bool {
+        match self {
+            State::Running | State::SensorFailed => true,
+            State::Inhibited(inhibition) => inhibition != Inhibition::Idle,
+            State::Paused | State::Overridden | State::DisplayOff => false,
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Running => f.write_str("running"),
+            State::Paused => f.write_str("paused"),
+            State::Overridden => f.write_str("overridden"),
+            State::Inhibited(Inhibition::Idle) => f.write_str("inhibited (user idle)"),
+            State::Inhibited(Inhibition::Privacy) => f.write_str("inhibited (camera covered)"),
+            State::Inhibited(Inhibition::Fullscreen) => f.write_str("inhibited (fullscreen)"),
+            State::SensorFailed => f.write_str("sensor failed"),
+            State::DisplayOff => f.write_str("display off"),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// src/correction.rs
+//! Camera luma put on an ambient light sensor's scale (`camera_correction`).
+//!
+//! Auto-exposure flattens a webcam's response: a lamp switched on moves the
+//! frame's luma far less than the light in the room. With an IIO light
+//! sensor next to the camera, the calibration wizard records pairs of
+//! camera luma and sensor reading while the light changes and fits a
+//! piecewise-linear curve through them. Camera readings then go through the
+//! curve before anything else sees them.
+
+/// Bins the samples are averaged into; at most this many points.
+const BINS: usize = 8;
+/// Least spread of camera luma worth fitting a curve to.
+const MIN_SPREAD: f32 = 0.02;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    /// (camera luma, sensor luma), camera luma rising.
+    points: Vec<[f32; 2]>,
+}
+
+impl Correction {
+    /// None for an empty curve, i.e. no correction.
+    pub fn new(points: &[[f32; 2]]) -> Option<Self> {
+        (points.len() >= 2).then(|| Self {
+            points: points.to_vec(),
+        })
+    }
+
+    /// `luma` on the sensor's scale. Outside the recorded range the end
+    /// segments carry on.
+    pub fn apply(&self, luma: f32) -> f32 {
+        let i = self
+            .points
+            .partition_point(|p| p[0] <= luma)
+            .clamp(1, self.points.len() - 1);
+        let ([x0, y0], [x1, y1]) = (self.points[i - 1], self.points[i]);
+        (y0 + (luma - x0) * (y1 - y0) / (x1 - x0)).clamp(0.0, 1.0)
+    }
+}
+
+/// `camera_correction` as the config file has it.
+pub fn check(points: &[[f32; 2]]) -> Result<(), String> {
+    if points.len() == 1 {
+        return Err("camera_correction needs at least two points".into());
+    }
+    if points.iter().flatten().any(|v| !(0.0..=1.0).contains(v)) {
+        return Err("camera_correction values must be in the range [0, 1]".into());
+    }
+    if points.windows(2).any(|w| w[1][0] <= w[0][0] || w[1][1] < w[0][1]) {
+        return Err(
+            "camera_correction must rise: camera luma strictly, sensor luma never falling".into(),
+        );
+    }
+    Ok(())
+}
+
+/// Fit a curve to (camera luma, sensor luma) samples: the samples are
+/// averaged in bins of equal count along camera luma, and neighbouring bins
+/// that would make the curve fall are pooled. None if the camera luma
+/// hardly varied or the sensor didn't rise with it.
+pub fn fit(samples: &[(f32, f32)]) -> Option<Vec<[f32; 2]>> {
+    let mut samples: Vec<_> = samples
+        .iter()
+        .copied()
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .collect();
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if samples.last()?.0 - samples.first()?.0 < MIN_SPREAD {
+        return None;
+    }
+    // (mean camera luma, mean sensor luma, samples)
+    let mut blocks: Vec<(f32, f32, f32)> = Vec::with_capacity(BINS);
+    for chunk in samples.chunks(samples.len().div_ceil(BINS)) {
+        let n = chunk.len() as f32;
+        let x = chunk.iter().map(|s| s.0).sum::<f32>() / n;
+        let y = chunk.iter().map(|s| s.1).sum::<f32>() / n;
+        blocks.push((x, y, n));
+        while let [.., a, b] = blocks[..]
+            && a.1 > b.1
+        {
+            blocks.truncate(blocks.len() - 2);
+            let n = a.2 + b.2;
+            blocks.push(((a.0 * a.2 + b.0 * b.2) / n, (a.1 * a.2 + b.1 * b.2) / n, n));
+        }
+    }
+    let mut points: Vec<[f32; 2]> = Vec::with_capacity(blocks.len());
+    for (x, y, _) in blocks {
+        if points.last().is_none_or(|p| x > p[0]) {
+            points.push([x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)]);
+        }
+    }
+    (points.len() >= 2 && points[points.len() - 1][1] > points[0][1]).then_some(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitted_curve_follows_the_sensor_and_never_falls() {
+        // The camera compresses the upper range; one noisy sample dips.
+        let mut samples: Vec<(f32, f32)> = (0..=40)
+            .map(|i| {
+                let y = i as f32 / 40.0;
+                (0.3 + 0.4 * y.sqrt(), y)
+            })
+            .collect();
+        samples.push((0.69, 0.2));
+        let points = fit(&samples).unwrap();
+        assert!(check(&points).is_ok());
+        let curve = Correction::new(&points).unwrap();
+        assert!(curve.apply(0.3) < 0.05);
+        assert!((curve.apply(0.5) - 0.25).abs() < 0.08);
+        assert!(curve.apply(0.7) > 0.8);
+        assert_eq!(fit(&[(0.4, 0.1), (0.41, 0.9)]), None);
+    }
+}
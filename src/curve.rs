@@ -0,0 +1,137 @@
+// src/curve.rs
+use serde::{Deserialize, Serialize};
+
+/// Luma → brightness mapping, stored as the `[curve]` config table.
+///
+/// Anchors are `[luma, brightness]` pairs with luma normalized to 0–1. With
+/// the default unit, brightness is a fraction of the calibrated
+/// `screen_brightness_min..max` range; with `unit = "nits"` it is a target
+/// luminance, mapped onto the panel through `panel_min_nits..panel_max_nits`.
+/// Between anchors the curve is piecewise-linear, outside them it is flat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    #[serde(default = "default_points")]
+    pub points: Vec<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "CurveUnit::is_fraction")]
+    pub unit: CurveUnit,
+}
+
+/// What the brightness half of each anchor means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurveUnit {
+    /// Fraction (0–1) of the calibrated brightness range.
+    #[default]
+    Fraction,
+    /// Target luminance in cd/m².
+    Nits,
+}
+
+impl CurveUnit {
+    pub fn is_fraction(&self) -> bool {
+        *self == CurveUnit::Fraction
+    }
+}
+
+/// Smallest luma gap kept between neighbouring anchors.
+pub const MIN_GAP: f32 = 0.01;
+
+fn default_points() -> Vec<[f32; 2]> {
+    vec![[0.0, 0.0], [1.0, 1.0]]
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self {
+            points: default_points(),
+            unit: CurveUnit::Fraction,
+        }
+    }
+}
+
+impl Curve {
+    pub fn eval(&self, luma: f32) -> f32 {
+        let pts = &self.points;
+        let Some(first) = pts.first() else {
+            return luma.clamp(0.0, 1.0);
+        };
+        if luma <= first[0] {
+            return first[1];
+        }
+        for w in pts.windows(2) {
+            let ([x0, y0], [x1, y1]) = (w[0], w[1]);
+            if luma <= x1 {
+                let t = (luma - x0) / (x1 - x0);
+                return y0 + t * (y1 - y0);
+            }
+        }
+        pts[pts.len() - 1][1]
+    }
+
+    /// Curve through `[luma, brightness]` points recorded by the wizard, in
+    /// the order they were taken. A later point closer than `MIN_GAP` in
+    /// luma to an earlier one replaces it. None with fewer than two left.
+    pub fn from_points(recorded: &[[f32; 2]]) -> Option<Self> {
+        let mut points: Vec<[f32; 2]> = Vec::with_capacity(recorded.len());
+        for &[x, y] in recorded {
+            let point = [x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)];
+            points.retain(|p| (p[0] - point[0]).abs() >= MIN_GAP);
+            points.push(point);
+        }
+        if points.len() < 2 {
+            return None;
+        }
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        Some(Self {
+            points,
+            unit: CurveUnit::Fraction,
+        })
+    }
+
+    pub fn is_linear(&self) -> bool {
+        *self == Curve::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.points.len() < 2 {
+            return Err("curve.points needs at least two anchors".into());
+        }
+        for [x, y] in &self.points {
+            let y_ok = match self.unit {
+                CurveUnit::Fraction => (0.0..=1.0).contains(y),
+                CurveUnit::Nits => y.is_finite() && *y >= 0.0,
+            };
+            if !(0.0..=1.0).contains(x) || !y_ok {
+                return Err(match self.unit {
+                    CurveUnit::Fraction => {
+                        format!("curve anchor [{}, {}] must be within 0.0..=1.0", x, y)
+                    }
+                    CurveUnit::Nits => format!(
+                        "curve anchor [{}, {}] needs luma within 0.0..=1.0 and non-negative nits",
+                        x, y
+                    ),
+                });
+            }
+        }
+        if self.points.windows(2).any(|w| w[1][0] <= w[0][0]) {
+            return Err("curve.points must be sorted by strictly increasing luma".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_points_are_sorted_and_retaken_ones_replace_earlier() {
+        let curve =
+            Curve::from_points(&[[0.8, 0.9], [0.1, 0.2], [0.805, 0.7], [0.4, 0.5]]).unwrap();
+        assert_eq!(curve.points, vec![[0.1, 0.2], [0.4, 0.5], [0.805, 0.7]]);
+        assert!(curve.validate().is_ok());
+        assert!((curve.eval(0.25) - 0.35).abs() < 1e-6);
+        assert_eq!(curve.eval(0.0), 0.2);
+        assert!(Curve::from_points(&[[0.5, 0.5], [0.502, 0.6]]).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+// src/dashboard.rs
+//! Single-page web dashboard on `dashboard_listen` (feature `dashboard`).
+//!
+//! `GET /` serves the page, `GET /api/status` the live `Snapshot` as JSON.
+//! `POST /api/pause`, `/api/resume`, `/api/boost` (`{"percent": 20,
+//! "minutes": 10}`) and `/api/profile` (`{"name": "office"}`) drive the
+//! daemon. POSTs must be `application/json`, which browsers won't send
+//! cross-origin without a preflight this server never answers, and the
+//! `Host` header must name a loopback address, against DNS rebinding.
+//! The server itself only listens on loopback addresses.
+
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::control::Control;
+
+const INDEX: &str = include_str!("dashboard/index.html");
+const MAX_BODY: usize = 4096;
+
+/// Bind `addr` and serve requests on a background thread.
+pub fn serve(addr: &str, control: Arc<Control>) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)
+        .map_err(|e| format!("cannot listen on {} for the dashboard: {}", addr, e))?;
+    // `localhost` could resolve to anything; check what was bound.
+    if !listener.local_addr()?.ip().is_loopback() {
+        return Err(format!(
+            "not serving the dashboard on {}: it has no authentication, so it \
+             listens on loopback addresses only",
+            listener.local_addr()?
+        )
+        .into());
+    }
+    info!(target: "dashboard", "Dashboard at http://{}/", listener.local_addr()?);
+    std::thread::Builder::new()
+        .name("dashboard".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = handle(stream, &control) {
+                    debug!(target: "dashboard", "request failed: {}", e);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    host: String,
+    json: bool,
+    body: Vec<u8>,
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request, Box<dyn Error>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    let (mut host, mut json, mut length) = (String::new(), false, 0usize);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else { continue };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "host" => host = value.to_string(),
+            "content-type" => json = value.starts_with("application/json"),
+            "content-length" => length = value.parse()?,
+            _ => {}
+        }
+    }
+    if length > MAX_BODY {
+        return Err("request body too large".into());
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        host,
+        json,
+        body,
+    })
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+#[derive(Deserialize)]
+struct BoostRequest {
+    percent: f32,
+    #[serde(default = "default_boost_minutes")]
+    minutes: f32,
+}
+
+fn default_boost_minutes() -> f32 {
+    30.0
+}
+
+#[derive(Deserialize)]
+struct ProfileRequest {
+    name: String,
+}
+
+fn handle(mut stream: TcpStream, control: &Control) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let req = read_request(&stream)?;
+    if !is_loopback_host(&req.host) {
+        warn!(target: "dashboard", "Rejected request for host '{}'", req.host);
+        return respond(&mut stream, "403 Forbidden", "text/plain", "forbidden");
+    }
+    if req.method == "POST" && !req.json {
+        return respond(&mut stream, "415 Unsupported Media Type", "text/plain", "expected JSON");
+    }
+    let result: Result<(), String> = match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/") => return respond(&mut stream, "200 OK", "text/html; charset=utf-8", INDEX),
+        ("GET", "/api/status") => {
+            let body = serde_json::to_string(&control.snapshot())?;
+            return respond(&mut stream, "200 OK", "application/json", &body);
+        }
+        ("POST", "/api/pause") => {
+            control.set_paused(true);
+            Ok(())
+        }
+        ("POST", "/api/resume") => {
+            control.set_paused(false);
+            Ok(())
+        }
+        ("POST", "/api/boost") => serde_json::from_slice::<BoostRequest>(&req.body)
+            .map_err(|e| e.to_string())
+            .map(|b| {
+                let minutes = b.minutes.clamp(0.0, 24.0 * 60.0);
+                control.set_boost(b.percent, Duration::from_secs_f32(minutes * 60.0));
+            }),
+        ("POST", "/api/profile") => serde_json::from_slice::<ProfileRequest>(&req.body)
+            .map_err(|e| e.to_string())
+            .and_then(|p| control.request_profile(&p.name)),
+        (_, "/" | "/api/status" | "/api/pause" | "/api/resume" | "/api/boost" | "/api/profile") => {
+            return respond(&mut stream, "405 Method Not Allowed", "text/plain", "method not allowed");
+        }
+        _ => return respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+    };
+    match result {
+        Ok(()) => {
+            info!(target: "dashboard", "{} {}", req.method, req.path);
+            respond(&mut stream, "204 No Content", "text/plain", "")
+        }
+        Err(e) => respond(&mut stream, "400 Bad Request", "text/plain", &e),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<(), Box<dyn Error>> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+// src/devices.rs
+//! `smart-brightness devices`: what the daemon could drive and read from
+//! on this machine, to pick `camera_index`, `ambient_sensor` and
+//! `[[displays]]` by.
+
+use std::fs;
+use std::path::Path;
+
+use crate::camera::{self, CameraKind};
+use crate::config::Config;
+use crate::displays;
+
+/// Relative to the sysfs root.
+const IIO_DEVICES: &str = "bus/iio/devices";
+const VIDEO_DEVICES: &str = "class/video4linux";
+
+pub fn print(cfg: &Config) {
+    let root = cfg.sysfs_root();
+
+    println!("Backlights:");
+    let backlights = displays::detect(&root);
+    if backlights.is_empty() {
+        println!("  none");
+    }
+    for d in backlights {
+        let monitor = d.edid_name.map(|n| format!(" – {}", n)).unwrap_or_default();
+        println!("  {:<16} {}, max {}{}", d.name, d.backend, d.max_brightness, monitor);
+    }
+
+    println!("Ambient light sensors:");
+    let sensors = light_sensors(&root);
+    if sensors.is_empty() {
+        println!("  none");
+    }
+    for (name, label) in sensors {
+        println!("  {:<16} {}", name, label);
+    }
+
+    println!("Cameras:");
+    let cameras = cameras(&root);
+    if cameras.is_empty() {
+        println!("  none");
+    }
+    for (index, label) in cameras {
+        let kind = match camera::camera_kind(index) {
+            CameraKind::Infrared => "  (infrared)",
+            CameraKind::Other => "  (no capture)",
+            CameraKind::Visible => "",
+        };
+        let current = if index == cfg.camera_device { "  (camera_index)" } else { "" };
+        println!("  {:<16} {}{}{}", format!("/dev/video{}", index), label, kind, current);
+    }
+}
+
+/// IIO devices with an illuminance channel, with their driver name.
+fn light_sensors(root: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(root.join(IIO_DEVICES)) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(String, String)> = entries
+        .flatten()
+        .filter(|e| {
+            let dir = e.path();
+            ["in_illuminance", "in_illuminance0"]
+                .iter()
+                .any(|c| dir.join(format!("{}_input", c)).exists() || dir.join(format!("{}_raw", c)).exists())
+        })
+        .map(|e| {
+            let name = fs::read_to_string(e.path().join("name")).unwrap_or_default();
+            (e.file_name().to_string_lossy().into_owned(), name.trim().to_string())
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Video capture nodes by index, with the name the driver gives them.
+pub fn cameras(root: &Path) -> Vec<(usize, String)> {
+    let Ok(entries) = fs::read_dir(root.join(VIDEO_DEVICES)) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(usize, String)> = entries
+        .flatten()
+        .filter_map(|e| {
+            let index = e.file_name().to_str()?.strip_prefix("video")?.parse().ok()?;
+            let name = fs::read_to_string(e.path().join("name")).unwrap_or_default();
+            Some((index, name.trim().to_string()))
+        })
+        .collect();
+    found.sort();
+    found
+}
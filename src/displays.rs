@@ -0,0 +1,222 @@
+// src/displays.rs
+//! Secondary displays that follow the primary backlight (`[[displays]]`).
+//!
+//! ```toml
+//! [[displays]]
+//! name = "ddcci13"
+//! multiplier = 1.2
+//! offset = -5.0
+//! ```
+//!
+//! Each linked display is set to `primary% × multiplier + offset%` of its own
+//! range, clamped to its `min`–`max`, whenever the primary is written. Panels
+//! differ a lot in how bright the same percentage looks, so this lets an
+//! external monitor track the laptop panel without matching it number for
+//! number. A listed display with `enabled = false` is left alone, and is
+//! never picked as the primary either.
+//!
+//! On Wayland, `output = "DP-2"` ties a display to the compositor's output
+//! of that name: it is attached while the output is connected and powered,
+//! and let go otherwise, so a monitor on a dock comes and goes with it.
+//! Displays without one that couldn't be opened are tried again whenever
+//! the outputs change.
+//!
+//! `backend` picks how the display is reached; `name` means something
+//! different for each:
+//!
+//! - `sysfs` (default): device under `class/backlight`;
+//! - `logind`: the same, always written through logind's `SetBrightness`;
+//! - `ddc`: monitor number as `ddcutil detect` lists it, for monitors
+//!   without ddcci-driver-linux;
+//! - `keyboard`: LED under `class/leds`, or `auto` for the first
+//!   `*::kbd_backlight`;
+//! - `xrandr`: X11 output, dimming the picture.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// How a linked display is driven.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Sysfs,
+    Logind,
+    Ddc,
+    Keyboard,
+    Xrandr,
+}
+
+/// One `[[displays]]` entry as written in the config.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LinkedDisplay {
+    /// Device name under `class/backlight`, e.g. `ddcci13` for an external
+    /// monitor driven by ddcci-driver-linux.
+    pub name: String,
+    #[serde(default)]
+    pub backend: Backend,
+    /// Wayland output (connector name) the display is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f32,
+    /// Percentage points added after the multiplier.
+    #[serde(default)]
+    pub offset: f32,
+    /// Limits in percent of the display's own range.
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default = "default_max")]
+    pub max: f32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_multiplier() -> f32 {
+    1.0
+}
+
+fn default_max() -> f32 {
+    100.0
+}
+
+impl LinkedDisplay {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            backend: Backend::Sysfs,
+            output: None,
+            enabled: default_enabled(),
+            multiplier: default_multiplier(),
+            offset: 0.0,
+            min: 0.0,
+            max: default_max(),
+        }
+    }
+
+    /// Level for this display, as a fraction of its range, when the primary
+    /// is at `primary` (also a fraction).
+    pub fn fraction(&self, primary: f32) -> f32 {
+        (primary * self.multiplier + self.offset / 100.0).clamp(self.min / 100.0, self.max / 100.0)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.contains('/') {
+            return Err(format!("displays: invalid device name '{}'", self.name));
+        }
+        if self.backend == Backend::Ddc && self.name.parse::<u32>().is_err() {
+            return Err(format!(
+                "displays.{}: a ddc display is named by its ddcutil display number",
+                self.name
+            ));
+        }
+        if self.output.as_deref().is_some_and(|o| o.trim().is_empty()) {
+            return Err(format!("displays.{}: output must not be empty", self.name));
+        }
+        if !self.multiplier.is_finite() || self.multiplier < 0.0 {
+            return Err(format!("displays.{}: multiplier must be 0 or more", self.name));
+        }
+        if !(-100.0..=100.0).contains(&self.offset) {
+            return Err(format!(
+                "displays.{}: offset must be between -100 and 100",
+                self.name
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.min) || !(0.0..=100.0).contains(&self.max) || self.min > self.max {
+            return Err(format!(
+                "displays.{}: min and max must satisfy 0 <= min <= max <= 100",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn validate(displays: &[LinkedDisplay]) -> Result<(), String> {
+    for (i, display) in displays.iter().enumerate() {
+        display.validate()?;
+        if displays[..i]
+            .iter()
+            .any(|d| d.name == display.name && d.backend == display.backend)
+        {
+            return Err(format!("displays: '{}' is listed twice", display.name));
+        }
+    }
+    Ok(())
+}
+
+/// A backlight device found under `<sysfs_root>/class/backlight`.
+#[derive(Debug, Clone)]
+pub struct Detected {
+    pub name: String,
+    /// Monitor name from the EDID of the connector it drives, if known.
+    pub edid_name: Option<String>,
+    pub backend: &'static str,
+    pub max_brightness: u32,
+}
+
+/// Backlight devices, sorted by name. Empty where there is no sysfs.
+pub fn detect(sysfs_root: &Path) -> Vec<Detected> {
+    let Ok(entries) = fs::read_dir(sysfs_root.join("class/backlight")) else {
+        return Vec::new();
+    };
+    let mut found: Vec<Detected> = entries
+        .flatten()
+        .filter_map(|e| {
+            let dir = e.path();
+            let max_brightness = fs::read_to_string(dir.join("max_brightness"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            let name = e.file_name().to_string_lossy().into_owned();
+            let kind = fs::read_to_string(dir.join("type")).unwrap_or_default();
+            let backend = if name.starts_with("ddcci") {
+                "DDC/CI"
+            } else {
+                match kind.trim() {
+                    "firmware" => "firmware",
+                    "platform" => "platform",
+                    _ => "raw",
+                }
+            };
+            // Panel backlights hang off their DRM connector, which has the EDID.
+            let edid_name = fs::read(dir.join("device/edid"))
+                .ok()
+                .and_then(|edid| edid_monitor_name(&edid));
+            Some(Detected {
+                name,
+                edid_name,
+                backend,
+                max_brightness,
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    found
+}
+
+/// The monitor name descriptor (tag 0xFC) of an EDID base block.
+fn edid_monitor_name(edid: &[u8]) -> Option<String> {
+    if edid.len() < 128 {
+        return None;
+    }
+    edid[54..126].chunks_exact(18).find_map(|d| {
+        if d[..3] != [0, 0, 0] || d[3] != 0xFC {
+            return None;
+        }
+        let text: String = d[5..]
+            .iter()
+            .take_while(|&&b| b != b'\n')
+            .map(|&b| b as char)
+            .collect();
+        let text = text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    })
+}
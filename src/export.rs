@@ -0,0 +1,216 @@
+// src/export.rs
+//! Periodic metrics push for home Influx / Telegraf / statsd setups.
+//!
+//! `metrics_export_url` picks the wire format by scheme:
+//! - `udp://host:port`: Influx line protocol over UDP (Telegraf
+//!   `socket_listener`, InfluxDB 1.x UDP input)
+//! - `http://host:port/path?query`: line protocol POSTed over HTTP, e.g.
+//!   `/write?db=home` (1.x) or `/api/v2/write?org=o&bucket=b` (2.x, with
+//!   `metrics_export_token`)
+//! - `statsd://host:port`: statsd gauges over UDP
+//!
+//! Sending happens on a background thread so a slow endpoint never delays
+//! brightness updates; a sample that arrives while it is still busy is
+//! dropped.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+const MEASUREMENT: &str = "smart_brightness";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    InfluxUdp(String),
+    InfluxHttp { addr: String, path: String },
+    Statsd(String),
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| format!("'{}' has no scheme (udp://, http:// or statsd://)", url))?;
+        let (addr, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if addr.rsplit_once(':').is_none_or(|(_, port)| port.parse::<u16>().is_err()) {
+            return Err(format!("'{}' needs a host:port", url));
+        }
+        let addr = addr.to_string();
+        match scheme {
+            "udp" => Ok(Endpoint::InfluxUdp(addr)),
+            "statsd" => Ok(Endpoint::Statsd(addr)),
+            "http" => Ok(Endpoint::InfluxHttp {
+                addr,
+                path: path.to_string(),
+            }),
+            "https" => Err("https is not supported; point it at a local Telegraf instead".into()),
+            _ => Err(format!("unknown scheme '{}' (udp, http or statsd)", scheme)),
+        }
+    }
+}
+
+/// One sample of the values pushed.
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    pub luma: f32,
+    pub lux: Option<f32>,
+    pub brightness: u32,
+    pub target: u32,
+    pub brightness_changes: u64,
+    pub capture_errors: u64,
+    pub motion_skipped: u64,
+}
+
+impl Metrics {
+    fn line_protocol(&self) -> String {
+        let mut fields = format!(
+            "luma={},brightness={}i,target={}i,brightness_changes={}i,capture_errors={}i,motion_skipped={}i",
+            self.luma,
+            self.brightness,
+            self.target,
+            self.brightness_changes,
+            self.capture_errors,
+            self.motion_skipped
+        );
+        if let Some(lux) = self.lux {
+            fields.push_str(&format!(",lux={}", lux));
+        }
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        format!("{} {} {}\n", MEASUREMENT, fields, nanos)
+    }
+
+    /// Counters are sent as gauges of the running total, so a restarted
+    /// exporter can't count anything twice.
+    fn statsd(&self) -> String {
+        let mut out = String::new();
+        let mut gauge = |name: &str, value: String| {
+            out.push_str(&format!("{}.{}:{}|g\n", MEASUREMENT, name, value));
+        };
+        gauge("luma", self.luma.to_string());
+        gauge("brightness", self.brightness.to_string());
+        gauge("target", self.target.to_string());
+        gauge("brightness_changes", self.brightness_changes.to_string());
+        gauge("capture_errors", self.capture_errors.to_string());
+        gauge("motion_skipped", self.motion_skipped.to_string());
+        if let Some(lux) = self.lux {
+            gauge("lux", lux.to_string());
+        }
+        out
+    }
+}
+
+pub struct MetricsExporter {
+    tx: Option<SyncSender<Metrics>>,
+    interval: Duration,
+    last_push: Option<Instant>,
+}
+
+impl MetricsExporter {
+    pub fn from_config(cfg: &Config) -> Self {
+        let endpoint = cfg.metrics_export_url.as_deref().map(Endpoint::parse);
+        let tx = match endpoint {
+            Some(Ok(endpoint)) => {
+                let token = cfg.metrics_export_token.clone();
+                let (tx, rx) = mpsc::sync_channel::<Metrics>(1);
+                let spawned = std::thread::Builder::new()
+                    .name("metrics-export".into())
+                    .spawn(move || {
+                        let mut failing = false;
+                        for metrics in rx {
+                            match send(&endpoint, token.as_deref(), &metrics) {
+                                Ok(()) => failing = false,
+                                // Warn once per outage, not on every push.
+                                Err(e) if !failing => {
+                                    warn!(target: "telemetry", "metrics export failed: {}", e);
+                                    failing = true;
+                                }
+                                Err(e) => debug!(target: "telemetry", "metrics export failed: {}", e),
+                            }
+                        }
+                    });
+                spawned.ok().map(|_| tx)
+            }
+            Some(Err(e)) => {
+                warn!(target: "telemetry", "metrics export disabled: {}", e);
+                None
+            }
+            None => None,
+        };
+        Self {
+            tx,
+            interval: Duration::from_secs(cfg.metrics_export_interval_secs.max(1)),
+            last_push: None,
+        }
+    }
+
+    /// Queue a sample if the interval has passed; `metrics` is only called
+    /// then.
+    pub fn maybe_push(&mut self, metrics: impl FnOnce() -> Metrics) {
+        let Some(tx) = &self.tx else { return };
+        if self.last_push.is_some_and(|t| t.elapsed() < self.interval) {
+            return;
+        }
+        self.last_push = Some(Instant::now());
+        if let Err(TrySendError::Full(_)) = tx.try_send(metrics()) {
+            debug!(target: "telemetry", "metrics export still busy; sample dropped");
+        }
+    }
+}
+
+fn send(endpoint: &Endpoint, token: Option<&str>, metrics: &Metrics) -> std::io::Result<()> {
+    match endpoint {
+        Endpoint::InfluxUdp(addr) => send_udp(addr, &metrics.line_protocol()),
+        Endpoint::Statsd(addr) => send_udp(addr, &metrics.statsd()),
+        Endpoint::InfluxHttp { addr, path } => send_http(addr, path, token, &metrics.line_protocol()),
+    }
+}
+
+fn send_udp(addr: &str, payload: &str) -> std::io::Result<()> {
+    let target = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::other(format!("cannot resolve {}", addr)))?;
+    let bind = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    UdpSocket::bind(bind)?.send_to(payload.as_bytes(), target)?;
+    Ok(())
+}
+
+fn send_http(addr: &str, path: &str, token: Option<&str>, body: &str) -> std::io::Result<()> {
+    let target = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::other(format!("cannot resolve {}", addr)))?;
+    let mut stream = TcpStream::connect_timeout(&target, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let auth = token.map_or(String::new(), |t| format!("Authorization: Token {}\r\n", t));
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        addr,
+        auth,
+        body.len(),
+        body
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.split_whitespace().nth(1).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        let first = response.lines().next().unwrap_or_default();
+        Err(std::io::Error::other(format!("server answered '{}'", first)))
+    }
+}
@@ -0,0 +1,326 @@
+// src/expression.rs
+//! Arithmetic expressions for `mapping_expression`.
+//!
+//! Numbers, variables, `+ - * / ^`, unary minus, parentheses and the
+//! functions `pow`, `sqrt`, `exp`, `ln`, `log10`, `abs`, `min`, `max` and
+//! `clamp`. `^` binds tightest and is right-associative. A name followed by
+//! `(` is a function, otherwise a variable, so `min` can be both.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Names an expression may refer to; values are passed to `eval` in this
+/// order.
+pub const VARIABLES: [&str; 3] = ["luma", "min", "max"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f32),
+    Var(usize),
+    Neg(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Pow,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Abs,
+    Min,
+    Max,
+    Clamp,
+}
+
+impl Func {
+    fn lookup(name: &str) -> Option<(Func, usize)> {
+        Some(match name {
+            "pow" => (Func::Pow, 2),
+            "sqrt" => (Func::Sqrt, 1),
+            "exp" => (Func::Exp, 1),
+            "ln" => (Func::Ln, 1),
+            "log10" => (Func::Log10, 1),
+            "abs" => (Func::Abs, 1),
+            "min" => (Func::Min, 2),
+            "max" => (Func::Max, 2),
+            "clamp" => (Func::Clamp, 3),
+            _ => return None,
+        })
+    }
+}
+
+/// A parsed expression; (de)serializes as its source text.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: source.char_indices().peekable(),
+            source,
+        };
+        let root = parser.expr()?;
+        parser.skip_space();
+        if let Some(&(pos, c)) = parser.chars.peek() {
+            return Err(format!("unexpected '{}' at position {}", c, pos + 1));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    /// Evaluate with `vars` in the order of `VARIABLES`.
+    pub fn eval(&self, vars: [f32; VARIABLES.len()]) -> f32 {
+        eval(&self.root, &vars)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, String> {
+        Expression::parse(&source)
+    }
+}
+
+impl From<Expression> for String {
+    fn from(e: Expression) -> String {
+        e.source
+    }
+}
+
+fn eval(node: &Node, vars: &[f32]) -> f32 {
+    match node {
+        Node::Number(n) => *n,
+        Node::Var(i) => vars[*i],
+        Node::Neg(a) => -eval(a, vars),
+        Node::Binary(op, a, b) => {
+            let (a, b) = (eval(a, vars), eval(b, vars));
+            match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' => a / b,
+                _ => a.powf(b),
+            }
+        }
+        Node::Call(func, args) => {
+            let a: Vec<f32> = args.iter().map(|n| eval(n, vars)).collect();
+            match func {
+                Func::Pow => a[0].powf(a[1]),
+                Func::Sqrt => a[0].sqrt(),
+                Func::Exp => a[0].exp(),
+                Func::Ln => a[0].ln(),
+                Func::Log10 => a[0].log10(),
+                Func::Abs => a[0].abs(),
+                Func::Min => a[0].min(a[1]),
+                Func::Max => a[0].max(a[1]),
+                Func::Clamp => a[0].max(a[1]).min(a[2]),
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    source: &'a str,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn expect(&mut self, want: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == want => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected '{}', found '{}'", want, c)),
+            None => Err(format!("expected '{}' at end of expression", want)),
+        }
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut lhs = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.chars.next();
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    /// product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Node, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.chars.next();
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.peek() == Some('-') {
+            self.chars.next();
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    /// power := atom ('^' unary)?
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.chars.next();
+            return Ok(Node::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let inner = self.expr()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.name(),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("expression ends too early".into()),
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let start = self.chars.peek().map_or(self.source.len(), |&(i, _)| i);
+        while self.chars.next_if(|&(_, c)| f(c)).is_some() {}
+        let end = self.chars.peek().map_or(self.source.len(), |&(i, _)| i);
+        &self.source[start..end]
+    }
+
+    fn number(&mut self) -> Result<Node, String> {
+        let text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+        text.parse()
+            .map(Node::Number)
+            .map_err(|_| format!("'{}' is not a number", text))
+    }
+
+    fn name(&mut self) -> Result<Node, String> {
+        let name = self
+            .take_while(|c| c.is_ascii_alphanumeric() || c == '_')
+            .to_string();
+        if self.peek() != Some('(') {
+            return match name.as_str() {
+                "pi" => Ok(Node::Number(std::f32::consts::PI)),
+                "e" => Ok(Node::Number(std::f32::consts::E)),
+                _ => VARIABLES
+                    .iter()
+                    .position(|v| *v == name)
+                    .map(Node::Var)
+                    .ok_or_else(|| {
+                        format!("unknown variable '{}' (known: {})", name, VARIABLES.join(", "))
+                    }),
+            };
+        }
+        let (func, arity) =
+            Func::lookup(&name).ok_or_else(|| format!("unknown function '{}'", name))?;
+        self.chars.next();
+        let mut args = Vec::new();
+        if self.peek() != Some(')') {
+            args.push(self.expr()?);
+            while self.peek() == Some(',') {
+                self.chars.next();
+                args.push(self.expr()?);
+            }
+        }
+        self.expect(')')?;
+        if args.len() != arity {
+            return Err(format!(
+                "{}() takes {} argument(s), got {}",
+                name,
+                arity,
+                args.len()
+            ));
+        }
+        Ok(Node::Call(func, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(source: &str) -> f32 {
+        Expression::parse(source).unwrap().eval([0.5, 10.0, 110.0])
+    }
+
+    fn error(source: &str) -> String {
+        Expression::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(value("2 + 3 * 4"), 14.0);
+        assert_eq!(value("(2 + 3) * 4"), 20.0);
+        assert_eq!(value("10 - 4 - 3"), 3.0);
+        assert_eq!(value("8 / 4 / 2"), 1.0);
+        assert_eq!(value("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(value("-2 ^ 2"), -4.0);
+        assert_eq!(value("2 ^ -1"), 0.5);
+        assert_eq!(value("3 * -2"), -6.0);
+        assert_eq!(value("- -3"), 3.0);
+        assert_eq!(value("pow(2, 3) * 2"), 16.0);
+        assert_eq!(value("pow(2, 3 ^ 2)"), 512.0);
+        assert_eq!(value("min(min, max) + max(1, 2)"), 12.0);
+        assert_eq!(value("clamp(luma * 4, 0, 1)"), 1.0);
+    }
+
+    #[test]
+    fn the_documented_gamma_curve() {
+        let expr = Expression::parse("min + (max-min) * pow(luma, 0.6)").unwrap();
+        for (luma, want) in [(0.0, 10.0), (0.25, 53.5275), (0.5, 75.9754), (1.0, 110.0)] {
+            let got = expr.eval([luma, 10.0, 110.0]);
+            assert!((got - want).abs() < 1e-3, "luma {}: {} != {}", luma, got, want);
+        }
+        assert_eq!(expr.to_string(), "min + (max-min) * pow(luma, 0.6)");
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        assert_eq!(error("pow(luma)"), "pow() takes 2 argument(s), got 1");
+        assert_eq!(error("sqrt(1, 2)"), "sqrt() takes 1 argument(s), got 2");
+        assert_eq!(error("clamp()"), "clamp() takes 3 argument(s), got 0");
+        assert_eq!(error("gamma(luma)"), "unknown function 'gamma'");
+        assert_eq!(error("lux * 2"), "unknown variable 'lux' (known: luma, min, max)");
+        assert_eq!(error("luma 2"), "unexpected '2' at position 6");
+        assert_eq!(error("(luma + 1))"), "unexpected ')' at position 11");
+        assert_eq!(error("(luma + 1"), "expected ')' at end of expression");
+        assert_eq!(error("luma *"), "expression ends too early");
+        assert_eq!(error("1.2.3"), "'1.2.3' is not a number");
+    }
+}
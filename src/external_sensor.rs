@@ -0,0 +1,210 @@
+// src/external_sensor.rs
+//! Ambient readings from a user-supplied program.
+//!
+//! Protocol, one line each way: the daemon writes `MEASURE` to the
+//! program's stdin, and the program answers on stdout with
+//!
+//! ```text
+//! LUMA 0.42
+//! LUMA 0.42 LUX 310
+//! ERR sensor not ready
+//! ```
+//!
+//! `LUMA` is ambient brightness in [0, 1]; `LUX` is optional. `ERR` fails
+//! that reading only. Anything else, a late answer or the program exiting
+//! counts as a crash: the process is killed and restarted with backoff.
+//! Stderr is passed through to the daemon's.
+
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::sensor::{AmbientSensor, Reading, Unit};
+
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct ExternalSensor {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+    running: Option<Running>,
+    backoff: Duration,
+    retry_at: Option<Instant>,
+    last_lux: Option<f32>,
+}
+
+impl ExternalSensor {
+    /// Start `command`, split on whitespace into program and arguments (no
+    /// shell quoting; wrap it in a script if you need more).
+    pub fn spawn(command: &str, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words.next().ok_or("sensor_command is empty")?;
+        let mut sensor = Self {
+            program,
+            args: words.collect(),
+            timeout,
+            running: None,
+            backoff: RESTART_BACKOFF_MIN,
+            retry_at: None,
+            last_lux: None,
+        };
+        sensor.start()?;
+        Ok(sensor)
+    }
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("cannot start sensor command '{}': {}", self.program, e))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err("sensor command has no stdio pipes".into());
+        };
+        // Blocking reads happen on their own thread so a hung program can't
+        // stall the control loop past `timeout`.
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        info!(target: "sensor", "Started sensor command '{}' (pid {})", self.program, child.id());
+        self.running = Some(Running { child, stdin, lines });
+        Ok(())
+    }
+
+    /// Drop the process and schedule a restart.
+    fn fail(&mut self, reason: String) -> Box<dyn Error> {
+        self.running = None;
+        self.last_lux = None;
+        self.retry_at = Some(Instant::now() + self.backoff);
+        warn!(
+            target: "sensor",
+            "Sensor command '{}' {}; restarting in {:?}",
+            self.program,
+            reason,
+            self.backoff
+        );
+        self.backoff = (self.backoff * 2).min(RESTART_BACKOFF_MAX);
+        reason.into()
+    }
+
+    fn measure(&mut self) -> Result<f32, Box<dyn Error>> {
+        if self.running.is_none() {
+            if self.retry_at.is_some_and(|t| Instant::now() < t) {
+                return Err("sensor command is restarting".into());
+            }
+            if let Err(e) = self.start() {
+                return Err(self.fail(e.to_string()));
+            }
+        }
+        let running = self.running.as_mut().expect("started above");
+        if let Err(e) = writeln!(running.stdin, "MEASURE").and_then(|_| running.stdin.flush()) {
+            return Err(self.fail(format!("stopped accepting input ({})", e)));
+        }
+        let line = match running.lines.recv_timeout(self.timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(self.fail(format!("did not answer within {:?}", self.timeout)));
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let status = running.child.try_wait().ok().flatten();
+                let reason = match status {
+                    Some(status) => format!("exited ({})", status),
+                    None => "closed its output".to_string(),
+                };
+                return Err(self.fail(reason));
+            }
+        };
+        match parse_reply(&line) {
+            Ok(Reply::Reading { luma, lux }) => {
+                self.backoff = RESTART_BACKOFF_MIN;
+                self.last_lux = lux;
+                Ok(luma)
+            }
+            Ok(Reply::Error(msg)) => {
+                debug!(target: "sensor", "sensor command reported: {}", msg);
+                Err(format!("sensor command: {}", msg).into())
+            }
+            Err(e) => Err(self.fail(format!("sent an invalid reply {:?} ({})", line, e))),
+        }
+    }
+
+}
+
+impl AmbientSensor for ExternalSensor {
+    fn name(&self) -> String {
+        format!("sensor command '{}'", self.program)
+    }
+
+    fn read(&mut self) -> Result<Reading, Box<dyn Error>> {
+        self.measure().map(|luma| Reading {
+            value: luma,
+            unit: Unit::Luma,
+            timestamp: Instant::now(),
+        })
+    }
+
+    /// Lux from the last reply, if the program reports it.
+    fn lux(&self) -> Option<f32> {
+        self.last_lux
+    }
+}
+
+/// One reply line; also the format of `sensor_file` and `sensor_listen`.
+pub enum Reply {
+    Reading { luma: f32, lux: Option<f32> },
+    Error(String),
+}
+
+pub fn parse_reply(line: &str) -> Result<Reply, String> {
+    let line = line.trim();
+    if let Some(msg) = line.strip_prefix("ERR") {
+        return Ok(Reply::Error(msg.trim().to_string()));
+    }
+    let mut luma = None;
+    let mut lux = None;
+    let mut words = line.split_whitespace();
+    while let Some(key) = words.next() {
+        let value: f32 = words
+            .next()
+            .ok_or_else(|| format!("{} has no value", key))?
+            .parse()
+            .map_err(|e| format!("{}: {}", key, e))?;
+        if !value.is_finite() {
+            return Err(format!("{} is not finite", key));
+        }
+        match key {
+            "LUMA" => luma = Some(value.clamp(0.0, 1.0)),
+            "LUX" => lux = Some(value.max(0.0)),
+            _ => return Err(format!("unknown field {}", key)),
+        }
+    }
+    let luma = luma.ok_or("missing LUMA")?;
+    Ok(Reply::Reading { luma, lux })
+}
@@ -0,0 +1,202 @@
+// src/ffi.rs
+//! C API (`ffi` feature) over the same curve, luma average and brightness
+//! stepper, for bars and compositors that want the daemon's mapping without
+//! running it. Declarations are in `include/smart_brightness.h`:
+//!
+//! ```c
+//! float points[] = {0.0f, 0.1f, 0.5f, 0.6f, 1.0f, 1.0f};
+//! sb_curve *curve = sb_curve_new(points, 3);
+//! sb_ema *ema = sb_ema_new(0.2f);
+//! float fraction = sb_curve_eval(curve, sb_ema_update(ema, luma, 1.0f));
+//! sb_ema_free(ema);
+//! sb_curve_free(curve);
+//! ```
+//!
+//! Every `_new` returns an object owned by the caller, to be passed to the
+//! matching `_free` once; the objects aren't thread-safe. As in the Python
+//! bindings, a transition takes the time as milliseconds from any start.
+use std::time::{Duration, Instant};
+
+use crate::curve::Curve;
+use crate::smooth_transition::SmoothTransition;
+use crate::smoothing::Ema;
+
+/// The brightness stepper on a clock of its own.
+pub struct Transition {
+    inner: SmoothTransition,
+    start: Instant,
+}
+
+/// A curve through `count` anchors, given as `count` pairs of normalized
+/// luma and brightness fraction; NULL if they don't make a valid curve.
+///
+/// # Safety
+///
+/// `points` must point to `2 * count` floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_curve_new(points: *const f32, count: usize) -> *mut Curve {
+    if points.is_null() {
+        return std::ptr::null_mut();
+    }
+    let flat = unsafe { std::slice::from_raw_parts(points, count * 2) };
+    let curve = Curve {
+        points: flat.chunks_exact(2).map(|p| [p[0], p[1]]).collect(),
+        ..Curve::default()
+    };
+    match curve.validate() {
+        Ok(()) => Box::into_raw(Box::new(curve)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Brightness fraction for a normalized luma.
+///
+/// # Safety
+///
+/// `curve` must come from `sb_curve_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_curve_eval(curve: *const Curve, luma: f32) -> f32 {
+    unsafe { &*curve }.eval(luma)
+}
+
+/// # Safety
+///
+/// `curve` must be NULL or come from `sb_curve_new`, and is invalid after.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_curve_free(curve: *mut Curve) {
+    if !curve.is_null() {
+        drop(unsafe { Box::from_raw(curve) });
+    }
+}
+
+/// The luma average (`ambient_smoothing_strength` is `alpha`).
+#[unsafe(no_mangle)]
+pub extern "C" fn sb_ema_new(alpha: f32) -> *mut Ema {
+    Box::into_raw(Box::new(Ema::new(alpha)))
+}
+
+/// Add a reading, counted `weight` times as much as a plain one; returns
+/// the new average.
+///
+/// # Safety
+///
+/// `ema` must come from `sb_ema_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_ema_update(ema: *mut Ema, x: f32, weight: f32) -> f32 {
+    unsafe { &mut *ema }.update_weighted(x, weight)
+}
+
+/// # Safety
+///
+/// `ema` must be NULL or come from `sb_ema_new`, and is invalid after.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_ema_free(ema: *mut Ema) {
+    if !ema.is_null() {
+        drop(unsafe { Box::from_raw(ema) });
+    }
+}
+
+/// A stepper at `initial` (`brightness_step_interval_ms`, `_divisor` and
+/// `_max` are the other arguments).
+#[unsafe(no_mangle)]
+pub extern "C" fn sb_transition_new(
+    initial: u32,
+    interval_ms: u64,
+    divisor: u32,
+    max_step: u32,
+) -> *mut Transition {
+    Box::into_raw(Box::new(Transition {
+        inner: SmoothTransition::new(initial, interval_ms, divisor, max_step),
+        start: Instant::now(),
+    }))
+}
+
+/// # Safety
+///
+/// `transition` must come from `sb_transition_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_transition_set_target(
+    transition: *mut Transition,
+    target: u32,
+    max_brightness: u32,
+) {
+    unsafe { &mut *transition }
+        .inner
+        .set_target(target, max_brightness);
+}
+
+/// Step at `ms`; true, with the level in `*level`, if a step was due.
+///
+/// # Safety
+///
+/// `transition` must come from `sb_transition_new` and not have been freed,
+/// and `level` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_transition_update(
+    transition: *mut Transition,
+    ms: u64,
+    level: *mut u32,
+) -> bool {
+    let transition = unsafe { &mut *transition };
+    let now = transition.start + Duration::from_millis(ms);
+    match transition.inner.update(now) {
+        Some(value) => {
+            unsafe { level.write(value) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// The level last stepped to.
+///
+/// # Safety
+///
+/// `transition` must come from `sb_transition_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_transition_current(transition: *const Transition) -> u32 {
+    unsafe { &*transition }.inner.current_value()
+}
+
+/// # Safety
+///
+/// `transition` must be NULL or come from `sb_transition_new`, and is
+/// invalid after.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sb_transition_free(transition: *mut Transition) {
+    if !transition.is_null() {
+        drop(unsafe { Box::from_raw(transition) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_round_trip_through_the_c_api() {
+        unsafe {
+            let points = [0.0, 0.0, 1.0, 1.0];
+            let curve = sb_curve_new(points.as_ptr(), 2);
+            assert!(!curve.is_null());
+            assert_eq!(sb_curve_eval(curve, 0.25), 0.25);
+            sb_curve_free(curve);
+            // Luma must rise along the curve.
+            let backwards = [1.0, 0.0, 0.0, 1.0];
+            assert!(sb_curve_new(backwards.as_ptr(), 2).is_null());
+
+            let ema = sb_ema_new(0.5);
+            assert_eq!(sb_ema_update(ema, 1.0, 1.0), 1.0);
+            assert_eq!(sb_ema_update(ema, 0.0, 1.0), 0.5);
+            sb_ema_free(ema);
+
+            let transition = sb_transition_new(0, 10, 1, 100);
+            sb_transition_set_target(transition, 40, 100);
+            let mut level = 0;
+            assert!(sb_transition_update(transition, 10, &mut level));
+            assert_eq!(level, sb_transition_current(transition));
+            assert!(level > 0);
+            sb_transition_free(transition);
+        }
+    }
+}
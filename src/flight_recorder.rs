@@ -0,0 +1,302 @@
+// src/flight_recorder.rs
+//! The last few minutes of the control loop, kept in memory and written to
+//! the log directory on SIGQUIT or a panic (`flight_recorder`).
+//!
+//! Readings, backlight writes and notable events go into a fixed ring of
+//! atomics, so recording never blocks or allocates and the panic hook can
+//! read the ring whatever state the loop is in. `kill -QUIT <pid>` writes
+//! `flight-<time>.txt` next to `latest-log.txt` and the daemon carries on:
+//!
+//! ```text
+//! 2026-10-17T14:32:05.123+02:00 reading luma=0.412 target=150 actual=148
+//! 2026-10-17T14:32:05.143+02:00 write target=150 actual=149
+//! ```
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{Local, SecondsFormat, TimeZone};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::logging;
+
+/// Enough for five minutes at the default capture interval, with room for
+/// the writes of a few transitions.
+const CAPACITY: usize = 4096;
+/// Entries older than this are left out of a dump.
+const RETENTION: Duration = Duration::from_secs(300);
+const NO_VALUE: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Kind {
+    Reading,
+    Write,
+    CaptureError,
+    Privacy,
+    Override,
+    Ramp,
+    Woke,
+    Fallback,
+}
+
+impl Kind {
+    const ALL: [Kind; 8] = [
+        Kind::Reading,
+        Kind::Write,
+        Kind::CaptureError,
+        Kind::Privacy,
+        Kind::Override,
+        Kind::Ramp,
+        Kind::Woke,
+        Kind::Fallback,
+    ];
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Reading => "reading",
+            Kind::Write => "write",
+            Kind::CaptureError => "capture-error",
+            Kind::Privacy => "privacy",
+            Kind::Override => "override",
+            Kind::Ramp => "ramp",
+            Kind::Woke => "woke",
+            Kind::Fallback => "fallback",
+        })
+    }
+}
+
+/// One recorded moment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    /// Unix time in milliseconds.
+    pub at_ms: u64,
+    pub kind: Kind,
+    pub luma: Option<f32>,
+    pub target: u32,
+    /// Last value that reached the backlight, if known.
+    pub actual: Option<u32>,
+}
+
+/// A ring slot guarded by a sequence number: odd while it is written, so
+/// a reader can tell a torn entry and skip it.
+struct Slot {
+    seq: AtomicU32,
+    at_ms: AtomicU64,
+    kind: AtomicU8,
+    luma: AtomicU32,
+    target: AtomicU32,
+    actual: AtomicU32,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            at_ms: AtomicU64::new(0),
+            kind: AtomicU8::new(0),
+            luma: AtomicU32::new(0),
+            target: AtomicU32::new(0),
+            actual: AtomicU32::new(0),
+        }
+    }
+
+    fn write(&self, entry: &Entry) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(1) | 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.at_ms.store(entry.at_ms, Ordering::Relaxed);
+        self.kind.store(entry.kind as u8, Ordering::Relaxed);
+        self.luma.store(entry.luma.map_or(NO_VALUE, f32::to_bits), Ordering::Relaxed);
+        self.target.store(entry.target, Ordering::Relaxed);
+        self.actual.store(entry.actual.unwrap_or(NO_VALUE), Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(2) & !1, Ordering::Release);
+    }
+
+    fn read(&self) -> Option<Entry> {
+        let seq = self.seq.load(Ordering::Acquire);
+        if seq == 0 || seq & 1 == 1 {
+            return None;
+        }
+        let at_ms = self.at_ms.load(Ordering::Relaxed);
+        let kind = self.kind.load(Ordering::Relaxed);
+        let luma = self.luma.load(Ordering::Relaxed);
+        let target = self.target.load(Ordering::Relaxed);
+        let actual = self.actual.load(Ordering::Relaxed);
+        fence(Ordering::Acquire);
+        if self.seq.load(Ordering::Relaxed) != seq {
+            return None;
+        }
+        Some(Entry {
+            at_ms,
+            kind: *Kind::ALL.get(kind as usize)?,
+            luma: (luma != NO_VALUE).then(|| f32::from_bits(luma)),
+            target,
+            actual: (actual != NO_VALUE).then_some(actual),
+        })
+    }
+}
+
+pub struct Recorder {
+    slots: [Slot; CAPACITY],
+    /// Number of entries recorded so far; the next goes to `next % CAPACITY`.
+    next: AtomicUsize,
+}
+
+impl Recorder {
+    const fn new() -> Self {
+        Self {
+            slots: [const { Slot::new() }; CAPACITY],
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, entry: Entry) {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % CAPACITY;
+        self.slots[i].write(&entry);
+    }
+
+    /// Entries at or after `since_ms`, oldest first.
+    fn entries(&self, since_ms: u64) -> Vec<Entry> {
+        let next = self.next.load(Ordering::Acquire);
+        let first = next.saturating_sub(CAPACITY);
+        (first..next)
+            .filter_map(|n| self.slots[n % CAPACITY].read())
+            .filter(|e| e.at_ms >= since_ms)
+            .collect()
+    }
+}
+
+static RECORDER: Recorder = Recorder::new();
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Note one moment of the loop; nothing without `flight_recorder`.
+pub fn record(kind: Kind, luma: Option<f32>, target: u32, actual: Option<u32>) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    RECORDER.push(Entry {
+        at_ms: now_ms(),
+        kind,
+        luma,
+        target,
+        actual,
+    });
+}
+
+/// Start recording, and dump on a panic and on every SIGQUIT. Call before
+/// any other thread is started, so SIGQUIT reaches the thread waiting for
+/// it.
+pub fn install(cfg: &Config) {
+    if !cfg.flight_recorder {
+        return;
+    }
+    let _ = DIR.set(logging::log_dir(cfg.logging_path.as_deref()));
+    ENABLED.store(true, Ordering::Relaxed);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Ok(path) = dump() {
+            eprintln!("Flight recorder written to {}", path.display());
+        }
+        previous(info);
+    }));
+    #[cfg(unix)]
+    if let Err(e) = wait_for_sigquit() {
+        warn!(target: "daemon", "Flight recorder: cannot catch SIGQUIT: {}", e);
+    }
+}
+
+/// Block SIGQUIT here, and so in every thread started from now on, and
+/// dump from a thread of its own whenever it arrives.
+#[cfg(unix)]
+fn wait_for_sigquit() -> Result<(), Box<dyn std::error::Error>> {
+    use nix::sys::signal::{SigSet, Signal};
+
+    let mut set = SigSet::empty();
+    set.add(Signal::SIGQUIT);
+    set.thread_block()?;
+    std::thread::Builder::new()
+        .name("flight-recorder".into())
+        .spawn(move || {
+            while set.wait().is_ok() {
+                match dump() {
+                    Ok(path) => info!(target: "daemon", "Flight recorder written to {}", path.display()),
+                    Err(e) => warn!(target: "daemon", "Flight recorder: cannot write dump: {}", e),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Write the last `RETENTION` of entries to the log directory.
+fn dump() -> io::Result<PathBuf> {
+    let dir = DIR.get().ok_or_else(|| io::Error::other("flight recorder not installed"))?;
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("flight-{}.txt", Local::now().format("%Y%m%d-%H%M%S")));
+    let mut out = BufWriter::new(File::create(&path)?);
+    let since = now_ms().saturating_sub(RETENTION.as_millis() as u64);
+    for entry in RECORDER.entries(since) {
+        writeln!(out, "{}", line(&entry))?;
+    }
+    out.flush()?;
+    Ok(path)
+}
+
+fn line(entry: &Entry) -> String {
+    let time = Local
+        .timestamp_millis_opt(entry.at_ms as i64)
+        .single()
+        .map_or_else(|| entry.at_ms.to_string(), |t| t.to_rfc3339_opts(SecondsFormat::Millis, false));
+    let mut line = format!("{} {}", time, entry.kind);
+    if let Some(luma) = entry.luma {
+        line.push_str(&format!(" luma={:.3}", luma));
+    }
+    line.push_str(&format!(" target={}", entry.target));
+    if let Some(actual) = entry.actual {
+        line.push_str(&format!(" actual={}", actual));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at_ms: u64) -> Entry {
+        Entry {
+            at_ms,
+            kind: Kind::Reading,
+            luma: Some(0.5),
+            target: at_ms as u32,
+            actual: None,
+        }
+    }
+
+    #[test]
+    fn ring_keeps_the_newest_entries_in_order() {
+        let ring = Box::new(Recorder::new());
+        for at in 1..=(CAPACITY as u64 + 10) {
+            ring.push(entry(at));
+        }
+        let entries = ring.entries(0);
+        assert_eq!(entries.len(), CAPACITY);
+        assert_eq!(entries[0], entry(11));
+        assert!(entries.windows(2).all(|w| w[0].at_ms < w[1].at_ms));
+        assert_eq!(ring.entries(CAPACITY as u64 + 5).len(), 6);
+    }
+}
@@ -0,0 +1,180 @@
+// src/history.rs
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::report::DailyReport;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// One sample of the ambient → brightness pipeline, stored as a JSON line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    /// Unix timestamp (seconds).
+    pub t: i64,
+    /// Normalized ambient luma after circadian adjustment.
+    pub luma: f32,
+    /// Brightness the daemon was driving at the time.
+    pub brightness: u32,
+    /// Brightness read back from sysfs; differs from `brightness` when the
+    /// user (or another tool) changed it manually.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<u32>,
+    /// Settings file in use during `--ab-test`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl HistoryRecord {
+    pub fn is_manual(&self) -> bool {
+        self.actual.is_some_and(|a| a.abs_diff(self.brightness) > 1)
+    }
+}
+
+pub fn default_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("smart-brightness")
+        .join(HISTORY_FILE)
+}
+
+/// Append-only history file with retention-based pruning on open.
+pub struct HistoryStore {
+    writer: BufWriter<File>,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path, retention: Duration) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        prune(path, Utc::now().timestamp() - retention.as_secs() as i64)?;
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn append(&mut self, record: &HistoryRecord) -> io::Result<()> {
+        let line = serde_json::to_string(record)?;
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()
+    }
+}
+
+/// Read all records with `t >= since`; malformed lines are skipped.
+pub fn load_since(path: &Path, since: i64) -> io::Result<Vec<HistoryRecord>> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if let Ok(rec) = serde_json::from_str::<HistoryRecord>(&line)
+            && rec.t >= since
+        {
+            records.push(rec);
+        }
+    }
+    Ok(records)
+}
+
+fn prune(path: &Path, cutoff: i64) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let records = load_since(path, i64::MIN)?;
+    let kept: Vec<_> = records.iter().filter(|r| r.t >= cutoff).collect();
+    if kept.len() == records.len() {
+        return Ok(());
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    for rec in kept {
+        writeln!(out, "{}", serde_json::to_string(rec)?)?;
+    }
+    out.flush()
+}
+
+/// Samples the pipeline into the history store at a fixed cadence.
+pub struct HistoryRecorder {
+    store: Option<HistoryStore>,
+    interval: Duration,
+    last: Option<Instant>,
+    variant: Option<String>,
+    /// For the daily report: calibrated range, and the day of the last
+    /// sample.
+    range: (u32, u32),
+    day: Option<NaiveDate>,
+}
+
+impl HistoryRecorder {
+    pub fn from_config(cfg: &Config) -> Self {
+        let store = if cfg.history_enabled {
+            let retention = Duration::from_secs(cfg.history_retention_days as u64 * 86_400);
+            match HistoryStore::open(&default_path(), retention) {
+                Ok(store) => Some(store),
+                Err(e) => {
+                    warn!(target: "history", "History disabled: cannot open store: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self {
+            store,
+            interval: Duration::from_secs(cfg.history_sample_secs),
+            last: None,
+            variant: None,
+            range: (cfg.real_min_brightness, cfg.real_max_brightness),
+            day: None,
+        }
+    }
+
+    /// Tag the samples from now on with the A/B test variant.
+    pub fn set_variant(&mut self, variant: &str) {
+        self.variant = Some(variant.to_string());
+    }
+
+    /// `actual` is only evaluated when a sample is due, to avoid sysfs reads
+    /// on every capture.
+    pub fn maybe_record<F>(&mut self, luma: f32, brightness: u32, actual: F)
+    where
+        F: FnOnce() -> Option<u32>,
+    {
+        let Some(store) = self.store.as_mut() else {
+            return;
+        };
+        if self.last.is_some_and(|t| t.elapsed() < self.interval) {
+            return;
+        }
+        self.last = Some(Instant::now());
+        let record = HistoryRecord {
+            t: Utc::now().timestamp(),
+            luma,
+            brightness,
+            actual: actual(),
+            variant: self.variant.clone(),
+        };
+        if let Err(e) = store.append(&record) {
+            warn!(target: "history", "History disabled: write failed: {}", e);
+            self.store = None;
+            return;
+        }
+        let today = Local::now().date_naive();
+        if let Some(day) = self.day.replace(today).filter(|&d| d != today) {
+            match DailyReport::load(day, self.range, self.interval.as_secs()) {
+                Ok(report) => info!(target: "history", "{}", report),
+                Err(e) => warn!(target: "history", "No report for {}: {}", day, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// src/hooks.rs
+//! User commands run on daemon events (`on_change`, `on_pause`, `on_error`).
+//!
+//! Each hook runs at most once per `hook_min_interval_ms` and never twice
+//! at the same time. An event arriving in between replaces the one still
+//! waiting, so the last brightness always gets delivered once the interval
+//! has passed. Commands are split on whitespace like `sensor_command`; the
+//! event details are passed as `SB_*` environment variables.
+
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Change,
+    Pause,
+    Error,
+}
+
+impl Event {
+    const ALL: [Event; 3] = [Event::Change, Event::Pause, Event::Error];
+
+    fn name(self) -> &'static str {
+        match self {
+            Event::Change => "change",
+            Event::Pause => "pause",
+            Event::Error => "error",
+        }
+    }
+}
+
+struct Hook {
+    event: Event,
+    program: String,
+    args: Vec<String>,
+    last_run: Option<Instant>,
+    running: Option<Child>,
+    pending: Option<Vec<(&'static str, String)>>,
+}
+
+impl Hook {
+    fn new(event: Event, command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        Some(Self {
+            event,
+            program: words.next()?,
+            args: words.collect(),
+            last_run: None,
+            running: None,
+            pending: None,
+        })
+    }
+
+    fn busy(&mut self) -> bool {
+        match self.running.as_mut().map(Child::try_wait) {
+            Some(Ok(None)) => true,
+            Some(Ok(Some(status))) => {
+                if !status.success() {
+                    debug!(target: "hooks", "on_{} hook exited with {}", self.event.name(), status);
+                }
+                self.running = None;
+                false
+            }
+            Some(Err(_)) | None => {
+                self.running = None;
+                false
+            }
+        }
+    }
+
+    fn poll(&mut self, min_interval: Duration) {
+        if self.pending.is_none() || self.busy() {
+            return;
+        }
+        if self.last_run.is_some_and(|t| t.elapsed() < min_interval) {
+            return;
+        }
+        let Some(env) = self.pending.take() else { return };
+        self.last_run = Some(Instant::now());
+        let spawned = Command::new(&self.program)
+            .args(&self.args)
+            .env("SB_EVENT", self.event.name())
+            .envs(env)
+            .stdin(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(child) => self.running = Some(child),
+            Err(e) => warn!(
+                target: "hooks",
+                "cannot run on_{} hook '{}': {}",
+                self.event.name(),
+                self.program,
+                e
+            ),
+        }
+    }
+}
+
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    min_interval: Duration,
+}
+
+impl Hooks {
+    pub fn from_config(cfg: &Config) -> Self {
+        let hooks = Event::ALL
+            .into_iter()
+            .filter_map(|event| {
+                let command = match event {
+                    Event::Change => cfg.on_change.as_deref(),
+                    Event::Pause => cfg.on_pause.as_deref(),
+                    Event::Error => cfg.on_error.as_deref(),
+                }?;
+                Hook::new(event, command)
+            })
+            .collect();
+        Self {
+            hooks,
+            min_interval: Duration::from_millis(cfg.hook_min_interval_ms),
+        }
+    }
+
+    /// Queue `event` with its variables and run it if the rate limit allows.
+    pub fn fire(&mut self, event: Event, env: Vec<(&'static str, String)>) {
+        if let Some(hook) = self.hooks.iter_mut().find(|h| h.event == event) {
+            hook.pending = Some(env);
+            hook.poll(self.min_interval);
+        }
+    }
+
+    /// Whether a hook is configured for `event`; lets callers skip
+    /// formatting variables nobody will read.
+    pub fn wants(&self, event: Event) -> bool {
+        self.hooks.iter().any(|h| h.event == event)
+    }
+
+    /// Run events held back by the rate limit once it has passed.
+    pub fn poll(&mut self) {
+        for hook in &mut self.hooks {
+            hook.poll(self.min_interval);
+        }
+    }
+}
+
+impl Drop for Hooks {
+    fn drop(&mut self) {
+        // Reap hooks still running in the background rather than leaving
+        // zombies behind for the rest of the daemon's life.
+        let children: Vec<Child> = self.hooks.iter_mut().filter_map(|h| h.running.take()).collect();
+        if !children.is_empty() {
+            std::thread::spawn(move || {
+                for mut child in children {
+                    let _ = child.wait();
+                }
+            });
+        }
+    }
+}
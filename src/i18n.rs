@@ -0,0 +1,106 @@
+// src/i18n.rs
+//! User-facing strings, looked up in Fluent catalogs under `locales/`.
+//!
+//! The locale is taken from `LC_ALL`, `LC_MESSAGES` or `LANG` (first one set).
+//! Messages missing from the selected catalog fall back to English, and
+//! unknown ids are returned verbatim so a typo never hides output.
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+const FALLBACK_LOCALE: &str = "en-US";
+
+/// Bundled catalogs: (locale, Fluent source).
+const CATALOGS: &[(&str, &str)] = &[
+    ("en-US", include_str!("../locales/en-US/smart-brightness.ftl")),
+    ("de", include_str!("../locales/de/smart-brightness.ftl")),
+];
+
+struct Catalog {
+    primary: Option<FluentBundle<FluentResource>>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Translate `id` with optional arguments. Prefer the [`tr!`](crate::tr) macro.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let catalog = CATALOG.get_or_init(load);
+    catalog
+        .primary
+        .as_ref()
+        .and_then(|b| format(b, id, args))
+        .or_else(|| format(&catalog.fallback, id, args))
+        .unwrap_or_else(|| id.to_string())
+}
+
+fn format(bundle: &FluentBundle<FluentResource>, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    Some(bundle.format_pattern(pattern, args, &mut errors).into_owned())
+}
+
+fn load() -> Catalog {
+    let fallback = bundle_for(FALLBACK_LOCALE).expect("bundled en-US catalog must parse");
+    let primary = detect_locale()
+        .and_then(|wanted| select_catalog(&wanted))
+        .filter(|locale| *locale != FALLBACK_LOCALE)
+        .and_then(bundle_for);
+    Catalog { primary, fallback }
+}
+
+fn bundle_for(locale: &str) -> Option<FluentBundle<FluentResource>> {
+    let (_, source) = CATALOGS.iter().find(|(l, _)| *l == locale)?;
+    let langid: LanguageIdentifier = locale.parse().ok()?;
+    let resource = FluentResource::try_new(source.to_string()).ok()?;
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Unicode isolation marks show up as garbage in most terminals.
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).ok()?;
+    Some(bundle)
+}
+
+/// Pick the best bundled catalog for a POSIX-style locale such as
+/// `de_DE.UTF-8`: exact match first, then language only.
+fn select_catalog(wanted: &str) -> Option<&'static str> {
+    let tag = wanted.replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default();
+    CATALOGS
+        .iter()
+        .map(|(l, _)| *l)
+        .find(|l| l.eq_ignore_ascii_case(&tag))
+        .or_else(|| {
+            CATALOGS
+                .iter()
+                .map(|(l, _)| *l)
+                .find(|l| l.split('-').next() == Some(language))
+        })
+}
+
+fn detect_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty())
+        .map(|v| {
+            // Strip `.UTF-8` encodings and `@euro` style modifiers.
+            v.split(['.', '@']).next().unwrap_or_default().to_string()
+        })
+        .filter(|v| v != "C" && v != "POSIX")
+}
+
+/// Look up a localized message: `tr!("id")` or `tr!("id", name = value, ..)`.
+/// Values must convert into a Fluent value (strings; format numbers first).
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
@@ -0,0 +1,236 @@
+// src/integrations.rs
+//! Desktop integrations whose implementation depends on the session type:
+//! idle detection, fullscreen detection, screen content, connected outputs
+//! and software dimming; and the desktop's own automatic brightness.
+//!
+//! The session is detected from `XDG_SESSION_TYPE`, `WAYLAND_DISPLAY` and
+//! `DISPLAY` unless `session_type` overrides it. Each integration is `None`
+//! when the session has no implementation; backends shell out to the usual
+//! desktop tools and treat a missing tool as "no answer".
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::config::Config;
+
+mod auto_brightness;
+mod dimming;
+mod fullscreen;
+mod idle;
+mod screen;
+mod wayland;
+
+pub use auto_brightness::take_over as take_over_auto_brightness;
+pub use dimming::{MIN_FACTOR as MIN_DIM_FACTOR, SoftwareDimmer, xrandr};
+pub use wayland::{Output, OutputWatch, watch_outputs};
+
+/// How often idle and fullscreen state are re-checked; each check starts
+/// a process.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionType {
+    #[default]
+    Auto,
+    X11,
+    Wayland,
+    Headless,
+}
+
+/// What to do about the desktop's own automatic brightness
+/// (`desktop_auto_brightness`).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DesktopAutoBrightness {
+    /// Leave it alone; warn if it is on.
+    #[default]
+    Keep,
+    /// Switch it off while the daemon runs.
+    Disable,
+}
+
+impl SessionType {
+    /// Resolve `Auto` from the environment.
+    pub fn resolve(self) -> SessionType {
+        if self != SessionType::Auto {
+            return self;
+        }
+        match env::var("XDG_SESSION_TYPE").as_deref() {
+            Ok("wayland") => return SessionType::Wayland,
+            Ok("x11") => return SessionType::X11,
+            Ok("tty") => return SessionType::Headless,
+            _ => {}
+        }
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            SessionType::Wayland
+        } else if env::var_os("DISPLAY").is_some() {
+            SessionType::X11
+        } else {
+            SessionType::Headless
+        }
+    }
+}
+
+/// Run `program` and return its stdout if it succeeded.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    if !out.status.success() {
+        debug!(target: "integrations", "{} failed: {}", program, out.status);
+        return None;
+    }
+    String::from_utf8(out.stdout).ok()
+}
+
+trait IdleDetector {
+    fn idle_for(&self) -> Option<Duration>;
+}
+
+trait FullscreenDetector {
+    fn fullscreen(&self) -> Option<bool>;
+}
+
+/// Idle and fullscreen state for the control loop, polled at most every
+/// `POLL_INTERVAL`.
+pub struct Desktop {
+    idle: Option<Box<dyn IdleDetector>>,
+    idle_after: Duration,
+    fullscreen: Option<Box<dyn FullscreenDetector>>,
+    screen: Option<screen::ScreenContent>,
+    last_poll: Option<Instant>,
+    is_idle: bool,
+    is_fullscreen: bool,
+}
+
+impl Desktop {
+    pub fn from_config(cfg: &Config) -> Self {
+        let wants_idle = cfg.idle_pause_secs > 0;
+        let wants_fullscreen = cfg.fullscreen_hold;
+        let wants_screen = cfg.screen_compensation > 0.0;
+        let (idle, fullscreen, screen) = if wants_idle || wants_fullscreen || wants_screen {
+            let session = cfg.session_type.resolve();
+            let idle = wants_idle.then(|| idle::for_session(session)).flatten();
+            let fullscreen = wants_fullscreen
+                .then(|| fullscreen::for_session(session))
+                .flatten();
+            let screen = wants_screen.then(|| screen::for_session(session)).flatten();
+            info!(
+                target: "integrations",
+                "{:?} session: idle detection {}, fullscreen detection {}, screen content {}",
+                session,
+                availability(wants_idle, idle.is_some()),
+                availability(wants_fullscreen, fullscreen.is_some()),
+                availability(wants_screen, screen.is_some())
+            );
+            (idle, fullscreen, screen)
+        } else {
+            (None, None, None)
+        };
+        Self {
+            idle,
+            idle_after: Duration::from_secs(cfg.idle_pause_secs),
+            fullscreen,
+            screen,
+            last_poll: None,
+            is_idle: false,
+            is_fullscreen: false,
+        }
+    }
+
+    fn poll(&mut self) {
+        if self.idle.is_none() && self.fullscreen.is_none() {
+            return;
+        }
+        if self.last_poll.is_some_and(|t| t.elapsed() < POLL_INTERVAL) {
+            return;
+        }
+        self.last_poll = Some(Instant::now());
+        if let Some(idle) = &self.idle {
+            let was = self.is_idle;
+            self.is_idle = idle.idle_for().is_some_and(|d| d >= self.idle_after);
+            if was != self.is_idle {
+                info!(
+                    target: "integrations",
+                    "{}",
+                    if self.is_idle { "User idle: pausing capture" } else { "User active: resuming capture" }
+                );
+            }
+        }
+        if let Some(fullscreen) = &self.fullscreen {
+            let was = self.is_fullscreen;
+            self.is_fullscreen = fullscreen.fullscreen().unwrap_or(false);
+            if was != self.is_fullscreen {
+                info!(
+                    target: "integrations",
+                    "{}",
+                    if self.is_fullscreen { "Fullscreen window: holding brightness" } else { "Fullscreen ended" }
+                );
+            }
+        }
+    }
+
+    /// The user has been idle for at least `idle_pause_secs`.
+    pub fn idle(&mut self) -> bool {
+        self.poll();
+        self.is_idle
+    }
+
+    /// A fullscreen window has focus and `fullscreen_hold` is on.
+    pub fn fullscreen(&mut self) -> bool {
+        self.poll();
+        self.is_fullscreen
+    }
+
+    /// Mean luma of the screen content in linear light, while
+    /// `screen_compensation` is on and a capture is running.
+    pub fn screen_luma(&self) -> Option<f32> {
+        self.screen.as_ref().and_then(screen::ScreenContent::luma)
+    }
+}
+
+fn availability(wanted: bool, available: bool) -> &'static str {
+    match (wanted, available) {
+        (false, _) => "off",
+        (true, true) => "on",
+        (true, false) => "unavailable",
+    }
+}
+
+/// Software dimmer for the configured session, if `software_dimming` is on
+/// and the session supports one.
+pub fn software_dimmer(cfg: &Config) -> Option<Box<dyn SoftwareDimmer>> {
+    if !cfg.software_dimming {
+        return None;
+    }
+    dimming::for_session(cfg.session_type.resolve())
+}
+
+/// Show a desktop notification: `notify-send` on Linux, Notification
+/// Center on macOS. Where neither works, it is only logged.
+pub fn notify(summary: &str, body: &str) {
+    let status = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {:?} with title \"smart-brightness\" subtitle {:?}",
+            body, summary
+        );
+        Command::new("osascript").args(["-e", &script]).output()
+    } else {
+        Command::new("notify-send")
+            .args(["--app-name=smart-brightness", summary, body])
+            .output()
+    };
+    match status {
+        Ok(out) if out.status.success() => {}
+        Ok(out) => debug!(target: "integrations", "notification failed: {}", out.status),
+        Err(e) => debug!(target: "integrations", "cannot show notification: {}", e),
+    }
+}
+
+/// Dimmer for the picture stage below the backlight floor
+/// (`pixel_dimming`), if the session supports one.
+pub fn picture_dimmer(cfg: &Config) -> Option<Box<dyn SoftwareDimmer>> {
+    dimming::for_session(cfg.session_type.resolve())
+}
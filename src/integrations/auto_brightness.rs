@@ -0,0 +1,110 @@
+// src/integrations/auto_brightness.rs
+//! The desktop's own automatic brightness (`desktop_auto_brightness`).
+//!
+//! GNOME adjusts the backlight from an ambient light sensor when
+//! `org.gnome.settings-daemon.plugins.power ambient-enabled` is on, and the
+//! two daemons then fight over it. With `disable` the key is switched off
+//! for as long as the daemon runs. A marker file records that it was on, so
+//! a run that didn't get to restore it (a crash, `kill -9`) is undone by the
+//! next start.
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use tracing::{debug, info, warn};
+
+use super::{DesktopAutoBrightness, output};
+use crate::config::Config;
+
+const SCHEMA: &str = "org.gnome.settings-daemon.plugins.power";
+const KEY: &str = "ambient-enabled";
+
+fn marker_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("smart-brightness")
+        .join("desktop-auto-brightness-disabled")
+}
+
+/// `ambient-enabled`, or None without gsettings or the schema.
+fn enabled() -> Option<bool> {
+    match output("gsettings", &["get", SCHEMA, KEY])?.trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn set_enabled(on: bool) -> bool {
+    let value = if on { "true" } else { "false" };
+    match Command::new("gsettings").args(["set", SCHEMA, KEY, value]).status() {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            warn!(target: "integrations", "gsettings set {} {} failed: {}", KEY, value, status);
+            false
+        }
+        Err(e) => {
+            warn!(target: "integrations", "cannot run gsettings: {}", e);
+            false
+        }
+    }
+}
+
+/// Keeps GNOME's automatic brightness off; turns it back on when dropped.
+pub struct AutoBrightnessGuard {
+    marker: PathBuf,
+}
+
+impl Drop for AutoBrightnessGuard {
+    fn drop(&mut self) {
+        if set_enabled(true) {
+            info!(target: "integrations", "Turned GNOME's automatic brightness back on");
+            let _ = fs::remove_file(&self.marker);
+        }
+    }
+}
+
+/// Apply `desktop_auto_brightness` at startup. Returns the guard that
+/// restores the desktop setting, if it was switched off.
+pub fn take_over(cfg: &Config) -> Option<AutoBrightnessGuard> {
+    let on = enabled()?;
+    let marker = marker_path();
+    let left_off = marker.exists();
+    match cfg.desktop_auto_brightness {
+        DesktopAutoBrightness::Keep => {
+            if left_off {
+                if !on && set_enabled(true) {
+                    info!(
+                        target: "integrations",
+                        "Turned GNOME's automatic brightness back on; an earlier run left it off"
+                    );
+                }
+                let _ = fs::remove_file(&marker);
+            } else if on {
+                warn!(
+                    target: "integrations",
+                    "GNOME's automatic brightness is on and will fight over the backlight; \
+                     turn it off in Settings → Power or set desktop_auto_brightness = \"disable\""
+                );
+            }
+            None
+        }
+        DesktopAutoBrightness::Disable => {
+            if !on && !left_off {
+                debug!(target: "integrations", "GNOME's automatic brightness is already off");
+                return None;
+            }
+            if on && !set_enabled(false) {
+                return None;
+            }
+            if let Some(dir) = marker.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Err(e) = fs::write(&marker, "") {
+                debug!(target: "integrations", "cannot write {}: {}", marker.display(), e);
+            }
+            info!(target: "integrations", "Turned GNOME's automatic brightness off until exit");
+            Some(AutoBrightnessGuard { marker })
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// src/integrations/dimming.rs
+use std::io;
+use std::process::Command;
+
+use super::{SessionType, output};
+
+/// Dims the picture rather than the backlight: for monitors the daemon
+/// can't reach otherwise. Levels run from 0 to `max_value`.
+pub trait SoftwareDimmer {
+    fn max_value(&self) -> u32;
+    fn set(&self, value: u32) -> io::Result<()>;
+    fn name(&self) -> String;
+}
+
+/// `xrandr --brightness` on every connected output.
+struct XRandr {
+    outputs: Vec<String>,
+}
+
+/// Below this the picture is too dark to recover from without a second
+/// screen; the range is mapped onto MIN_FACTOR..=1.0.
+pub const MIN_FACTOR: f32 = 0.2;
+
+impl SoftwareDimmer for XRandr {
+    fn max_value(&self) -> u32 {
+        100
+    }
+
+    fn set(&self, value: u32) -> io::Result<()> {
+        let factor = MIN_FACTOR + (1.0 - MIN_FACTOR) * value.min(100) as f32 / 100.0;
+        let factor = format!("{:.2}", factor);
+        for name in &self.outputs {
+            let status = Command::new("xrandr")
+                .args(["--output", name, "--brightness", &factor])
+                .status()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("xrandr --output {} failed: {}", name, status)));
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!("xrandr ({})", self.outputs.join(", "))
+    }
+}
+
+fn connected_outputs() -> Vec<String> {
+    output("xrandr", &["--query"])
+        .unwrap_or_default()
+        .lines()
+        .filter(|l| l.contains(" connected"))
+        .filter_map(|l| l.split_whitespace().next().map(str::to_string))
+        .collect()
+}
+
+/// `xrandr --brightness` on one output, whether or not it is connected
+/// yet.
+pub fn xrandr(output: &str) -> Box<dyn SoftwareDimmer> {
+    Box::new(XRandr {
+        outputs: vec![output.to_string()],
+    })
+}
+
+/// Wayland compositors don't let clients change gamma generically, so
+/// only X11 is supported.
+pub(super) fn for_session(session: SessionType) -> Option<Box<dyn SoftwareDimmer>> {
+    match session {
+        SessionType::X11 => {
+            let outputs = connected_outputs();
+            (!outputs.is_empty()).then(|| Box::new(XRandr { outputs }) as Box<dyn SoftwareDimmer>)
+        }
+        SessionType::Wayland | SessionType::Headless | SessionType::Auto => None,
+    }
+}
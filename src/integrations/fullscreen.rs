@@ -0,0 +1,71 @@
+// src/integrations/fullscreen.rs
+use std::env;
+
+use serde_json::Value;
+
+use super::{FullscreenDetector, SessionType, output};
+
+/// EWMH `_NET_WM_STATE_FULLSCREEN` on the active window, via `xprop`.
+struct XProp;
+
+impl FullscreenDetector for XProp {
+    fn fullscreen(&self) -> Option<bool> {
+        let active = output("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+        let id = active.split_whitespace().last()?;
+        if id == "0x0" {
+            return Some(false);
+        }
+        let state = output("xprop", &["-id", id, "_NET_WM_STATE"])?;
+        Some(state.contains("_NET_WM_STATE_FULLSCREEN"))
+    }
+}
+
+/// Focused node's `fullscreen_mode` in `swaymsg -t get_tree`.
+struct Sway;
+
+impl FullscreenDetector for Sway {
+    fn fullscreen(&self) -> Option<bool> {
+        let tree: Value = serde_json::from_str(&output("swaymsg", &["-t", "get_tree"])?).ok()?;
+        Some(focused_fullscreen(&tree))
+    }
+}
+
+fn focused_fullscreen(node: &Value) -> bool {
+    if node["focused"].as_bool() == Some(true) {
+        return node["fullscreen_mode"].as_u64().is_some_and(|m| m > 0);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[key].as_array())
+        .flatten()
+        .any(focused_fullscreen)
+}
+
+/// `fullscreen` of `hyprctl activewindow -j` (a bool or mode number
+/// depending on the Hyprland version).
+struct Hyprland;
+
+impl FullscreenDetector for Hyprland {
+    fn fullscreen(&self) -> Option<bool> {
+        let window: Value =
+            serde_json::from_str(&output("hyprctl", &["activewindow", "-j"])?).ok()?;
+        match &window["fullscreen"] {
+            Value::Bool(b) => Some(*b),
+            Value::Number(n) => Some(n.as_u64() != Some(0)),
+            _ => Some(false),
+        }
+    }
+}
+
+/// Wayland has no common protocol for this, so only compositors with an
+/// IPC for it are supported.
+pub(super) fn for_session(session: SessionType) -> Option<Box<dyn FullscreenDetector>> {
+    match session {
+        SessionType::X11 => Some(Box::new(XProp)),
+        SessionType::Wayland if env::var_os("SWAYSOCK").is_some() => Some(Box::new(Sway)),
+        SessionType::Wayland if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() => {
+            Some(Box::new(Hyprland))
+        }
+        SessionType::Wayland | SessionType::Headless | SessionType::Auto => None,
+    }
+}
@@ -0,0 +1,46 @@
+// src/integrations/idle.rs
+use std::time::Duration;
+
+use super::{IdleDetector, SessionType, output};
+
+/// X11 idle time from the screensaver extension, via `xprintidle`.
+struct XPrintIdle;
+
+impl IdleDetector for XPrintIdle {
+    fn idle_for(&self) -> Option<Duration> {
+        let ms = output("xprintidle", &[])?.trim().parse().ok()?;
+        Some(Duration::from_millis(ms))
+    }
+}
+
+/// logind's session `IdleHint`, set by the compositor once its own idle
+/// timeout passes. It carries no duration, so idle counts as "long enough".
+struct LogindIdleHint;
+
+impl IdleDetector for LogindIdleHint {
+    fn idle_for(&self) -> Option<Duration> {
+        let reply = output(
+            "busctl",
+            &[
+                "get-property",
+                "org.freedesktop.login1",
+                "/org/freedesktop/login1/session/auto",
+                "org.freedesktop.login1.Session",
+                "IdleHint",
+            ],
+        )?;
+        match reply.trim() {
+            "b true" => Some(Duration::MAX),
+            "b false" => Some(Duration::ZERO),
+            _ => None,
+        }
+    }
+}
+
+pub(super) fn for_session(session: SessionType) -> Option<Box<dyn IdleDetector>> {
+    match session {
+        SessionType::X11 => Some(Box::new(XPrintIdle)),
+        SessionType::Wayland => Some(Box::new(LogindIdleHint)),
+        SessionType::Headless | SessionType::Auto => None,
+    }
+}
@@ -0,0 +1,67 @@
+// src/integrations/screen.rs
+//! Average light output of the screen, so the share of an ambient reading
+//! that is the display lighting up the room can be taken out again
+//! (`screen_compensation`).
+// Without a capture backend nothing ever publishes a value.
+#![cfg_attr(not(all(feature = "wayland", target_os = "linux")), allow(dead_code))]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::SessionType;
+
+#[cfg(all(feature = "wayland", target_os = "linux"))]
+mod wlr;
+
+/// Latest screen luma, written by a capture thread. NaN stands for "none":
+/// before the first frame, and after the thread gave up.
+#[derive(Clone)]
+struct Shared(Arc<AtomicU32>);
+
+impl Shared {
+    fn new() -> Self {
+        Self(Arc::new(AtomicU32::new(f32::NAN.to_bits())))
+    }
+
+    fn set(&self, luma: Option<f32>) {
+        self.0
+            .store(luma.unwrap_or(f32::NAN).to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Option<f32> {
+        let luma = f32::from_bits(self.0.load(Ordering::Relaxed));
+        (!luma.is_nan()).then_some(luma)
+    }
+}
+
+/// Screen content of the built-in panel, captured in the background.
+pub struct ScreenContent(Shared);
+
+impl ScreenContent {
+    /// Mean luma in linear light, 0.0–1.0.
+    pub fn luma(&self) -> Option<f32> {
+        self.0.get()
+    }
+}
+
+/// Only wlroots-based compositors let clients read the screen without
+/// asking the user through a portal.
+#[cfg(all(feature = "wayland", target_os = "linux"))]
+pub(super) fn for_session(session: SessionType) -> Option<ScreenContent> {
+    if session != SessionType::Wayland {
+        return None;
+    }
+    let shared = Shared::new();
+    match wlr::start(shared.clone()) {
+        Ok(()) => Some(ScreenContent(shared)),
+        Err(e) => {
+            tracing::debug!(target: "integrations", "screen capture unavailable: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(all(feature = "wayland", target_os = "linux")))]
+pub(super) fn for_session(_session: SessionType) -> Option<ScreenContent> {
+    None
+}
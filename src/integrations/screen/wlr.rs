@@ -0,0 +1,406 @@
+// src/integrations/screen/wlr.rs
+//! wlr-screencopy capture of the built-in panel into shared memory.
+//!
+//! Frames are requested with `copy_with_damage`, so the compositor only
+//! answers once something on screen has changed and says which regions
+//! did. The screen is split into a `GRID`×`GRID` grid of cells, and only
+//! the cells touched by damage are read again, sampling every
+//! `SAMPLE_STEP`th pixel of every `SAMPLE_STEP`th row. A static desktop
+//! costs nothing; a playing video costs a few hundred small reads per
+//! `MIN_INTERVAL`.
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::os::fd::AsFd;
+use std::os::unix::fs::{FileExt, OpenOptionsExt};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::protocol::wl_shm::{self, WlShm};
+use wayland_client::protocol::wl_shm_pool::WlShmPool;
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle, WEnum, delegate_noop};
+use wayland_protocols_wlr::screencopy::v1::client::zwlr_screencopy_frame_v1::{
+    self, ZwlrScreencopyFrameV1,
+};
+use wayland_protocols_wlr::screencopy::v1::client::zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1;
+
+use super::Shared;
+use crate::config::runtime_dir;
+
+const GRID: usize = 8;
+const SAMPLE_STEP: usize = 8;
+/// Upper bound on the capture rate; while a video plays there is damage
+/// on every frame.
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// Damage only covers the last commit before the copy, so changes made
+/// while we sleep can be missed; every this many frames all cells are
+/// read again.
+const FULL_REFRESH_FRAMES: u32 = 20;
+
+/// Buffer parameters the compositor offered for shared memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+    format: wl_shm::Format,
+    width: u32,
+    height: u32,
+    stride: u32,
+}
+
+#[derive(Default)]
+struct Frame {
+    layout: Option<Layout>,
+    buffer_done: bool,
+    ready: bool,
+    failed: bool,
+    damage: Vec<(u32, u32, u32, u32)>,
+}
+
+struct State {
+    outputs: Vec<(WlOutput, Option<String>)>,
+    frame: Frame,
+}
+
+struct ShmBuffer {
+    file: File,
+    pool: WlShmPool,
+    buffer: WlBuffer,
+    layout: Layout,
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+        self.pool.destroy();
+    }
+}
+
+struct Capture {
+    queue: EventQueue<State>,
+    state: State,
+    manager: ZwlrScreencopyManagerV1,
+    shm: WlShm,
+    output: WlOutput,
+    buffer: Option<ShmBuffer>,
+    cells: [f32; GRID * GRID],
+    linear: [f32; 256],
+    shared: Shared,
+}
+
+/// Connect and pick the output, then keep capturing on a background thread.
+pub(super) fn start(shared: Shared) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+    let qh = queue.handle();
+    let manager: ZwlrScreencopyManagerV1 = globals
+        .bind(&qh, 3..=3, ())
+        .map_err(|_| "compositor has no wlr-screencopy v3")?;
+    let shm: WlShm = globals.bind(&qh, 1..=1, ())?;
+    let outputs: Vec<(u32, u32)> = globals.contents().with_list(|list| {
+        list.iter()
+            .filter(|g| g.interface == "wl_output")
+            .map(|g| (g.name, g.version))
+            .collect()
+    });
+    let mut state = State {
+        outputs: outputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, version))| {
+                let output = globals
+                    .registry()
+                    .bind::<WlOutput, _, _>(name, version.min(4), &qh, i);
+                (output, None)
+            })
+            .collect(),
+        frame: Frame::default(),
+    };
+    // Output names (wl_output v4) arrive after the bind.
+    queue.roundtrip(&mut state)?;
+    let (output, name) = panel(&state.outputs).ok_or("no outputs")?;
+    info!(
+        target: "integrations",
+        "Measuring screen content of {}",
+        name.as_deref().unwrap_or("the first output")
+    );
+    let mut capture = Capture {
+        queue,
+        state,
+        manager,
+        shm,
+        output,
+        buffer: None,
+        cells: [0.0; GRID * GRID],
+        linear: std::array::from_fn(|i| (i as f32 / 255.0).powf(2.2)),
+        shared,
+    };
+    thread::Builder::new()
+        .name("screencopy".into())
+        .spawn(move || {
+            if let Err(e) = capture.run() {
+                warn!(target: "integrations", "Screen capture stopped: {}", e);
+            }
+            capture.shared.set(None);
+        })?;
+    Ok(())
+}
+
+/// The built-in panel if its connector name gives it away, else the first
+/// output.
+fn panel(outputs: &[(WlOutput, Option<String>)]) -> Option<(WlOutput, Option<String>)> {
+    let builtin = |name: &Option<String>| {
+        name.as_deref()
+            .is_some_and(|n| ["eDP", "LVDS", "DSI"].iter().any(|p| n.starts_with(p)))
+    };
+    outputs
+        .iter()
+        .find(|(_, name)| builtin(name))
+        .or_else(|| outputs.first())
+        .cloned()
+}
+
+impl Capture {
+    fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        let qh = self.queue.handle();
+        let mut frames = 0u32;
+        loop {
+            self.state.frame = Frame::default();
+            let frame = self.manager.capture_output(0, &self.output, &qh, ());
+            self.dispatch_until(|f| f.buffer_done || f.failed)?;
+            if self.state.frame.failed {
+                frame.destroy();
+                self.retry_later();
+                continue;
+            }
+            let Some(layout) = self.state.frame.layout else {
+                frame.destroy();
+                return Err("compositor offered no supported shared-memory format".into());
+            };
+            let resized = self.buffer.as_ref().is_none_or(|b| b.layout != layout);
+            if resized {
+                self.buffer = Some(self.allocate(layout, &qh)?);
+            }
+            let buffer = self.buffer.as_ref().expect("allocated above");
+            // A fresh buffer is filled right away; later ones wait for a change.
+            if resized {
+                frame.copy(&buffer.buffer);
+            } else {
+                frame.copy_with_damage(&buffer.buffer);
+            }
+            self.dispatch_until(|f| f.ready || f.failed)?;
+            frame.destroy();
+            if self.state.frame.failed {
+                self.retry_later();
+                continue;
+            }
+            let damage = if resized || frames.is_multiple_of(FULL_REFRESH_FRAMES) {
+                vec![(0, 0, layout.width, layout.height)]
+            } else {
+                std::mem::take(&mut self.state.frame.damage)
+            };
+            self.update(&damage)?;
+            frames = frames.wrapping_add(1);
+            let mean = self.cells.iter().sum::<f32>() / self.cells.len() as f32;
+            self.shared.set(Some(mean));
+            thread::sleep(MIN_INTERVAL);
+        }
+    }
+
+    /// The output may be off or being reconfigured.
+    fn retry_later(&self) {
+        debug!(target: "integrations", "screen capture failed; retrying");
+        thread::sleep(MIN_INTERVAL * 4);
+    }
+
+    fn dispatch_until(&mut self, done: impl Fn(&Frame) -> bool) -> Result<(), Box<dyn Error>> {
+        while !done(&self.state.frame) {
+            self.queue.blocking_dispatch(&mut self.state)?;
+        }
+        Ok(())
+    }
+
+    /// A buffer backed by an unlinked file in the runtime directory
+    /// (normally tmpfs), only readable by us.
+    fn allocate(&self, layout: Layout, qh: &QueueHandle<State>) -> Result<ShmBuffer, Box<dyn Error>> {
+        let size = layout.stride as u64 * layout.height as u64;
+        let dir = runtime_dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("screencopy-{}", std::process::id()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&path)?;
+        fs::remove_file(&path)?;
+        file.set_len(size)?;
+        let pool = self.shm.create_pool(file.as_fd(), i32::try_from(size)?, qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            layout.width as i32,
+            layout.height as i32,
+            layout.stride as i32,
+            layout.format,
+            qh,
+            (),
+        );
+        debug!(
+            target: "integrations",
+            "screen capture buffer {}x{} {:?}",
+            layout.width,
+            layout.height,
+            layout.format
+        );
+        Ok(ShmBuffer {
+            file,
+            pool,
+            buffer,
+            layout,
+        })
+    }
+
+    /// Re-read the cells that `damage` rectangles touch.
+    fn update(&mut self, damage: &[(u32, u32, u32, u32)]) -> Result<(), Box<dyn Error>> {
+        let buffer = self.buffer.as_ref().expect("buffer allocated before copy");
+        let Layout {
+            format,
+            width,
+            height,
+            stride,
+        } = buffer.layout;
+        let (w, h) = (width as usize, height as usize);
+        if w < GRID || h < GRID {
+            return Ok(());
+        }
+        let mut dirty = [false; GRID * GRID];
+        for &(x, y, dw, dh) in damage {
+            let (x0, y0) = (x as usize * GRID / w, y as usize * GRID / h);
+            let x1 = ((x + dw) as usize * GRID).div_ceil(w).min(GRID);
+            let y1 = ((y + dh) as usize * GRID).div_ceil(h).min(GRID);
+            for cy in y0..y1 {
+                for cx in x0..x1 {
+                    dirty[cy * GRID + cx] = true;
+                }
+            }
+        }
+        // Byte offsets of red and blue; both formats are little-endian words.
+        let (r, b) = match format {
+            wl_shm::Format::Xbgr8888 | wl_shm::Format::Abgr8888 => (0, 2),
+            _ => (2, 0),
+        };
+        let mut sums = [(0.0f32, 0u32); GRID * GRID];
+        let mut row = vec![0u8; w * 4];
+        for y in (0..h).step_by(SAMPLE_STEP) {
+            let cy = y * GRID / h;
+            let band = &dirty[cy * GRID..(cy + 1) * GRID];
+            if !band.contains(&true) {
+                continue;
+            }
+            buffer.file.read_exact_at(&mut row, y as u64 * stride as u64)?;
+            for x in (0..w).step_by(SAMPLE_STEP) {
+                let cx = x * GRID / w;
+                if !band[cx] {
+                    continue;
+                }
+                let px = &row[x * 4..x * 4 + 4];
+                let luma = 0.2126 * self.linear[px[r] as usize]
+                    + 0.7152 * self.linear[px[1] as usize]
+                    + 0.0722 * self.linear[px[b] as usize];
+                let cell = &mut sums[cy * GRID + cx];
+                cell.0 += luma;
+                cell.1 += 1;
+            }
+        }
+        for (i, &(sum, count)) in sums.iter().enumerate() {
+            if dirty[i] && count > 0 {
+                self.cells[i] = sum / count as f32;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as wayland_client::Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlOutput, usize> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlOutput,
+        event: wl_output::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.outputs[*index].1 = Some(name);
+        }
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use zwlr_screencopy_frame_v1::Event;
+        let frame = &mut state.frame;
+        match event {
+            Event::Buffer {
+                format: WEnum::Value(format),
+                width,
+                height,
+                stride,
+            } if supported(format) => {
+                frame.layout = Some(Layout {
+                    format,
+                    width,
+                    height,
+                    stride,
+                });
+            }
+            Event::BufferDone => frame.buffer_done = true,
+            Event::Damage {
+                x,
+                y,
+                width,
+                height,
+            } => frame.damage.push((x, y, width, height)),
+            Event::Ready { .. } => frame.ready = true,
+            Event::Failed => frame.failed = true,
+            _ => {}
+        }
+    }
+}
+
+fn supported(format: wl_shm::Format) -> bool {
+    matches!(
+        format,
+        wl_shm::Format::Xrgb8888
+            | wl_shm::Format::Argb8888
+            | wl_shm::Format::Xbgr8888
+            | wl_shm::Format::Abgr8888
+    )
+}
+
+delegate_noop!(State: ignore WlShm);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ignore WlBuffer);
+delegate_noop!(State: ZwlrScreencopyManagerV1);
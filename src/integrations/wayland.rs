@@ -0,0 +1,84 @@
+// src/integrations/wayland.rs
+//! Outputs as the Wayland compositor reports them: connected or not, and
+//! powered up or blanked, so linked displays (`[[displays]]` with `output`)
+//! follow a dock. Outputs are tracked on a background thread through
+//! `wl_output` and `xdg-output` for names and `wlr-output-power-management`
+//! for power.
+// Without a Wayland client nothing ever publishes outputs.
+#![cfg_attr(not(all(feature = "wayland", target_os = "linux")), allow(dead_code))]
+
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+
+use crate::config::Config;
+
+#[cfg(all(feature = "wayland", target_os = "linux"))]
+mod outputs;
+
+/// An output the compositor reports, by connector name (`DP-2`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub name: String,
+    /// Off while DPMS has it blanked; None if the compositor doesn't say.
+    pub powered: Option<bool>,
+}
+
+impl Output {
+    /// Connected and not powered down.
+    pub fn lit(outputs: &[Output], name: &str) -> bool {
+        outputs.iter().any(|o| o.name == name && o.powered != Some(false))
+    }
+}
+
+/// The outputs as last published by the watching thread, and how often
+/// they have changed.
+#[derive(Default)]
+struct Outputs {
+    generation: u64,
+    list: Vec<Output>,
+}
+
+/// Outputs coming and going, and powering up and down, tracked in the
+/// background so linked displays can follow a dock.
+pub struct OutputWatch {
+    shared: Arc<Mutex<Outputs>>,
+    seen: Cell<u64>,
+}
+
+impl OutputWatch {
+    /// The outputs, if they changed since the last call.
+    pub fn changed(&self) -> Option<Vec<Output>> {
+        let outputs = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        if outputs.generation == self.seen.replace(outputs.generation) {
+            return None;
+        }
+        Some(outputs.list.clone())
+    }
+}
+
+/// Watch outputs for `[[displays]]` in a Wayland session; None elsewhere or
+/// without linked displays.
+#[cfg(all(feature = "wayland", target_os = "linux"))]
+pub fn watch_outputs(cfg: &Config) -> Option<OutputWatch> {
+    if !cfg.displays.iter().any(|d| d.enabled)
+        || cfg.session_type.resolve() != super::SessionType::Wayland
+    {
+        return None;
+    }
+    let shared = Arc::new(Mutex::new(Outputs::default()));
+    match outputs::start(shared.clone()) {
+        Ok(()) => Some(OutputWatch {
+            shared,
+            seen: Cell::new(0),
+        }),
+        Err(e) => {
+            tracing::debug!(target: "integrations", "output tracking unavailable: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(all(feature = "wayland", target_os = "linux")))]
+pub fn watch_outputs(_cfg: &Config) -> Option<OutputWatch> {
+    None
+}
@@ -0,0 +1,240 @@
+// src/integrations/wayland/outputs.rs
+//! The compositor's outputs, kept up to date on a background thread.
+//!
+//! Every `wl_output` global is bound as it appears and forgotten when it is
+//! removed. Its connector name comes from `wl_output` v4, or from
+//! `xdg-output` on older compositors; its power state from
+//! `wlr-output-power-management` where the compositor has it. Each change
+//! is published as a new generation of the list.
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tracing::{debug, warn};
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, WEnum, delegate_noop};
+use wayland_protocols::xdg::xdg_output::zv1::client::zxdg_output_manager_v1::ZxdgOutputManagerV1;
+use wayland_protocols::xdg::xdg_output::zv1::client::zxdg_output_v1::{self, ZxdgOutputV1};
+use wayland_protocols_wlr::output_power_management::v1::client::zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1;
+use wayland_protocols_wlr::output_power_management::v1::client::zwlr_output_power_v1::{
+    self, ZwlrOutputPowerV1,
+};
+
+use super::{Output, Outputs};
+
+/// One bound `wl_output`, by the name of its global.
+struct Tracked {
+    global: u32,
+    output: WlOutput,
+    xdg: Option<ZxdgOutputV1>,
+    power: Option<ZwlrOutputPowerV1>,
+    name: Option<String>,
+    powered: Option<bool>,
+}
+
+struct State {
+    tracked: Vec<Tracked>,
+    xdg_manager: Option<ZxdgOutputManagerV1>,
+    power_manager: Option<ZwlrOutputPowerManagerV1>,
+    changed: bool,
+}
+
+impl State {
+    fn add(&mut self, registry: &WlRegistry, global: u32, version: u32, qh: &QueueHandle<Self>) {
+        let output: WlOutput = registry.bind(global, version.min(4), qh, global);
+        let xdg = self.xdg_manager.as_ref().map(|m| m.get_xdg_output(&output, qh, global));
+        let power = self
+            .power_manager
+            .as_ref()
+            .map(|m| m.get_output_power(&output, qh, global));
+        self.tracked.push(Tracked {
+            global,
+            output,
+            xdg,
+            power,
+            name: None,
+            powered: None,
+        });
+        self.changed = true;
+    }
+
+    fn remove(&mut self, global: u32) {
+        let Some(i) = self.tracked.iter().position(|t| t.global == global) else {
+            return;
+        };
+        let gone = self.tracked.remove(i);
+        if let Some(xdg) = gone.xdg {
+            xdg.destroy();
+        }
+        if let Some(power) = gone.power {
+            power.destroy();
+        }
+        if gone.output.version() >= 3 {
+            gone.output.release();
+        }
+        self.changed = true;
+    }
+
+    fn tracked(&mut self, global: u32) -> Option<&mut Tracked> {
+        self.tracked.iter_mut().find(|t| t.global == global)
+    }
+
+    /// Outputs whose name is known yet.
+    fn outputs(&self) -> Vec<Output> {
+        self.tracked
+            .iter()
+            .filter_map(|t| {
+                Some(Output {
+                    name: t.name.clone()?,
+                    powered: t.powered,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Connect and bind the outputs there are now, then follow changes on a
+/// background thread.
+pub(super) fn start(shared: Arc<Mutex<Outputs>>) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+    let qh = queue.handle();
+    let mut state = State {
+        tracked: Vec::new(),
+        xdg_manager: globals.bind(&qh, 2..=3, ()).ok(),
+        power_manager: globals.bind(&qh, 1..=1, ()).ok(),
+        changed: false,
+    };
+    if state.power_manager.is_none() {
+        debug!(
+            target: "integrations",
+            "compositor has no wlr-output-power-management; power state unknown"
+        );
+    }
+    let outputs: Vec<(u32, u32)> = globals.contents().with_list(|list| {
+        list.iter()
+            .filter(|g| g.interface == WlOutput::interface().name)
+            .map(|g| (g.name, g.version))
+            .collect()
+    });
+    for (global, version) in outputs {
+        state.add(globals.registry(), global, version, &qh);
+    }
+    // Names and power modes arrive after the binds.
+    queue.roundtrip(&mut state)?;
+    publish(&shared, &mut state);
+    thread::Builder::new()
+        .name("wl-outputs".into())
+        .spawn(move || {
+            loop {
+                if let Err(e) = queue.blocking_dispatch(&mut state) {
+                    warn!(target: "integrations", "Output tracking stopped: {}", e);
+                    return;
+                }
+                if state.changed {
+                    publish(&shared, &mut state);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn publish(shared: &Mutex<Outputs>, state: &mut State) {
+    state.changed = false;
+    let list = state.outputs();
+    let mut outputs = shared.lock().unwrap_or_else(|e| e.into_inner());
+    if outputs.generation > 0 && outputs.list == list {
+        return;
+    }
+    debug!(target: "integrations", "outputs: {:?}", list);
+    outputs.generation += 1;
+    outputs.list = list;
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == WlOutput::interface().name => state.add(registry, name, version, qh),
+            wl_registry::Event::GlobalRemove { name } => state.remove(name),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlOutput,
+        event: wl_output::Event,
+        global: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event
+            && let Some(tracked) = state.tracked(*global)
+        {
+            tracked.name = Some(name);
+            state.changed = true;
+        }
+    }
+}
+
+impl Dispatch<ZxdgOutputV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZxdgOutputV1,
+        event: zxdg_output_v1::Event,
+        global: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // wl_output's own name wins where both give one.
+        if let zxdg_output_v1::Event::Name { name } = event
+            && let Some(tracked) = state.tracked(*global)
+            && tracked.name.is_none()
+        {
+            tracked.name = Some(name);
+            state.changed = true;
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputPowerV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrOutputPowerV1,
+        event: zwlr_output_power_v1::Event,
+        global: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(tracked) = state.tracked(*global) else {
+            return;
+        };
+        tracked.powered = match event {
+            zwlr_output_power_v1::Event::Mode {
+                mode: WEnum::Value(mode),
+            } => Some(mode == zwlr_output_power_v1::Mode::On),
+            // The output went away or another client controls its power.
+            zwlr_output_power_v1::Event::Failed => None,
+            _ => return,
+        };
+        state.changed = true;
+    }
+}
+
+delegate_noop!(State: ZxdgOutputManagerV1);
+delegate_noop!(State: ZwlrOutputPowerManagerV1);
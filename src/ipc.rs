@@ -0,0 +1,239 @@
+// src/ipc.rs
+//! `ctl` commands over a Unix socket.
+//!
+//! One JSON request per connection, one JSON reply line back:
+//! `{"cmd": "config_set", "key": "smoothing_factor", "value": "0.2",
+//! "persist": false}` → `{"Ok": "0.2"}` or `{"Err": "..."}`. The socket is
+//! created with mode 0600 in a directory only the daemon's user can enter,
+//! so no other user can connect.
+// Only the client half builds without Unix sockets, and it always fails.
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, Config};
+use crate::control::Feature;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    ConfigGet {
+        key: String,
+    },
+    ConfigSet {
+        key: String,
+        value: String,
+        /// Also write the setting to the user's config file.
+        persist: bool,
+    },
+    /// Start a one-off ramp; see `ramps`.
+    Ramp {
+        brightness: f32,
+        at: String,
+    },
+    /// The daemon's state as JSON; see `state_dump`.
+    DumpState,
+    /// Today's summary from history; see `report`.
+    Report,
+    /// Current readings and what is holding brightness, if anything.
+    Status,
+    /// The run summary so far; see `stats`.
+    Stats,
+    /// Stop adjusting brightness, or start again.
+    Pause {
+        paused: bool,
+    },
+    /// Hold a brightness % until `Unlock`, or for `minutes`.
+    Lock {
+        brightness: f32,
+        minutes: Option<u64>,
+    },
+    Unlock,
+    /// Switch a feature off or back on until the daemon exits.
+    Toggle {
+        feature: Feature,
+        on: bool,
+    },
+}
+
+/// The applied value as TOML, or what went wrong.
+pub type Response = Result<String, String>;
+
+pub fn socket_path(cfg: &Config) -> PathBuf {
+    cfg.ipc_socket
+        .clone()
+        .unwrap_or_else(|| config::runtime_dir().join("control.sock"))
+}
+
+/// Write one setting into the config file `read_config` would load,
+/// leaving profiles and everything else as they are.
+fn persist(key: &str, value: &str) -> Result<(), String> {
+    let (cfg, _) = config::read_config().with_key(key, value)?;
+    config::save_config(&cfg).map_err(|e| format!("cannot save config: {}", e))
+}
+
+#[cfg(unix)]
+pub use unix::{request, serve};
+
+#[cfg(unix)]
+mod unix {
+    use std::error::Error;
+    use std::fs;
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use nix::sys::stat::{umask, Mode};
+    use nix::unistd::geteuid;
+    use tracing::{debug, info, warn};
+
+    use super::{persist, Request, Response};
+    use crate::control::Control;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Create `dir` as 0700 if it's missing, and refuse it unless it is
+    /// ours and closed to everyone else: without `XDG_RUNTIME_DIR` it lies
+    /// in the shared temp directory, where anyone could have made it first.
+    fn private_dir(dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        let meta = fs::symlink_metadata(dir)?;
+        if !meta.is_dir() || meta.uid() != geteuid().as_raw() || meta.mode() & 0o077 != 0 {
+            return Err(format!(
+                "{} must be a directory owned by this user with mode 0700",
+                dir.display()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Bind `path` and answer requests on a background thread.
+    pub fn serve(path: &Path, control: Arc<Control>) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            private_dir(dir)?;
+        }
+        // A socket nobody answers on is left over from a crash.
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(format!("{} is in use; is another daemon running?", path.display()).into());
+            }
+            fs::remove_file(path)?;
+        }
+        // Created 0600 rather than narrowed afterwards, so it is never open.
+        let old_mask = umask(Mode::from_bits_truncate(0o177));
+        let bound = UnixListener::bind(path);
+        umask(old_mask);
+        let listener =
+            bound.map_err(|e| format!("cannot listen on {}: {}", path.display(), e))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        info!(target: "ipc", "Listening for ctl commands on {}", path.display());
+        std::thread::Builder::new()
+            .name("ipc".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = handle(stream, &control) {
+                        debug!(target: "ipc", "request failed: {}", e);
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    fn handle(stream: UnixStream, control: &Control) -> Result<(), Box<dyn Error>> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let response: Response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::ConfigGet { key }) => control.config_key(&key),
+            Ok(Request::ConfigSet {
+                key,
+                value,
+                persist: save,
+            }) => control.set_config_key(&key, &value).map(|applied| {
+                info!(target: "ipc", "Setting {} = {}", key, applied);
+                // The setting is live either way; say so rather than fail.
+                match save.then(|| persist(&key, &value)) {
+                    Some(Err(e)) => {
+                        warn!(target: "ipc", "Setting {} applied but not saved: {}", key, e);
+                        format!("{} (applied, but not saved: {})", applied, e)
+                    }
+                    _ => applied,
+                }
+            }),
+            Ok(Request::Ramp { brightness, at }) => {
+                control.request_ramp(brightness, &at).inspect(|applied| {
+                    info!(target: "ipc", "Ramp requested: {}", applied);
+                })
+            }
+            Ok(Request::DumpState) => {
+                serde_json::to_string_pretty(&control.dump_state()).map_err(|e| e.to_string())
+            }
+            Ok(Request::Report) => control.report(),
+            Ok(Request::Status) => Ok(control.status()),
+            Ok(Request::Stats) => control.stats(),
+            Ok(Request::Pause { paused }) => {
+                control.set_paused(paused);
+                let applied = if paused { "paused" } else { "resumed" };
+                info!(target: "ipc", "Brightness control {}", applied);
+                Ok(applied.to_string())
+            }
+            Ok(Request::Lock {
+                brightness,
+                minutes,
+            }) => {
+                let duration = minutes.map(|m| Duration::from_secs(m * 60));
+                control.set_lock(brightness, duration).inspect(|applied| {
+                    info!(target: "ipc", "Brightness {}", applied);
+                })
+            }
+            Ok(Request::Unlock) => {
+                let applied = control.unlock();
+                info!(target: "ipc", "Brightness lock: {}", applied);
+                Ok(applied)
+            }
+            Ok(Request::Toggle { feature, on }) => {
+                let applied = control.set_bypass(feature, on);
+                info!(target: "ipc", "Turned {}", applied);
+                Ok(applied)
+            }
+            Err(e) => Err(format!("bad request: {}", e)),
+        };
+        let mut out = serde_json::to_string(&response)?;
+        out.push('\n');
+        (&stream).write_all(out.as_bytes())?;
+        Ok(())
+    }
+
+    /// Send `request` to the daemon listening on `path`.
+    pub fn request(path: &Path, request: &Request) -> Result<String, Box<dyn Error>> {
+        let mut stream = UnixStream::connect(path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound | ErrorKind::ConnectionRefused => {
+                format!("no daemon listening on {}", path.display())
+            }
+            _ => format!("cannot connect to {}: {}", path.display(), e),
+        })?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+        let mut reply = String::new();
+        BufReader::new(&stream).read_line(&mut reply)?;
+        let response: Response = serde_json::from_str(&reply)?;
+        Ok(response?)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn request(
+    _path: &std::path::Path,
+    _request: &Request,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Err("ctl needs Unix sockets, which this platform lacks".into())
+}
@@ -0,0 +1,14 @@
+// src/lib.rs
+//! The self-contained parts of the brightness engine: the luma → brightness
+//! curve, the luma average and the transition stepper. The daemon is built
+//! on them. With the `python` feature they are a Python module too, and
+//! with `ffi` a C library.
+pub mod curve;
+pub mod smooth_transition;
+pub mod smoothing;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
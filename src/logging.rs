@@ -1,101 +1,115 @@
-use std::fmt::Display;
+// src/logging.rs
+//! Log output for the daemon, built on `tracing`.
+//!
+//! The `logging` config level picks the default filter; `RUST_LOG` directives
+//! are layered on top, so `RUST_LOG=camera=trace` adds camera detail without
+//! turning everything else up. Events go to the console (warnings and errors
+//! on stderr) and, through [`FileLayer`], to the rotating log file.
+use std::fmt;
 use std::fs::{self, File};
 use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 
 use chrono::Local;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use tar::Builder;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer};
 
 use crate::config::LogLevel;
 
 const MAX_ARCHIVES: usize = 10;
 const LATEST_LOG: &str = "latest-log.txt";
 
-#[derive(Clone)]
-pub struct Logger {
-    level: LogLevel,
-    sink: Option<Arc<LogSink>>,
+/// Target of the periodic brightness/summary lines, shown from `low` upwards.
+pub const STATUS: &str = "status";
+
+/// Install the global subscriber. Safe to call more than once; later calls
+/// are ignored.
+pub fn init(level: LogLevel, path: Option<&str>) {
+    let file = match FileLayer::create(path) {
+        Ok(layer) => Some(layer),
+        Err(err) => {
+            eprintln!("Failed to initialize log file: {}", err);
+            None
+        }
+    };
+    let _ = tracing_subscriber::registry()
+        .with(filter(level))
+        .with(ConsoleLayer)
+        .with(file)
+        .try_init();
 }
 
-impl Logger {
-    pub fn new(level: LogLevel, path: Option<&str>) -> Self {
-        let sink = match LogSink::create(path) {
-            Ok(opt) => opt.map(Arc::new),
-            Err(err) => {
-                eprintln!("Failed to initialize log file: {}", err);
-                None
-            }
-        };
-        Self { level, sink }
+fn default_directives(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Off => "off",
+        LogLevel::Minimal => "warn",
+        LogLevel::Low => "warn,status=info",
+        LogLevel::Medium => "info",
+        LogLevel::High => "debug",
+        LogLevel::Verbose => "trace",
     }
+}
 
-    #[inline]
-    pub fn enabled(&self, level: LogLevel) -> bool {
-        level <= self.level
+fn filter(level: LogLevel) -> EnvFilter {
+    let base = default_directives(level);
+    let extra = std::env::var("RUST_LOG").unwrap_or_default();
+    if extra.trim().is_empty() {
+        return EnvFilter::new(base);
     }
+    EnvFilter::try_new(format!("{},{}", base, extra)).unwrap_or_else(|err| {
+        eprintln!("Ignoring invalid RUST_LOG ({}): {}", extra, err);
+        EnvFilter::new(base)
+    })
+}
 
-    #[inline]
-    pub fn info<F>(&self, f: F)
-    where
-        F: FnOnce() -> String,
-    {
-        self.log(LogLevel::Medium, Target::Stdout, f);
-    }
+/// Collects the formatted `message` field of an event.
+#[derive(Default)]
+struct Message(String);
 
-    #[inline]
-    pub fn status<F>(&self, f: F)
-    where
-        F: FnOnce() -> String,
-    {
-        self.log(LogLevel::Low, Target::Stdout, f);
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
     }
+}
 
-    #[inline]
-    pub fn warn<F>(&self, f: F)
-    where
-        F: FnOnce() -> String,
-    {
-        self.log(LogLevel::Minimal, Target::Stderr, f);
-    }
+fn message(event: &Event<'_>) -> String {
+    let mut msg = Message::default();
+    event.record(&mut msg);
+    msg.0
+}
 
-    #[inline]
-    pub fn error<E: Display>(&self, err: E) {
-        self.log(LogLevel::Minimal, Target::Stderr, || err.to_string());
-    }
+/// Plain messages on the terminal, as the daemon always printed them.
+struct ConsoleLayer;
 
-    fn log<F>(&self, level: LogLevel, target: Target, f: F)
-    where
-        F: FnOnce() -> String,
-    {
-        if !self.enabled(level) || level == LogLevel::Off {
-            return;
-        }
-        let msg = f();
-        match target {
-            Target::Stdout => println!("{}", msg),
-            Target::Stderr => eprintln!("{}", msg),
-        }
-        if let Some(sink) = &self.sink {
-            sink.write_line(level, &msg);
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let msg = message(event);
+        if *event.metadata().level() <= Level::WARN {
+            eprintln!("{}", msg);
+        } else {
+            println!("{}", msg);
         }
     }
 }
 
-#[derive(Clone, Copy)]
-enum Target {
-    Stdout,
-    Stderr,
-}
-
-struct LogSink {
+/// Timestamped copy of every event in `latest-log.txt`; the previous file is
+/// archived on startup.
+struct FileLayer {
     writer: Mutex<BufWriter<File>>,
 }
 
-impl LogSink {
-    fn create(path: Option<&str>) -> io::Result<Option<Self>> {
+impl FileLayer {
+    fn create(path: Option<&str>) -> io::Result<Self> {
         let (active_dir, archive_dir) = resolve_dirs(path)?;
         fs::create_dir_all(&active_dir)?;
         fs::create_dir_all(&archive_dir)?;
@@ -103,20 +117,52 @@ impl LogSink {
         let latest_path = active_dir.join(LATEST_LOG);
         rotate_logs(&latest_path, &archive_dir)?;
         let file = File::create(&latest_path)?;
-        Ok(Some(Self {
+        Ok(Self {
             writer: Mutex::new(BufWriter::new(file)),
-        }))
+        })
     }
+}
 
-    fn write_line(&self, level: LogLevel, msg: &str) {
+impl<S> Layer<S> for FileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let msg = message(event);
+        // Enclosing spans, outermost first: "calibrate > …".
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| span.name())
+                    .collect::<Vec<_>>()
+                    .join(" > ")
+            })
+            .unwrap_or_default();
         if let Ok(mut guard) = self.writer.lock() {
             let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
-            let _ = writeln!(guard, "[{}][{:?}] {}", timestamp, level, msg);
+            let _ = writeln!(
+                guard,
+                "[{}][{}][{}] {}{}{}",
+                timestamp,
+                meta.level(),
+                meta.target(),
+                spans,
+                if spans.is_empty() { "" } else { ": " },
+                msg
+            );
             let _ = guard.flush();
         }
     }
 }
 
+/// Directory `latest-log.txt` is written to, for `logging_path`.
+pub fn log_dir(custom: Option<&str>) -> PathBuf {
+    resolve_dirs(custom).map_or_else(|_| default_root(), |(active, _)| active)
+}
+
 fn resolve_dirs(custom: Option<&str>) -> io::Result<(PathBuf, PathBuf)> {
     if let Some(path) = custom {
         let base = expand_path(path);
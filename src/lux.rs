@@ -0,0 +1,54 @@
+// src/lux.rs
+use std::f32::consts::PI;
+
+use crate::config::Config;
+
+/// Reflected-light meter calibration constant (ISO 2720).
+const METER_K: f32 = 12.5;
+/// A "typical" scene averages to 18% reflectance.
+const MID_GREY: f32 = 0.18;
+/// Sensitivity assumed at unity gain.
+const BASE_ISO: f32 = 100.0;
+/// Frame luma is gamma-encoded; undo it before comparing to mid-grey.
+const GAMMA: f32 = 2.2;
+
+/// Exposure parameters of a frame as reported by the camera driver.
+#[derive(Debug, Clone, Copy)]
+pub struct Exposure {
+    pub time_secs: f32,
+    /// Analog gain as a multiple of the driver's default.
+    pub gain: f32,
+}
+
+/// Turns a frame's mean luma and exposure into approximate illuminance.
+///
+/// The camera acts as a reflected-light meter: exposure time, gain and the
+/// assumed aperture give the scene luminance that would render as mid-grey,
+/// which is scaled by how far the frame actually is from mid-grey and then
+/// converted to incident lux assuming an 18% grey scene. Webcams don't report
+/// their aperture and drivers disagree on gain units, so treat the result as
+/// ±50% at best; `lux_scale` corrects a constant offset against a meter.
+pub struct LuxEstimator {
+    aperture: f32,
+    scale: f32,
+}
+
+impl LuxEstimator {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            aperture: cfg.camera_aperture,
+            scale: cfg.lux_scale,
+        }
+    }
+
+    pub fn estimate(&self, luma: f32, exposure: Exposure) -> Option<f32> {
+        if exposure.time_secs <= 0.0 || exposure.gain <= 0.0 {
+            return None;
+        }
+        let iso = BASE_ISO * exposure.gain;
+        let mid_grey_luminance = METER_K * self.aperture * self.aperture / (exposure.time_secs * iso);
+        let linear = luma.clamp(1.0 / 255.0, 1.0).powf(GAMMA);
+        let luminance = mid_grey_luminance * linear / MID_GREY;
+        Some(luminance * PI / MID_GREY * self.scale)
+    }
+}
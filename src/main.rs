@@ -1,15 +1,52 @@
 // src/main.rs
+mod ab_test;
+mod als;
+mod audit;
 mod backlight;
 mod calibrate;
 mod camera;
+mod camera_test;
+mod circadian;
+mod cli;
 mod config;
+mod config_watch;
+mod control;
+mod controller;
+mod correction;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod devices;
+mod displays;
+mod export;
+mod expression;
+mod external_sensor;
+mod flight_recorder;
+mod history;
+mod hooks;
+mod i18n;
+mod integrations;
+mod ipc;
 mod logging;
-mod smooth_transition;
-mod smoothing;
+mod lux;
+mod masking;
+mod motion;
+mod override_file;
+mod presets;
+mod ramps;
+mod report;
+mod rules;
+mod sandbox;
+mod sensor;
+#[cfg(test)]
+mod simulation;
+mod state_dump;
+mod stats;
+mod telemetry;
 mod time_adjust;
 mod tui;
 
 use std::io;
+use std::path::PathBuf;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
@@ -17,51 +54,210 @@ use std::sync::{
 use std::thread;
 use std::time::{Duration, Instant};
 
+use ab_test::AbTest;
 use backlight::Backlight;
-use camera::Camera;
-use config::{read_config, Config, DaemonMode, LogLevel};
-use logging::Logger;
-use smooth_transition::SmoothTransition;
-use smoothing::Ema;
-use time_adjust::TimeAdjuster;
+use clap::Parser;
+use cli::{Cli, Command, ConfigAction, CtlCommand, Switch};
+use control::{Control, Feature};
+use controller::{Clock, Controller, SystemClock, Tick};
+use config::{read_config, Config, DaemonMode};
+use config_watch::ConfigWatch;
+use history::HistoryRecorder;
+use state_dump::LoopState;
+use hooks::{Event, Hooks};
+use sensor::AmbientInput;
+// From the library, so `crate::curve` and friends work as before.
+use smart_brightness::{curve, smooth_transition, smoothing};
+use stats::RunStats;
+use tracing::{error, info, info_span, warn};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Check for help flag
-    if std::env::args().any(|a| a == "--help" || a == "-h") {
+    let cli = Cli::parse();
+    if cli.help {
         print_help();
         return Ok(());
     }
 
-    let mut cfg = read_config();
+    if let Some(path) = cli.config.clone() {
+        config::set_config_path(path);
+    }
+
+    let (mut configure, mut calibrate) = (cli.configure, cli.calibrate);
+    let run = match cli.command {
+        Some(Command::Run(run)) => run,
+        Some(Command::Calibrate) => {
+            calibrate = true;
+            cli.run
+        }
+        Some(Command::Configure) => {
+            configure = true;
+            cli.run
+        }
+        Some(Command::Devices) => {
+            devices::print(&read_config());
+            return Ok(());
+        }
+        Some(Command::Status { socket }) => return send(socket, &ipc::Request::Status),
+        Some(Command::Set {
+            key,
+            value,
+            persist,
+            socket,
+        }) => {
+            return send(socket, &ipc::Request::ConfigSet {
+                key,
+                value,
+                persist,
+            });
+        }
+        Some(Command::Completions { shell }) => {
+            cli::write_completions(shell, &mut io::stdout())?;
+            return Ok(());
+        }
+        Some(Command::Profiles) => {
+            for name in read_config().profile_names() {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+        Some(Command::CameraTest) => {
+            if !camera_test::run(&read_config())? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Circadian { apply }) => {
+            circadian::report(read_config(), apply)?;
+            return Ok(());
+        }
+        Some(Command::Ctl { socket, command }) => {
+            let request = match command {
+                CtlCommand::Config {
+                    action: ConfigAction::Get { key },
+                } => ipc::Request::ConfigGet { key },
+                CtlCommand::Config {
+                    action: ConfigAction::Set {
+                        key,
+                        value,
+                        persist,
+                    },
+                } => ipc::Request::ConfigSet {
+                    key,
+                    value,
+                    persist,
+                },
+                CtlCommand::Ramp { brightness, at } => ipc::Request::Ramp { brightness, at },
+                CtlCommand::Lock {
+                    brightness,
+                    minutes,
+                } => ipc::Request::Lock {
+                    brightness,
+                    minutes,
+                },
+                CtlCommand::Unlock => ipc::Request::Unlock,
+                CtlCommand::Status => ipc::Request::Status,
+                CtlCommand::Stats => ipc::Request::Stats,
+                CtlCommand::Pause => ipc::Request::Pause { paused: true },
+                CtlCommand::Resume => ipc::Request::Pause { paused: false },
+                CtlCommand::DumpState => ipc::Request::DumpState,
+                CtlCommand::Report => ipc::Request::Report,
+                CtlCommand::Circadian { state } => ipc::Request::Toggle {
+                    feature: Feature::Circadian,
+                    on: state == Switch::On,
+                },
+                CtlCommand::Smoothing { state } => ipc::Request::Toggle {
+                    feature: Feature::Smoothing,
+                    on: state == Switch::On,
+                },
+            };
+            return send(socket, &request);
+        }
+        None => cli.run,
+    };
+
+    // A dump brings its own config, calibration included.
+    let mut resume = None;
+    let mut cfg = match run.load_state.as_deref() {
+        Some(path) => {
+            let dump = state_dump::load(path)?;
+            eprintln!("Loaded state from {} ({}, version {})", path.display(), dump.time, dump.version);
+            resume = Some(dump.state);
+            dump.config
+        }
+        None => read_config(),
+    };
 
     // Check for configure flag
-    if std::env::args().any(|a| a == "--configure") {
+    if configure {
         tui::run(cfg)?;
         return Ok(());
     }
 
-    let logger = Logger::new(cfg.logging, cfg.logging_path.as_deref());
-    let calibrate_requested = std::env::args().any(|a| a == "--calibrate");
+    logging::init(cfg.logging, cfg.logging_path.as_deref());
+    flight_recorder::install(&cfg);
 
-    if calibrate_requested {
-        logger.info(|| "Calibration requested via --calibrate".into());
+    if calibrate {
+        info!(target: "daemon", "Calibration requested via --calibrate");
         calibrate::run(cfg)?;
-        logger.info(|| "Calibration completed.".into());
+        info!(target: "daemon", "Calibration completed.");
         return Ok(());
     }
 
     if !cfg.calibrated {
-        logger.info(|| "No calibration found. Running automatic first-time calibration…".into());
+        info!(target: "daemon", "No calibration found. Running automatic first-time calibration…");
         cfg = calibrate::run(cfg)?;
-        logger.info(|| "Initial calibration completed.".into());
+        info!(target: "daemon", "Initial calibration completed.");
+    }
+
+    // Applied after calibration so the wizard never saves profile overrides
+    // into the top-level config. The mode's section comes first; a preset
+    // is the starting point a profile can refine.
+    let mode = cfg.effective_mode();
+    cfg = cfg
+        .with_mode(mode)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let base_cfg = cfg.clone();
+    if let Some(name) = run.preset.as_deref() {
+        cfg = cfg
+            .with_preset(name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        cfg.active_profile = Some(name.to_string());
+        info!(target: "daemon", "Using preset '{}'", name);
+    }
+    if let Some(name) = run.profile.as_deref() {
+        cfg = cfg
+            .with_profile(name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        cfg.active_profile = Some(name.to_string());
+        info!(target: "daemon", "Using profile '{}'", name);
     }
 
     if let Err(e) = cfg.validate() {
         let msg = format!("invalid config: {}", e);
-        logger.error(msg.clone());
+        error!(target: "daemon", "{}", msg);
         return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
     }
 
+    let mut ab_test = if run.ab_test.is_empty() {
+        None
+    } else {
+        let test = AbTest::load(&cfg, &run.ab_test, run.interval)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let (name, variant) = test.current();
+        info!(
+            target: "daemon",
+            "A/B test: {} and {} take turns every {}s, starting with '{}'",
+            run.ab_test[0].display(),
+            run.ab_test[1].display(),
+            run.interval.as_secs(),
+            name
+        );
+        cfg = variant.clone();
+        Some(test)
+    };
+
+    circadian::auto_tune(&mut cfg);
+
     // Handle interval_boot override
     // If enabled, we treat the current run as 'Interval' regardless of config.mode (unless overridden)
     // Actually, usually this means "on boot, if we are in boot mode, forces interval".
@@ -71,11 +267,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // However, usually "on boot" just means "when the daemon starts".
     // So if `interval_boot` is true, we override `cfg.mode` to `DaemonMode::Interval`.
     if cfg.interval_boot {
-        logger.info(|| "interval_boot is true: Forcing Interval mode.".into());
+        info!(target: "daemon", "interval_boot is true: Forcing Interval mode.");
         cfg.mode = DaemonMode::Interval;
     }
 
-    logger.info(|| format!("Starting Smart Brightness in {:?} mode", cfg.mode));
+    info!(target: "daemon", "Starting Smart Brightness in {:?} mode", cfg.mode);
+    // Restores the desktop's automatic brightness when main returns.
+    let _auto_brightness = integrations::take_over_auto_brightness(&cfg);
 
     // Ctrl-C handling
     let running = Arc::new(AtomicBool::new(true));
@@ -84,37 +282,101 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         ctrlc::set_handler(move || r.store(false, Ordering::SeqCst))?;
     }
 
+    let mut stats = RunStats::new();
+    let mut history = HistoryRecorder::from_config(&cfg);
+    if let Some(test) = &ab_test {
+        history.set_variant(test.current().0);
+    }
+    let control = Arc::new(Control::new(base_cfg, cfg.clone()));
+    // A dump's config is not the file's.
+    if run.load_state.is_none()
+        && let Some(path) = config::config_path()
+    {
+        control.watch_config(ConfigWatch::new(path));
+    }
+    start_dashboard(&cfg, &control);
+    start_ipc(&cfg, &control);
+    let control = Some(&control);
+
+    let mut devices = Devices::open(&cfg)?;
+    devices.resume = resume;
+    if let Some(control) = control {
+        control.set_devices(state_dump::Devices {
+            backlight: devices.backlight.name(),
+            backlight_actual: devices.backlight.actual_source(),
+            sensor: devices.sensor.name(),
+        });
+    }
     match cfg.mode {
         DaemonMode::Realtime => {
-            run_brightness_loop(&cfg, &logger, running, None)?;
+            devices = run_brightness_loop(
+                &cfg,
+                devices,
+                running,
+                None,
+                control,
+                &mut stats,
+                &mut history,
+                ab_test.as_mut(),
+            )?;
         }
         DaemonMode::Boot => {
             let duration = Duration::from_secs_f64(cfg.run_duration);
-            logger.info(|| format!("Running for {:.1} seconds...", cfg.run_duration));
-            run_brightness_loop(&cfg, &logger, running, Some(duration))?;
+            info!(target: "daemon", "Running for {:.1} seconds...", cfg.run_duration);
+            devices = run_brightness_loop(
+                &cfg,
+                devices,
+                running,
+                Some(duration),
+                control,
+                &mut stats,
+                &mut history,
+                ab_test.as_mut(),
+            )?;
         }
         DaemonMode::Interval => {
             let run_duration = Duration::from_secs_f64(cfg.run_duration);
             let pause_interval = Duration::from_secs_f64(cfg.pause_interval);
+            let mut hooks = Hooks::from_config(&cfg);
 
+            let mut first = true;
             while running.load(Ordering::SeqCst) {
-                logger.info(|| "Interval: Active phase started".into());
-                // We need a fresh 'running' signal for the inner loop if we want to support clean shutdown,
-                // but the inner loop checks 'running' anyway.
-                // However, the inner loop returns when duration expires.
-                // We should pass the same 'running' flag so Ctrl-C breaks the inner loop immediately.
-                
-                run_brightness_loop(&cfg, &logger, running.clone(), Some(run_duration))?;
+                info!(target: "daemon", "Interval: Active phase started");
+                if !first {
+                    devices.sensor.warmup(cfg.warmup_frames, cfg.warmup_tolerance);
+                }
+                first = false;
+                // Pass the same 'running' flag so Ctrl-C breaks the inner
+                // loop immediately; it also returns when the duration expires.
+                devices = run_brightness_loop(
+                    &cfg,
+                    devices,
+                    running.clone(),
+                    Some(run_duration),
+                    control,
+                    &mut stats,
+                    &mut history,
+                    ab_test.as_mut(),
+                )?;
 
                 if !running.load(Ordering::SeqCst) {
                    break;
                 }
 
-                logger.info(|| format!("Interval: Sleeping for {:.1} seconds...", cfg.pause_interval));
+                info!(target: "daemon", "Interval: Sleeping for {:.1} seconds...", cfg.pause_interval);
+                hooks.fire(
+                    Event::Pause,
+                    vec![("SB_PAUSE_SECS", format!("{:.1}", cfg.pause_interval))],
+                );
+                // The devices stay open, but the camera is powered off.
+                devices.sensor.pause();
                 
-                // Sleep with check for interrupt
+                // Sleep with check for interrupt. Time in suspend counts:
+                // `Instant` stands still meanwhile.
                 let sleep_start = Instant::now();
-                while sleep_start.elapsed() < pause_interval {
+                let suspended = SystemClock.suspended();
+                let slept = || sleep_start.elapsed() + SystemClock.suspended().saturating_sub(suspended);
+                while slept() < pause_interval {
                      if !running.load(Ordering::SeqCst) {
                          break;
                      }
@@ -123,363 +385,421 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     }
+    stats.backlight_writes += devices.backlight.writes();
 
-    logger.info(|| "Smart Brightness – stopped".into());
+    stats.log_summary();
+    if let Some(test) = &ab_test {
+        test.log_summary();
+    }
+    info!(target: "daemon", "Smart Brightness – stopped");
     Ok(())
 }
 
-fn run_brightness_loop(
-    cfg: &Config,
-    logger: &Logger,
-    running: Arc<AtomicBool>,
-    max_duration: Option<Duration>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let start_time = Instant::now();
-    
-    let bl = Backlight::resolve(cfg)?;
-    let hardware_max = bl.max_value;
-    let hardware_min = bl.min_value();
+/// Send `request` to the daemon on `socket`, or the one from the config,
+/// and print the reply.
+fn send(socket: Option<PathBuf>, request: &ipc::Request) -> Result<(), Box<dyn std::error::Error>> {
+    let socket = socket.unwrap_or_else(|| ipc::socket_path(&read_config()));
+    match ipc::request(&socket, request) {
+        Ok(value) => println!("{}", value),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
 
-    let real_min = cfg.real_min_brightness;
-    let real_max = cfg.real_max_brightness;
-    let range_u32 = real_max - real_min;
-    let range_f32 = range_u32 as f32;
+#[cfg(feature = "dashboard")]
+fn start_dashboard(cfg: &Config, control: &Arc<Control>) {
+    let Some(addr) = cfg.dashboard_listen.as_deref() else { return };
+    if let Err(e) = dashboard::serve(addr, control.clone()) {
+        error!(target: "dashboard", "{}", e);
+    }
+}
+
+#[cfg(not(feature = "dashboard"))]
+fn start_dashboard(cfg: &Config, _control: &Arc<Control>) {
+    if cfg.dashboard_listen.is_some() {
+        warn!(
+            target: "dashboard",
+            "dashboard_listen is set, but this build has no dashboard (enable the `dashboard` feature)"
+        );
+    }
+}
 
-    logger.info(|| {
-        format!(
+#[cfg(unix)]
+fn start_ipc(cfg: &Config, control: &Arc<Control>) {
+    if !cfg.ipc_enabled {
+        return;
+    }
+    if let Err(e) = ipc::serve(&ipc::socket_path(cfg), control.clone()) {
+        warn!(target: "ipc", "ctl commands unavailable: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn start_ipc(_cfg: &Config, _control: &Arc<Control>) {}
+
+/// The ambient sensor and backlight, opened once and reused by every
+/// active phase in interval mode.
+struct Devices {
+    sensor: AmbientInput,
+    backlight: Backlight,
+    /// State for the next run to pick up (`--load-state`).
+    resume: Option<LoopState>,
+}
+
+impl Devices {
+    fn open(cfg: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let bl = Backlight::resolve(cfg)?;
+        let hardware_max = bl.max_value - bl.pixel_levels();
+        let hardware_min = bl.min_value();
+
+        let real_min = cfg.real_min_brightness;
+        let real_max = cfg.real_max_brightness;
+
+        info!(
+            target: "daemon",
             "Hardware brightness range: {} → {} (max possible)",
-            hardware_min, hardware_max
-        )
-    });
-    logger.info(|| {
-        format!(
+            hardware_min,
+            hardware_max
+        );
+        info!(
+            target: "daemon",
             "Configured brightness range: {} → {} (from calibration)",
-            real_min, real_max
-        )
-    });
-    
-    // Warn if configured range seems limited
-    if real_min > hardware_min + 10 {
-        logger.warn(|| {
-            format!(
+            real_min,
+            real_max
+        );
+
+        // Warn if configured range seems limited
+        if real_min > hardware_min + 10 {
+            warn!(
+                target: "daemon",
                 "⚠ Configured minimum ({}) is significantly above hardware minimum ({}). \
                  Run calibration to use full range.",
-                real_min, hardware_min
-            )
-        });
-    }
-    if real_max < hardware_max - 10 {
-        logger.info(|| {
-            format!(
+                real_min,
+                hardware_min
+            );
+        }
+        check_floor(cfg, bl.floor(), hardware_max)?;
+        if real_max < hardware_max - 10 {
+            info!(
+                target: "daemon",
                 "ℹ Configured maximum ({}) is below hardware maximum ({}). \
                  This is normal if set during calibration.",
-                real_max, hardware_max
-            )
-        });
-    }
+                real_max,
+                hardware_max
+            );
+        }
 
-    logger.info(|| {
-        format!(
-            "Config: smoothing={:.3}, circadian_enabled={}, min_luma_delta={:.3}, status_interval={}s, fast_interval={:.2}s",
+        info!(
+            target: "daemon",
+            "Config: smoothing={:.3}, circadian_enabled={}, min_luma_delta={:.3}, status_min_change={}",
             cfg.smoothing_factor,
             cfg.enable_circadian,
             cfg.min_luma_delta,
-            cfg.status_interval_secs,
-            cfg.status_fast_interval_secs,
-        )
-    });
+            cfg.status_threshold,
+        );
+
+        let mut sensor = AmbientInput::open(cfg)?;
+        sensor.warmup(cfg.warmup_frames, cfg.warmup_tolerance);
+        Ok(Self {
+            sensor,
+            backlight: bl,
+            resume: None,
+        })
+    }
+}
 
-    let (w, h) = (cfg.resolution[0], cfg.resolution[1]);
-    let mut cam = Camera::open(cfg.camera_device, w, h)?;
-    cam.warmup(cfg.warmup_frames);
+/// Hold `backlight_floor_percent` against the calibrated range and the
+/// curve: a floor above the range leaves nothing to adjust, one inside it
+/// flattens the curve below some luma.
+fn check_floor(cfg: &Config, floor: u32, hardware_max: u32) -> Result<(), String> {
+    let (real_min, real_max) = (cfg.real_min_brightness, cfg.real_max_brightness);
+    if floor == 0 || floor <= real_min {
+        return Ok(());
+    }
+    if floor >= real_max {
+        return Err(format!(
+            "backlight_floor_percent puts the floor at {} of {}, at or above \
+             screen_brightness_max ({}); brightness could never change",
+            floor, hardware_max, real_max
+        ));
+    }
+    let fraction = (floor - real_min) as f32 / (real_max - real_min) as f32;
+    let cut = (0..=100)
+        .map(|i| i as f32 / 100.0)
+        .take_while(|&luma| cfg.brightness_fraction(luma) < fraction)
+        .last();
+    if let Some(luma) = cut {
+        warn!(
+            target: "daemon",
+            "Backlight floor {} is above screen_brightness_min ({}); the curve is flat \
+             at the floor up to ambient luma {:.2}",
+            floor,
+            real_min,
+            luma
+        );
+    }
+    Ok(())
+}
 
-    let mut ema = Ema::new(cfg.smoothing_factor);
+/// Run until `max_duration` expires or `running` is cleared, then hand the
+/// devices back for the next run.
+fn run_brightness_loop(
+    cfg: &Config,
+    devices: Devices,
+    running: Arc<AtomicBool>,
+    max_duration: Option<Duration>,
+    control: Option<&Arc<Control>>,
+    stats: &mut RunStats,
+    history: &mut HistoryRecorder,
+    mut ab_test: Option<&mut AbTest>,
+) -> Result<Devices, Box<dyn std::error::Error>> {
+    let _span = info_span!("brightness_loop").entered();
+
+    let Devices {
+        sensor,
+        backlight: bl,
+        mut resume,
+    } = devices;
+    let hardware_max = bl.max_value;
+    let real_min = cfg.real_min_brightness;
+    let real_max = cfg.real_max_brightness + bl.pixel_levels();
+    // Read back every run: the level may have been changed while paused.
     let start_val = bl
         .actual()
         .or_else(|| bl.current())
         .unwrap_or(real_min)
         .clamp(real_min, real_max);
-    let mut transition = SmoothTransition::new(
-        start_val,
-        cfg.smooth_interval_ms,
-        cfg.smooth_step_divisor,
-        cfg.smooth_max_step,
-    );
-    let mut status = StatusReporter::new(
-        start_val,
-        logger.clone(),
-        cfg.status_interval_secs,
-        cfg.status_threshold,
-        cfg.status_fast_interval_secs,
-        cfg.status_fast_threshold,
-        cfg.log_target_brightness,
-        cfg.status_log_only_on_change,
-    );
-    let circadian = TimeAdjuster::from_config(cfg);
-
-    let capture_interval = Duration::from_millis(cfg.capture_interval_ms);
-    let mut last_capture = Instant::now() - capture_interval;
-    let mut capture_errors = ErrorThrottle::new(
-        Duration::from_secs(cfg.error_throttle_secs),
-        logger.clone(),
-        LogLevel::Minimal,
-    );
-
-    let mut last_adjusted_luma = 0.0f32;
-    let mut has_luma = false;
-
-    while running.load(Ordering::SeqCst) {
-        // Check duration
-        if let Some(limit) = max_duration {
-            if start_time.elapsed() >= limit {
-                logger.info(|| "Run duration expired.".into());
-                break;
-            }
+    let started = Instant::now();
+    // A profile or setting picked from a front end outlives this run
+    // (interval mode), and so does the A/B test variant in use.
+    let mut switched_cfg = control.and_then(|c| c.switched_config()).map(tuned);
+    if let Some(test) = ab_test.as_deref() {
+        switched_cfg = Some(tuned(test.current().1.clone()));
+    }
+    let mut parts = (sensor, bl, start_val);
+    loop {
+        let (sensor, bl, start_val) = parts;
+        let remaining = max_duration.map(|d| d.saturating_sub(started.elapsed()));
+        let mut controller = Controller::new(
+            switched_cfg.as_ref().unwrap_or(cfg),
+            sensor,
+            bl,
+            SystemClock,
+            start_val,
+            hardware_max,
+            remaining,
+        );
+        if let Some(control) = control {
+            controller.set_control(control.clone());
+        }
+        // The grace period is for the first readings after startup only.
+        if switched_cfg.is_some() {
+            controller.skip_grace();
+        }
+        if let Some(state) = resume.take() {
+            controller.restore(&state);
         }
 
-        let mut work_done = false;
-
-        // 1. Capture new frame at configured rate
-        if last_capture.elapsed() >= capture_interval {
-            match cam.measure_luma(cfg.half_precision) {
-                Ok(raw_luma) => {
-                    let normalized = normalize_luma(cfg, raw_luma);
-                    let smoothed = ema.update(normalized);
-                    let adjusted = apply_circadian(cfg, &circadian, smoothed);
-                    if let Some(target) = update_brightness(
-                        adjusted,
-                        &mut has_luma,
-                        &mut last_adjusted_luma,
-                        cfg.min_luma_delta,
-                        range_f32,
-                        real_min,
-                        real_max,
-                        hardware_max,
-                    ) {
-                        transition.set_target(target, hardware_max);
-                    }
+        let mut switch = None;
+        let mut reopen = false;
+        while running.load(Ordering::SeqCst) {
+            if let Some(next) = control.and_then(|c| c.take_profile_switch()) {
+                match next {
+                    Ok(next) => match with_variant(ab_test.as_deref_mut(), next) {
+                        Ok(next) => {
+                            info!(
+                                target: "daemon",
+                                "Switching to profile '{}'",
+                                next.active_profile.as_deref().unwrap_or_default()
+                            );
+                            switch = Some(next);
+                            break;
+                        }
+                        Err(e) => error!(target: "daemon", "Cannot switch profile: {}", e),
+                    },
+                    Err(e) => error!(target: "daemon", "Cannot switch profile: {}", e),
                 }
-                Err(err) => {
-                    capture_errors.log("Camera capture failed", err);
+            }
+            if let Some(next) = control.and_then(|c| c.take_reloaded()) {
+                match next {
+                    Ok(next) => match with_variant(ab_test.as_deref_mut(), next) {
+                        Ok(next) => {
+                            info!(target: "daemon", "Config file changed; applying it");
+                            reopen = switched_cfg.as_ref().unwrap_or(cfg).sensor_changed(&next);
+                            switch = Some(next);
+                            break;
+                        }
+                        Err(e) => warn!(target: "daemon", "Ignoring changed config file: {}", e),
+                    },
+                    Err(e) => warn!(target: "daemon", "Ignoring changed config file: {}", e),
                 }
             }
-            last_capture = Instant::now();
-            work_done = true;
-        }
-
-        // Always update status, regardless of capture interval
-        status.record(transition.current_value(), last_adjusted_luma);
-
-        // 2. Apply smooth step
-        if let Some(val) = transition.update() {
-            let _ = bl.set(val);
-            work_done = true;
-        }
-
-        // 3. Sleep just enough to wait for the next due event
-        if !work_done {
-            let since_capture = last_capture.elapsed();
-            let capture_wait = if since_capture >= capture_interval {
-                Duration::from_millis(0)
-            } else {
-                capture_interval - since_capture
-            };
-            let smooth_wait = transition.time_until_next_step();
-            let sleep_for = capture_wait.min(smooth_wait).min(Duration::from_millis(10));
-            if sleep_for.is_zero() {
-                std::thread::yield_now();
-            } else {
-                thread::sleep(sleep_for);
+            if let Some(next) = control.and_then(|c| c.take_reconfigured()) {
+                info!(target: "daemon", "Applying changed settings");
+                switch = Some(next);
+                break;
+            }
+            if let Some((name, next)) = ab_test.as_deref_mut().and_then(|t| t.advance()) {
+                info!(target: "daemon", "A/B test: switching to '{}'", name);
+                history.set_variant(name);
+                switch = Some(next.clone());
+                break;
+            }
+            match controller.tick(stats, history) {
+                Tick::Expired => {
+                    info!(target: "daemon", "Run duration expired.");
+                    break;
+                }
+                Tick::Busy => {}
+                // Sleep just enough to wait for the next due event
+                Tick::Idle(wait) => {
+                    let sleep_for = wait.min(Duration::from_millis(10));
+                    if sleep_for.is_zero() {
+                        std::thread::yield_now();
+                    } else {
+                        thread::sleep(sleep_for);
+                    }
+                }
             }
         }
-    }
-    
-    // Safety check: ensure we didn't crash
-    Ok(())
-}
-
-struct StatusReporter {
-    last_value: u32,
-    last_luma: f32,
-    last_print: Instant,
-    base_interval: Duration,
-    base_threshold: u32,
-    fast_interval: Duration,
-    fast_threshold: u32,
-    logger: Logger,
-    level: LogLevel,
-    enabled: bool,
-    only_on_change: bool,
-}
-
-impl StatusReporter {
-    fn new(
-        initial: u32,
-        logger: Logger,
-        interval_secs: u64,
-        threshold: u32,
-        fast_interval_secs: f64,
-        fast_threshold: u32,
-        enabled: bool,
-        only_on_change: bool,
-    ) -> Self {
-        let base_interval = Duration::from_secs(interval_secs.max(1));
-        Self {
-            last_value: initial,
-            last_luma: 0.0,
-            // Initialize as if the last print was one full interval ago so that
-            // the first significant brightness change can be logged promptly.
-            last_print: Instant::now() - base_interval,
-            base_interval,
-            base_threshold: threshold.max(1),
-            fast_interval: Duration::from_secs_f64(fast_interval_secs),
-            fast_threshold: fast_threshold.max(1),
-            logger,
-            level: LogLevel::Low,
-            enabled,
-            only_on_change,
-        }
-    }
 
-    fn record(&mut self, brightness: u32, normalized_luma: f32) {
-        if !self.enabled {
-            self.last_value = brightness;
-            self.last_luma = normalized_luma;
-            return;
-        }
-        let now = Instant::now();
-        let delta = brightness.abs_diff(self.last_value);
-        let interval = if delta >= self.fast_threshold {
-            self.fast_interval
-        } else {
-            self.base_interval
+        parts = controller.into_parts();
+        let Some(next) = switch else {
+            let (sensor, backlight, _) = parts;
+            return Ok(Devices {
+                sensor,
+                backlight,
+                resume: None,
+            });
         };
-        let changed = delta >= self.base_threshold;
-        let expired = now.duration_since(self.last_print) >= interval;
-        let should_log = if self.only_on_change {
-            changed && expired
-        } else {
-            changed || expired
-        };
-        if should_log {
-            if self.logger.enabled(self.level) {
-                let value = brightness;
-                let luma = normalized_luma;
-                self.logger
-                    .status(|| format!("→ Target brightness {} (normalized {:.3})", value, luma));
+        if reopen {
+            let (sensor, bl, val) = parts;
+            let current = switched_cfg.as_ref().unwrap_or(cfg);
+            parts = (reopen_sensor(sensor, current, &next)?, bl, val);
+            if let Some(control) = control {
+                control.set_devices(state_dump::Devices {
+                    backlight: parts.1.name(),
+                    backlight_actual: parts.1.actual_source(),
+                    sensor: parts.0.name(),
+                });
             }
-            self.last_value = brightness;
-            self.last_luma = normalized_luma;
-            self.last_print = now;
-        } else {
-            self.last_luma = normalized_luma;
         }
+        switched_cfg = Some(tuned(next));
     }
 }
 
-struct ErrorThrottle {
-    last_log: Option<Instant>,
-    interval: Duration,
-    logger: Logger,
-    level: LogLevel,
-}
-
-impl ErrorThrottle {
-    fn new(interval: Duration, logger: Logger, level: LogLevel) -> Self {
-        Self {
-            last_log: None,
-            interval,
-            logger,
-            level,
-        }
-    }
-
-    fn log<E: std::fmt::Display>(&mut self, context: &str, err: E) {
-        let should_log = self
-            .last_log
-            .map(|t| t.elapsed() >= self.interval)
-            .unwrap_or(true);
-        if should_log && self.logger.enabled(self.level) {
-            let msg = format!("{}: {}", context, err);
-            self.logger.warn(|| msg);
-            self.last_log = Some(Instant::now());
-        }
+/// `next` with the A/B test's current set laid over it, while one runs, so
+/// samples stay tagged with the set actually in use.
+fn with_variant(test: Option<&mut AbTest>, next: Config) -> Result<Config, String> {
+    match test {
+        Some(test) => test.rebase(&next).map(|(_, cfg)| cfg.clone()),
+        None => Ok(next),
     }
 }
 
-fn normalize_luma(cfg: &config::Config, raw: f32) -> f32 {
-    if let (Some(min), Some(max)) = (cfg.camera_min_luma, cfg.camera_max_luma) {
-        if max > min {
-            return ((raw - min) / (max - min)).clamp(0.0, 1.0);
-        }
-    }
-    raw
+/// `cfg` after `circadian_auto_tune`, as the run started with it.
+fn tuned(mut cfg: Config) -> Config {
+    circadian::auto_tune(&mut cfg);
+    cfg
 }
 
-fn apply_circadian(cfg: &config::Config, circadian: &TimeAdjuster, smoothed: f32) -> f32 {
-    if cfg.enable_circadian {
-        circadian.adjust(smoothed)
-    } else {
-        smoothed
-    }
-}
-
-fn update_brightness(
-    adjusted: f32,
-    has_luma: &mut bool,
-    last_adjusted_luma: &mut f32,
-    min_luma_delta: f32,
-    range_f32: f32,
-    real_min: u32,
-    real_max: u32,
-    hardware_max: u32,
-) -> Option<u32> {
-    let luma_delta = if *has_luma {
-        (adjusted - *last_adjusted_luma).abs()
-    } else {
-        f32::MAX
+/// Close `sensor` and open it with the settings of `next`; if that fails,
+/// go back to those of `current`.
+fn reopen_sensor(
+    sensor: AmbientInput,
+    current: &Config,
+    next: &Config,
+) -> Result<AmbientInput, Box<dyn std::error::Error>> {
+    info!(target: "daemon", "Sensor settings changed; opening the sensor again");
+    // The camera can only be opened once.
+    drop(sensor);
+    let mut sensor = match AmbientInput::open(next) {
+        Ok(sensor) => sensor,
+        Err(e) => {
+            error!(target: "daemon", "Cannot open the sensor with the new settings: {}", e);
+            AmbientInput::open(current)?
+        }
     };
-    if *has_luma && luma_delta < min_luma_delta {
-        *last_adjusted_luma = adjusted;
-        return None;
-    }
-    *has_luma = true;
-    *last_adjusted_luma = adjusted;
-    let mapped = adjusted.mul_add(range_f32, real_min as f32).round() as u32;
-    let final_target = mapped.clamp(real_min, real_max).min(hardware_max);
-    Some(final_target)
+    sensor.warmup(next.warmup_frames, next.warmup_tolerance);
+    Ok(sensor)
 }
 
 fn print_help() {
-    println!("Smart Brightness - Automatic screen brightness adjustment");
+    println!("{}", tr!("help-title"));
     println!();
-    println!("USAGE:");
-    println!("    smart_brightness [OPTIONS]");
+    println!("{}", tr!("help-usage"));
+    println!("    smart_brightness [run] [OPTIONS]");
+    println!("    smart_brightness <COMMAND> [--help]");
     println!();
-    println!("OPTIONS:");
-    println!("    --configure     Launch TUI configuration interface");
-    println!("    --calibrate     Run calibration wizard to detect camera sensitivity");
-    println!("                    and monitor brightness range");
-    println!("    -h, --help      Display this help message");
+    println!("{}", tr!("help-options"));
+    println!("    --config <FILE> {}", tr!("help-opt-config"));
+    println!("    -p, --profile <NAME>");
+    println!("                    {}", tr!("help-opt-profile"));
+    println!("    --preset <low-vision|power-saver|responsive>");
+    println!("                    {}", tr!("help-opt-preset"));
+    println!("    --load-state <FILE>");
+    println!("                    {}", tr!("help-opt-load-state"));
+    println!("    --ab-test <A.toml> <B.toml> [--interval <10m>]");
+    println!("                    {}", tr!("help-opt-ab-test"));
+    println!("    -h, --help      {}", tr!("help-opt-help"));
     println!();
-    println!("CONFIGURATION:");
-    println!("    Config files are loaded from (in order):");
+    println!("{}", tr!("help-commands"));
+    println!("    run [OPTIONS]   {}", tr!("help-cmd-run"));
+    println!("    calibrate       {}", tr!("help-opt-calibrate"));
+    println!("                    {}", tr!("help-opt-calibrate-cont"));
+    println!("    configure       {}", tr!("help-opt-configure"));
+    println!("    status          {}", tr!("help-cmd-ctl-status"));
+    println!("    set <KEY> <VALUE> [--persist]");
+    println!("                    {}", tr!("help-cmd-ctl"));
+    println!("    devices         {}", tr!("help-cmd-devices"));
+    println!("    completions <bash|zsh|fish>");
+    println!("                    {}", tr!("help-cmd-completions"));
+    println!("    profiles        {}", tr!("help-cmd-profiles"));
+    println!("    camera-test     {}", tr!("help-cmd-camera-test"));
+    println!("    circadian [--apply]");
+    println!("                    {}", tr!("help-cmd-circadian"));
+    println!("    ctl config get <KEY>");
+    println!("    ctl config set <KEY> <VALUE> [--persist]");
+    println!("                    {}", tr!("help-cmd-ctl"));
+    println!("    ctl ramp <PERCENT> <HH:MM>");
+    println!("                    {}", tr!("help-cmd-ctl-ramp"));
+    println!("    ctl lock <PERCENT> [--for <MINUTES>] | ctl unlock");
+    println!("                    {}", tr!("help-cmd-ctl-lock"));
+    println!("    ctl status      {}", tr!("help-cmd-ctl-status"));
+    println!("    ctl pause | ctl resume");
+    println!("                    {}", tr!("help-cmd-ctl-pause"));
+    println!("    ctl dump-state  {}", tr!("help-cmd-ctl-dump-state"));
+    println!("    ctl report      {}", tr!("help-cmd-ctl-report"));
+    println!("    ctl circadian|smoothing on|off");
+    println!("                    {}", tr!("help-cmd-ctl-toggle"));
+    println!();
+    println!("{}", tr!("help-configuration"));
+    println!("    {}", tr!("help-config-order"));
     println!("      1. ~/.config/smart-brightness/config.toml");
     println!("      2. /etc/smart-brightness/config.toml");
-    println!("      3. ./config.toml (current directory)");
+    println!("      3. ./config.toml {}", tr!("help-config-cwd"));
+    println!();
+    println!("{}", tr!("help-daemon-modes"));
+    println!("    realtime    - {}", tr!("help-mode-realtime"));
+    println!("    boot        - {}", tr!("help-mode-boot"));
+    println!("    interval    - {}", tr!("help-mode-interval"));
+    println!();
+    println!("{}", tr!("help-examples"));
+    println!("    {}", tr!("help-example-calibrate"));
+    println!("    smart_brightness calibrate");
     println!();
-    println!("DAEMON MODES:");
-    println!("    realtime    - Continuously adjust brightness (default)");
-    println!("    boot        - Run for specified duration then exit");
-    println!("    interval    - Run for duration, pause, then repeat");
+    println!("    {}", tr!("help-example-completions"));
+    println!("    smart_brightness completions bash > ~/.local/share/bash-completion/completions/smart-brightness");
     println!();
-    println!("EXAMPLES:");
-    println!("    # Run calibration");
-    println!("    smart_brightness --calibrate");
+    println!("    {}", tr!("help-example-ctl"));
+    println!("    smart_brightness ctl config set smoothing_factor 0.2");
     println!();
-    println!("    # Run in realtime mode (reads from config)");
+    println!("    {}", tr!("help-example-run"));
     println!("    smart_brightness");
     println!();
-    println!("For more information, visit:");
+    println!("{}", tr!("help-more-info"));
     println!("    https://github.com/CodeByHardik/Smart-Brightness");
 }
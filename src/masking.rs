@@ -0,0 +1,104 @@
+// src/masking.rs
+use std::time::Instant;
+
+/// Coarse grid the frame is divided into for region statistics.
+pub const GRID_COLS: usize = 16;
+pub const GRID_ROWS: usize = 12;
+const CELLS: usize = GRID_COLS * GRID_ROWS;
+
+/// Per-cell luma sums for one frame.
+pub struct CellGrid {
+    sum: [f32; CELLS],
+    count: [u32; CELLS],
+}
+
+impl CellGrid {
+    pub fn new() -> Self {
+        Self {
+            sum: [0.0; CELLS],
+            count: [0; CELLS],
+        }
+    }
+
+    pub fn index(px: usize, py: usize, w: usize, h: usize) -> usize {
+        let col = (px * GRID_COLS / w.max(1)).min(GRID_COLS - 1);
+        let row = (py * GRID_ROWS / h.max(1)).min(GRID_ROWS - 1);
+        row * GRID_COLS + col
+    }
+
+    pub fn add(&mut self, cell: usize, luma: f32) {
+        self.sum[cell] += luma;
+        self.count[cell] += 1;
+    }
+
+    /// Mean luma per cell, normalized to [0, 1]; empty cells read as 0.
+    pub fn means(&self) -> [f32; CELLS] {
+        let mut out = [0.0; CELLS];
+        for (i, m) in out.iter_mut().enumerate() {
+            if self.count[i] > 0 {
+                *m = self.sum[i] / self.count[i] as f32 / 255.0;
+            }
+        }
+        out
+    }
+}
+
+// Hysteresis on the persistence score so a cell doesn't flicker in and out.
+const ENGAGE: f32 = 0.8;
+const RELEASE: f32 = 0.5;
+
+/// Learns which regions of the frame are persistently very bright (a window
+/// or lamp behind the monitor) and down-weights them, so they stop dominating
+/// the ambient estimate.
+pub struct BrightMask {
+    /// Exponentially averaged fraction of time each cell was above threshold.
+    score: Vec<f32>,
+    masked: Vec<bool>,
+    threshold: f32,
+    adapt_secs: f32,
+    weight: f32,
+    last: Option<Instant>,
+}
+
+impl BrightMask {
+    pub fn new(threshold: f32, adapt_secs: u64, weight: f32) -> Self {
+        Self {
+            score: vec![0.0; CELLS],
+            masked: vec![false; CELLS],
+            threshold,
+            adapt_secs: adapt_secs.max(1) as f32,
+            weight,
+            last: None,
+        }
+    }
+
+    pub fn weight(&self, cell: usize) -> f32 {
+        if self.masked[cell] { self.weight } else { 1.0 }
+    }
+
+    pub fn masked_cells(&self) -> usize {
+        self.masked.iter().filter(|m| **m).count()
+    }
+
+    /// Fold one frame into the scores. The averaging is time-based, so a
+    /// region has to stay bright for roughly `adapt_secs` before it is masked
+    /// regardless of how often frames arrive.
+    pub fn update(&mut self, grid: &CellGrid) {
+        let now = Instant::now();
+        let dt = self.last.map_or(0.0, |t| now.duration_since(t).as_secs_f32());
+        self.last = Some(now);
+        let alpha = (dt / self.adapt_secs).min(1.0);
+        if alpha == 0.0 {
+            return;
+        }
+        for (i, mean) in grid.means().iter().enumerate() {
+            let bright = if *mean >= self.threshold { 1.0 } else { 0.0 };
+            self.score[i] += alpha * (bright - self.score[i]);
+            if self.score[i] >= ENGAGE {
+                self.masked[i] = true;
+            } else if self.score[i] < RELEASE {
+                self.masked[i] = false;
+            }
+        }
+    }
+}
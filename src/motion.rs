@@ -0,0 +1,77 @@
+// src/motion.rs
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, MotionGating};
+use crate::masking::CellGrid;
+
+/// Frames with no brightness update at all are capped, so a crowd that
+/// never stops moving can't freeze the screen at one level.
+const MAX_HOLD: Duration = Duration::from_secs(30);
+
+/// Frame-difference metric over the region grid. Each frame is divided by its
+/// own mean first, so a light switching on (which scales every region alike)
+/// reads as no motion while someone walking past does.
+pub struct MotionDetector {
+    prev: Option<Vec<f32>>,
+}
+
+impl MotionDetector {
+    pub fn new() -> Self {
+        Self { prev: None }
+    }
+
+    /// Mean absolute change of the normalized regions since the previous
+    /// frame; 0 for the first frame.
+    pub fn update(&mut self, grid: &CellGrid) -> f32 {
+        let means = grid.means();
+        let frame_mean = means.iter().sum::<f32>() / means.len() as f32;
+        // Near-black frames: normalizing would amplify sensor noise.
+        let scale = if frame_mean > 0.02 { frame_mean } else { 1.0 };
+        let current: Vec<f32> = means.iter().map(|m| m / scale).collect();
+        let score = self.prev.as_ref().map_or(0.0, |prev| {
+            prev.iter()
+                .zip(&current)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>()
+                / current.len() as f32
+        });
+        self.prev = Some(current);
+        score
+    }
+}
+
+/// Decides how much a reading may count given the motion measured with it.
+pub struct MotionGate {
+    enabled: bool,
+    threshold: f32,
+    mode: MotionGating,
+    last_accepted: Instant,
+}
+
+impl MotionGate {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            enabled: cfg.motion_gate_enabled,
+            threshold: cfg.motion_gate_threshold,
+            mode: cfg.motion_gate_mode,
+            last_accepted: Instant::now(),
+        }
+    }
+
+    /// Weight in [0, 1] for a reading; 0 means discard it.
+    pub fn weight(&mut self, motion: f32) -> f32 {
+        let calm = !self.enabled || motion <= self.threshold;
+        let weight = if calm || self.last_accepted.elapsed() >= MAX_HOLD {
+            1.0
+        } else {
+            match self.mode {
+                MotionGating::Skip => 0.0,
+                MotionGating::Weight => (self.threshold / motion).powi(2),
+            }
+        };
+        if weight > 0.0 {
+            self.last_accepted = Instant::now();
+        }
+        weight
+    }
+}
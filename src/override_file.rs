@@ -0,0 +1,106 @@
+// src/override_file.rs
+//! Cooperative mode for tools that set brightness themselves.
+//!
+//! While `$XDG_RUNTIME_DIR/smart-brightness/override` (or `override_file`)
+//! exists, it wins over ambient light:
+//!
+//! - `pause`: leave the backlight alone, e.g. while a slider is dragged;
+//! - `75%`: that share of the calibrated brightness range;
+//! - `1200`: that raw backlight value, as `brightnessctl get` prints it.
+//!
+//! Deleting the file, or leaving it empty, hands control back.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use tracing::warn;
+
+use crate::config::{runtime_dir, Config};
+
+/// How often the file is looked at.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Override {
+    Pause,
+    Percent(f32),
+    Raw(u32),
+}
+
+impl Override {
+    fn parse(s: &str) -> Result<Option<Self>, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(None);
+        }
+        if s.eq_ignore_ascii_case("pause") {
+            return Ok(Some(Override::Pause));
+        }
+        if let Some(percent) = s.strip_suffix('%') {
+            return match percent.trim().parse::<f32>() {
+                Ok(p) if (0.0..=100.0).contains(&p) => Ok(Some(Override::Percent(p))),
+                _ => Err(format!("'{}' is not a percentage from 0% to 100%", s)),
+            };
+        }
+        s.parse()
+            .map(|v| Some(Override::Raw(v)))
+            .map_err(|_| format!("'{}' is neither 'pause', a percentage nor a backlight value", s))
+    }
+}
+
+pub struct OverrideFile {
+    path: Option<PathBuf>,
+    last_poll: Option<Instant>,
+    /// Modification time of the contents `current` came from; None while
+    /// the file is missing.
+    modified: Option<SystemTime>,
+    current: Option<Override>,
+}
+
+impl OverrideFile {
+    pub fn from_config(cfg: &Config) -> Self {
+        let path = cfg.override_enabled.then(|| {
+            cfg.override_file
+                .clone()
+                .unwrap_or_else(|| runtime_dir().join("override"))
+        });
+        Self {
+            path,
+            last_poll: None,
+            modified: None,
+            current: None,
+        }
+    }
+
+    /// Look at the file if `POLL_INTERVAL` has passed; true when the
+    /// override in effect changed.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+        if self.last_poll.is_some_and(|t| now.duration_since(t) < POLL_INTERVAL) {
+            return false;
+        }
+        self.last_poll = Some(now);
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        let next = match modified.map(|_| fs::read_to_string(path)) {
+            None | Some(Err(_)) => None,
+            Some(Ok(text)) => Override::parse(&text).unwrap_or_else(|e| {
+                warn!(target: "daemon", "Ignoring {}: {}", path.display(), e);
+                None
+            }),
+        };
+        let changed = next != self.current;
+        self.current = next;
+        changed
+    }
+
+    pub fn current(&self) -> Option<Override> {
+        self.current
+    }
+}
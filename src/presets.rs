@@ -0,0 +1,92 @@
+// src/presets.rs
+//! Built-in parameter sets. They are applied exactly like a
+//! `[profiles.<name>]` table, so a profile of the same name in the config
+//! replaces the built-in one.
+
+use crate::config::Responsiveness;
+
+pub const NAMES: [&str; 3] = ["low-vision", "power-saver", "responsive"];
+
+/// Keys use the names written to config.toml.
+fn source(name: &str) -> Option<&'static str> {
+    Some(match name {
+        // Never dims below 40% of the calibrated range, lifts mid-tones
+        // (gamma ≈ 0.6) so text keeps its contrast, and follows brightening
+        // quickly.
+        "low-vision" => {
+            r#"
+            ambient_smoothing_strength = 0.35
+            ambient_luma_min_change = 0.01
+            brightness_step_divisor = 4
+            brightness_step_max = 200
+            curve = { points = [[0.0, 0.4], [0.1, 0.55], [0.3, 0.69], [0.7, 0.88], [1.0, 1.0]] }
+            "#
+        }
+        // Dimmer overall, capped at 80%, and samples the camera less often.
+        "power-saver" => {
+            r#"
+            ambient_smoothing_strength = 0.08
+            capture_interval_ms = 500
+            curve = { points = [[0.0, 0.0], [0.5, 0.35], [1.0, 0.8]] }
+            "#
+        }
+        // Reacts to small changes within a few frames.
+        "responsive" => {
+            r#"
+            ambient_smoothing_strength = 0.4
+            ambient_luma_min_change = 0.005
+            capture_interval_ms = 100
+            brightness_step_divisor = 3
+            "#
+        }
+        _ => return None,
+    })
+}
+
+/// Settings a `responsiveness` level stands for; see
+/// `Config::expand_responsiveness`. `balanced` is the defaults.
+fn responsiveness_source(level: Responsiveness) -> &'static str {
+    match level {
+        // Ignores flicker and passing shadows; changes take a few seconds.
+        Responsiveness::Relaxed => {
+            r#"
+            ambient_smoothing_strength = 0.06
+            ambient_luma_min_change = 0.03
+            capture_interval_ms = 400
+            brightness_step_interval_ms = 40
+            brightness_step_divisor = 30
+            brightness_step_max = 40
+            "#
+        }
+        Responsiveness::Balanced => {
+            r#"
+            ambient_smoothing_strength = 0.15
+            ambient_luma_min_change = 0.01
+            capture_interval_ms = 150
+            brightness_step_interval_ms = 20
+            brightness_step_divisor = 10
+            brightness_step_max = 100
+            "#
+        }
+        // Follows a lamp switched on within half a second.
+        Responsiveness::Snappy => {
+            r#"
+            ambient_smoothing_strength = 0.4
+            ambient_luma_min_change = 0.005
+            capture_interval_ms = 80
+            brightness_step_interval_ms = 15
+            brightness_step_divisor = 3
+            brightness_step_max = 200
+            "#
+        }
+    }
+}
+
+pub fn responsiveness(level: Responsiveness) -> toml::Table {
+    toml::from_str(responsiveness_source(level)).expect("built-in levels are valid TOML")
+}
+
+pub fn get(name: &str) -> Option<toml::Table> {
+    let table = toml::from_str(source(name)?).expect("built-in presets are valid TOML");
+    Some(table)
+}
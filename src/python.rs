@@ -0,0 +1,140 @@
+// src/python.rs
+//! Python bindings (`python` feature), to try mapping and smoothing ideas
+//! on recorded traces (`history_enabled`, `ctl dump-state`) with the
+//! daemon's own code:
+//!
+//! ```python
+//! import smart_brightness as sb
+//!
+//! curve = sb.Curve([[0.0, 0.1], [0.5, 0.6], [1.0, 1.0]])
+//! ema = sb.Ema(0.2)
+//! fractions = [curve.eval(ema.update(luma)) for luma in trace]
+//! ```
+//!
+//! Luma is the normalized value the daemon logs, so no sensor is needed.
+//! `Transition` takes the time as milliseconds from any start, which lets a
+//! trace run faster than it was recorded.
+use std::time::{Duration, Instant};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::curve;
+use crate::smooth_transition::SmoothTransition;
+use crate::smoothing;
+
+/// `[curve]`: anchors of normalized luma → fraction of the brightness range.
+#[pyclass]
+struct Curve(curve::Curve);
+
+#[pymethods]
+impl Curve {
+    #[new]
+    fn new(points: Vec<[f32; 2]>) -> PyResult<Self> {
+        let curve = curve::Curve {
+            points,
+            ..curve::Curve::default()
+        };
+        curve.validate().map_err(PyValueError::new_err)?;
+        Ok(Self(curve))
+    }
+
+    /// The curve the calibration wizard saves for points recorded in this
+    /// order; None with fewer than two.
+    #[staticmethod]
+    fn from_points(points: Vec<[f32; 2]>) -> Option<Self> {
+        curve::Curve::from_points(&points).map(Self)
+    }
+
+    fn eval(&self, luma: f32) -> f32 {
+        self.0.eval(luma)
+    }
+
+    #[getter]
+    fn points(&self) -> Vec<[f32; 2]> {
+        self.0.points.clone()
+    }
+}
+
+/// The luma average (`ambient_smoothing_strength`).
+#[pyclass]
+struct Ema(smoothing::Ema);
+
+#[pymethods]
+impl Ema {
+    #[new]
+    fn new(alpha: f32) -> Self {
+        Self(smoothing::Ema::new(alpha))
+    }
+
+    #[pyo3(signature = (x, weight = 1.0))]
+    fn update(&mut self, x: f32, weight: f32) -> f32 {
+        self.0.update_weighted(x, weight)
+    }
+
+    fn seed(&mut self, value: f32) {
+        self.0.seed(value);
+    }
+
+    #[getter]
+    fn value(&self) -> Option<f32> {
+        self.0.value()
+    }
+}
+
+/// The brightness stepper (`brightness_step_interval_ms`, `_divisor` and
+/// `_max`), on a clock of its own.
+#[pyclass]
+struct Transition {
+    inner: SmoothTransition,
+    start: Instant,
+}
+
+impl Transition {
+    fn at(&self, ms: u64) -> Instant {
+        self.start + Duration::from_millis(ms)
+    }
+}
+
+#[pymethods]
+impl Transition {
+    #[new]
+    fn new(initial: u32, interval_ms: u64, divisor: u32, max_step: u32) -> Self {
+        Self {
+            inner: SmoothTransition::new(initial, interval_ms, divisor, max_step),
+            start: Instant::now(),
+        }
+    }
+
+    fn set_target(&mut self, target: u32, max_brightness: u32) {
+        self.inner.set_target(target, max_brightness);
+    }
+
+    fn jump_to(&mut self, target: u32, max_brightness: u32) {
+        self.inner.jump_to(target, max_brightness);
+    }
+
+    /// The level stepped to at `ms`, or None if no step is due.
+    fn update(&mut self, ms: u64) -> Option<u32> {
+        let now = self.at(ms);
+        self.inner.update(now)
+    }
+
+    #[getter]
+    fn current(&self) -> u32 {
+        self.inner.current_value()
+    }
+
+    #[getter]
+    fn target(&self) -> u32 {
+        self.inner.target_value()
+    }
+}
+
+#[pymodule]
+fn smart_brightness(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Curve>()?;
+    m.add_class::<Ema>()?;
+    m.add_class::<Transition>()?;
+    Ok(())
+}
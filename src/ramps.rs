@@ -0,0 +1,95 @@
+// src/ramps.rs
+//! Scheduled brightness ramps (`[[ramps]]`): reach a level by a time of day.
+//!
+//! ```toml
+//! [[ramps]]
+//! at = "07:00"
+//! brightness = 80
+//! duration_mins = 20
+//! ```
+//!
+//! From `duration_mins` before `at`, brightness moves steadily to
+//! `brightness`% of the calibrated range and gets there at `at`, like a
+//! wake-up light. Ambient readings are ignored while a ramp runs; afterwards
+//! the display follows the room again. `smart-brightness ctl ramp` starts a
+//! one-off ramp on the running daemon.
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// One `[[ramps]]` entry as written in the config.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Ramp {
+    /// Local time the level is reached, `HH:MM`.
+    pub at: String,
+    /// Percent of the calibrated brightness range.
+    pub brightness: f32,
+    #[serde(default = "default_duration_mins")]
+    pub duration_mins: u32,
+}
+
+fn default_duration_mins() -> u32 {
+    30
+}
+
+impl Ramp {
+    /// Time left until `at` if the ramp is running at `now`.
+    fn remaining(&self, now: NaiveTime) -> Option<Duration> {
+        let at = parse_time(&self.at).ok()?;
+        let until = until(now, at);
+        (until > 0 && until <= self.duration_mins as u64 * 60).then(|| Duration::from_secs(until))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        parse_time(&self.at).map_err(|e| format!("ramps: {}", e))?;
+        if !(0.0..=100.0).contains(&self.brightness) {
+            return Err(format!("ramps.{}: brightness must be between 0 and 100", self.at));
+        }
+        if self.duration_mins == 0 {
+            return Err(format!("ramps.{}: duration_mins must be greater than 0", self.at));
+        }
+        Ok(())
+    }
+}
+
+pub fn validate(ramps: &[Ramp]) -> Result<(), String> {
+    ramps.iter().try_for_each(Ramp::validate)
+}
+
+/// The ramp running now, as (brightness %, time left), if any. When
+/// windows overlap the one ending first wins.
+pub fn due(ramps: &[Ramp]) -> Option<(f32, Duration)> {
+    if ramps.is_empty() {
+        return None;
+    }
+    let now = Local::now().time();
+    ramps
+        .iter()
+        .filter_map(|r| r.remaining(now).map(|left| (r.brightness, left)))
+        .min_by_key(|&(_, left)| left)
+}
+
+/// Time from now until the next `HH:MM`, for one-off ramps.
+pub fn until_next(at: &str) -> Result<Duration, String> {
+    let at = parse_time(at)?;
+    let secs = match until(Local::now().time(), at) {
+        0 => DAY_SECS,
+        secs => secs,
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// Seconds from `now` to the next `at`, 0 when they are the same second.
+fn until(now: NaiveTime, at: NaiveTime) -> u64 {
+    let (now, at) = (now.num_seconds_from_midnight() as u64, at.num_seconds_from_midnight() as u64);
+    (at + DAY_SECS - now) % DAY_SECS
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map_err(|_| format!("invalid time '{}', expected HH:MM", s))
+}
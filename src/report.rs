@@ -0,0 +1,156 @@
+// src/report.rs
+//! Summary of a day of history: how long the screen sat in each tenth of
+//! the calibrated range, how often it was corrected by hand, and how bright
+//! the room was hour by hour. What a brightness policy costs in battery
+//! shows up in the upper deciles.
+//!
+//! `ctl report` asks the daemon for today so far; the daemon logs the
+//! finished day on its own once the date changes.
+
+use std::fmt;
+use std::io;
+
+use chrono::{Local, NaiveDate, TimeZone, Timelike};
+
+use crate::history::{self, HistoryRecord};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyReport {
+    pub day: NaiveDate,
+    /// Seconds at 0–10 %, 10–20 % … 90–100 % of the calibrated range.
+    pub deciles: [u64; 10],
+    /// Stretches of samples where the level differed from what the daemon
+    /// drove, counted once each.
+    pub manual_overrides: usize,
+    /// Mean ambient luma per local hour, None for hours without samples.
+    pub hourly_luma: [Option<f32>; 24],
+}
+
+impl DailyReport {
+    /// Each record stands for `sample_secs` of screen time.
+    pub fn from_records(
+        day: NaiveDate,
+        records: &[HistoryRecord],
+        range: (u32, u32),
+        sample_secs: u64,
+    ) -> Self {
+        let (min, max) = range;
+        let span = max.saturating_sub(min).max(1) as f32;
+        let mut deciles = [0; 10];
+        let mut manual_overrides = 0;
+        let mut was_manual = false;
+        let mut hours = [(0.0f64, 0usize); 24];
+        for rec in records {
+            let Some(time) = Local.timestamp_opt(rec.t, 0).single() else {
+                continue;
+            };
+            if time.date_naive() != day {
+                continue;
+            }
+            let level = rec.actual.unwrap_or(rec.brightness);
+            let fraction = (level.saturating_sub(min) as f32 / span).clamp(0.0, 1.0);
+            deciles[((fraction * 10.0) as usize).min(9)] += sample_secs;
+            let manual = rec.is_manual();
+            if manual && !was_manual {
+                manual_overrides += 1;
+            }
+            was_manual = manual;
+            let hour = &mut hours[time.hour() as usize];
+            hour.0 += rec.luma as f64;
+            hour.1 += 1;
+        }
+        Self {
+            day,
+            deciles,
+            manual_overrides,
+            hourly_luma: hours.map(|(sum, n)| (n > 0).then(|| (sum / n as f64) as f32)),
+        }
+    }
+
+    /// Report for `day` from the history store.
+    pub fn load(day: NaiveDate, range: (u32, u32), sample_secs: u64) -> io::Result<Self> {
+        let since = day
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| Local.from_local_datetime(&t).earliest())
+            .map_or(i64::MIN, |t| t.timestamp());
+        let records = history::load_since(&history::default_path(), since)?;
+        Ok(Self::from_records(day, &records, range, sample_secs))
+    }
+
+    pub fn recorded_secs(&self) -> u64 {
+        self.deciles.iter().sum()
+    }
+}
+
+impl fmt::Display for DailyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.recorded_secs();
+        writeln!(f, "Report for {}: {} min recorded", self.day, total / 60)?;
+        if total == 0 {
+            return Ok(());
+        }
+        writeln!(f, "Time at brightness:")?;
+        for (i, secs) in self.deciles.iter().enumerate() {
+            writeln!(
+                f,
+                "  {:>3}–{:>3} %  {:>5} min  {:>3.0} %",
+                i * 10,
+                i * 10 + 10,
+                secs / 60,
+                *secs as f64 / total as f64 * 100.0
+            )?;
+        }
+        writeln!(f, "Manual overrides: {}", self.manual_overrides)?;
+        write!(f, "Ambient luma by hour:")?;
+        for (hour, luma) in self.hourly_luma.iter().enumerate() {
+            if let Some(luma) = luma {
+                write!(f, "\n  {:02}:00  {:.2}", hour, luma)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> i64 {
+        Local
+            .with_ymd_and_hms(2024, 3, 5, hour, minute, 0)
+            .single()
+            .unwrap()
+            .timestamp()
+    }
+
+    fn record(t: i64, luma: f32, brightness: u32, actual: u32) -> HistoryRecord {
+        HistoryRecord {
+            t,
+            luma,
+            brightness,
+            actual: Some(actual),
+            variant: None,
+        }
+    }
+
+    #[test]
+    fn sorts_time_into_deciles_and_counts_overrides_once() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let records = [
+            record(at(9, 0), 0.2, 10, 10),
+            record(at(9, 1), 0.4, 50, 90),
+            record(at(9, 2), 0.4, 50, 90),
+            record(at(10, 0), 0.6, 100, 100),
+            // The next day is not part of the report.
+            record(at(9, 0) + 86_400, 0.9, 100, 0),
+        ];
+        let report = DailyReport::from_records(day, &records, (0, 100), 60);
+        assert_eq!(report.deciles[1], 60);
+        assert_eq!(report.deciles[9], 180);
+        assert_eq!(report.recorded_secs(), 240);
+        assert_eq!(report.manual_overrides, 1);
+        assert!((report.hourly_luma[9].unwrap() - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(report.hourly_luma[10], Some(0.6));
+        assert_eq!(report.hourly_luma[11], None);
+    }
+}
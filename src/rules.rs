@@ -0,0 +1,479 @@
+// src/rules.rs
+//! Conditional overrides from `[[rules]]`, evaluated on every reading.
+//!
+//! ```toml
+//! [[rules]]
+//! when = "luma < 0.05 and hour >= 22"
+//! then = "cap 20%"
+//!
+//! [[rules]]
+//! when = "profile == office"
+//! then = "disable circadian"
+//! ```
+//!
+//! Conditions compare `luma`, `lux`, `hour`, `minute`, `weekday` (1 = Monday)
+//! or `profile` with `<`, `<=`, `>`, `>=`, `==` or `!=`, joined by `and` /
+//! `or` (`and` binds tighter). Actions, comma-separated: `cap N%`,
+//! `floor N%` (of the calibrated brightness range) and `disable circadian`.
+//! When several rules match, the lowest cap and highest floor win.
+
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// One `[[rules]]` entry as written in the config.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Rule {
+    pub when: String,
+    pub then: String,
+}
+
+/// What the matching rules ask for this cycle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effects {
+    /// Upper and lower bound as fractions of the calibrated range.
+    pub cap: f32,
+    pub floor: f32,
+    pub disable_circadian: bool,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            cap: 1.0,
+            floor: 0.0,
+            disable_circadian: false,
+        }
+    }
+}
+
+impl Effects {
+    /// Clamp a brightness fraction; a cap below the floor wins.
+    pub fn clamp(&self, fraction: f32) -> f32 {
+        fraction.max(self.floor).min(self.cap)
+    }
+}
+
+/// Values conditions can refer to.
+pub struct Context<'a> {
+    pub luma: f32,
+    pub lux: Option<f32>,
+    pub hour: u32,
+    pub minute: u32,
+    pub weekday: u32,
+    pub profile: Option<&'a str>,
+}
+
+impl<'a> Context<'a> {
+    /// Context at the current local time.
+    pub fn now(luma: f32, lux: Option<f32>, profile: Option<&'a str>) -> Self {
+        let now = Local::now();
+        Self {
+            luma,
+            lux,
+            hour: now.hour(),
+            minute: now.minute(),
+            weekday: now.weekday().number_from_monday(),
+            profile,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Var {
+    Luma,
+    Lux,
+    Hour,
+    Minute,
+    Weekday,
+    Profile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f32),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Comparison {
+    var: Var,
+    op: Op,
+    value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Cap(f32),
+    Floor(f32),
+    DisableCircadian,
+}
+
+/// A parsed rule: any of the `or` branches, each a list of comparisons that
+/// must all hold.
+#[derive(Debug, Clone, PartialEq)]
+struct Compiled {
+    any_of: Vec<Vec<Comparison>>,
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleSet {
+    rules: Vec<Compiled>,
+}
+
+impl RuleSet {
+    pub fn compile(rules: &[Rule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| compile(rule).map_err(|e| format!("rules[{}]: {}", i, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn evaluate(&self, ctx: &Context) -> Effects {
+        let mut effects = Effects::default();
+        let matching = self
+            .rules
+            .iter()
+            .filter(|r| r.any_of.iter().any(|all| all.iter().all(|c| c.holds(ctx))));
+        for rule in matching {
+            for action in &rule.actions {
+                match *action {
+                    Action::Cap(f) => effects.cap = effects.cap.min(f),
+                    Action::Floor(f) => effects.floor = effects.floor.max(f),
+                    Action::DisableCircadian => effects.disable_circadian = true,
+                }
+            }
+        }
+        effects
+    }
+}
+
+impl Comparison {
+    fn holds(&self, ctx: &Context) -> bool {
+        let lhs = match self.var {
+            Var::Luma => Value::Number(ctx.luma),
+            Var::Lux => match ctx.lux {
+                Some(lux) => Value::Number(lux),
+                None => return false,
+            },
+            Var::Hour => Value::Number(ctx.hour as f32),
+            Var::Minute => Value::Number(ctx.minute as f32),
+            Var::Weekday => Value::Number(ctx.weekday as f32),
+            Var::Profile => Value::Text(ctx.profile.unwrap_or_default().to_string()),
+        };
+        let ordering = match (&lhs, &self.value) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        let Some(ordering) = ordering else { return false };
+        match self.op {
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+        }
+    }
+}
+
+fn compile(rule: &Rule) -> Result<Compiled, String> {
+    let any_of = split_words(&rule.when, "or")
+        .into_iter()
+        .map(|branch| {
+            split_words(&branch, "and")
+                .iter()
+                .map(|c| parse_comparison(c))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let actions = rule
+        .then
+        .split(',')
+        .map(parse_action)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Compiled { any_of, actions })
+}
+
+/// Split on a keyword standing as its own word.
+fn split_words(text: &str, keyword: &str) -> Vec<String> {
+    let mut parts = vec![Vec::new()];
+    for word in text.split_whitespace() {
+        if word.eq_ignore_ascii_case(keyword) {
+            parts.push(Vec::new());
+        } else if let Some(last) = parts.last_mut() {
+            last.push(word);
+        }
+    }
+    parts.into_iter().map(|p| p.join(" ")).collect()
+}
+
+fn parse_comparison(text: &str) -> Result<Comparison, String> {
+    const OPS: [(&str, Op); 6] = [
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+    let (pos, token, op) = OPS
+        .iter()
+        .filter_map(|&(token, op)| text.find(token).map(|pos| (pos, token, op)))
+        .min_by_key(|&(pos, token, _)| (pos, usize::MAX - token.len()))
+        .ok_or_else(|| format!("'{}' has no comparison operator", text))?;
+    let name = text[..pos].trim();
+    let raw = text[pos + token.len()..].trim();
+    let var = match name {
+        "luma" => Var::Luma,
+        "lux" => Var::Lux,
+        "hour" => Var::Hour,
+        "minute" => Var::Minute,
+        "weekday" => Var::Weekday,
+        "profile" => Var::Profile,
+        "" => return Err(format!("'{}' is missing a name", text)),
+        _ => return Err(format!("unknown variable '{}'", name)),
+    };
+    if raw.is_empty() {
+        return Err(format!("'{}' is missing a value", text));
+    }
+    let value = if var == Var::Profile {
+        if !matches!(op, Op::Eq | Op::Ne) {
+            return Err("profile can only be compared with == or !=".into());
+        }
+        Value::Text(raw.trim_matches(['"', '\'']).to_string())
+    } else {
+        Value::Number(
+            raw.parse()
+                .map_err(|_| format!("'{}' is not a number", raw))?,
+        )
+    };
+    Ok(Comparison { var, op, value })
+}
+
+fn parse_action(text: &str) -> Result<Action, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        ["cap", pct] => parse_percent(pct).map(Action::Cap),
+        ["floor", pct] => parse_percent(pct).map(Action::Floor),
+        ["disable", "circadian"] => Ok(Action::DisableCircadian),
+        _ => Err(format!(
+            "unknown action '{}' (expected cap N%, floor N% or disable circadian)",
+            text.trim()
+        )),
+    }
+}
+
+fn parse_percent(text: &str) -> Result<f32, String> {
+    let pct: f32 = text
+        .strip_suffix('%')
+        .ok_or_else(|| format!("'{}' must be a percentage like 20%", text))?
+        .parse()
+        .map_err(|_| format!("'{}' is not a percentage", text))?;
+    if !(0.0..=100.0).contains(&pct) {
+        return Err(format!("{} is outside 0%..100%", text));
+    }
+    Ok(pct / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(luma: f32, hour: u32, profile: Option<&str>) -> Context<'_> {
+        Context {
+            luma,
+            lux: None,
+            hour,
+            minute: 30,
+            weekday: 3,
+            profile,
+        }
+    }
+
+    fn holds(condition: &str, ctx: &Context) -> bool {
+        parse_comparison(condition).unwrap().holds(ctx)
+    }
+
+    fn rule(when: &str, then: &str) -> Rule {
+        Rule {
+            when: when.into(),
+            then: then.into(),
+        }
+    }
+
+    #[test]
+    fn the_longest_operator_at_the_first_position_wins() {
+        let op = |text: &str| parse_comparison(text).unwrap().op;
+        assert_eq!(op("luma <= 0.5"), Op::Le);
+        assert_eq!(op("luma < 0.5"), Op::Lt);
+        assert_eq!(op("luma >= 0.5"), Op::Ge);
+        assert_eq!(op("luma > 0.5"), Op::Gt);
+        assert_eq!(op("luma == 0.5"), Op::Eq);
+        assert_eq!(op("luma != 0.5"), Op::Ne);
+        assert_eq!(op("hour<=7"), Op::Le);
+        assert_eq!(
+            parse_comparison("lux>=120").unwrap(),
+            Comparison {
+                var: Var::Lux,
+                op: Op::Ge,
+                value: Value::Number(120.0),
+            }
+        );
+    }
+
+    #[test]
+    fn every_operator_compares_numbers() {
+        let at = ctx(0.5, 22, None);
+        for (condition, want) in [
+            ("luma < 0.5", false),
+            ("luma < 0.6", true),
+            ("luma <= 0.5", true),
+            ("luma <= 0.4", false),
+            ("luma > 0.5", false),
+            ("luma > 0.4", true),
+            ("luma >= 0.5", true),
+            ("luma >= 0.6", false),
+            ("luma == 0.5", true),
+            ("luma == 0.4", false),
+            ("luma != 0.5", false),
+            ("luma != 0.4", true),
+        ] {
+            assert_eq!(holds(condition, &at), want, "{}", condition);
+        }
+    }
+
+    #[test]
+    fn times_and_weekdays_compare_as_numbers() {
+        let at = ctx(0.5, 22, None);
+        assert!(holds("hour >= 22", &at));
+        assert!(!holds("hour < 22", &at));
+        assert!(holds("minute == 30", &at));
+        assert!(holds("weekday <= 5", &at));
+        assert!(!holds("weekday > 5", &at));
+        // Without a lux estimate no lux condition holds, not even `!=`.
+        assert!(!holds("lux != 100", &at));
+        let lit = Context {
+            lux: Some(250.0),
+            ..ctx(0.5, 22, None)
+        };
+        assert!(holds("lux > 100", &lit));
+    }
+
+    #[test]
+    fn profiles_compare_by_name() {
+        let office = ctx(0.5, 12, Some("office"));
+        assert!(holds("profile == office", &office));
+        assert!(holds("profile == \"office\"", &office));
+        assert!(holds("profile == 'office'", &office));
+        assert!(!holds("profile != office", &office));
+        assert!(holds("profile != home", &office));
+        // No profile in use compares as an empty name.
+        assert!(holds("profile != office", &ctx(0.5, 12, None)));
+        assert!(!holds("profile == office", &ctx(0.5, 12, None)));
+    }
+
+    #[test]
+    fn keywords_split_only_as_whole_words() {
+        assert_eq!(
+            split_words("luma < 0.1 and hour > 6", "and"),
+            ["luma < 0.1", "hour > 6"]
+        );
+        assert_eq!(
+            split_words("profile == band AND hour>6", "and"),
+            ["profile == band", "hour>6"]
+        );
+        assert_eq!(
+            split_words("profile == sandbox", "and"),
+            ["profile == sandbox"]
+        );
+        assert_eq!(split_words("  luma   <  0.1  ", "or"), ["luma < 0.1"]);
+        assert_eq!(split_words("or", "or"), ["", ""]);
+    }
+
+    #[test]
+    fn percentages_must_be_within_0_and_100() {
+        assert_eq!(parse_percent("0%"), Ok(0.0));
+        assert_eq!(parse_percent("20%"), Ok(0.2));
+        assert_eq!(parse_percent("100%"), Ok(1.0));
+        assert!(parse_percent("100.5%").is_err());
+        assert!(parse_percent("-1%").is_err());
+        assert!(parse_percent("20").is_err());
+        assert!(parse_percent("twenty%").is_err());
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected() {
+        for (when, then) in [
+            ("", "cap 20%"),
+            ("luma 0.5", "cap 20%"),
+            ("< 0.5", "cap 20%"),
+            ("luma <", "cap 20%"),
+            ("brightness < 0.5", "cap 20%"),
+            ("luma < dark", "cap 20%"),
+            ("profile < office", "cap 20%"),
+            ("luma < 0.5 and", "cap 20%"),
+            ("luma < 0.5 or or hour > 6", "cap 20%"),
+            ("luma < 0.5", ""),
+            ("luma < 0.5", "cap"),
+            ("luma < 0.5", "cap 120%"),
+            ("luma < 0.5", "dim 20%"),
+            ("luma < 0.5", "cap 20%,"),
+            ("luma < 0.5", "disable smoothing"),
+        ] {
+            assert!(
+                RuleSet::compile(&[rule(when, then)]).is_err(),
+                "{:?} → {:?}",
+                when,
+                then
+            );
+        }
+        let err = RuleSet::compile(&[rule("luma < 0.5", "cap 20%"), rule("luma", "cap 20%")]);
+        assert_eq!(
+            err.unwrap_err(),
+            "rules[1]: 'luma' has no comparison operator"
+        );
+    }
+
+    #[test]
+    fn matching_rules_combine_their_effects() {
+        let rules = RuleSet::compile(&[
+            rule("luma < 0.05 and hour >= 22 or hour < 6", "cap 20%"),
+            rule("hour >= 20", "cap 50%, floor 10%"),
+            rule("profile == office", "floor 30%, disable circadian"),
+        ])
+        .unwrap();
+        assert_eq!(rules.evaluate(&ctx(0.5, 12, None)), Effects::default());
+        let night = rules.evaluate(&ctx(0.01, 23, None));
+        assert_eq!(
+            (night.cap, night.floor, night.disable_circadian),
+            (0.2, 0.1, false)
+        );
+        // `and` binds tighter: early morning matches whatever the light.
+        assert_eq!(rules.evaluate(&ctx(0.9, 5, None)).cap, 0.2);
+        let office = rules.evaluate(&ctx(0.9, 21, Some("office")));
+        assert_eq!(
+            (office.cap, office.floor, office.disable_circadian),
+            (0.5, 0.3, true)
+        );
+        assert_eq!(office.clamp(0.1), 0.3);
+        assert_eq!(office.clamp(0.9), 0.5);
+    }
+}
@@ -0,0 +1,96 @@
+// src/sandbox.rs
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Application sandboxes that hide or block `/dev/video*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sandbox {
+    Flatpak { app_id: Option<String> },
+    Snap { name: Option<String> },
+}
+
+impl Sandbox {
+    pub fn detect() -> Option<Self> {
+        if Path::new("/.flatpak-info").exists() || std::env::var_os("FLATPAK_ID").is_some() {
+            return Some(Sandbox::Flatpak {
+                app_id: std::env::var("FLATPAK_ID").ok(),
+            });
+        }
+        if std::env::var_os("SNAP").is_some() {
+            return Some(Sandbox::Snap {
+                name: std::env::var("SNAP_NAME").ok(),
+            });
+        }
+        None
+    }
+
+    /// Command the user can run to grant camera access.
+    pub fn remedy(&self) -> String {
+        match self {
+            Sandbox::Flatpak { app_id } => format!(
+                "flatpak override --user --device=all {}",
+                app_id.as_deref().unwrap_or("<app-id>")
+            ),
+            Sandbox::Snap { name } => format!(
+                "snap connect {}:camera",
+                name.as_deref().unwrap_or("<snap-name>")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Sandbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sandbox::Flatpak { .. } => write!(f, "Flatpak"),
+            Sandbox::Snap { .. } => write!(f, "Snap"),
+        }
+    }
+}
+
+/// Camera access was refused because of sandbox confinement.
+#[derive(Debug)]
+pub struct CameraDenied {
+    pub sandbox: Sandbox,
+    pub source: io::Error,
+}
+
+impl fmt::Display for CameraDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "camera access blocked by the {} sandbox ({}). Grant access with `{}`, \
+             or set ambient_sensor = \"als\" to use an ambient light sensor instead",
+            self.sandbox,
+            self.source,
+            self.sandbox.remedy()
+        )
+    }
+}
+
+impl Error for CameraDenied {}
+
+/// If `err` looks like sandbox confinement (device node missing or access
+/// denied while running inside a sandbox), wrap it in [`CameraDenied`].
+pub fn classify_camera_error(err: Box<dyn Error>) -> Box<dyn Error> {
+    let Some(sandbox) = Sandbox::detect() else {
+        return err;
+    };
+    match err.downcast::<io::Error>() {
+        Ok(io_err)
+            if matches!(
+                io_err.kind(),
+                io::ErrorKind::PermissionDenied | io::ErrorKind::NotFound
+            ) =>
+        {
+            Box::new(CameraDenied {
+                sandbox,
+                source: *io_err,
+            })
+        }
+        Ok(io_err) => io_err,
+        Err(other) => other,
+    }
+}
@@ -0,0 +1,579 @@
+// src/sensor.rs
+//! Ambient light sources behind one interface.
+//!
+//! Each backend (camera, IIO light sensor, `sensor_command`, `sensor_file`,
+//! `sensor_listen`) implements [`AmbientSensor`] and reports readings in its
+//! own unit. [`AmbientInput`] opens `ambient_sensor` and the
+//! `sensor_fallbacks` behind it, turns readings into luma in [0, 1], keeps
+//! track of how each sensor is doing and moves down the list when the
+//! active one fails `sensor_failover_after` times in a row. A preferred
+//! sensor that was given up on is tried again every `FAILBACK_INTERVAL`.
+//! Camera readings go through `camera_correction` first, if calibrated.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use tracing::{error, info, warn};
+
+use crate::als::AmbientLightSensor;
+use crate::camera::{self, Camera, CameraKind};
+use crate::config::{AmbientSensorKind, CameraCaptureMode, Config};
+use crate::correction::Correction;
+use crate::devices;
+use crate::external_sensor::ExternalSensor;
+use crate::lux::LuxEstimator;
+use crate::masking::BrightMask;
+use crate::sandbox::{self, CameraDenied};
+
+mod file;
+mod network;
+
+use file::FileSensor;
+use network::NetworkSensor;
+
+/// How often a preferred sensor that failed is tried again.
+const FAILBACK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a reading's `value` measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Already ambient brightness in [0, 1].
+    Luma,
+    /// Illuminance; scaled by `als_max_value`.
+    Lux,
+    /// Device counts; scaled by `als_max_value`.
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Reading {
+    pub value: f32,
+    pub unit: Unit,
+    pub timestamp: Instant,
+}
+
+/// A source of ambient light readings.
+pub trait AmbientSensor {
+    fn name(&self) -> String;
+
+    fn read(&mut self) -> Result<Reading, Box<dyn Error>>;
+
+    /// A cheaper reading where the sensor has one, for the fast path of the
+    /// control loop.
+    fn read_fast(&mut self) -> Result<Reading, Box<dyn Error>> {
+        self.read()
+    }
+
+    /// Illuminance that came with the last reading, for sensors whose
+    /// readings aren't in lux.
+    fn lux(&self) -> Option<f32> {
+        None
+    }
+
+    fn warmup(&mut self, _max_frames: usize, _tolerance: f32) {}
+
+    /// Release the hardware until the next reading.
+    fn pause(&mut self) {}
+
+    /// Motion in the last reading, 0 where the sensor can't tell.
+    fn motion(&self) -> f32 {
+        0.0
+    }
+
+    /// Regions of the view ignored as bright light sources.
+    fn masked_cells(&self) -> usize {
+        0
+    }
+}
+
+impl AmbientSensor for Camera {
+    fn name(&self) -> String {
+        "camera".into()
+    }
+
+    fn read(&mut self) -> Result<Reading, Box<dyn Error>> {
+        self.measure_luma(false).map(luma_reading)
+    }
+
+    fn read_fast(&mut self) -> Result<Reading, Box<dyn Error>> {
+        self.measure_luma(true).map(luma_reading)
+    }
+
+    /// Estimated from the exposure settings.
+    fn lux(&self) -> Option<f32> {
+        Camera::lux(self)
+    }
+
+    fn warmup(&mut self, max_frames: usize, tolerance: f32) {
+        Camera::warmup(self, max_frames, tolerance);
+    }
+
+    fn pause(&mut self) {
+        self.close();
+    }
+
+    fn motion(&self) -> f32 {
+        Camera::motion(self)
+    }
+
+    fn masked_cells(&self) -> usize {
+        Camera::masked_cells(self)
+    }
+}
+
+fn luma_reading(value: f32) -> Reading {
+    Reading {
+        value,
+        unit: Unit::Luma,
+        timestamp: Instant::now(),
+    }
+}
+
+/// How a sensor has been doing.
+#[derive(Debug, Clone, Copy, Default)]
+struct Health {
+    consecutive_failures: u32,
+    failures: u64,
+    last_ok: Option<Instant>,
+    /// When to try it again after it was given up on.
+    retry_at: Option<Instant>,
+}
+
+struct Slot {
+    kind: AmbientSensorKind,
+    /// None while it can't be opened.
+    sensor: Option<Box<dyn AmbientSensor>>,
+    health: Health,
+}
+
+impl Slot {
+    /// A sensor that couldn't be opened is retried after `FAILBACK_INTERVAL`.
+    fn new(kind: AmbientSensorKind, sensor: Option<Box<dyn AmbientSensor>>) -> Self {
+        let retry_at = sensor.is_none().then(|| Instant::now() + FAILBACK_INTERVAL);
+        Self {
+            kind,
+            sensor,
+            health: Health {
+                retry_at,
+                ..Health::default()
+            },
+        }
+    }
+
+    fn read(&mut self, fast: bool) -> Result<Reading, Box<dyn Error>> {
+        let sensor = self.sensor.as_mut().ok_or("sensor is not open")?;
+        let r = if fast { sensor.read_fast() } else { sensor.read() };
+        match &r {
+            Ok(_) => {
+                self.health.consecutive_failures = 0;
+                self.health.last_ok = Some(Instant::now());
+                self.health.retry_at = None;
+            }
+            Err(_) => {
+                self.health.consecutive_failures += 1;
+                self.health.failures += 1;
+            }
+        }
+        r
+    }
+}
+
+/// The configured sensors in order of preference, one of them active.
+pub struct AmbientInput {
+    slots: Vec<Slot>,
+    active: usize,
+    full_scale: f32,
+    failover_after: u32,
+    warmup: (usize, f32),
+    last_lux: Option<f32>,
+    correction: Option<Correction>,
+    /// For reopening sensors that couldn't be opened at first.
+    cfg: Config,
+}
+
+impl AmbientInput {
+    /// Open `ambient_sensor` and its fallbacks; the first that opens becomes
+    /// active. A camera blocked by a sandbox falls back to an ambient light
+    /// sensor even without `sensor_fallbacks`.
+    pub fn open(cfg: &Config) -> Result<Self, Box<dyn Error>> {
+        let mut kinds = vec![cfg.ambient_sensor];
+        for kind in &cfg.sensor_fallbacks {
+            if !kinds.contains(kind) {
+                kinds.push(*kind);
+            }
+        }
+        let mut slots = Vec::with_capacity(kinds.len() + 1);
+        let mut first_error: Option<Box<dyn Error>> = None;
+        let mut camera_denied = false;
+        for kind in kinds {
+            let sensor = match open_sensor(cfg, kind) {
+                Ok(sensor) => Some(sensor),
+                Err(err) => {
+                    let err = match kind {
+                        AmbientSensorKind::Camera => sandbox::classify_camera_error(err),
+                        _ => err,
+                    };
+                    if err.is::<CameraDenied>() {
+                        error!(target: "sensor", "{}", err);
+                        camera_denied = true;
+                    } else if !cfg.sensor_fallbacks.is_empty() {
+                        warn!(target: "sensor", "Cannot open {:?} sensor: {}", kind, err);
+                    }
+                    first_error.get_or_insert(err);
+                    None
+                }
+            };
+            slots.push(Slot::new(kind, sensor));
+        }
+        if camera_denied
+            && cfg.sensor_fallbacks.is_empty()
+            && let Some(als) = AmbientLightSensor::detect(&cfg.sysfs_root())
+        {
+            slots.push(Slot::new(AmbientSensorKind::Als, Some(Box::new(als))));
+        }
+        let Some(active) = slots.iter().position(|s| s.sensor.is_some()) else {
+            return Err(first_error.unwrap_or_else(|| "no ambient sensor configured".into()));
+        };
+        if active > 0 {
+            warn!(
+                target: "sensor",
+                "Falling back to {}",
+                slots[active].sensor.as_ref().map(|s| s.name()).unwrap_or_default()
+            );
+        }
+        Ok(Self {
+            slots,
+            active,
+            full_scale: cfg.als_max_value.max(f32::EPSILON),
+            failover_after: cfg.sensor_failover_after,
+            warmup: (cfg.warmup_frames, cfg.warmup_tolerance),
+            last_lux: None,
+            correction: Correction::new(&cfg.camera_correction),
+            cfg: cfg.clone(),
+        })
+    }
+
+    fn sensor_mut(&mut self) -> Option<&mut Box<dyn AmbientSensor>> {
+        self.slots[self.active].sensor.as_mut()
+    }
+
+    fn sensor(&self) -> Option<&dyn AmbientSensor> {
+        self.slots[self.active].sensor.as_deref()
+    }
+
+    /// Name of the active sensor.
+    pub fn name(&self) -> String {
+        self.sensor().map(|s| s.name()).unwrap_or_default()
+    }
+
+    pub fn warmup(&mut self, max_frames: usize, tolerance: f32) {
+        self.warmup = (max_frames, tolerance);
+        if let Some(sensor) = self.sensor_mut() {
+            sensor.warmup(max_frames, tolerance);
+        }
+    }
+
+    /// Power the camera off until the next warmup or measurement; other
+    /// sensors are cheap to keep open.
+    pub fn pause(&mut self) {
+        if let Some(sensor) = self.sensor_mut() {
+            sensor.pause();
+        }
+    }
+
+    /// A reading from the active sensor as luma in [0, 1], failing over to
+    /// the next sensor once this one keeps failing.
+    pub fn measure(&mut self, half_precision: bool) -> Result<f32, Box<dyn Error>> {
+        self.try_fail_back(half_precision);
+        let result = self.slots[self.active].read(half_precision);
+        match result {
+            Ok(reading) => Ok(self.accept(reading)),
+            Err(e) => {
+                self.last_lux = None;
+                if self.slots[self.active].health.consecutive_failures >= self.failover_after {
+                    self.fail_over();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Switch to the next open sensor without waiting for
+    /// `sensor_failover_after`; false if there is none.
+    pub fn fall_back(&mut self) -> bool {
+        let active = self.active;
+        self.fail_over();
+        self.active != active
+    }
+
+    /// Normalize `reading` and note its illuminance.
+    fn accept(&mut self, reading: Reading) -> f32 {
+        self.last_lux = match reading.unit {
+            Unit::Lux => Some(reading.value),
+            Unit::Luma | Unit::Raw => self.sensor().and_then(|s| s.lux()),
+        };
+        let luma = match reading.unit {
+            Unit::Luma => reading.value.clamp(0.0, 1.0),
+            Unit::Lux | Unit::Raw => (reading.value / self.full_scale).clamp(0.0, 1.0),
+        };
+        match &self.correction {
+            Some(correction) if self.slots[self.active].kind == AmbientSensorKind::Camera => {
+                correction.apply(luma)
+            }
+            _ => luma,
+        }
+    }
+
+    /// Give up on the active sensor for the next one that is open.
+    fn fail_over(&mut self) {
+        let now = Instant::now();
+        let slot = &mut self.slots[self.active];
+        slot.health.retry_at = Some(now + FAILBACK_INTERVAL);
+        let failed = slot.sensor.as_ref().map(|s| s.name()).unwrap_or_default();
+        let health = slot.health;
+        let next = (1..self.slots.len())
+            .map(|i| (self.active + i) % self.slots.len())
+            .find(|&i| self.slots[i].sensor.is_some());
+        let Some(next) = next else {
+            return;
+        };
+        if let Some(sensor) = self.sensor_mut() {
+            sensor.pause();
+        }
+        self.active = next;
+        let (frames, tolerance) = self.warmup;
+        let sensor = self.slots[next].sensor.as_mut().expect("checked above");
+        warn!(
+            target: "sensor",
+            "{} failed {} times in a row ({} in total, last good reading {}); switching to {}",
+            failed,
+            health.consecutive_failures,
+            health.failures,
+            health
+                .last_ok
+                .map_or("never".into(), |t| format!("{:.0?} ago", t.elapsed())),
+            sensor.name()
+        );
+        sensor.warmup(frames, tolerance);
+    }
+
+    /// Go back to a more preferred sensor once it works again.
+    fn try_fail_back(&mut self, fast: bool) {
+        let now = Instant::now();
+        for i in 0..self.active {
+            let slot = &mut self.slots[i];
+            if slot.health.retry_at.is_none_or(|t| now < t) {
+                continue;
+            }
+            slot.health.retry_at = Some(now + FAILBACK_INTERVAL);
+            if slot.sensor.is_none() {
+                slot.sensor = open_sensor(&self.cfg, slot.kind).ok();
+            }
+            let Some(sensor) = slot.sensor.as_mut() else {
+                continue;
+            };
+            let (frames, tolerance) = self.warmup;
+            sensor.warmup(frames, tolerance);
+            if slot.read(fast).is_ok() {
+                info!(
+                    target: "sensor",
+                    "{} works again; switching back",
+                    slot.sensor.as_ref().map(|s| s.name()).unwrap_or_default()
+                );
+                if let Some(sensor) = self.sensor_mut() {
+                    sensor.pause();
+                }
+                self.active = i;
+                return;
+            }
+            if let Some(sensor) = self.slots[i].sensor.as_mut() {
+                sensor.pause();
+            }
+        }
+    }
+
+    /// Motion score of the last camera frame; always 0 for other sensors.
+    pub fn motion(&self) -> f32 {
+        self.sensor().map_or(0.0, |s| s.motion())
+    }
+
+    /// Illuminance of the last reading: measured by an ALS that reports lux,
+    /// estimated from exposure settings for the camera, and whatever an
+    /// external sensor sent.
+    pub fn lux(&self) -> Option<f32> {
+        self.last_lux
+    }
+
+    /// Frame regions the camera currently ignores as bright sources.
+    pub fn masked_cells(&self) -> usize {
+        self.sensor().map_or(0, |s| s.masked_cells())
+    }
+
+    pub fn average_over(&mut self, samples: usize) -> Result<f32, Box<dyn Error>> {
+        let samples = samples.max(1);
+        let mut acc = 0.0f32;
+        for _ in 0..samples {
+            acc += self.measure(false)?;
+            if self.slots[self.active].kind != AmbientSensorKind::Camera {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        Ok(acc / samples as f32)
+    }
+}
+
+fn open_sensor(cfg: &Config, kind: AmbientSensorKind) -> Result<Box<dyn AmbientSensor>, Box<dyn Error>> {
+    let stale = Duration::from_secs(cfg.sensor_stale_secs);
+    Ok(match kind {
+        AmbientSensorKind::Camera => Box::new(open_camera(cfg)?),
+        AmbientSensorKind::Als => Box::new(
+            AmbientLightSensor::detect(&cfg.sysfs_root()).ok_or("no IIO ambient light sensor found")?,
+        ),
+        AmbientSensorKind::Command => {
+            let command = cfg.sensor_command.as_deref().unwrap_or_default();
+            let timeout = Duration::from_millis(cfg.sensor_command_timeout_ms);
+            Box::new(ExternalSensor::spawn(command, timeout)?)
+        }
+        AmbientSensorKind::File => {
+            let path = cfg.sensor_file.clone().unwrap_or_default();
+            Box::new(FileSensor::open(path, stale)?)
+        }
+        AmbientSensorKind::Network => {
+            let addr = cfg.sensor_listen.as_deref().unwrap_or_default();
+            Box::new(NetworkSensor::bind(addr, stale)?)
+        }
+    })
+}
+
+/// `camera_index`, unless that is the default 0 and an infrared camera while
+/// another one sees the room: laptops often list their face-login camera
+/// first.
+fn pick_camera(cfg: &Config) -> usize {
+    let index = cfg.camera_device;
+    if camera::camera_kind(index) != CameraKind::Infrared {
+        return index;
+    }
+    let visible = devices::cameras(&cfg.sysfs_root())
+        .into_iter()
+        .map(|(i, _)| i)
+        .find(|&i| i != index && camera::camera_kind(i) == CameraKind::Visible);
+    match visible {
+        Some(other) if index == 0 => {
+            info!(target: "camera", "/dev/video0 is an infrared camera; using /dev/video{} instead", other);
+            other
+        }
+        _ => {
+            warn!(
+                target: "camera",
+                "camera_index {} is an infrared or depth camera; its readings won't follow the room{}",
+                index,
+                visible.map(|i| format!(" (try camera_index = {})", i)).unwrap_or_default()
+            );
+            index
+        }
+    }
+}
+
+fn open_camera(cfg: &Config) -> Result<Camera, Box<dyn Error>> {
+    let (w, h) = (cfg.resolution[0], cfg.resolution[1]);
+    let index = pick_camera(cfg);
+    let mut cam = match cfg.camera_capture_mode {
+        CameraCaptureMode::Continuous => Camera::open(index, w, h, cfg.camera_fourcc)?,
+        CameraCaptureMode::Burst => {
+            info!(
+                target: "camera",
+                "Burst capture: {} frames every {} ms",
+                cfg.camera_burst_frames,
+                cfg.camera_burst_interval_ms
+            );
+            Camera::open_burst(index, w, h, cfg.camera_fourcc, cfg.camera_burst_frames)?
+        }
+    };
+    if cfg.bright_mask_enabled {
+        cam.set_bright_mask(Some(BrightMask::new(
+            cfg.bright_mask_threshold,
+            cfg.bright_mask_adapt_secs,
+            cfg.bright_mask_weight,
+        )));
+    }
+    cam.set_roi(cfg.camera_roi);
+    cam.set_center_exclusion(cfg.camera_exclude_center);
+    cam.set_luma_metric(cfg.luma_metric);
+    cam.set_luma_weighting(cfg.luma_weighting);
+    cam.set_motion_detection(cfg.motion_gate_enabled);
+    cam.set_fixed_exposure(cfg.camera_exposure, cfg.camera_gain);
+    cam.set_lux_estimator(LuxEstimator::from_config(cfg));
+    cam.set_warm_start(Duration::from_secs(cfg.camera_warm_start_secs));
+    Ok(cam)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails the first `failures` reads, then reports `value` in `unit`.
+    struct Scripted {
+        failures: u32,
+        value: f32,
+        unit: Unit,
+    }
+
+    impl AmbientSensor for Scripted {
+        fn name(&self) -> String {
+            "scripted".into()
+        }
+
+        fn read(&mut self) -> Result<Reading, Box<dyn Error>> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err("not ready".into());
+            }
+            Ok(Reading {
+                value: self.value,
+                unit: self.unit,
+                timestamp: Instant::now(),
+            })
+        }
+    }
+
+    fn input(sensors: Vec<Scripted>) -> AmbientInput {
+        AmbientInput {
+            slots: sensors
+                .into_iter()
+                .map(|s| Slot::new(AmbientSensorKind::Command, Some(Box::new(s))))
+                .collect(),
+            active: 0,
+            full_scale: 1000.0,
+            failover_after: 2,
+            warmup: (0, 0.0),
+            last_lux: None,
+            correction: None,
+            cfg: Config::default(),
+        }
+    }
+
+    #[test]
+    fn fails_over_after_repeated_errors_and_normalizes_lux() {
+        let mut input = input(vec![
+            Scripted { failures: u32::MAX, value: 0.9, unit: Unit::Luma },
+            Scripted { failures: 0, value: 250.0, unit: Unit::Lux },
+        ]);
+        assert!(input.measure(false).is_err());
+        assert_eq!(input.active, 0);
+        assert!(input.measure(false).is_err());
+        assert_eq!(input.active, 1);
+        assert_eq!(input.measure(false).unwrap(), 0.25);
+        assert_eq!(input.lux(), Some(250.0));
+    }
+
+    #[test]
+    fn a_single_failure_does_not_switch() {
+        let mut input = input(vec![
+            Scripted { failures: 1, value: 0.4, unit: Unit::Luma },
+            Scripted { failures: 0, value: 0.9, unit: Unit::Luma },
+        ]);
+        assert!(input.measure(false).is_err());
+        assert_eq!(input.measure(false).unwrap(), 0.4);
+        assert_eq!(input.active, 0);
+    }
+}
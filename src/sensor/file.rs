@@ -0,0 +1,61 @@
+// src/sensor/file.rs
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::{AmbientSensor, Reading, Unit};
+use crate::external_sensor::{parse_reply, Reply};
+
+/// A file another program keeps up to date with one line in the
+/// `sensor_command` reply format, e.g. from a Home Assistant automation.
+/// A file not written for `stale_after` no longer counts as a reading.
+pub struct FileSensor {
+    path: PathBuf,
+    stale_after: Duration,
+    last_lux: Option<f32>,
+}
+
+impl FileSensor {
+    pub fn open(path: PathBuf, stale_after: Duration) -> Result<Self, Box<dyn Error>> {
+        fs::metadata(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Ok(Self {
+            path,
+            stale_after,
+            last_lux: None,
+        })
+    }
+}
+
+impl AmbientSensor for FileSensor {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn read(&mut self) -> Result<Reading, Box<dyn Error>> {
+        self.last_lux = None;
+        let age = fs::metadata(&self.path)?
+            .modified()?
+            .elapsed()
+            .unwrap_or_default();
+        if age > self.stale_after {
+            return Err(format!("{} not updated for {:?}", self.path.display(), age).into());
+        }
+        let text = fs::read_to_string(&self.path)?;
+        match parse_reply(&text)? {
+            Reply::Reading { luma, lux } => {
+                self.last_lux = lux;
+                Ok(Reading {
+                    value: luma,
+                    unit: Unit::Luma,
+                    timestamp: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+                })
+            }
+            Reply::Error(msg) => Err(format!("{}: {}", self.path.display(), msg).into()),
+        }
+    }
+
+    fn lux(&self) -> Option<f32> {
+        self.last_lux
+    }
+}
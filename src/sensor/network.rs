@@ -0,0 +1,81 @@
+// src/sensor/network.rs
+use std::error::Error;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+
+use super::{AmbientSensor, Reading, Unit};
+use crate::external_sensor::{parse_reply, Reply};
+
+/// Readings pushed over UDP, one datagram per reading in the
+/// `sensor_command` reply format, e.g. from an ESP32 with a light sensor.
+/// Each `read` takes the newest datagram that arrived; none within
+/// `stale_after` means no reading.
+pub struct NetworkSensor {
+    socket: UdpSocket,
+    stale_after: Duration,
+    last: Option<(Reading, Option<f32>)>,
+}
+
+impl NetworkSensor {
+    pub fn bind(addr: &str, stale_after: Duration) -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind(addr).map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+        socket.set_nonblocking(true)?;
+        info!(target: "sensor", "Listening for readings on udp://{}", addr);
+        Ok(Self {
+            socket,
+            stale_after,
+            last: None,
+        })
+    }
+
+    /// Take everything queued on the socket, keeping the newest reading.
+    fn drain(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Ok(r) => r,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            let text = String::from_utf8_lossy(&buf[..len]);
+            match parse_reply(&text) {
+                Ok(Reply::Reading { luma, lux }) => {
+                    let reading = Reading {
+                        value: luma,
+                        unit: Unit::Luma,
+                        timestamp: Instant::now(),
+                    };
+                    self.last = Some((reading, lux));
+                }
+                Ok(Reply::Error(msg)) => debug!(target: "sensor", "{} reported: {}", from, msg),
+                Err(e) => debug!(target: "sensor", "ignoring datagram from {}: {}", from, e),
+            }
+        }
+    }
+}
+
+impl AmbientSensor for NetworkSensor {
+    fn name(&self) -> String {
+        match self.socket.local_addr() {
+            Ok(addr) => format!("udp://{}", addr),
+            Err(_) => "network sensor".into(),
+        }
+    }
+
+    fn read(&mut self) -> Result<Reading, Box<dyn Error>> {
+        self.drain()?;
+        match self.last {
+            Some((reading, _)) if reading.timestamp.elapsed() <= self.stale_after => Ok(reading),
+            _ => Err(format!("no reading received for {:?}", self.stale_after).into()),
+        }
+    }
+
+    fn lux(&self) -> Option<f32> {
+        self.last
+            .filter(|(reading, _)| reading.timestamp.elapsed() <= self.stale_after)
+            .and_then(|(_, lux)| lux)
+    }
+}
@@ -0,0 +1,355 @@
+// src/simulation.rs
+//! Deterministic runs of the controller for regression tests.
+//!
+//! A `Scenario` scripts ambient luma over simulated time and drives a
+//! `Controller` with a fake clock that jumps straight to the next due event,
+//! so minutes of behavior run in microseconds and every backlight write is
+//! recorded with the exact simulated time it happened at.
+
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use crate::config::Config;
+use crate::controller::{BacklightSink, Clock, Controller, LumaSource, Tick};
+use crate::history::HistoryRecorder;
+use crate::state_dump::LoopState;
+use crate::stats::RunStats;
+
+/// A clock that only moves when told to; clones share the same time. The
+/// wall clock keeps pace with it, suspended time included.
+#[derive(Clone)]
+pub struct FakeClock(Rc<Cell<Instant>>, Rc<Cell<Duration>>, (Instant, DateTime<Local>));
+
+impl FakeClock {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self(
+            Rc::new(Cell::new(now)),
+            Rc::new(Cell::new(Duration::ZERO)),
+            (now, Local::now()),
+        )
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.0.set(self.0.get() + d);
+    }
+
+    /// Sleep for `d`: suspended time grows while `now` stands still.
+    pub fn suspend(&self, d: Duration) {
+        self.1.set(self.1.get() + d);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+
+    fn suspended(&self) -> Duration {
+        self.1.get()
+    }
+
+    fn wall(&self) -> DateTime<Local> {
+        let (start, wall) = self.2;
+        let passed = self.0.get().duration_since(start) + self.1.get();
+        wall + chrono::Duration::from_std(passed).unwrap_or_else(|_| chrono::Duration::zero())
+    }
+}
+
+/// Luma as a step function of simulated time.
+struct ScriptedSensor {
+    clock: FakeClock,
+    start: Instant,
+    steps: Vec<(Duration, f32)>,
+}
+
+impl LumaSource for ScriptedSensor {
+    fn measure(&mut self, _half_precision: bool) -> Result<f32, Box<dyn Error>> {
+        let t = self.clock.now().duration_since(self.start);
+        self.steps
+            .iter()
+            .take_while(|(at, _)| *at <= t)
+            .last()
+            .map(|&(_, luma)| luma)
+            .ok_or_else(|| "no luma scripted yet".into())
+    }
+}
+
+/// Records every write with the simulated time it happened at.
+struct RecordingBacklight {
+    clock: FakeClock,
+    start: Instant,
+    writes: RefCell<Vec<(Duration, u32)>>,
+}
+
+impl BacklightSink for RecordingBacklight {
+    fn set(&self, value: u32) -> std::io::Result<()> {
+        let t = self.clock.now().duration_since(self.start);
+        self.writes.borrow_mut().push((t, value));
+        Ok(())
+    }
+
+    fn actual(&self) -> Option<u32> {
+        self.writes.borrow().last().map(|&(_, v)| v)
+    }
+}
+
+pub struct Scenario {
+    cfg: Config,
+    start_value: u32,
+    hardware_max: u32,
+    steps: Vec<(Duration, f32)>,
+    resume: Option<LoopState>,
+}
+
+/// Backlight writes of a finished run, as (time since start, value).
+#[derive(Debug)]
+pub struct Trace {
+    pub writes: Vec<(Duration, u32)>,
+    pub stats: RunStats,
+}
+
+/// Guards against a controller that reports work without time moving.
+const MAX_TICKS: usize = 1_000_000;
+
+impl Scenario {
+    pub fn new(cfg: Config) -> Self {
+        Self {
+            start_value: cfg.real_min_brightness,
+            hardware_max: cfg.real_max_brightness,
+            cfg,
+            steps: Vec::new(),
+            resume: None,
+        }
+    }
+
+    pub fn start_at(mut self, value: u32) -> Self {
+        self.start_value = value;
+        self
+    }
+
+    /// Start from a loop state taken with `ctl dump-state`.
+    pub fn resume(mut self, state: LoopState) -> Self {
+        self.resume = Some(state);
+        self
+    }
+
+    /// Ambient luma is `luma` from `at` on, until the next step.
+    pub fn luma_at(mut self, at: Duration, luma: f32) -> Self {
+        self.steps.push((at, luma));
+        self.steps.sort_by_key(|&(at, _)| at);
+        self
+    }
+
+    pub fn run_for(&self, duration: Duration) -> Trace {
+        let clock = FakeClock::new();
+        let start = clock.now();
+        let sensor = ScriptedSensor {
+            clock: clock.clone(),
+            start,
+            steps: self.steps.clone(),
+        };
+        let backlight = RecordingBacklight {
+            clock: clock.clone(),
+            start,
+            writes: RefCell::new(Vec::new()),
+        };
+        let mut ctl = Controller::new(
+            &self.cfg,
+            sensor,
+            backlight,
+            clock.clone(),
+            self.start_value,
+            self.hardware_max,
+            Some(duration),
+        );
+        if let Some(state) = &self.resume {
+            ctl.restore(state);
+        }
+        let mut stats = RunStats::new();
+        let mut history = HistoryRecorder::from_config(&self.cfg);
+        for _ in 0..MAX_TICKS {
+            match ctl.tick(&mut stats, &mut history) {
+                Tick::Expired => {
+                    let writes = ctl.into_parts().1.writes.into_inner();
+                    return Trace { writes, stats };
+                }
+                Tick::Busy => {}
+                Tick::Idle(wait) => clock.advance(wait.max(Duration::from_millis(1))),
+            }
+        }
+        panic!("simulation did not finish within {} ticks", MAX_TICKS);
+    }
+}
+
+impl Trace {
+    pub fn last_value(&self) -> Option<u32> {
+        self.writes.last().map(|&(_, v)| v)
+    }
+
+    /// Writes at or after `t`.
+    pub fn after(&self, t: Duration) -> impl Iterator<Item = (Duration, u32)> + '_ {
+        self.writes.iter().copied().filter(move |&(at, _)| at >= t)
+    }
+
+    /// Shortest time between two consecutive writes.
+    pub fn min_gap(&self) -> Option<Duration> {
+        self.writes.windows(2).map(|w| w[1].0 - w[0].0).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    fn base_config() -> Config {
+        Config {
+            smoothing_factor: 1.0,
+            real_min_brightness: 0,
+            real_max_brightness: 100,
+            camera_min_luma: None,
+            camera_max_luma: None,
+            enable_circadian: false,
+            min_luma_delta: 0.02,
+            capture_interval_ms: 100,
+            smooth_interval_ms: 20,
+            smooth_step_divisor: 4,
+            smooth_max_step: 10,
+            history_enabled: false,
+            log_target_brightness: false,
+            override_enabled: false,
+            startup_grace_captures: 0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn step_in_light_converges_monotonically() {
+        let trace = Scenario::new(base_config())
+            .start_at(20)
+            .luma_at(secs(0.0), 0.2)
+            .luma_at(secs(1.0), 0.8)
+            .run_for(secs(5.0));
+        assert_eq!(trace.last_value(), Some(80));
+        let rising: Vec<u32> = trace.after(secs(1.0)).map(|(_, v)| v).collect();
+        assert!(rising.windows(2).all(|w| w[0] < w[1]), "{:?}", rising);
+        // Nothing moves before the light does.
+        assert!(trace.writes.iter().all(|&(t, _)| t >= secs(1.0)));
+    }
+
+    #[test]
+    fn steps_respect_interval_and_max_step() {
+        let cfg = base_config();
+        let trace = Scenario::new(cfg.clone())
+            .start_at(0)
+            .luma_at(secs(0.0), 1.0)
+            .run_for(secs(3.0));
+        assert_eq!(trace.last_value(), Some(100));
+        assert!(trace.min_gap().unwrap() >= Duration::from_millis(cfg.smooth_interval_ms));
+        let mut prev = 0;
+        for (_, v) in &trace.writes {
+            assert!(v - prev <= cfg.smooth_max_step, "jump {} -> {}", prev, v);
+            prev = *v;
+        }
+    }
+
+    #[test]
+    fn first_step_of_a_transition_is_immediate() {
+        let trace = Scenario::new(base_config())
+            .start_at(50)
+            .luma_at(secs(0.0), 0.5)
+            .luma_at(secs(1.0), 0.7)
+            .run_for(secs(2.0));
+        // The change is seen at the first capture at or after 1 s.
+        let (t, _) = trace.writes[0];
+        assert!(t >= secs(1.0) && t < secs(1.1), "{:?}", t);
+    }
+
+    #[test]
+    fn startup_grace_holds_writes_then_converges_from_current() {
+        let cfg = Config {
+            startup_grace_captures: 5,
+            ..base_config()
+        };
+        let trace = Scenario::new(cfg)
+            .start_at(30)
+            .luma_at(secs(0.0), 0.9)
+            .run_for(secs(3.0));
+        // Five readings at 100 ms intervals go by before the first target.
+        let (t, first) = trace.writes[0];
+        assert!(t >= secs(0.5), "{:?}", t);
+        assert!(first > 30 && first < 90, "jumped to {}", first);
+        assert_eq!(trace.last_value(), Some(90));
+    }
+
+    #[test]
+    fn flicker_below_threshold_causes_no_writes() {
+        let mut scenario = Scenario::new(base_config()).start_at(50);
+        for i in 0..50 {
+            let luma = if i % 2 == 0 { 0.50 } else { 0.51 };
+            scenario = scenario.luma_at(secs(i as f64 * 0.1), luma);
+        }
+        let trace = scenario.run_for(secs(5.0));
+        assert!(trace.writes.is_empty(), "{:?}", trace.writes);
+    }
+
+    #[test]
+    fn smoothing_spreads_a_step_over_several_captures() {
+        let cfg = Config {
+            smoothing_factor: 0.3,
+            ..base_config()
+        };
+        let trace = Scenario::new(cfg)
+            .start_at(0)
+            .luma_at(secs(0.0), 0.0)
+            .luma_at(secs(1.0), 1.0)
+            .run_for(secs(10.0));
+        // The average stops within `min_luma_delta` of the new level.
+        assert!(trace.last_value().unwrap() >= 90, "{:?}", trace.writes);
+        // Without smoothing, 85 would be reached in 9 steps (0.2 s).
+        let reached = trace.writes.iter().find(|&&(_, v)| v >= 85).unwrap().0;
+        assert!(reached > secs(1.4), "{:?}", reached);
+        assert!(trace.stats.brightness_changes > 1);
+    }
+
+    #[test]
+    fn dumped_state_resumes_mid_transition() {
+        let state = LoopState {
+            ema: Some(0.8),
+            adjusted_luma: 0.8,
+            brightness: 20,
+            target: 80,
+            step: 5,
+            ..LoopState::default()
+        };
+        let trace = Scenario::new(base_config())
+            .luma_at(secs(0.0), 0.8)
+            .resume(state)
+            .run_for(secs(2.0));
+        // Back at the dumped level, then on in the dumped step size; the
+        // unchanged reading sets no new target.
+        assert_eq!(trace.writes[0], (secs(0.0), 20));
+        assert_eq!(trace.writes[1].1, 25);
+        assert_eq!(trace.last_value(), Some(80));
+    }
+
+    #[test]
+    fn runs_are_reproducible() {
+        let scenario = Scenario::new(base_config())
+            .start_at(30)
+            .luma_at(secs(0.0), 0.3)
+            .luma_at(secs(0.5), 0.9)
+            .luma_at(secs(2.0), 0.1);
+        let a = scenario.run_for(secs(4.0));
+        let b = scenario.run_for(secs(4.0));
+        assert_eq!(a.writes, b.writes);
+    }
+}
@@ -1,70 +1,470 @@
 // src/smooth_transition.rs
 use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
+
+/// How a transition moves: a step every `interval`, each `1/divisor` of the
+/// distance when the target was set, and at most `max_step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pace {
+    pub interval: Duration,
+    pub divisor: u32,
+    pub max_step: u32,
+}
+
+impl Pace {
+    /// The whole way in one step.
+    pub const INSTANT: Pace = Pace {
+        interval: Duration::ZERO,
+        divisor: 1,
+        max_step: u32::MAX,
+    };
+
+    pub fn new(interval_ms: u64, divisor: u32, max_step: u32) -> Self {
+        Self {
+            interval: Duration::from_millis(interval_ms),
+            divisor: divisor.max(1),
+            max_step: max_step.max(1),
+        }
+    }
+}
+
+/// What set a target, for the pace of getting there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    /// A reading; the top-level step settings.
+    Ambient,
+    /// The time of day moved the target, not the room.
+    Circadian,
+    /// A boost from the dashboard.
+    Boost,
+    /// A level set by hand through the override file.
+    Manual,
+}
+
+/// One `[easing.<cause>]` table; unset fields keep the cause's default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaceSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_divisor: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_step: Option<u32>,
+}
+
+impl PaceSettings {
+    fn over(&self, default: Pace) -> Pace {
+        Pace {
+            interval: self.interval_ms.map_or(default.interval, Duration::from_millis),
+            divisor: self.step_divisor.map_or(default.divisor, |d| d.max(1)),
+            max_step: self.max_step.map_or(default.max_step, |s| s.max(1)),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Transition paces per cause (`[easing]`). Ambient changes use the
+/// top-level `brightness_step_*` settings; by default a manual level is
+/// applied at once, a boost takes three steps and a circadian shift creeps
+/// in over about 15 seconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Easing {
+    #[serde(default, skip_serializing_if = "PaceSettings::is_default")]
+    pub manual: PaceSettings,
+    #[serde(default, skip_serializing_if = "PaceSettings::is_default")]
+    pub boost: PaceSettings,
+    #[serde(default, skip_serializing_if = "PaceSettings::is_default")]
+    pub circadian: PaceSettings,
+}
+
+impl Easing {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Pace for targets set by `cause`.
+    pub fn pace(&self, cause: Cause, ambient: Pace) -> Pace {
+        match cause {
+            Cause::Ambient => ambient,
+            Cause::Manual => self.manual.over(Pace::INSTANT),
+            Cause::Boost => self.boost.over(Pace { divisor: 3, ..ambient }),
+            Cause::Circadian => self.circadian.over(Pace::new(250, 60, 5)),
+        }
+    }
+}
+
 pub struct SmoothTransition {
     target: u32,
     current: u32,
     step: u32,
     min_step: u32,
     max_step: u32,
-    last: Instant,
+    /// Time of the last step; the first step of a transition is immediate.
+    last: Option<Instant>,
     interval: Duration,
     divisor: u32,
+    /// Pace of targets from readings, and the paces for other causes.
+    ambient: Pace,
+    easing: Easing,
+    /// Every pace is `Pace::INSTANT`; see `set_instant`.
+    instant: bool,
+    /// Set by `ramp_to`: the target is reached at an even pace by a fixed
+    /// time instead of in steps.
+    ramp: Option<Ramp>,
+    /// How long the way back to tracked targets takes after a hold; see
+    /// `release`.
+    return_over: Duration,
+    returning: bool,
+    /// A blend back waiting for its first `update` to start the ramp.
+    blend: Option<Duration>,
+}
+
+struct Ramp {
+    from: u32,
+    start: Instant,
+    end: Instant,
+}
+
+impl Ramp {
+    fn value_at(&self, now: Instant, target: u32) -> u32 {
+        if now >= self.end {
+            return target;
+        }
+        let total = self.end.duration_since(self.start).as_secs_f64();
+        let done = now.saturating_duration_since(self.start).as_secs_f64() / total;
+        let (from, target) = (self.from as f64, target as f64);
+        (from + (target - from) * done).round() as u32
+    }
 }
 
 impl SmoothTransition {
     pub fn new(initial: u32, interval_ms: u64, divisor: u32, max_step: u32) -> Self {
-        let divisor = divisor.max(1);
-        let max_step = max_step.max(1);
+        let ambient = Pace::new(interval_ms, divisor, max_step);
         Self {
             target: initial,
             current: initial,
             step: 1,
             min_step: 1,
-            max_step,
-            last: Instant::now(),
-            interval: Duration::from_millis(interval_ms),
-            divisor,
+            max_step: ambient.max_step,
+            last: None,
+            interval: ambient.interval,
+            divisor: ambient.divisor,
+            ambient,
+            easing: Easing::default(),
+            instant: false,
+            ramp: None,
+            return_over: Duration::ZERO,
+            returning: false,
+            blend: None,
         }
     }
 
+    /// Paces for targets not set by readings.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Time to blend back to tracked targets after `release`; zero paces
+    /// them like any other.
+    pub fn with_return(mut self, over: Duration) -> Self {
+        self.return_over = over;
+        self
+    }
+
+    /// A pause, boost or lock has ended: the next target is reached at an
+    /// even pace over the return time, from wherever the hold left the
+    /// brightness, instead of at its cause's pace.
+    pub fn release(&mut self) {
+        self.returning = !self.return_over.is_zero();
+    }
+
+    /// Head for `t` at the ambient pace.
     pub fn set_target(&mut self, t: u32, max_brightness: u32) {
+        self.set_target_by(t, max_brightness, Cause::Ambient);
+    }
+
+    /// Go every later target's whole way in one step, whatever set it.
+    pub fn set_instant(&mut self, instant: bool) {
+        self.instant = instant;
+    }
+
+    /// Head for `t` at the pace `[easing]` gives `cause`.
+    pub fn set_target_by(&mut self, t: u32, max_brightness: u32, cause: Cause) {
+        let pace = if self.instant {
+            Pace::INSTANT
+        } else {
+            self.easing.pace(cause, self.ambient)
+        };
+        self.interval = pace.interval;
+        self.divisor = pace.divisor;
+        self.max_step = pace.max_step;
+        self.ramp = None;
+        let returning = std::mem::take(&mut self.returning) && !self.instant;
+        self.blend = returning.then_some(self.return_over);
         self.target = t.clamp(0, max_brightness);
         let diff = self.target.abs_diff(self.current);
         self.step = (diff / self.divisor).max(self.min_step).min(self.max_step);
     }
 
-    pub fn update(&mut self) -> Option<u32> {
+    /// Be at `t` straight away, without stepping there.
+    pub fn jump_to(&mut self, t: u32, max_brightness: u32) {
+        self.ramp = None;
+        self.blend = None;
+        self.target = t.clamp(0, max_brightness);
+        self.current = self.target;
+    }
+
+    /// Continue a transition from `current` towards `target` in steps of
+    /// `step`, as `set_target` had left it.
+    pub fn resume(&mut self, current: u32, target: u32, step: u32, max_brightness: u32) {
+        self.ramp = None;
+        self.blend = None;
+        self.current = current.min(max_brightness);
+        self.target = target.min(max_brightness);
+        self.step = step.clamp(self.min_step, self.max_step);
+    }
+
+    /// Size of each step towards the target.
+    pub fn step_size(&self) -> u32 {
+        self.step
+    }
+
+    /// Move to `t` at an even pace, arriving at `end` however far off that
+    /// is; values still change at most once per interval. `set_target`
+    /// cancels the ramp.
+    pub fn ramp_to(&mut self, t: u32, max_brightness: u32, now: Instant, end: Instant) {
+        self.target = t.clamp(0, max_brightness);
+        self.blend = None;
+        self.ramp = Some(Ramp {
+            from: self.current,
+            start: now,
+            end,
+        });
+    }
+
+    /// Take a step towards the target if one is due at `now`.
+    pub fn update(&mut self, now: Instant) -> Option<u32> {
         if self.current == self.target {
+            self.ramp = None;
+            self.blend = None;
             return None;
         }
-        let now = Instant::now();
-        if now.duration_since(self.last) < self.interval {
+        if let Some(over) = self.blend.take() {
+            self.ramp = Some(Ramp {
+                from: self.current,
+                start: now,
+                end: now + over,
+            });
+        }
+        if self
+            .last
+            .is_some_and(|last| now.saturating_duration_since(last) < self.interval)
+        {
             return None;
         }
+        if let Some(ramp) = &self.ramp {
+            let value = ramp.value_at(now, self.target);
+            if value == self.current {
+                return None;
+            }
+            self.current = value;
+            self.last = Some(now);
+            return Some(value);
+        }
         let step = self.step.min(self.target.abs_diff(self.current));
         self.current = if self.current < self.target {
             (self.current + step).min(self.target)
         } else {
             (self.current - step).max(self.target)
         };
-        self.last = now;
+        self.last = Some(now);
         Some(self.current)
     }
 
-    pub fn time_until_next_step(&self) -> Duration {
+    pub fn time_until_next_step(&self, now: Instant) -> Duration {
         if self.current == self.target {
             return Duration::from_secs(3600);
         }
-        let elapsed = Instant::now().saturating_duration_since(self.last);
-        if elapsed >= self.interval {
-            Duration::default()
-        } else {
-            self.interval - elapsed
-        }
+        let elapsed = self
+            .last
+            .map_or(self.interval, |last| now.saturating_duration_since(last));
+        let wait = match &self.ramp {
+            // Roughly how long one unit takes at the ramp's pace.
+            Some(ramp) => {
+                let units = ramp.from.abs_diff(self.target).max(1);
+                self.interval.max(ramp.end.saturating_duration_since(ramp.start) / units)
+            }
+            None => self.interval,
+        };
+        wait.saturating_sub(elapsed)
     }
 
     pub fn current_value(&self) -> u32 {
         self.current
     }
+
+    pub fn target_value(&self) -> u32 {
+        self.target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        SetTarget(u32),
+        Advance(u64),
+    }
+
+    fn op(max_brightness: u32) -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (0..=max_brightness.saturating_mul(2)).prop_map(Op::SetTarget),
+            (0u64..200).prop_map(Op::Advance),
+        ]
+    }
+
+    fn params() -> impl Strategy<Value = (u32, u64, u32, u32, u32)> {
+        (1u32..=100_000).prop_flat_map(|max| (0..=max, 0u64..100, 0u32..10, 0u32..500, Just(max)))
+    }
+
+    proptest! {
+        #[test]
+        fn steps_move_towards_target_within_limits(
+            (initial, interval_ms, divisor, max_step, max) in params(),
+            ops in prop::collection::vec(op(100_000), 1..200),
+        ) {
+            let mut st = SmoothTransition::new(initial, interval_ms, divisor, max_step);
+            let interval = Duration::from_millis(interval_ms);
+            let mut now = Instant::now();
+            let mut last_step: Option<Instant> = None;
+            for op in ops {
+                match op {
+                    Op::SetTarget(t) => st.set_target(t, max),
+                    Op::Advance(ms) => now += Duration::from_millis(ms),
+                }
+                prop_assert!(st.target_value() <= max);
+                let before = st.current_value();
+                let due = st.time_until_next_step(now).is_zero();
+                match st.update(now) {
+                    Some(value) => {
+                        prop_assert!(due);
+                        prop_assert_eq!(value, st.current_value());
+                        let target = st.target_value();
+                        // Strictly closer, never past the target.
+                        prop_assert!(value.abs_diff(target) < before.abs_diff(target));
+                        prop_assert!(value.min(before) >= before.min(target));
+                        prop_assert!(value.max(before) <= before.max(target));
+                        prop_assert!(value.abs_diff(before) <= max_step.max(1));
+                        if let Some(t) = last_step {
+                            prop_assert!(now.duration_since(t) >= interval);
+                        }
+                        last_step = Some(now);
+                    }
+                    None => {
+                        prop_assert!(!due || before == st.target_value());
+                        prop_assert_eq!(before, st.current_value());
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn transition_reaches_target(
+            (initial, interval_ms, divisor, max_step, max) in params(),
+            target in 0u32..=200_000,
+        ) {
+            let mut st = SmoothTransition::new(initial, interval_ms, divisor, max_step);
+            st.set_target(target, max);
+            let expected = target.min(max);
+            let mut now = Instant::now();
+            let mut steps = 0u32;
+            while st.current_value() != expected {
+                now += st.time_until_next_step(now);
+                prop_assert!(st.update(now).is_some());
+                steps += 1;
+                // Each step covers at least one unit.
+                prop_assert!(steps <= initial.abs_diff(expected));
+            }
+            prop_assert!(st.update(now + Duration::from_secs(3600)).is_none());
+        }
+
+        #[test]
+        fn ramp_moves_steadily_and_arrives_on_time(
+            initial in 0u32..=1000,
+            target in 0u32..=1000,
+            secs in 1u64..7200,
+            interval_ms in 0u64..100,
+        ) {
+            let mut st = SmoothTransition::new(initial, interval_ms, 4, 10);
+            let start = Instant::now();
+            let end = start + Duration::from_secs(secs);
+            st.ramp_to(target, 1000, start, end);
+            let mut now = start;
+            let mut prev = initial;
+            while now < end {
+                if let Some(value) = st.update(now) {
+                    prop_assert!(value.abs_diff(target) < prev.abs_diff(target));
+                    prev = value;
+                }
+                now += st.time_until_next_step(now).max(Duration::from_millis(1));
+            }
+            st.update(now);
+            prop_assert_eq!(st.current_value(), target);
+        }
+    }
+
+    #[test]
+    fn pace_follows_the_cause_of_the_target() {
+        let easing = Easing {
+            circadian: PaceSettings {
+                max_step: Some(2),
+                ..PaceSettings::default()
+            },
+            ..Easing::default()
+        };
+        let mut st = SmoothTransition::new(0, 20, 10, 100).with_easing(easing);
+        let start = Instant::now();
+        st.set_target_by(80, 100, Cause::Manual);
+        assert_eq!(st.update(start), Some(80));
+        st.set_target_by(40, 100, Cause::Circadian);
+        // A 40 step distance is under 60 steps: one unit at a time.
+        assert_eq!(st.update(start + Duration::from_secs(1)), Some(79));
+        assert_eq!(st.time_until_next_step(start + Duration::from_secs(1)), Duration::from_millis(250));
+        st.set_target(0, 100);
+        assert_eq!(st.update(start + Duration::from_secs(2)), Some(72));
+    }
+
+    #[test]
+    fn released_hold_blends_back_over_the_return_time() {
+        let mut st = SmoothTransition::new(80, 20, 1, 100).with_return(Duration::from_secs(5));
+        let start = Instant::now();
+        st.release();
+        st.set_target(30, 100);
+        assert_eq!(st.update(start), None);
+        assert_eq!(st.update(start + Duration::from_secs(1)), Some(70));
+        assert_eq!(st.update(start + Duration::from_millis(2500)), Some(55));
+        assert_eq!(st.update(start + Duration::from_secs(5)), Some(30));
+        // Only the first target after the hold is blended into.
+        st.set_target(60, 100);
+        assert_eq!(st.update(start + Duration::from_secs(6)), Some(60));
+    }
+
+    #[test]
+    fn first_step_is_immediate_and_later_steps_wait() {
+        let mut st = SmoothTransition::new(0, 50, 1, 10);
+        let start = Instant::now();
+        st.set_target(30, 100);
+        assert_eq!(st.update(start), Some(10));
+        assert_eq!(st.update(start + Duration::from_millis(49)), None);
+        assert_eq!(st.time_until_next_step(start + Duration::from_millis(20)), Duration::from_millis(30));
+        assert_eq!(st.update(start + Duration::from_millis(50)), Some(20));
+    }
 }
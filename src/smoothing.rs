@@ -14,12 +14,30 @@ impl Ema {
         }
     }
 
-    pub fn update(&mut self, x: f32) -> f32 {
+    /// The average so far; None before the first sample.
+    pub fn value(&self) -> Option<f32> {
+        self.init.then_some(self.value)
+    }
+
+    /// Start from `value` as if it had been averaged already.
+    pub fn seed(&mut self, value: f32) {
+        self.value = value;
+        self.init = true;
+    }
+
+    /// Fold in `x`, with the step scaled by `weight` in [0, 1]. The first
+    /// sample always initializes the average. A non-finite `x` or `weight`
+    /// leaves the average as it is.
+    pub fn update_weighted(&mut self, x: f32, weight: f32) -> f32 {
+        if !(x.is_finite() && weight.is_finite()) {
+            return self.value;
+        }
         if !self.init {
             self.value = x;
             self.init = true;
         } else {
-            self.value = self.alpha * x + (1.0 - self.alpha) * self.value;
+            let alpha = self.alpha * weight.clamp(0.0, 1.0);
+            self.value = alpha * x + (1.0 - alpha) * self.value;
         }
         self.value
     }
@@ -0,0 +1,100 @@
+// src/state_dump.rs
+//! Snapshots of the running daemon for bug reports (`ctl dump-state`), and
+//! starting from one again (`--load-state`).
+//!
+//! A dump is one JSON document: the config in effect, the control loop's
+//! internal state, the recent readings kept for charts and the devices in
+//! use. Loaded, its config replaces the user's and the loop picks up where
+//! the dump left off, so the behavior in a report can be reproduced on
+//! another machine, or in a `simulation::Scenario`.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::control::Sample;
+use crate::controller::State;
+
+/// What the control loop carries from one reading to the next.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoopState {
+    /// Smoothed luma, before the circadian adjustment; None before the
+    /// first reading.
+    pub ema: Option<f32>,
+    /// Last reading as mapped to brightness.
+    pub adjusted_luma: f32,
+    pub lux: Option<f32>,
+    pub brightness: u32,
+    pub target: u32,
+    /// Size of each step of the transition under way.
+    pub step: u32,
+    /// Time left of a ramp under way.
+    pub ramp_remaining_secs: Option<f64>,
+    /// Readings failed for long and brightness is held.
+    pub held: bool,
+    /// The camera is covered by a privacy switch.
+    pub privacy: bool,
+    #[serde(default)]
+    pub state: State,
+}
+
+/// Devices the daemon opened at startup.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Devices {
+    pub backlight: String,
+    /// Where the backlight level is read back from.
+    pub backlight_actual: String,
+    pub sensor: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDump {
+    pub version: String,
+    /// Local time of the dump, RFC 3339.
+    pub time: String,
+    pub config: Config,
+    pub state: LoopState,
+    /// Luma and brightness over the last few minutes, oldest first.
+    pub samples: Vec<Sample>,
+    pub devices: Devices,
+}
+
+pub fn load(path: &Path) -> Result<StateDump, Box<dyn Error>> {
+    let data = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let dump: StateDump =
+        serde_json::from_str(&data).map_err(|e| format!("{} is not a state dump: {}", path.display(), e))?;
+    dump.config
+        .validate()
+        .map_err(|e| format!("{}: invalid config: {}", path.display(), e))?;
+    Ok(dump)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_round_trips_through_json() {
+        let mut config = Config::default();
+        config.profiles.insert("dim".into(), toml::from_str("screen_brightness_max = 50").unwrap());
+        let dump = StateDump {
+            version: "0".into(),
+            time: "2026-10-17T12:00:00+02:00".into(),
+            config,
+            state: LoopState {
+                ema: Some(0.4),
+                target: 120,
+                ..LoopState::default()
+            },
+            samples: Vec::new(),
+            devices: Devices::default(),
+        };
+        let back: StateDump = serde_json::from_str(&serde_json::to_string(&dump).unwrap()).unwrap();
+        assert_eq!(back.state, dump.state);
+        assert_eq!(back.config.get_key("screen_brightness_max"), dump.config.get_key("screen_brightness_max"));
+        assert!(back.config.profiles.contains_key("dim"));
+    }
+}
@@ -0,0 +1,151 @@
+// src/stats.rs
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::logging::STATUS;
+use crate::tr;
+
+/// Running min/max/average over a stream of samples.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Summary {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl Summary {
+    pub fn add(&mut self, v: f64) {
+        if self.count == 0 {
+            self.min = v;
+            self.max = v;
+        } else {
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+        self.sum += v;
+        self.count += 1;
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn avg(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+/// Counters accumulated over the whole daemon run (across interval cycles).
+#[derive(Debug, Clone)]
+pub struct RunStats {
+    started: Instant,
+    pub luma: Summary,
+    pub brightness: Summary,
+    /// Approximate illuminance, when the sensor can tell.
+    pub lux: Summary,
+    pub brightness_changes: u64,
+    pub capture_errors: u64,
+    /// Readings dropped by the motion gate.
+    pub motion_skipped: u64,
+    pub backlight_writes: u64,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            luma: Summary::default(),
+            brightness: Summary::default(),
+            lux: Summary::default(),
+            brightness_changes: 0,
+            capture_errors: 0,
+            motion_skipped: 0,
+            backlight_writes: 0,
+        }
+    }
+
+    pub fn record_sample(&mut self, normalized_luma: f32, brightness: u32) {
+        self.luma.add(normalized_luma as f64);
+        self.brightness.add(brightness as f64);
+    }
+
+    pub fn runtime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Human-readable summary, one entry per line.
+    pub fn lines(&self) -> Vec<String> {
+        let fmt_f = |v: Option<f64>| v.map_or_else(|| "-".to_string(), |v| format!("{:.3}", v));
+        let fmt_u = |v: Option<f64>| v.map_or_else(|| "-".to_string(), |v| format!("{:.0}", v));
+        let mut lines = vec![
+            tr!("summary-title"),
+            format!("  {}", tr!("summary-runtime", value = format_duration(self.runtime()))),
+            format!("  {}", tr!("summary-changes", value = self.brightness_changes.to_string())),
+            format!(
+                "  {}",
+                tr!(
+                    "summary-luma",
+                    min = fmt_f(self.luma.min()),
+                    max = fmt_f(self.luma.max()),
+                    avg = fmt_f(self.luma.avg()),
+                )
+            ),
+            format!(
+                "  {}",
+                tr!(
+                    "summary-brightness",
+                    min = fmt_u(self.brightness.min()),
+                    max = fmt_u(self.brightness.max()),
+                    avg = fmt_u(self.brightness.avg()),
+                )
+            ),
+        ];
+        if self.lux.avg().is_some() {
+            lines.push(format!(
+                "  {}",
+                tr!(
+                    "summary-lux",
+                    min = fmt_u(self.lux.min()),
+                    max = fmt_u(self.lux.max()),
+                    avg = fmt_u(self.lux.avg()),
+                )
+            ));
+        }
+        lines.extend([
+            format!("  {}", tr!("summary-capture-errors", value = self.capture_errors.to_string())),
+            format!("  {}", tr!("summary-motion-skipped", value = self.motion_skipped.to_string())),
+            format!("  {}", tr!("summary-writes", value = self.backlight_writes.to_string())),
+        ]);
+        lines
+    }
+
+    pub fn log_summary(&self) {
+        for line in self.lines() {
+            info!(target: STATUS, "{}", line);
+        }
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{:.1}s", d.as_secs_f64())
+    }
+}
@@ -0,0 +1,126 @@
+// src/telemetry.rs
+use std::time::{Duration, Instant};
+
+use tracing::{debug, Level};
+
+const TARGET: &str = "telemetry";
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+/// Per-window cap so a stalled report can't grow memory without bound.
+const MAX_SAMPLES: usize = 1 << 16;
+
+/// Samples collected since the last report.
+#[derive(Default)]
+pub struct Histogram {
+    samples: Vec<Duration>,
+}
+
+impl Histogram {
+    pub fn record(&mut self, d: Duration) {
+        if self.samples.len() < MAX_SAMPLES {
+            self.samples.push(d);
+        }
+    }
+
+    /// (p50, p95, p99), or None without samples.
+    pub fn percentiles(&mut self) -> Option<[Duration; 3]> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        self.samples.sort_unstable();
+        let at = |p: f64| {
+            let idx = ((self.samples.len() - 1) as f64 * p).round() as usize;
+            self.samples[idx]
+        };
+        Some([at(0.50), at(0.95), at(0.99)])
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+/// Per-cycle timings of the brightness loop, logged as percentiles once a
+/// minute. Only active when the `telemetry` target is enabled at debug level
+/// (`logging = "high"` or above, or `RUST_LOG=telemetry=debug`).
+pub struct LoopTelemetry {
+    enabled: bool,
+    capture: Histogram,
+    processing: Histogram,
+    write: Histogram,
+    /// Deviation of the actual capture spacing from the configured interval.
+    jitter: Histogram,
+    last_report: Instant,
+}
+
+impl LoopTelemetry {
+    pub fn new() -> Self {
+        Self {
+            enabled: tracing::enabled!(target: TARGET, Level::DEBUG),
+            capture: Histogram::default(),
+            processing: Histogram::default(),
+            write: Histogram::default(),
+            jitter: Histogram::default(),
+            last_report: Instant::now(),
+        }
+    }
+
+    pub fn capture(&mut self, d: Duration) {
+        if self.enabled {
+            self.capture.record(d);
+        }
+    }
+
+    pub fn processing(&mut self, d: Duration) {
+        if self.enabled {
+            self.processing.record(d);
+        }
+    }
+
+    pub fn write(&mut self, d: Duration) {
+        if self.enabled {
+            self.write.record(d);
+        }
+    }
+
+    /// Record how far the time since the previous capture strayed from the
+    /// configured capture interval.
+    pub fn capture_gap(&mut self, actual: Duration, expected: Duration) {
+        if self.enabled {
+            self.jitter.record(actual.abs_diff(expected));
+        }
+    }
+
+    pub fn maybe_report(&mut self) {
+        if !self.enabled || self.last_report.elapsed() < REPORT_INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+        for (name, hist) in [
+            ("capture", &mut self.capture),
+            ("processing", &mut self.processing),
+            ("sysfs write", &mut self.write),
+            ("capture jitter", &mut self.jitter),
+        ] {
+            if let Some([p50, p95, p99]) = hist.percentiles() {
+                debug!(
+                    target: TARGET,
+                    "{}: p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms (n={})",
+                    name,
+                    ms(p50),
+                    ms(p95),
+                    ms(p99),
+                    hist.len()
+                );
+            }
+            hist.clear();
+        }
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
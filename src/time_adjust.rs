@@ -1,14 +1,39 @@
-use chrono::{Local, Timelike};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset, Local, Timelike};
 use crate::config::Config;
 
+/// Exponent between luma and perceived lightness.
+const GAMMA: f32 = 2.2;
+/// How long the schedule takes to move from one level to the other after
+/// `day_start_hour` or `night_start_hour`.
+const CROSSFADE_MINUTES: f32 = 60.0;
+/// Wall-clock drift from the monotonic clock that counts as a jump.
+const CLOCK_JUMP: Duration = Duration::from_secs(60);
+
 /// Applies a simple circadian boost to normalized ambient readings so the display
 /// feels brighter during the day and softer at night.
+///
+/// The multipliers don't scale luma directly: that does nothing to a bright
+/// room by day and hardly anything to a dark one at night. Instead, in
+/// perceived lightness, a day boost of `1 + s` moves a reading the share `s`
+/// of the way up to full, and a night dim of `1 - s` moves it that share
+/// of the way down to dark. Nothing is clipped, distinct readings stay
+/// distinct, and the change looks alike across the range.
+///
+/// With a `weight` the result is then mixed, again in perceived lightness,
+/// with a plain time-of-day schedule that ignores the room: `day_level` by
+/// day, `night_level` by night.
 #[derive(Debug, Clone)]
 pub struct TimeAdjuster {
     day_multiplier: f32,
     night_multiplier: f32,
     day_start_hour: u8,
     night_start_hour: u8,
+    weight: f32,
+    day_level: f32,
+    night_level: f32,
 }
 
 impl Default for TimeAdjuster {
@@ -18,6 +43,9 @@ impl Default for TimeAdjuster {
             night_multiplier: 0.95,
             day_start_hour: 7,
             night_start_hour: 20,
+            weight: 0.0,
+            day_level: 0.6,
+            night_level: 0.1,
         }
     }
 }
@@ -29,6 +57,9 @@ impl TimeAdjuster {
             night_multiplier: cfg.circadian_night_multiplier.max(0.0),
             day_start_hour: cfg.circadian_day_start_hour,
             night_start_hour: cfg.circadian_night_start_hour,
+            weight: cfg.circadian_weight.clamp(0.0, 1.0),
+            day_level: cfg.circadian_day_level.clamp(0.0, 1.0),
+            night_level: cfg.circadian_night_level.clamp(0.0, 1.0),
         }
     }
 
@@ -41,8 +72,7 @@ impl TimeAdjuster {
         }
     }
 
-    pub fn factor_now(&self) -> f32 {
-        let hour = Local::now().hour() as u8;
+    fn factor_at(&self, hour: u8) -> f32 {
         if self.is_day(hour) {
             self.day_multiplier
         } else {
@@ -52,6 +82,177 @@ impl TimeAdjuster {
 
     #[inline]
     pub fn adjust(&self, normalized_luma: f32) -> f32 {
-        (normalized_luma * self.factor_now()).clamp(0.0, 1.0)
+        let now = Local::now();
+        let adjusted = self.adjust_at(now.hour() as u8, normalized_luma);
+        self.blend_at(now.hour() * 60 + now.minute(), adjusted)
+    }
+
+    /// The luma the schedule alone calls for at `minute` of the day. Each
+    /// level fades into the other over the first hour after its start.
+    pub fn schedule_at(&self, minute: u32) -> f32 {
+        let minute = minute % 1440;
+        let since = |hour: u8| (minute + 1440 - hour as u32 * 60) % 1440;
+        let (from, to, into) = if self.is_day((minute / 60) as u8) {
+            (self.night_level, self.day_level, since(self.day_start_hour))
+        } else {
+            (self.day_level, self.night_level, since(self.night_start_hour))
+        };
+        let t = (into as f32 / CROSSFADE_MINUTES).min(1.0);
+        from + (to - from) * t
+    }
+
+    /// Mix `adjusted` with the schedule by `weight`.
+    fn blend_at(&self, minute: u32, adjusted: f32) -> f32 {
+        if self.weight <= 0.0 {
+            return adjusted;
+        }
+        let p = adjusted.clamp(0.0, 1.0).powf(1.0 / GAMMA);
+        let s = self.schedule_at(minute).powf(1.0 / GAMMA);
+        (p + self.weight * (s - p)).clamp(0.0, 1.0).powf(GAMMA)
+    }
+
+    pub fn adjust_at(&self, hour: u8, luma: f32) -> f32 {
+        let s = (self.factor_at(hour) - 1.0).clamp(-1.0, 1.0);
+        let p = luma.clamp(0.0, 1.0).powf(1.0 / GAMMA);
+        let p = if s >= 0.0 { p + s * (1.0 - p) } else { p * (1.0 + s) };
+        p.clamp(0.0, 1.0).powf(GAMMA)
+    }
+
+    /// The reading `adjust` turned into `adjusted` during `hour`, e.g. to
+    /// look at recorded history without the circadian adjustment. The
+    /// schedule is taken at the middle of the hour. Readings a full boost,
+    /// dim or weight flattened come back as the adjusted value.
+    pub fn undo_at(&self, hour: u8, adjusted: f32) -> f32 {
+        if self.weight >= 1.0 {
+            return adjusted.clamp(0.0, 1.0);
+        }
+        let adjusted = if self.weight > 0.0 {
+            let p = adjusted.clamp(0.0, 1.0).powf(1.0 / GAMMA);
+            let s = self.schedule_at(hour as u32 * 60 + 30).powf(1.0 / GAMMA);
+            ((p - self.weight * s) / (1.0 - self.weight)).clamp(0.0, 1.0).powf(GAMMA)
+        } else {
+            adjusted
+        };
+        let s = (self.factor_at(hour) - 1.0).clamp(-1.0, 1.0);
+        if s.abs() >= 1.0 {
+            return adjusted.clamp(0.0, 1.0);
+        }
+        let p = adjusted.clamp(0.0, 1.0).powf(1.0 / GAMMA);
+        let p = if s >= 0.0 { (p - s) / (1.0 - s) } else { p / (1.0 + s) };
+        p.clamp(0.0, 1.0).powf(GAMMA)
+    }
+}
+
+/// A change of the local time that didn't come from time passing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockChange {
+    Zone { from: FixedOffset, to: FixedOffset },
+    /// Seconds the wall clock moved by on its own.
+    Jump(i64),
+}
+
+impl fmt::Display for ClockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockChange::Zone { from, to } => write!(f, "Time zone changed from {} to {}", from, to),
+            ClockChange::Jump(secs) => write!(f, "System clock jumped by {:+}s", secs),
+        }
+    }
+}
+
+/// Notices the time zone changing under a running daemon (travel), or the
+/// wall clock being set (NTP correcting a wrong clock). The time of day is
+/// read afresh for every reading, but a target only moves once the
+/// adjusted luma does by `min_luma_delta`; a change here is reason to set
+/// it again regardless.
+#[derive(Debug, Default)]
+pub struct ClockWatch {
+    /// Monotonic time, time spent suspended and local time when last polled.
+    last: Option<(Instant, Duration, DateTime<Local>)>,
+}
+
+impl ClockWatch {
+    pub fn poll(&mut self, now: Instant, suspended: Duration, wall: DateTime<Local>) -> Option<ClockChange> {
+        let last = self.last.replace((now, suspended, wall));
+        let (then, then_suspended, then_wall) = last?;
+        if then_wall.offset() != wall.offset() {
+            return Some(ClockChange::Zone {
+                from: *then_wall.offset(),
+                to: *wall.offset(),
+            });
+        }
+        let passed = now.saturating_duration_since(then) + suspended.saturating_sub(then_suspended);
+        let moved = wall.signed_duration_since(then_wall);
+        let drift = moved - chrono::Duration::from_std(passed).ok()?;
+        (drift.num_seconds().unsigned_abs() >= CLOCK_JUMP.as_secs())
+            .then(|| ClockChange::Jump(drift.num_seconds()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u8 = 12;
+    const NIGHT: u8 = 23;
+
+    #[test]
+    fn night_dims_bright_rooms_and_day_lifts_dim_ones() {
+        let adj = TimeAdjuster::default();
+        assert!(adj.adjust_at(NIGHT, 1.0) < 0.95);
+        assert!(adj.adjust_at(DAY, 0.05) > 0.05 * 1.05);
+        assert_eq!(adj.adjust_at(DAY, 1.0), 1.0);
+    }
+
+    #[test]
+    fn weight_mixes_in_the_schedule() {
+        let adj = TimeAdjuster {
+            day_multiplier: 1.0,
+            weight: 1.0,
+            ..TimeAdjuster::default()
+        };
+        assert!((adj.blend_at(DAY as u32 * 60, 0.0) - adj.day_level).abs() < 1e-4);
+        let dawn = (adj.day_level + adj.night_level) / 2.0;
+        assert!((adj.schedule_at(7 * 60 + 30) - dawn).abs() < 1e-4);
+        let half = TimeAdjuster { weight: 0.5, ..adj };
+        let mixed = half.blend_at(DAY as u32 * 60, 0.0);
+        assert!(mixed > 0.0 && mixed < half.day_level);
+        assert!(half.undo_at(DAY, mixed).abs() < 1e-4);
+    }
+
+    #[test]
+    fn adjustment_keeps_order_and_can_be_undone() {
+        let adj = TimeAdjuster::default();
+        for hour in [DAY, NIGHT] {
+            let mut prev = -1.0;
+            for i in 0..=100 {
+                let luma = i as f32 / 100.0;
+                let adjusted = adj.adjust_at(hour, luma);
+                assert!(adjusted > prev, "{} at {}:00", luma, hour);
+                assert!((adj.undo_at(hour, adjusted) - luma).abs() < 1e-4);
+                prev = adjusted;
+            }
+        }
+    }
+
+    #[test]
+    fn clock_watch_tells_jumps_from_time_passing() {
+        use chrono::TimeZone;
+        let mut watch = ClockWatch::default();
+        let start = Instant::now();
+        let wall = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+        let at = |secs: u64, suspended: u64, wall_secs: i64| {
+            (
+                start + Duration::from_secs(secs),
+                Duration::from_secs(suspended),
+                wall + chrono::Duration::seconds(wall_secs),
+            )
+        };
+        let mut poll = |(now, suspended, wall)| watch.poll(now, suspended, wall);
+        assert_eq!(poll(at(0, 0, 0)), None);
+        assert_eq!(poll(at(30, 0, 30)), None);
+        // Asleep for an hour: the wall clock moved with the suspended time.
+        assert_eq!(poll(at(31, 3600, 3631)), None);
+        assert_eq!(poll(at(32, 3600, 3632 - 600)), Some(ClockChange::Jump(-600)));
     }
 }
@@ -1,27 +1,414 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use chrono::Utc;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, List, ListItem, ListState, Paragraph},
     Frame, Terminal,
 };
 use std::{error::Error, io};
 
-use crate::config::{save_config, Config, DaemonMode};
+use crate::config::{save_config, Config, DaemonMode, MANAGED_PATH};
+use crate::curve::{Curve, CurveUnit, MIN_GAP};
+use crate::displays::{self, Detected, LinkedDisplay};
+use crate::history::{self, HistoryRecord};
+use crate::tr;
+
+/// Visible window widths for the history chart, widest first.
+const ZOOM_LEVELS_HOURS: [f64; 6] = [24.0, 12.0, 6.0, 3.0, 1.0, 0.5];
+const HISTORY_SPAN_HOURS: f64 = 24.0;
+
+type Series = Vec<(f64, f64)>;
+
+struct HistoryView {
+    records: Vec<HistoryRecord>,
+    now: i64,
+    zoom: usize,
+    /// Distance of the right edge of the window from "now", in hours.
+    offset_hours: f64,
+    error: Option<String>,
+}
+
+impl HistoryView {
+    fn load() -> Self {
+        let now = Utc::now().timestamp();
+        let since = now - (HISTORY_SPAN_HOURS * 3600.0) as i64;
+        let (records, error) = match history::load_since(&history::default_path(), since) {
+            Ok(r) => (r, None),
+            Err(e) => (Vec::new(), Some(tr!("tui-history-error", error = e.to_string()))),
+        };
+        Self {
+            records,
+            now,
+            zoom: 0,
+            offset_hours: 0.0,
+            error,
+        }
+    }
+
+    fn span(&self) -> f64 {
+        ZOOM_LEVELS_HOURS[self.zoom]
+    }
+
+    /// Window bounds in hours relative to now (negative = past).
+    fn window(&self) -> [f64; 2] {
+        let end = -self.offset_hours;
+        [end - self.span(), end]
+    }
+
+    fn zoom_in(&mut self) {
+        self.zoom = (self.zoom + 1).min(ZOOM_LEVELS_HOURS.len() - 1);
+    }
+
+    fn zoom_out(&mut self) {
+        self.zoom = self.zoom.saturating_sub(1);
+        self.offset_hours = self.offset_hours.min(HISTORY_SPAN_HOURS - self.span());
+    }
+
+    fn pan_left(&mut self) {
+        let max_offset = HISTORY_SPAN_HOURS - self.span();
+        self.offset_hours = (self.offset_hours + self.span() / 4.0).min(max_offset);
+    }
+
+    fn pan_right(&mut self) {
+        self.offset_hours = (self.offset_hours - self.span() / 4.0).max(0.0);
+    }
+
+    fn hours_ago(&self, t: i64) -> f64 {
+        (t - self.now) as f64 / 3600.0
+    }
+
+    /// (luma, brightness, manual) series, brightness as a fraction of the
+    /// configured range so both share the 0–1 axis.
+    fn series(&self, cfg: &Config) -> (Series, Series, Series) {
+        let min = cfg.real_min_brightness as f64;
+        let range = (cfg.real_max_brightness as f64 - min).max(1.0);
+        let frac = |b: u32| ((b as f64 - min) / range).clamp(0.0, 1.0);
+        let [lo, hi] = self.window();
+        let mut luma = Vec::new();
+        let mut brightness = Vec::new();
+        let mut manual = Vec::new();
+        for rec in &self.records {
+            let x = self.hours_ago(rec.t);
+            if x < lo || x > hi {
+                continue;
+            }
+            luma.push((x, rec.luma as f64));
+            brightness.push((x, frac(rec.brightness)));
+            if let Some(actual) = rec.actual.filter(|_| rec.is_manual()) {
+                manual.push((x, frac(actual)));
+            }
+        }
+        (luma, brightness, manual)
+    }
+}
+
+/// Keyboard editor for the `[curve]` anchors. Works on a copy; `Enter`
+/// writes it back to the config.
+struct CurveEditor {
+    curve: Curve,
+    selected: usize,
+    /// Top of the brightness axis: 1.0, or `panel_max_nits` for a curve in
+    /// nits. Anchors are edited and plotted relative to it.
+    y_max: f32,
+}
+
+impl CurveEditor {
+    fn new(cfg: &Config) -> Self {
+        let y_max = match cfg.curve.unit {
+            CurveUnit::Nits => cfg.panel_max_nits.unwrap_or(1.0),
+            CurveUnit::Fraction => 1.0,
+        };
+        // A hand-edited curve that fails validation starts over from linear.
+        let mut editor = Self {
+            curve: cfg.curve.clone(),
+            selected: 0,
+            y_max,
+        };
+        if cfg.curve.validate().is_err() {
+            editor.reset();
+        }
+        editor
+    }
+
+    fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.curve.points.len();
+    }
+
+    fn select_prev(&mut self) {
+        let n = self.curve.points.len();
+        self.selected = (self.selected + n - 1) % n;
+    }
+
+    /// Move the selected anchor; luma stays between its neighbours so the
+    /// anchors never reorder.
+    fn nudge(&mut self, dx: f32, dy: f32) {
+        let i = self.selected;
+        let pts = &mut self.curve.points;
+        let lo = if i == 0 { 0.0 } else { pts[i - 1][0] + MIN_GAP };
+        let hi = if i + 1 == pts.len() { 1.0 } else { pts[i + 1][0] - MIN_GAP };
+        pts[i][0] = (pts[i][0] + dx).clamp(lo, hi);
+        pts[i][1] = (pts[i][1] + dy * self.y_max).clamp(0.0, self.y_max);
+    }
+
+    /// Insert an anchor on the curve halfway to the next one (or the
+    /// previous one when the last anchor is selected).
+    fn add(&mut self) {
+        let pts = &self.curve.points;
+        let (a, b) = if self.selected + 1 < pts.len() {
+            (self.selected, self.selected + 1)
+        } else {
+            (self.selected - 1, self.selected)
+        };
+        let x = (pts[a][0] + pts[b][0]) / 2.0;
+        if x - pts[a][0] < MIN_GAP {
+            return;
+        }
+        let y = self.curve.eval(x);
+        self.curve.points.insert(b, [x, y]);
+        self.selected = b;
+    }
+
+    fn remove(&mut self) {
+        if self.curve.points.len() <= 2 {
+            return;
+        }
+        self.curve.points.remove(self.selected);
+        self.selected = self.selected.min(self.curve.points.len() - 1);
+    }
+
+    fn reset(&mut self) {
+        let unit = self.curve.unit;
+        self.curve = Curve {
+            points: vec![[0.0, 0.0], [1.0, self.y_max]],
+            unit,
+        };
+        self.selected = 0;
+    }
+
+    fn series(&self) -> (Series, Series, Series) {
+        let line = (0..=100)
+            .map(|i| {
+                let x = i as f32 / 100.0;
+                (x as f64, (self.curve.eval(x) / self.y_max) as f64)
+            })
+            .collect();
+        let point = |p: &[f32; 2]| (p[0] as f64, (p[1] / self.y_max) as f64);
+        let anchors = self.curve.points.iter().map(point).collect();
+        let selected = vec![point(&self.curve.points[self.selected])];
+        (line, anchors, selected)
+    }
+}
+
+/// Fields of the per-display settings page, in order.
+const DISPLAY_FIELDS: usize = 5;
+
+/// Defaults for a display that isn't listed yet.
+fn unlinked(name: &str) -> LinkedDisplay {
+    LinkedDisplay {
+        enabled: false,
+        ..LinkedDisplay::new(name)
+    }
+}
+
+/// Display picker for `[[displays]]`: detected backlight devices plus
+/// configured ones that are missing. Works on a copy; `Esc` writes it back
+/// to the config.
+struct DisplayPicker {
+    detected: Vec<Detected>,
+    displays: Vec<LinkedDisplay>,
+    /// Device names, detected first.
+    rows: Vec<String>,
+    state: ListState,
+    /// Settings page of the selected display: selected field, and the text
+    /// being typed while editing it.
+    page: Option<(usize, Option<String>)>,
+    message: Option<String>,
+}
+
+impl DisplayPicker {
+    fn new(cfg: &Config) -> Self {
+        let detected = displays::detect(&cfg.sysfs_root());
+        let mut rows: Vec<String> = detected.iter().map(|d| d.name.clone()).collect();
+        for d in &cfg.displays {
+            if !rows.contains(&d.name) {
+                rows.push(d.name.clone());
+            }
+        }
+        let mut state = ListState::default();
+        state.select((!rows.is_empty()).then_some(0));
+        Self {
+            detected,
+            displays: cfg.displays.clone(),
+            rows,
+            state,
+            page: None,
+            message: None,
+        }
+    }
+
+    fn selected_name(&self) -> Option<&str> {
+        self.state.selected().map(|i| self.rows[i].as_str())
+    }
+
+    fn entry(&self, name: &str) -> Option<&LinkedDisplay> {
+        self.displays.iter().find(|d| d.name == name)
+    }
+
+    /// The selected display's entry, created (disabled) if it isn't listed.
+    fn entry_mut(&mut self) -> Option<&mut LinkedDisplay> {
+        let name = self.selected_name()?.to_string();
+        let i = match self.displays.iter().position(|d| d.name == name) {
+            Some(i) => i,
+            None => {
+                self.displays.push(unlinked(&name));
+                self.displays.len() - 1
+            }
+        };
+        Some(&mut self.displays[i])
+    }
+
+    fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.rows.len()));
+        }
+    }
+
+    fn select_prev(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + self.rows.len() - 1) % self.rows.len()));
+        }
+    }
+
+    fn toggle(&mut self) {
+        if let Some(entry) = self.entry_mut() {
+            entry.enabled = !entry.enabled;
+        }
+    }
+
+    /// Drop the selected display's entry, so it is no longer touched.
+    fn remove(&mut self) {
+        if let Some(name) = self.selected_name().map(str::to_string) {
+            self.displays.retain(|d| d.name != name);
+        }
+    }
+
+    fn open_page(&mut self) {
+        if self.selected_name().is_some() {
+            self.page = Some((0, None));
+        }
+    }
+
+    /// Leave the settings page. A display that was opened but never
+    /// enabled or changed isn't kept, so it can still be the primary.
+    fn close_page(&mut self) {
+        self.page = None;
+        if let Some(name) = self.selected_name().map(str::to_string) {
+            let untouched = unlinked(&name);
+            self.displays.retain(|d| *d != untouched);
+        }
+    }
+
+    fn field_value(entry: &LinkedDisplay, field: usize) -> String {
+        match field {
+            0 => entry.enabled.to_string(),
+            1 => format!("{:.2}", entry.multiplier),
+            2 => format!("{:.1}", entry.offset),
+            3 => format!("{:.1}", entry.min),
+            _ => format!("{:.1}", entry.max),
+        }
+    }
+
+    fn edit_field(&mut self) {
+        let Some((field, _)) = self.page else { return };
+        if field == 0 {
+            self.toggle();
+            return;
+        }
+        let Some(entry) = self.entry_mut() else { return };
+        let value = Self::field_value(entry, field);
+        self.page = Some((field, Some(value)));
+    }
+
+    fn submit_field(&mut self) {
+        let Some((field, Some(input))) = self.page.clone() else { return };
+        self.page = Some((field, None));
+        let Ok(value) = input.trim().parse::<f32>() else {
+            self.message = Some(tr!("tui-displays-invalid", error = input));
+            return;
+        };
+        let Some(entry) = self.entry_mut() else { return };
+        let mut edited = entry.clone();
+        match field {
+            1 => edited.multiplier = value,
+            2 => edited.offset = value,
+            3 => edited.min = value,
+            _ => edited.max = value,
+        }
+        match edited.validate() {
+            Ok(()) => {
+                *entry = edited;
+                self.message = None;
+            }
+            Err(e) => self.message = Some(tr!("tui-displays-invalid", error = e)),
+        }
+    }
+
+    fn describe(&self, name: &str) -> String {
+        let detected = self.detected.iter().find(|d| d.name == name);
+        let hardware = match detected {
+            Some(d) => format!(
+                "{:<24} {:<9} 0–{}",
+                d.edid_name.as_deref().unwrap_or("–"),
+                d.backend,
+                d.max_brightness
+            ),
+            None => tr!("tui-displays-missing"),
+        };
+        let link = match self.entry(name) {
+            Some(e) => format!(
+                "×{:.2} {:+.0}% {:.0}–{:.0}%",
+                e.multiplier, e.offset, e.min, e.max
+            ),
+            None => tr!("tui-displays-not-linked"),
+        };
+        let mark = if self.entry(name).is_some_and(|e| e.enabled) { "[x]" } else { "[ ]" };
+        format!("{} {:<16} {}  {}", mark, name, hardware, link)
+    }
+}
+
+/// The setting each editable item changes, as `managed.toml` names it.
+const ITEM_SETTINGS: [&str; 8] = [
+    "mode",
+    "run_duration",
+    "pause_interval",
+    "interval_boot",
+    "screen_brightness_min",
+    "screen_brightness_max",
+    "ambient_smoothing_strength",
+    "half_precision",
+];
+/// List positions of the action buttons after the settings.
+const ITEM_SAVE: usize = ITEM_SETTINGS.len();
+const ITEM_CANCEL: usize = ITEM_SAVE + 1;
 
 struct App {
     config: Config,
     state: ListState,
-    items: Vec<&'static str>,
+    items: Vec<String>,
     edit_mode: bool,
     input_buffer: String,
     status_message: String,
+    history: Option<HistoryView>,
+    curve_editor: Option<CurveEditor>,
+    display_picker: Option<DisplayPicker>,
 }
 
 impl App {
@@ -32,18 +419,23 @@ impl App {
             config,
             state,
             items: vec![
-                "Daemon Mode",
-                "Run Duration (Boot/Interval)",
-                "Pause Interval (Interval)",
-                "Min Brightness",
-                "Max Brightness",
-                "Smoothing Factor",
-                "Save & Exit",
-                "Cancel",
+                tr!("tui-item-mode"),
+                tr!("tui-item-run-duration"),
+                tr!("tui-item-pause-interval"),
+                tr!("tui-item-interval-boot"),
+                tr!("tui-item-min-brightness"),
+                tr!("tui-item-max-brightness"),
+                tr!("tui-item-smoothing"),
+                tr!("tui-item-half-precision"),
+                tr!("tui-item-save"),
+                tr!("tui-item-cancel"),
             ],
             edit_mode: false,
             input_buffer: String::new(),
-            status_message: String::from("Press 'Enter' to edit, 'q' to quit"),
+            status_message: tr!("tui-help"),
+            history: None,
+            curve_editor: None,
+            display_picker: None,
         }
     }
 
@@ -82,42 +474,79 @@ impl App {
             0 => format!("{:?}", self.config.mode),
             1 => format!("{:.1}", self.config.run_duration),
             2 => format!("{:.1}", self.config.pause_interval),
-            3 => format!("{}", self.config.real_min_brightness),
-            4 => format!("{}", self.config.real_max_brightness),
-            5 => format!("{:.2}", self.config.smoothing_factor),
+            3 => format!("{}", self.config.interval_boot),
+            4 => format!("{}", self.config.real_min_brightness),
+            5 => format!("{}", self.config.real_max_brightness),
+            6 => format!("{:.2}", self.config.smoothing_factor),
+            7 => format!("{}", self.config.half_precision),
             _ => String::new(),
         }
     }
 
+    /// Whether `managed.toml` locks the setting of item `idx`.
+    fn locked(&self, idx: usize) -> bool {
+        ITEM_SETTINGS.get(idx).is_some_and(|name| self.config.is_managed(name))
+    }
+
     fn enter_edit(&mut self) {
         let idx = self.state.selected().unwrap_or(0);
-        if idx >= 6 { return; } // Don't edit action buttons
+        if idx >= ITEM_SAVE { return; } // Don't edit action buttons
+        if self.locked(idx) {
+            self.status_message = tr!("tui-managed-locked", path = MANAGED_PATH);
+            return;
+        }
         self.edit_mode = true;
         self.input_buffer = self.current_value();
-        self.status_message = String::from("Editing... Press Enter to confirm, Esc to cancel");
+        self.status_message = tr!("tui-editing-help");
     }
 
     fn submit_edit(&mut self) {
         let idx = self.state.selected().unwrap_or(0);
-        match idx {
+        let input = self.input_buffer.trim();
+        let mut next = self.config.clone();
+        let parsed = match idx {
             0 => {
-                // Cycle modes for simplicity if typing is annoying, or parse
-                 match self.input_buffer.to_lowercase().as_str() {
-                    "boot" => self.config.mode = DaemonMode::Boot,
-                    "interval" => self.config.mode = DaemonMode::Interval,
-                    "realtime" => self.config.mode = DaemonMode::Realtime,
-                    _ => self.status_message = String::from("Invalid mode! Use: boot, interval, realtime"),
-                }
+                let mode = match input.to_lowercase().as_str() {
+                    "boot" => DaemonMode::Boot,
+                    "interval" => DaemonMode::Interval,
+                    "realtime" => DaemonMode::Realtime,
+                    _ => {
+                        self.status_message = tr!("tui-invalid-mode");
+                        return;
+                    }
+                };
+                next.mode = mode;
+                Some(())
             }
-            1 => if let Ok(v) = self.input_buffer.parse() { self.config.run_duration = v; },
-            2 => if let Ok(v) = self.input_buffer.parse() { self.config.pause_interval = v; },
-            3 => if let Ok(v) = self.input_buffer.parse() { self.config.real_min_brightness = v; },
-            4 => if let Ok(v) = self.input_buffer.parse() { self.config.real_max_brightness = v; },
-            5 => if let Ok(v) = self.input_buffer.parse() { self.config.smoothing_factor = v; },
-            _ => {}
+            1 => input.parse().ok().map(|v| next.run_duration = v),
+            2 => input.parse().ok().map(|v| next.pause_interval = v),
+            3 => parse_switch(input).map(|v| next.interval_boot = v),
+            4 => input.parse().ok().map(|v| next.real_min_brightness = v),
+            5 => input.parse().ok().map(|v| next.real_max_brightness = v),
+            6 => input.parse().ok().map(|v| next.smoothing_factor = v),
+            7 => parse_switch(input).map(|v| next.half_precision = v),
+            _ => None,
+        };
+        if parsed.is_none() {
+            self.status_message = tr!("tui-invalid-value", input = input.to_string());
+            return;
         }
+        if let Err(e) = next.validate() {
+            self.status_message = tr!("tui-invalid-setting", error = e);
+            return;
+        }
+        self.config = next;
         self.edit_mode = false;
-        self.status_message = String::from("Value updated. Don't forget to 'Save & Exit'");
+        self.status_message = tr!("tui-value-updated");
+    }
+}
+
+/// `true`/`false`, `on`/`off` or `yes`/`no`.
+fn parse_switch(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "true" | "on" | "yes" => Some(true),
+        "false" | "off" | "no" => Some(false),
+        _ => None,
     }
 }
 
@@ -151,12 +580,73 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
         terminal.draw(|f| ui(f, &mut app))?;
 
         if let Event::Key(key) = event::read()? {
-            if app.edit_mode {
+            if let Some(view) = app.history.as_mut() {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('h') | KeyCode::Char('q') => app.history = None,
+                    KeyCode::Left => view.pan_left(),
+                    KeyCode::Right => view.pan_right(),
+                    KeyCode::Char('+') | KeyCode::Char('=') => view.zoom_in(),
+                    KeyCode::Char('-') => view.zoom_out(),
+                    KeyCode::Char('r') => *view = HistoryView::load(),
+                    _ => {}
+                }
+            } else if let Some(editor) = app.curve_editor.as_mut() {
+                let step = if key.modifiers.contains(KeyModifiers::SHIFT) { 0.05 } else { 0.01 };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        app.curve_editor = None;
+                        app.status_message = tr!("tui-curve-discarded");
+                    }
+                    KeyCode::Enter => {
+                        app.config.curve = editor.curve.clone();
+                        app.curve_editor = None;
+                        app.status_message = tr!("tui-value-updated");
+                    }
+                    KeyCode::Tab | KeyCode::Char('n') => editor.select_next(),
+                    KeyCode::BackTab | KeyCode::Char('p') => editor.select_prev(),
+                    KeyCode::Left => editor.nudge(-step, 0.0),
+                    KeyCode::Right => editor.nudge(step, 0.0),
+                    KeyCode::Up => editor.nudge(0.0, step),
+                    KeyCode::Down => editor.nudge(0.0, -step),
+                    KeyCode::Char('a') | KeyCode::Insert => editor.add(),
+                    KeyCode::Char('d') | KeyCode::Delete => editor.remove(),
+                    KeyCode::Char('r') => editor.reset(),
+                    _ => {}
+                }
+            } else if let Some(picker) = app.display_picker.as_mut() {
+                picker.message = None;
+                match (&mut picker.page, key.code) {
+                    (Some((_, Some(input))), KeyCode::Char(c)) => input.push(c),
+                    (Some((_, Some(input))), KeyCode::Backspace) => {
+                        input.pop();
+                    }
+                    (Some((_, Some(_))), KeyCode::Enter) => picker.submit_field(),
+                    (Some((_, input @ Some(_))), KeyCode::Esc) => *input = None,
+                    (Some((field, None)), KeyCode::Down) => *field = (*field + 1) % DISPLAY_FIELDS,
+                    (Some((field, None)), KeyCode::Up) => {
+                        *field = (*field + DISPLAY_FIELDS - 1) % DISPLAY_FIELDS
+                    }
+                    (Some((_, None)), KeyCode::Enter | KeyCode::Char(' ')) => picker.edit_field(),
+                    (Some((_, None)), KeyCode::Esc | KeyCode::Char('q')) => picker.close_page(),
+                    (Some(_), _) => {}
+                    (None, KeyCode::Down) => picker.select_next(),
+                    (None, KeyCode::Up) => picker.select_prev(),
+                    (None, KeyCode::Char(' ')) => picker.toggle(),
+                    (None, KeyCode::Char('x') | KeyCode::Delete) => picker.remove(),
+                    (None, KeyCode::Enter) => picker.open_page(),
+                    (None, KeyCode::Esc | KeyCode::Char('q')) => {
+                        app.config.displays = picker.displays.clone();
+                        app.display_picker = None;
+                        app.status_message = tr!("tui-value-updated");
+                    }
+                    (None, _) => {}
+                }
+            } else if app.edit_mode {
                 match key.code {
                     KeyCode::Enter => app.submit_edit(),
                     KeyCode::Esc => {
                         app.edit_mode = false;
-                        app.status_message = String::from("Editing cancelled");
+                        app.status_message = tr!("tui-editing-cancelled");
                     },
                     KeyCode::Backspace => { app.input_buffer.pop(); },
                     KeyCode::Char(c) => { app.input_buffer.push(c); },
@@ -165,19 +655,32 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
             } else {
                 match key.code {
                     KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('h') => app.history = Some(HistoryView::load()),
+                    KeyCode::Char('c') if app.config.is_managed("curve") => {
+                        app.status_message = tr!("tui-managed-locked", path = MANAGED_PATH);
+                    }
+                    KeyCode::Char('d') if app.config.is_managed("displays") => {
+                        app.status_message = tr!("tui-managed-locked", path = MANAGED_PATH);
+                    }
+                    KeyCode::Char('c') => {
+                        app.curve_editor = Some(CurveEditor::new(&app.config));
+                    }
+                    KeyCode::Char('d') => {
+                        app.display_picker = Some(DisplayPicker::new(&app.config));
+                    }
                     KeyCode::Down => app.next(),
                     KeyCode::Up => app.previous(),
                     KeyCode::Enter => {
                         let idx = app.state.selected().unwrap_or(0);
                         match idx {
-                            6 => { // Save & Exit
+                            ITEM_SAVE => {
                                 if let Err(e) = save_config(&app.config) {
-                                    app.status_message = format!("Error saving: {}", e);
+                                    app.status_message = tr!("tui-save-error", error = e.to_string());
                                 } else {
                                     return Ok(());
                                 }
                             },
-                            7 => return Ok(()), // Cancel
+                            ITEM_CANCEL => return Ok(()),
                             _ => app.enter_edit(),
                         }
                     }
@@ -201,30 +704,74 @@ fn ui(f: &mut Frame, app: &mut App) {
         )
         .split(f.size());
 
-    let title = Paragraph::new("Smart Brightness Configurator")
+    let title = Paragraph::new(tr!("tui-title"))
         .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(title, chunks[0]);
 
+    if let Some(view) = &app.history {
+        draw_history(f, view, &app.config, chunks[1]);
+        let help = view.error.clone().unwrap_or_else(|| tr!("tui-history-help"));
+        let footer = Paragraph::new(help)
+            .style(Style::default().fg(Color::Green))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+        return;
+    }
+
+    if let Some(editor) = &app.curve_editor {
+        draw_curve_editor(f, editor, &app.config, chunks[1]);
+        let footer = Paragraph::new(tr!("tui-curve-help"))
+            .style(Style::default().fg(Color::Green))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+        return;
+    }
+
+    if let Some(picker) = app.display_picker.as_mut() {
+        draw_display_picker(f, picker, chunks[1]);
+        let help = match (&picker.message, &picker.page) {
+            (Some(message), _) => message.clone(),
+            (None, Some((_, Some(input)))) => tr!("tui-displays-editing", input = input.clone()),
+            (None, Some(_)) => tr!("tui-displays-page-help"),
+            (None, None) => tr!("tui-displays-help"),
+        };
+        let footer = Paragraph::new(help)
+            .style(Style::default().fg(if picker.message.is_some() { Color::Red } else { Color::Green }))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+        return;
+    }
+
     let items: Vec<ListItem> = app
         .items
         .iter()
         .enumerate()
         .map(|(i, item)| {
-            let val = if i < 6 {
+            let val = if i < ITEM_SAVE {
                 format!(": {}", match i {
                     0 => format!("{:?}", app.config.mode),
                     1 => format!("{:.1}s", app.config.run_duration),
                     2 => format!("{:.1}s", app.config.pause_interval),
-                    3 => format!("{}", app.config.real_min_brightness),
-                    4 => format!("{}", app.config.real_max_brightness),
-                    5 => format!("{:.2}", app.config.smoothing_factor),
+                    3 => switch_label(app.config.interval_boot),
+                    4 => format!("{}", app.config.real_min_brightness),
+                    5 => format!("{}", app.config.real_max_brightness),
+                    6 => format!("{:.2}", app.config.smoothing_factor),
+                    7 => switch_label(app.config.half_precision),
                     _ => String::new(),
                 })
             } else {
                 String::new()
             };
 
+            if app.locked(i) {
+                let content = Line::from(vec![
+                    Span::raw(format!("{:<30}", item)),
+                    Span::raw(format!("{} ({})", val, tr!("tui-managed"))),
+                ]);
+                return ListItem::new(content).style(Style::default().fg(Color::DarkGray));
+            }
+
             let content = Line::from(vec![
                 Span::raw(format!("{:<30}", item)),
                 Span::styled(val, Style::default().fg(Color::Yellow)),
@@ -235,7 +782,7 @@ fn ui(f: &mut Frame, app: &mut App) {
         .collect();
 
     let items = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Settings"))
+        .block(Block::default().borders(Borders::ALL).title(tr!("tui-settings")))
         .highlight_style(
             Style::default()
                 .bg(Color::Blue)
@@ -246,7 +793,11 @@ fn ui(f: &mut Frame, app: &mut App) {
     f.render_stateful_widget(items, chunks[1], &mut app.state);
 
     let help_text = if app.edit_mode {
-        format!("EDITING: {} (Current: {})", app.input_buffer, app.current_value())
+        tr!(
+            "tui-editing",
+            input = app.input_buffer.clone(),
+            current = app.current_value()
+        )
     } else {
         app.status_message.clone()
     };
@@ -256,3 +807,194 @@ fn ui(f: &mut Frame, app: &mut App) {
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(footer, chunks[2]);
 }
+
+fn switch_label(on: bool) -> String {
+    if on { tr!("tui-on") } else { tr!("tui-off") }
+}
+
+fn draw_display_picker(f: &mut Frame, picker: &mut DisplayPicker, area: Rect) {
+    let highlight = Style::default()
+        .bg(Color::Blue)
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let Some((field, _)) = picker.page else {
+        let items: Vec<ListItem> = if picker.rows.is_empty() {
+            vec![ListItem::new(tr!("tui-displays-none"))]
+        } else {
+            picker.rows.iter().map(|name| ListItem::new(picker.describe(name))).collect()
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(tr!("tui-displays-title")))
+            .highlight_style(highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut picker.state);
+        return;
+    };
+
+    let name = picker.selected_name().unwrap_or_default().to_string();
+    let entry = picker.entry(&name).cloned().unwrap_or_else(|| unlinked(&name));
+    let labels = [
+        tr!("tui-displays-enabled"),
+        tr!("tui-displays-multiplier"),
+        tr!("tui-displays-offset"),
+        tr!("tui-displays-min"),
+        tr!("tui-displays-max"),
+    ];
+    let items: Vec<ListItem> = labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<30}", label)),
+                Span::styled(
+                    format!(": {}", DisplayPicker::field_value(&entry, i)),
+                    Style::default().fg(Color::Yellow),
+                ),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(field));
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(tr!("tui-displays-page-title", name = name)))
+        .highlight_style(highlight)
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_history(f: &mut Frame, view: &HistoryView, cfg: &Config, area: Rect) {
+    let (luma, brightness, manual) = view.series(cfg);
+    let datasets = vec![
+        Dataset::default()
+            .name(tr!("tui-series-luma"))
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&luma),
+        Dataset::default()
+            .name(tr!("tui-series-brightness"))
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&brightness),
+        Dataset::default()
+            .name(tr!("tui-series-manual"))
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Red))
+            .data(&manual),
+    ];
+
+    let [lo, hi] = view.window();
+    let hours_label = |h: f64| {
+        if h.abs() < f64::EPSILON {
+            tr!("tui-history-now")
+        } else {
+            format!("{:.1}h", h)
+        }
+    };
+    let x_axis = Axis::default()
+        .title(tr!("tui-history-hours"))
+        .style(Style::default().fg(Color::Gray))
+        .bounds([lo, hi])
+        .labels(vec![
+            Span::raw(hours_label(lo)),
+            Span::raw(hours_label((lo + hi) / 2.0)),
+            Span::raw(hours_label(hi)),
+        ]);
+    let y_axis = Axis::default()
+        .style(Style::default().fg(Color::Gray))
+        .bounds([0.0, 1.0])
+        .labels(vec![Span::raw("0%"), Span::raw("50%"), Span::raw("100%")]);
+
+    let title = tr!(
+        "tui-history-title",
+        count = view.records.len().to_string(),
+        hours = format!("{:.1}", view.span())
+    );
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .x_axis(x_axis)
+        .y_axis(y_axis);
+    f.render_widget(chart, area);
+}
+
+fn draw_curve_editor(f: &mut Frame, editor: &CurveEditor, cfg: &Config, area: Rect) {
+    let (line, anchors, selected) = editor.series();
+    let datasets = vec![
+        Dataset::default()
+            .name(tr!("tui-curve-series"))
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&line),
+        Dataset::default()
+            .name(tr!("tui-curve-anchors"))
+            .marker(symbols::Marker::Block)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&anchors),
+        Dataset::default()
+            .marker(symbols::Marker::Block)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Red))
+            .data(&selected),
+    ];
+
+    // Preview in hardware units, i.e. what the daemon would write.
+    let min = cfg.real_min_brightness as f32;
+    let range = cfg.real_max_brightness.saturating_sub(cfg.real_min_brightness) as f32;
+    let preview = Config {
+        curve: Curve {
+            points: vec![[0.0, 0.0], [1.0, editor.y_max]],
+            unit: editor.curve.unit,
+        },
+        ..cfg.clone()
+    };
+    // `t` is a position on the (normalized) y axis.
+    let to_level = |t: f32| preview.brightness_fraction(t).mul_add(range, min).round() as u32;
+
+    let x_axis = Axis::default()
+        .title(tr!("tui-curve-luma-axis"))
+        .style(Style::default().fg(Color::Gray))
+        .bounds([0.0, 1.0])
+        .labels(vec![Span::raw("0.0"), Span::raw("0.5"), Span::raw("1.0")]);
+    let y_axis = Axis::default()
+        .title(tr!("tui-series-brightness"))
+        .style(Style::default().fg(Color::Gray))
+        .bounds([0.0, 1.0])
+        .labels(vec![
+            Span::raw(to_level(0.0).to_string()),
+            Span::raw(to_level(0.5).to_string()),
+            Span::raw(to_level(1.0).to_string()),
+        ]);
+
+    let [x, y] = editor.curve.points[editor.selected];
+    let index = (editor.selected + 1).to_string();
+    let count = editor.curve.points.len().to_string();
+    let luma = format!("{:.2}", x);
+    let level = to_level(y / editor.y_max).to_string();
+    let title = match editor.curve.unit {
+        CurveUnit::Fraction => tr!(
+            "tui-curve-title",
+            index = index,
+            count = count,
+            luma = luma,
+            brightness = format!("{:.0}", y * 100.0),
+            level = level
+        ),
+        CurveUnit::Nits => tr!(
+            "tui-curve-title-nits",
+            index = index,
+            count = count,
+            luma = luma,
+            nits = format!("{:.0}", y),
+            level = level
+        ),
+    };
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .x_axis(x_axis)
+        .y_axis(y_axis);
+    f.render_widget(chart, area);
+}